@@ -0,0 +1,84 @@
+// Latency of the hot paths in message handling: parsing raw strings against the dictionary,
+// serializing, and computing body length and checksum. Run with `cargo bench`.
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fix_rs::data_dictionary::DataDictionary;
+use fix_rs::message::*;
+
+const LOGON: &str = "8=FIX.4.3|9=73|35=A|34=1|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|98=0|108=30|10=061|";
+const NEW_ORDER_SINGLE: &str = "8=FIX.4.3|9=171|35=D|34=2|49=BANZAI|52=20221006-08:43:37.104|56=FIXIMULATOR|11=1665045817100|21=1|38=100|40=2|44=15.75|54=1|55=IBM|59=0|60=20221006-08:43:37.100|10=081|";
+const NEW_ORDER_LIST: &str = "8=FIX.4.3|9=188|35=E|34=3|49=BANZAI|52=20221006-08:43:38.522|56=FIXIMULATOR|66=list_id|394=1|73=2|11=ClientOrderId1|67=1|78=2|79=AllocAct11|80=10|79=AllocAct12|80=20|11=ClientOrderId2|67=2|78=1|79=AllocAct21|80=30|10=075|";
+
+fn with_soh(s: &str) -> String {
+    s.replace('|', &SOH.to_string())
+}
+
+fn samples() -> [(&'static str, String); 3] {
+    [
+        ("logon", with_soh(LOGON)),
+        ("new_order_single", with_soh(NEW_ORDER_SINGLE)),
+        ("new_order_list", with_soh(NEW_ORDER_LIST)),
+    ]
+}
+
+fn parse(c: &mut Criterion) {
+    let dd = DataDictionary::from_xml("resources/FIX43.xml");
+    let mut group = c.benchmark_group("parse");
+    for (name, raw) in samples() {
+        // the benchmark is meaningless if the sample is rejected early
+        assert!(Message::from_str(&raw, &dd).is_ok(), "{} does not parse", name);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &raw, |b, raw| {
+            b.iter(|| Message::from_str(black_box(raw), &dd))
+        });
+        // each message goes back to the pool before the next is parsed
+        let pool = MessagePool::new(1);
+        group.bench_with_input(BenchmarkId::new("pooled", name), &raw, |b, raw| {
+            b.iter(|| pool.parse(black_box(raw), &dd, ParseOptions::default()))
+        });
+    }
+    group.finish();
+}
+
+fn serialize(c: &mut Criterion) {
+    let dd = DataDictionary::from_xml("resources/FIX43.xml");
+    let mut group = c.benchmark_group("serialize");
+    for (name, raw) in samples() {
+        let msg = Message::from_str(&raw, &dd).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &msg, |b, msg| {
+            b.iter(|| black_box(msg).to_string())
+        });
+        // into a buffer reused from message to message, as a writer would
+        let mut buf = Vec::with_capacity(512);
+        group.bench_with_input(BenchmarkId::new("write_to", name), &msg, |b, msg| {
+            b.iter(|| {
+                buf.clear();
+                black_box(msg).write_to(&mut buf);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn checksum(c: &mut Criterion) {
+    let dd = Arc::new(DataDictionary::from_xml("resources/FIX43.xml"));
+    let factory = MessageFactory::with_dictionary(Arc::clone(&dd));
+    let mut msg = factory.create("FIX.4.3", "D").unwrap();
+    for (tag, value) in [(49, "BANZAI"), (56, "FIXIMULATOR"), (34, "2")] {
+        msg.header_mut().set_field(StringField::new(tag, value), &dd).unwrap();
+    }
+    for (tag, value) in [
+        (11, "1665045817100"),
+        (21, "1"),
+        (38, "100"),
+        (40, "2"),
+        (54, "1"),
+    ] {
+        msg.set_field(StringField::new(tag, value));
+    }
+    c.bench_function("body_len", |b| b.iter(|| black_box(&mut msg).set_body_len()));
+    c.bench_function("checksum", |b| b.iter(|| black_box(&mut msg).set_checksum()));
+}
+
+criterion_group!(benches, parse, serialize, checksum);
+criterion_main!(benches);
@@ -1,12 +1,12 @@
 use crate::templates::*;
 use handlebars::Handlebars;
-use heck::ToUpperCamelCase;
+use heck::{ToSnakeCase, ToUpperCamelCase};
 use roxmltree::*;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, collections::HashSet, fs, fs::File, path::Path};
+use std::path::{Path, PathBuf};
+use std::{collections::HashMap, collections::HashSet, fs, fs::File};
 
 const ENUM_VARIANT_MAX_LEN: usize = 10; // max words in enum variant separated by `_`
-const ENUM_VARIANT_PREFIX: &str = "ENVal_";
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct XmlFixSpec {
@@ -22,6 +22,7 @@ impl XmlFixSpec {}
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Header {
     pub fields: HashSet<String>,
+    pub groups: Vec<XmlGroup>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -34,15 +35,18 @@ pub struct XmlMessage {
     pub msg_name: String,
     pub msg_type: String,
     pub msg_cat: String,
+    pub handler_name: String, // on_new_order_single
     pub fields: HashSet<String>,
-    pub groups: HashMap<String, XmlGroup>,
+    pub groups: Vec<XmlGroup>, // nested groups are listed after their parent
+    pub layout: Vec<u32>,      // body tags outside of groups in xml order, components expanded
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct XmlGroup {
     pub group_name: String,
-    pub group_fields: HashSet<String>,
-    pub groups: HashMap<String, XmlGroup>,
+    pub number: u32,
+    pub delimiter: u32,
+    pub group_fields: Vec<u32>, // tags of an instance in xml order, components expanded
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -50,13 +54,24 @@ pub struct XmlField {
     pub name: String,
     pub number: u32,
     pub fld_type: String,
+    pub fix_type: String,         // type as in the xml, STRING, CHAR, ...
+    pub fix_type_variant: String, // FixType of data_dictionary
     pub values: Vec<XmlFieldValue>,
 }
 
+// fields sorted by tag for the registry table
+#[derive(Debug, Serialize)]
+pub struct FieldRegistry<'a> {
+    pub begin_string: &'a str,
+    pub fields: Vec<&'a XmlField>,
+}
+
+// wire value and description are rust string literals, quotes and backslashes escaped
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct XmlFieldValue {
     pub enum_variant: String,
     pub variant_val: String,
+    pub description: String,
 }
 
 fn lookup_node<'a, 'input>(name: &str, document: &'a Document<'input>) -> Node<'a, 'input> {
@@ -68,9 +83,42 @@ fn lookup_node<'a, 'input>(name: &str, document: &'a Document<'input>) -> Node<'
         .expect("Could not lookup node")
 }
 
+fn get_fix_type_variant(field_type: &str) -> &'static str {
+    // same mapping as FixType::from_str
+    match field_type {
+        "CHAR" => "Char",
+        "BOOLEAN" => "Boolean",
+        "DATA" => "Data",
+        "FLOAT" => "Float",
+        "AMT" => "Amt",
+        "PERCENTAGE" => "Percentage",
+        "PRICE" => "Price",
+        "PRICEOFFSET" => "PriceOffset",
+        "QTY" => "Qty",
+        "INT" => "Int",
+        "LENGTH" => "Length",
+        "NUMINGROUP" => "NumInGroup",
+        "SEQNUM" => "Seqnum",
+        "TAGNUM" => "Tagnum",
+        "STRING" => "Str",
+        "COUNTRY" => "Country",
+        "CURRENCY" => "Currency",
+        "EXCHANGE" => "Exchange",
+        "LOCALMKTDATE" => "LocalMktDate",
+        "MONTHYEAR" => "MonthYear",
+        "MULTIPLEVALUESTRING" => "MultipleValueString",
+        "UTCDATE" => "UtcDate",
+        "UTCTIMEONLY" => "UtcTimeOnly",
+        "UTCTIMESTAMP" => "UtcTimestamp",
+        _ => "Unknown",
+    }
+}
+
 fn get_primitive_type(field_type: &str) -> String {
+    let decimal = std::env::var_os("CARGO_FEATURE_DECIMAL").is_some();
     let primitive = match field_type.to_lowercase().as_str() {
         "char" => "char",
+        "price" | "amt" | "qty" if decimal => "crate::types::Decimal",
         "boolean" => "bool",
         "data" | "string" | "country" | "currency" | "exchange" => "String",
         "float" | "price" | "amt" | "qty" | "priceoffset" => "f32",
@@ -87,70 +135,47 @@ fn get_primitive_type(field_type: &str) -> String {
     primitive.to_string()
 }
 
-fn get_enum_variant(field_type: &str, enum_val: &str, description: &str) -> String {
-    let enum_words = description
-        .split_terminator(&['_', '-'])
+fn get_enum_variant(description: &str, variants: &HashSet<String>, enum_val: &str) -> String {
+    // SELL_SHORT -> SellShort, 5_YR -> Val5Yr. descriptions that end up the same are told apart
+    // by the wire value
+    let mut variant = description
+        .split_terminator(&['_', '-', ' '])
+        .take(ENUM_VARIANT_MAX_LEN)
         .map(|s| s.to_upper_camel_case())
-        .collect::<Vec<String>>();
-    let mut short_description = if enum_words.len() > ENUM_VARIANT_MAX_LEN {
-        enum_words[..ENUM_VARIANT_MAX_LEN].join("")
-    } else {
-        enum_words[..].join("")
-    };
-
-    if short_description.chars().next().unwrap().is_numeric() {
-        // if the description starts with a number, prefix it with `Val`
-        short_description.insert_str(0, "Val")
+        .collect::<String>()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "");
+    if !variant.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        variant.insert_str(0, "Val");
     }
-
-    let enum_variant = match field_type {
-        "bool" | "char" | "u32" | "u64" | "i32" | "i64" | "f32" | "f64" => short_description,
-        _ => {
-            if enum_val.len() >= 2 {
-                // make enum variant compatible with variant naming convention
-                enum_val
-                    .split_terminator(&['_', '-', ' '])
-                    .map(|s| s.to_upper_camel_case())
-                    .collect::<String>()
-            } else {
-                short_description
-            }
-        }
-    };
-    enum_variant
+    if variant == "Self" || variants.contains(&variant) {
+        variant.push_str(
+            &enum_val.to_upper_camel_case().replace(|c: char| !c.is_ascii_alphanumeric(), ""),
+        );
+    }
+    variant
 }
 
 fn add_fields_to_spec(field_node: &Node, spec: &mut XmlFixSpec) {
-    for field in field_node
-        .children()
-        .filter(|node| node.is_element() && node.has_tag_name("field"))
+    for field in
+        field_node.children().filter(|node| node.is_element() && node.has_tag_name("field"))
     {
         let ftype = field.attribute("type").unwrap();
         let fld_type = get_primitive_type(ftype);
         let name = field.attribute("name").unwrap();
         let number = field.attribute("number").and_then(|s| s.parse::<u32>().ok()).unwrap();
+        let mut variants = HashSet::new();
         let values: Vec<XmlFieldValue> = field
             .children()
             .filter(|n| n.is_element() && n.has_tag_name("value"))
             .map(|node| {
-                let description = node
-                    .attribute("description")
-                    .unwrap()
-                    .split_terminator(&['_', '-'])
-                    .map(|s| s.to_upper_camel_case())
-                    // .take(ENUM_VARIANT_MAX_LEN)
-                    .collect::<String>();
-                let enum_value = node.attribute("enum").unwrap();
-                // variant's actual val will always be "enum" attribute
-                // let enum_variant = get_enum_variant(&fld_type, enum_value, description);
-                let mut enum_variant: String = description;
-                // prefix the variant name so that it distinguishes between Rust keywords like None
-                // `None` is enum values in some field's supported values
-                enum_variant.insert_str(0, ENUM_VARIANT_PREFIX);
-                let variant_val = enum_value.to_string();
+                let description = node.attribute("description").unwrap();
+                let variant_val = node.attribute("enum").unwrap();
+                let enum_variant = get_enum_variant(description, &variants, variant_val);
+                variants.insert(enum_variant.clone());
                 XmlFieldValue {
                     enum_variant,
-                    variant_val,
+                    variant_val: format!("{:?}", variant_val),
+                    description: format!("{:?}", description),
                 }
             })
             .collect();
@@ -159,6 +184,8 @@ fn add_fields_to_spec(field_node: &Node, spec: &mut XmlFixSpec) {
             name: name.to_string(),
             number,
             fld_type,
+            fix_type: ftype.to_string(),
+            fix_type_variant: get_fix_type_variant(ftype).to_string(),
             values,
         };
         spec.fields.push(xml_field);
@@ -184,12 +211,215 @@ pub fn get_fix_spec(src_dir: &Path, name: &str) -> XmlFixSpec {
         .collect();
     let fields_node = lookup_node("fields", &document);
     add_fields_to_spec(&fields_node, &mut fix_spec);
+    let numbers: HashMap<&str, u32> =
+        fix_spec.fields.iter().map(|field| (field.name.as_str(), field.number)).collect();
+    let mut header_groups = Vec::new();
+    add_groups(lookup_node("header", &document), &components, &numbers, &mut header_groups);
+    let messages_node = lookup_node("messages", &document);
+    let messages = get_messages(&messages_node, &components, &numbers);
+    fix_spec.header.groups = header_groups;
+    fix_spec.messages = messages;
     fix_spec
 }
 
-pub fn generate_fields(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) {
-    let mut file = File::create(out_dir.join(name)).expect("file could not be created");
+fn get_messages(
+    messages_node: &Node, components: &HashMap<String, Node>, numbers: &HashMap<&str, u32>,
+) -> Vec<XmlMessage> {
+    // name, type and category for the typed message structs, groups for the group structs
+    let mut messages = Vec::new();
+    for message in
+        messages_node.children().filter(|node| node.is_element() && node.has_tag_name("message"))
+    {
+        let msg_name = message.attribute("name").unwrap();
+        let mut groups = Vec::new();
+        add_groups(message, components, numbers, &mut groups);
+        let mut layout = Vec::new();
+        add_layout_tags(message, components, numbers, &mut layout);
+        messages.push(XmlMessage {
+            msg_name: msg_name.to_string(),
+            handler_name: format!("on_{}", msg_name.to_snake_case()),
+            msg_type: message.attribute("msgtype").unwrap().to_string(),
+            msg_cat: message.attribute("msgcat").unwrap().to_string(),
+            groups,
+            layout,
+            ..Default::default()
+        });
+    }
+    messages
+}
+
+fn add_layout_tags(
+    node: Node, components: &HashMap<String, Node>, numbers: &HashMap<&str, u32>,
+    tags: &mut Vec<u32>,
+) {
+    // groups are left out, count tag and all
+    for entry in node.children().filter(|n| n.is_element()) {
+        let name = entry.attribute("name").unwrap();
+        match entry.tag_name().name() {
+            "component" => add_layout_tags(components[name], components, numbers, tags),
+            "field" => tags.push(numbers[name]),
+            _ => {}
+        }
+    }
+}
+
+fn add_groups(
+    node: Node, components: &HashMap<String, Node>, numbers: &HashMap<&str, u32>,
+    groups: &mut Vec<XmlGroup>,
+) {
+    // groups of the node at any depth. a group name seen before keeps its first definition
+    for entry in node.children().filter(|n| n.is_element()) {
+        let name = entry.attribute("name").unwrap();
+        match entry.tag_name().name() {
+            "component" => add_groups(components[name], components, numbers, groups),
+            "group" => {
+                if !groups.iter().any(|group| group.group_name == name) {
+                    let mut group_fields = Vec::new();
+                    add_entry_tags(entry, components, numbers, &mut group_fields);
+                    groups.push(XmlGroup {
+                        group_name: name.to_string(),
+                        number: numbers[name],
+                        delimiter: group_fields[0],
+                        group_fields,
+                    });
+                }
+                add_groups(entry, components, numbers, groups);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_entry_tags(
+    node: Node, components: &HashMap<String, Node>, numbers: &HashMap<&str, u32>,
+    tags: &mut Vec<u32>,
+) {
+    // a nested group contributes its count tag only
+    for entry in node.children().filter(|n| n.is_element()) {
+        let name = entry.attribute("name").unwrap();
+        match entry.tag_name().name() {
+            "component" => add_entry_tags(components[name], components, numbers, tags),
+            _ => tags.push(numbers[name]),
+        }
+    }
+}
+
+pub fn module_name(name: &str) -> String {
+    // generated items each get a file, named after the item
+    const KEYWORDS: [&str; 8] = [
+        "yield", "type", "match", "ref", "mod", "use", "self", "final",
+    ];
+    let module = name.to_snake_case();
+    match KEYWORDS.contains(&module.as_str()) {
+        true => format!("r#{}", module),
+        false => module,
+    }
+}
+
+fn render_modules<T: Serialize>(
+    out_dir: &Path, name: &str, template: &str, items: &[(String, &T)],
+) -> Vec<PathBuf> {
+    // one file per item in out_dir/name, returns the files written
+    let dir = out_dir.join(name);
+    fs::create_dir_all(&dir).expect("module dir could not be created");
+    let mut handlebar = Handlebars::new();
+    // generated code is rust, not html
+    handlebar.register_escape_fn(handlebars::no_escape);
+    handlebar.register_template_string(name, template).unwrap();
+    let mut files = Vec::new();
+    for (module, item) in items {
+        let path = dir.join(format!("{}.rs", module.trim_start_matches("r#")));
+        let mut file = File::create(&path).expect("file could not be created");
+        handlebar.render_to_write(name, item, &mut file).unwrap();
+        files.push(path);
+    }
+    files
+}
+
+fn write_mod_rs(
+    out_dir: &Path, name: &str, modules: &[String], reexport: bool, extra: &str,
+) -> PathBuf {
+    let path = out_dir.join(name).join("mod.rs");
+    let mut mod_rs = String::new();
+    for module in modules {
+        match reexport {
+            true => mod_rs.push_str(&format!("mod {0};\npub use {0}::*;\n", module)),
+            false => mod_rs.push_str(&format!("pub mod {};\n", module)),
+        }
+    }
+    mod_rs.push_str(extra);
+    fs::write(&path, mod_rs).expect("mod.rs could not be written");
+    path
+}
+
+pub fn generate_fields(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) -> Vec<PathBuf> {
+    let items: Vec<_> =
+        xml_spec.fields.iter().map(|field| (module_name(&field.name), field)).collect();
+    let mut files = render_modules(out_dir, name, FIELD_STRUCT, &items);
+    let modules: Vec<String> = items.into_iter().map(|(module, _)| module).collect();
+    files.push(write_mod_rs(out_dir, name, &modules, true, ""));
+    files
+}
+
+pub fn generate_field_values(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) -> Vec<PathBuf> {
+    let items: Vec<_> = xml_spec
+        .fields
+        .iter()
+        .filter(|field| !field.values.is_empty())
+        .map(|field| (module_name(&field.name), field))
+        .collect();
+    let mut files = render_modules(out_dir, name, FIELD_ENUM, &items);
+    let modules: Vec<String> = items.into_iter().map(|(module, _)| module).collect();
+    files.push(write_mod_rs(out_dir, name, &modules, true, ""));
+    files
+}
+
+pub fn generate_messages(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) -> Vec<PathBuf> {
+    let items: Vec<_> = xml_spec
+        .messages
+        .iter()
+        .map(|message| (module_name(&message.msg_name), message))
+        .collect();
+    let mut files = render_modules(out_dir, name, MSG_STRUCT, &items);
+    let mut handlebar = Handlebars::new();
+    handlebar.register_escape_fn(handlebars::no_escape);
+    handlebar.register_template_string("cracker", MSG_CRACKER).unwrap();
+    let cracker = handlebar.render("cracker", &xml_spec).unwrap();
+    let modules: Vec<String> = items.into_iter().map(|(module, _)| module).collect();
+    files.push(write_mod_rs(out_dir, name, &modules, true, &cracker));
+    files
+}
+
+pub fn generate_groups(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) -> Vec<PathBuf> {
+    // groups of a message in a module of their own, a group name can differ between messages
+    let header = XmlMessage {
+        msg_name: "Header".to_string(),
+        groups: xml_spec.header.groups.iter().map(XmlGroup::clone).collect(),
+        ..Default::default()
+    };
+    let items: Vec<_> = std::iter::once(&header)
+        .chain(&xml_spec.messages)
+        .filter(|message| !message.groups.is_empty())
+        .map(|message| (module_name(&message.msg_name), message))
+        .collect();
+    let mut files = render_modules(out_dir, name, GROUP_STRUCT, &items);
+    let modules: Vec<String> = items.into_iter().map(|(module, _)| module).collect();
+    files.push(write_mod_rs(out_dir, name, &modules, false, ""));
+    files
+}
+
+pub fn generate_field_registry(out_dir: &Path, name: &str, xml_spec: &XmlFixSpec) -> PathBuf {
+    let mut fields: Vec<&XmlField> = xml_spec.fields.iter().collect();
+    fields.sort_by_key(|field| field.number);
+    let registry = FieldRegistry {
+        begin_string: &xml_spec.begin_string,
+        fields,
+    };
+    let path = out_dir.join(name);
+    let mut file = File::create(&path).expect("file could not be created");
     let mut handlebar = Handlebars::new();
-    handlebar.register_template_string("f_struct", FIELD_STRUCT).unwrap();
-    handlebar.render_to_write("f_struct", &xml_spec, &mut file).unwrap();
+    handlebar.register_escape_fn(handlebars::no_escape);
+    handlebar.register_template_string("registry", FIELD_REGISTRY).unwrap();
+    handlebar.render_to_write("registry", &registry, &mut file).unwrap();
+    path
 }
@@ -7,17 +7,78 @@ mod templates;
 
 use crate::code_generator::get_fix_spec;
 use code_generator::*;
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::{env, fs};
 
+const FIX_SPEC: &str = "FIX43.xml";
+// modified times of the spec and of this build script the output was generated from
+const STAMP_FILE: &str = "generated.stamp";
+// spec of the embedded-dictionary feature, relative to the crate root
+const EMBEDDED_SPEC_ENV: &str = "FIX_RS_DICTIONARY";
+
 pub fn main() {
     let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let source = root.join("resources");
     let out = PathBuf::from(env::var("OUT_DIR").unwrap());
     println!("cargo:warning={:?}", &out);
-    let fix = get_fix_spec(&source, "FIX43.xml");
-    generate_fields(&out, "fields.rs", &fix);
-    let mut mod_rs = fs::File::create(out.join("mod.rs")).expect("mod rs");
-    mod_rs.write_all(b"pub mod fields;").expect("pub mod");
+    println!("cargo:rerun-if-changed={}", source.join(FIX_SPEC).display());
+    println!("cargo:rerun-if-changed={}", root.join("build").display());
+    if env::var_os("CARGO_FEATURE_EMBEDDED_DICTIONARY").is_some() {
+        embed_dictionary(&root, &source.join(FIX_SPEC), &out);
+    }
+    let stamp = generated_stamp(&source.join(FIX_SPEC));
+    if fs::read_to_string(out.join(STAMP_FILE)).ok() == stamp {
+        return;
+    }
+    let fix = get_fix_spec(&source, FIX_SPEC);
+    for module in ["fields", "field_values", "messages", "groups"] {
+        // output of an earlier generation, single files or items since removed from the spec
+        let _ = fs::remove_file(out.join(module).with_extension("rs"));
+        let _ = fs::remove_dir_all(out.join(module));
+    }
+    let mut files = generate_fields(&out, "fields", &fix);
+    files.extend(generate_field_values(&out, "field_values", &fix));
+    files.extend(generate_messages(&out, "messages", &fix));
+    files.extend(generate_groups(&out, "groups", &fix));
+    files.push(generate_field_registry(&out, "field_registry.rs", &fix));
+    fs::write(
+        out.join("mod.rs"),
+        "pub mod field_registry;\npub mod field_values;\npub mod fields;\npub mod groups;\npub mod messages;\n",
+    )
+    .expect("pub mod");
+    format(&files);
+    if let Some(stamp) = stamp {
+        fs::write(out.join(STAMP_FILE), stamp).expect("stamp");
+    }
+}
+
+fn embed_dictionary(root: &Path, default_spec: &Path, out: &Path) {
+    println!("cargo:rerun-if-env-changed={}", EMBEDDED_SPEC_ENV);
+    let spec =
+        env::var_os(EMBEDDED_SPEC_ENV).map_or(default_spec.to_path_buf(), |spec| root.join(spec));
+    println!("cargo:rerun-if-changed={}", spec.display());
+    // a dictionary that does not parse is a build error rather than a panic at startup
+    let name = spec.file_name().and_then(|name| name.to_str()).expect("dictionary file name");
+    get_fix_spec(spec.parent().expect("dictionary dir"), name);
+    fs::copy(&spec, out.join("dictionary.xml")).expect("dictionary could not be embedded");
+}
+
+fn generated_stamp(spec: &Path) -> Option<String> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let spec_modified = modified(spec)?;
+    let script_modified = modified(&env::current_exe().ok()?)?;
+    Some(format!("{:?} {:?}", spec_modified, script_modified))
+}
+
+fn format(files: &[PathBuf]) {
+    // generated code is readable when it has to be looked at. without rustfmt it is left as is
+    let rustfmt = env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+    for chunk in files.chunks(256) {
+        let status = Command::new(&rustfmt).args(["--edition", "2021"]).args(chunk).status();
+        if !matches!(status, Ok(status) if status.success()) {
+            println!("cargo:warning=generated code could not be formatted with rustfmt");
+            return;
+        }
+    }
 }
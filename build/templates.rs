@@ -1,14 +1,13 @@
 pub const FIELD_STRUCT: &str = r#"
 use std::fmt::Display;
 
-{{#each fields}}
 #[derive(Debug)]
-pub struct {{this.name}} {
+pub struct {{name}} {
     tag: u32,
     value: String,
 } 
 
-impl {{this.name}} {
+impl {{name}} {
     pub fn new<T: Into<{{fld_type}}> + Display>(val: T) -> Self {
         Self {
             tag: {{number}},
@@ -20,54 +19,185 @@ impl {{this.name}} {
         {{number}}
     }
 }
+"#;
 
-{{/each}}
+pub const FIELD_ENUM: &str = r#"
+use crate::quickfix_errors::InvalidFieldValue;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// values of {{name}} ({{number}}), serialized as the wire value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum {{name}} {
+    {{#each values}}
+    #[serde(rename = {{this.variant_val}})]
+    {{this.enum_variant}},
+    {{/each}}
+}
+
+impl {{name}} {
+    pub fn field() -> u32 {
+        {{number}}
+    }
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            {{#each values}}
+            Self::{{this.enum_variant}} => {{this.variant_val}},
+            {{/each}}
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            {{#each values}}
+            Self::{{this.enum_variant}} => {{this.description}},
+            {{/each}}
+        }
+    }
+}
+
+impl fmt::Display for {{name}} {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.value())
+    }
+}
+
+impl FromStr for {{name}} {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            {{#each values}}
+            {{this.variant_val}} => Ok(Self::{{this.enum_variant}}),
+            {{/each}}
+            _ => Err(InvalidFieldValue::new("{{fix_type}}", s)),
+        }
+    }
+}
 "#;
 
-// {{#if values}}
-// #[allow(non_camel_case_types)]
-// #[derive(Debug)]
-// pub enum {{name}} {
-//     {{#each values}}
-//     {{this.enum_variant}},
-//     {{/each}}
-// }
-
-// impl {{name}} {
-//     fn get(&self) -> String {
-//         match self {
-//             {{#each values}}
-//             {{this.enum_variant}} => String::from("{{this.variant_val}}"),
-//             {{/each}}
-//         }
-//     }
-
-// }
-// {{/if}}
-// {{/each}}
-// "#;
-
-// const FIELD_ENUM: &'static str = r#"
-// #[derive(Debug)]
-// enum {{name}} {
-//     {{#each values}}
-//     {{this.enum_variant}},
-//     {{/each}}
-// }
-// "#;
-
-const MSG_STRUCT: &str = r#"
-#[derive(Debug, Default, Clone)]
+pub const FIELD_REGISTRY: &str = r#"
+use crate::data_dictionary::{FieldInfo, FixType};
+
+// dictionary the registry is generated from
+pub const BEGIN_STRING: &str = "{{begin_string}}";
+
+// sorted by tag
+pub static FIELDS: &[FieldInfo] = &[
+    {{#each fields}}
+    FieldInfo::new({{number}}, "{{name}}", FixType::{{fix_type_variant}}, &[
+        {{#each values}}
+        ({{this.variant_val}}, {{this.description}}),
+        {{/each}}
+    ]),
+    {{/each}}
+];
+
+pub fn field(tag: u32) -> Option<&'static FieldInfo> {
+    FIELDS.binary_search_by_key(&tag, FieldInfo::tag).ok().map(|idx| &FIELDS[idx])
+}
+
+pub fn field_by_name(name: &str) -> Option<&'static FieldInfo> {
+    FIELDS.iter().find(|info| info.name() == name)
+}
+"#;
+
+pub const MSG_STRUCT: &str = r#"
+use crate::message::{Message, TypedMessage, WireBuilder};
+
+#[derive(Debug, Clone)]
 pub struct {{msg_name}} {
-    header: Header,
-    trailer: Trailer,
-    body: FieldMap
+    message: Message,
 }
 
 impl {{msg_name}} {
-    pub fn new() -> Self {
-        let mut msg = Self::default();
-        msg.header
+    // body tags outside of repeating groups, in dictionary order
+    pub const LAYOUT: &'static [u32] = &[{{#each layout}}{{this}}, {{/each}}];
+
+    // builds the message straight into its wire format, see WireBuilder
+    pub fn builder() -> WireBuilder {
+        WireBuilder::new("{{msg_type}}", Self::LAYOUT)
     }
 }
+
+impl TypedMessage for {{msg_name}} {
+    const MSG_TYPE: &'static str = "{{msg_type}}";
+    const MSG_NAME: &'static str = "{{msg_name}}";
+
+    fn from_message(message: Message) -> Self {
+        Self { message }
+    }
+
+    fn message(&self) -> &Message {
+        &self.message
+    }
+
+    fn into_message(self) -> Message {
+        self.message
+    }
+}
+
+impl std::ops::Deref for {{msg_name}} {
+    type Target = Message;
+
+    fn deref(&self) -> &Message {
+        &self.message
+    }
+}
+"#;
+
+pub const MSG_CRACKER: &str = r#"
+use crate::message::{Message, TypedMessage};
+use crate::network::SessionMap;
+use crate::session::SessionId;
+
+// Downcasts a Message into its typed struct and calls the matching on_<message_name> method.
+// Implement the methods for the messages the application supports, the rest return false
+// meaning the message type is unsupported.
+pub trait MessageCracker {
+    fn crack(&self, session_id: &SessionId, sessions: &SessionMap, msg: Message) -> bool {
+        let msg_type = match msg.get_msg_type() {
+            Ok(msg_type) => msg_type,
+            Err(_) => return false,
+        };
+        match msg_type.as_str() {
+            {{#each messages}}
+            "{{this.msg_type}}" => self.{{this.handler_name}}(session_id, sessions, {{this.msg_name}}::from_message(msg)),
+            {{/each}}
+            _ => false,
+        }
+    }
+
+    {{#each messages}}
+    fn {{this.handler_name}}(&self, _session_id: &SessionId, _sessions: &SessionMap, _msg: {{this.msg_name}}) -> bool {
+        false
+    }
+
+    {{/each}}
+}
+"#;
+
+pub const GROUP_STRUCT: &str = r#"
+{{#each groups}}
+// {{group_name}} of {{../msg_name}}
+pub struct {{group_name}};
+
+impl {{group_name}} {
+    pub fn field() -> u32 {
+        {{number}}
+    }
+
+    pub fn delimiter() -> u32 {
+        {{delimiter}}
+    }
+
+    // tags of an instance in dictionary order, a nested group by its count tag
+    pub fn fields() -> &'static [u32] {
+        &[{{#each group_fields}}{{this}}, {{/each}}]
+    }
+}
+
+{{/each}}
 "#;
@@ -0,0 +1,155 @@
+// Market data acceptor: answers MarketDataRequests of its counterparty with a snapshot of the
+// book of each symbol asked for, then sends incremental refreshes as the prices move, until
+// the subscription is ended or the counterparty logs out. The book is made up, its prices
+// move by a cent every second.
+//
+//     cargo run --example market_data_server [port]
+use fix_rs::application::{Application, SessionContext};
+use fix_rs::field_values::{MDEntryType, MDReqRejReason, MDUpdateAction};
+use fix_rs::market_data::{snapshot, MDEntry, MarketDataSubscriptions};
+use fix_rs::message::Message;
+use fix_rs::network::{SessionMap, SocketAcceptor};
+use fix_rs::session::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CONFIG: &str = r#"
+    [Default]
+    connection_type = "acceptor"
+    begin_string = "FIX.4.3"
+    heartbeat_interval = 30
+
+    [Session]
+    sender_comp_id = "MDSERVER"
+    target_comp_id = "CLIENT"
+    socket_accept_port = {port}
+"#;
+
+const DEFAULT_PORT: u16 = 9881;
+
+// best bid and offer of every symbol, in cents
+#[derive(Debug, Default)]
+struct Book {
+    quotes: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl Book {
+    fn entries(&self, symbol: &str) -> Option<Vec<MDEntry>> {
+        let (bid, offer) = *self.quotes.lock().unwrap().get(symbol)?;
+        Some(vec![
+            MDEntry::new(MDEntryType::Bid).px(price(bid)).size(500),
+            MDEntry::new(MDEntryType::Offer).px(price(offer)).size(500),
+        ])
+    }
+
+    // moves every quote by a cent up or down, returns the changes
+    fn tick(&self, seed: u64) -> Vec<(String, Vec<MDEntry>)> {
+        let mut quotes = self.quotes.lock().unwrap();
+        quotes
+            .iter_mut()
+            .enumerate()
+            .map(|(i, (symbol, (bid, offer)))| {
+                if (seed + i as u64).is_multiple_of(2) {
+                    (*bid, *offer) = (*bid + 1, *offer + 1);
+                } else {
+                    (*bid, *offer) = (*bid - 1, *offer - 1);
+                }
+                let entries = vec![
+                    MDEntry::update(MDUpdateAction::Change, MDEntryType::Bid).px(price(*bid)),
+                    MDEntry::update(MDUpdateAction::Change, MDEntryType::Offer).px(price(*offer)),
+                ];
+                (symbol.clone(), entries)
+            })
+            .collect()
+    }
+}
+
+fn price(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+struct MarketDataServer {
+    subscriptions: Arc<MarketDataSubscriptions>,
+}
+
+impl Application for MarketDataServer {
+    fn to_app(_msg: String) {}
+
+    fn from_app(&self, ctx: &SessionContext, msg: Message) {
+        if msg.get_msg_type().ok().as_deref() != Some("V") {
+            return;
+        }
+        if let Err(e) = self.subscriptions.handle_request(ctx.session_id(), ctx.sessions(), &msg) {
+            println!("could not answer market data request of {}: {}", ctx.session_id(), e);
+        }
+    }
+
+    fn on_logout(&self, session_id: &SessionId, reason: Option<&str>) {
+        println!("{} logged out: {}", session_id, reason.unwrap_or_default());
+        self.subscriptions.remove_session(session_id);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let port = match std::env::args().nth(1) {
+        Some(port) => port.parse()?,
+        None => DEFAULT_PORT,
+    };
+    let book = Arc::new(Book::default());
+    for (symbol, bid) in [("EUR/USD", 10850), ("GBP/USD", 12710), ("USD/JPY", 14930)] {
+        book.quotes.lock().unwrap().insert(symbol.to_string(), (bid, bid + 2));
+    }
+
+    let mut subscriptions = MarketDataSubscriptions::new();
+    let subscribed_book = Arc::clone(&book);
+    subscriptions.on_subscribe(move |session_id, sessions, subscription| {
+        // all symbols are known before anything is sent
+        let books = subscription
+            .symbols
+            .iter()
+            .map(|symbol| Some((symbol, subscribed_book.entries(symbol)?)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MDReqRejReason::UnknownSymbol)?;
+        for (symbol, entries) in books {
+            let md_req_id = Some(subscription.md_req_id.as_str());
+            let full_refresh = sessions
+                .get_session(session_id)
+                .and_then(|session| snapshot(&session, md_req_id, symbol, &entries).ok());
+            if let Some(full_refresh) = full_refresh {
+                if let Err(e) = Session::sync_send_to_target(session_id, sessions, full_refresh) {
+                    println!("could not send {} to {}: {}", symbol, session_id, e);
+                }
+            }
+        }
+        Ok(())
+    });
+    let subscriptions = Arc::new(subscriptions);
+
+    let settings = Properties::from_str(&CONFIG.replace("{port}", &port.to_string()));
+    let app = MarketDataServer {
+        subscriptions: Arc::clone(&subscriptions),
+    };
+    let mut acceptor = SocketAcceptor::try_new(settings, app)?;
+    acceptor.start_accepting_connections()?;
+    println!("serving market data on {:?}", acceptor.local_addrs());
+
+    publish_ticks(&book, &subscriptions, acceptor.session_map()).await;
+    Ok(())
+}
+
+async fn publish_ticks(
+    book: &Book, subscriptions: &MarketDataSubscriptions, sessions: &SessionMap,
+) {
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+    for seed in 0.. {
+        ticks.tick().await;
+        for (symbol, entries) in book.tick(seed) {
+            for (session_id, e) in subscriptions.publish(sessions, &symbol, &entries) {
+                println!("could not send {} to {}: {}", symbol, session_id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Order entry over an initiator session: logs on to the broker, sends a NewOrderSingle and
+// follows it through the ExecutionReports until it is done, then logs out. Without a broker
+// address on the command line it trades with the simulator, started in-process.
+//
+//     cargo run --example orders_gateway [host:port]
+//
+// The engine only accepts connections, it has no initiator that owns a connection yet. This
+// one is put together from a Transport over the connection to the broker and the handlers the
+// engine's session task runs for every message: the seqnum checks, next_admin, which answers
+// TestRequests and ResendRequests and applies SequenceResets, request_resend for gaps and
+// next_app. Heartbeats go out on the session's interval. What it leaves out is what the
+// engine adds on top, e.g. reconnecting, schedules, TestRequests for a silent broker and the
+// Application callbacks.
+use fix_rs::data_dictionary::DataDictionary;
+use fix_rs::field_values::{HandlInst, OrdType, Side};
+use fix_rs::fields::*;
+use fix_rs::io::connector::connect_session;
+use fix_rs::io::{FrameReader, FrameWriter, Transport};
+use fix_rs::message::{decode_frame, encode_frame, Message, StringField};
+use fix_rs::network::SessionMap;
+use fix_rs::orders::OrderTracker;
+use fix_rs::session::*;
+use fix_rs::simulator::Simulator;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::channel;
+
+const CONFIG: &str = r#"
+    [Default]
+    connection_type = "initiator"
+    begin_string = "FIX.4.3"
+    heartbeat_interval = 30
+
+    [Session]
+    sender_comp_id = "CLIENT"
+    target_comp_id = "BROKER"
+    socket_connect_host = "{host}"
+    socket_connect_port = {port}
+"#;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let broker: SocketAddr = match std::env::args().nth(1) {
+        Some(addr) => addr.parse()?,
+        None => {
+            let dictionary = Arc::new(DataDictionary::from_xml("resources/FIX43.xml"));
+            let (addr, _) = Simulator::new(dictionary).listen("127.0.0.1:0".parse()?)?;
+            addr
+        }
+    };
+    let config = CONFIG
+        .replace("{host}", &broker.ip().to_string())
+        .replace("{port}", &broker.port().to_string());
+    let settings = Properties::from_str(&config);
+    let session_id = SessionIdBuilder::new("FIX.4.3", "CLIENT", "BROKER").build()?;
+
+    let mut session = Session::with_settings(&session_id, &settings)?;
+    let (tx, mut outgoing) = channel::<String>(session.send_queue_size());
+    session.set_responder(Some(tx));
+    let stream = connect_session(&settings, &session_id).await?;
+    let (mut reader, mut writer) = Transport::into_split(stream);
+    let writer_task = tokio::spawn(async move {
+        while let Some(raw) = outgoing.recv().await {
+            writer.write_frames(&[&encode_frame(&raw)]).await?;
+        }
+        writer.close().await
+    });
+
+    // observes the orders going out and the ExecutionReports coming in, see next_app
+    let mut tracker = OrderTracker::new();
+    tracker.on_change(|_, order| {
+        println!(
+            "order {} {:?}: {} of {} filled at {}",
+            order.cl_ord_id,
+            order.status,
+            order.cum_qty.as_deref().unwrap_or("0"),
+            order.order_qty.as_deref().unwrap_or("?"),
+            order.avg_px.as_deref().unwrap_or("-"),
+        );
+    });
+    let tracker = Arc::new(tracker);
+    session.set_order_tracker(Some(Arc::clone(&tracker)));
+    let mut heartbeats =
+        tokio::time::interval(Duration::from_secs(session.heartbeat_intrvl().into()));
+    let dd = Arc::clone(session.data_dictionary());
+    let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+
+    Session::logon(&session_id, &sessions)?;
+    loop {
+        let frame = tokio::select! {
+            frame = reader.read_frame() => frame?,
+            _ = heartbeats.tick() => {
+                let session = sessions.get_session(&session_id).unwrap();
+                if session.is_active() {
+                    let heartbeat = admin::heartbeat(&session, None)?;
+                    drop(session);
+                    Session::sync_send_to_target(&session_id, &sessions, heartbeat)?;
+                }
+                continue;
+            }
+        };
+        let msg = Message::from_str(&decode_frame(&frame), &dd)?;
+        let msg_type = msg.get_msg_type()?;
+        let received = msg.header().get_field::<u64>(MsgSeqNum::field())?;
+        // the checks the engine makes, in the same order
+        Session::verify(&msg, &sessions)?;
+        let session = sessions.get_session(&session_id).unwrap();
+        let expected = session.state().next_target_msg_seq_num();
+        let too_low = session.check_target_seq_num(&msg).err();
+        drop(session);
+        if let Some(reason) = too_low {
+            Session::terminate(&session_id, &sessions, &reason)?;
+            return Err(reason.into());
+        }
+        // past a gap, messages other than these come again with the resend
+        if received > expected && !matches!(msg_type.as_str(), "A" | "5" | "2" | "4") {
+            Session::request_resend(&session_id, &sessions, received)?;
+            continue;
+        }
+        match msg_type.as_str() {
+            // answering ours, it is not answered again
+            "5" => break,
+            admin_msg_type if dd.is_admin_msg_type(admin_msg_type) => {
+                Session::next_admin(&session_id, &sessions, &msg)?;
+                // seqnums the Logon or SequenceReset did not account for
+                Session::request_resend(&session_id, &sessions, received)?;
+                if admin_msg_type == "A" {
+                    println!("logged on to {}", broker);
+                    let session = sessions.get_session(&session_id).unwrap();
+                    let order = new_order_single(&session, "ORDER1", "IBM", 100, "125.50")?;
+                    drop(session);
+                    Session::sync_send_to_target(&session_id, &sessions, order)?;
+                }
+            }
+            _ => {
+                Session::next_app(&session_id, &sessions, &msg);
+                if msg_type == "8" && tracker.open_orders(&session_id).is_empty() {
+                    let logout =
+                        admin::logout(&sessions.get_session(&session_id).unwrap(), Some("done"));
+                    Session::sync_send_to_target(&session_id, &sessions, logout?)?;
+                }
+            }
+        }
+    }
+    // the session is the only sender, the writer task ends once it is gone
+    drop(sessions);
+    writer_task.await??;
+    println!("logged out");
+    Ok(())
+}
+
+fn new_order_single(
+    session: &Session, cl_ord_id: &str, symbol: &str, qty: u64, price: &str,
+) -> Result<Message, Box<dyn Error>> {
+    let mut order = session.new_message("D")?;
+    let transact_time = chrono::Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string();
+    let fields = [
+        (ClOrdID::field(), cl_ord_id),
+        (HandlInst::field(), HandlInst::AutomatedExecutionOrderPrivateNoBrokerIntervention.value()),
+        (Symbol::field(), symbol),
+        (Side::field(), Side::Buy.value()),
+        (TransactTime::field(), &transact_time),
+        (OrderQty::field(), &qty.to_string()),
+        (OrdType::field(), OrdType::Limit.value()),
+        (Price::field(), price),
+    ];
+    for (tag, value) in fields {
+        order.set_field(StringField::new(tag, value));
+    }
+    Ok(order)
+}
@@ -0,0 +1,290 @@
+// Runs QuickFIX style acceptance test definitions against an engine over loopback, so that the
+// session layer can be checked against the standard scenarios. A definition is a script of
+// lines, each starting with what it does:
+//
+//     iCONNECT        connect to the engine
+//     iDISCONNECT     close the connection
+//     eDISCONNECT     the engine closes the connection
+//     I8=FIX.4.3|...  send the message
+//     E8=FIX.4.3|...  the next message received is this one
+//
+// Fields are separated by SOH or '|'. A line can name the connection it is for, as in
+// I2,8=FIX.4.3|..., the first one is used otherwise. BodyLength and CheckSum of sent messages
+// are filled in, and <TIME>, <TIME+n> and <TIME-n> stand for now and n seconds from now. The
+// values of BodyLength, CheckSum and the timestamps of expected messages are not compared, and
+// fields are compared regardless of order except for repeated tags. Blank lines and lines
+// starting with # are skipped.
+use crate::io::frame::{read_frame, FrameDecoder};
+use crate::message::{decode_frame, encode_frame};
+use crate::quickfix_errors::ScriptError;
+use crate::types::{TimePrecision, UtcTimestamp};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+const SOH: char = '\u{1}';
+// tags whose value differs from run to run, only whether they are there is compared
+const UNCOMPARED_TAGS: [u32; 5] = [9, 10, 52, 60, 122];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Connect(usize),
+    Disconnect(usize),
+    ExpectDisconnect(usize),
+    Send(usize, String),
+    Expect(usize, String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AcceptanceScript {
+    // with the line of the script each step is on
+    steps: Vec<(usize, Step)>,
+}
+
+impl AcceptanceScript {
+    pub fn parse(script: &str) -> Result<Self, ScriptError> {
+        let mut steps = Vec::new();
+        for (line, text) in script.lines().enumerate().map(|(i, text)| (i + 1, text.trim())) {
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let syntax = |reason: &str| ScriptError::Syntax {
+                line,
+                reason: reason.to_string(),
+            };
+            let (kind, rest) = text.split_at(text.chars().next().map_or(0, char::len_utf8));
+            let (connection, rest) = match rest.split_once(',') {
+                Some((number, rest)) if number.chars().all(|c| c.is_ascii_digit()) => {
+                    (number.parse().map_err(|_| syntax("invalid connection number"))?, rest)
+                }
+                _ => (1, rest),
+            };
+            let step = match (kind, rest) {
+                ("i", "CONNECT") => Step::Connect(connection),
+                ("i", "DISCONNECT") => Step::Disconnect(connection),
+                ("e", "DISCONNECT") => Step::ExpectDisconnect(connection),
+                ("e", "CONNECT") => return Err(syntax("the engine under test only accepts")),
+                ("I", message) => Step::Send(connection, fields_of(message)),
+                ("E", message) => Step::Expect(connection, fields_of(message)),
+                _ => return Err(syntax(&format!("unknown step {:?}", text))),
+            };
+            steps.push((line, step));
+        }
+        Ok(Self { steps })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let script = std::fs::read_to_string(path).map_err(|e| ScriptError::Syntax {
+            line: 0,
+            reason: e.to_string(),
+        })?;
+        Self::parse(&script)
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().map(|(_, step)| step)
+    }
+
+    // runs the script against the engine listening on addr, waiting at most timeout for each
+    // message or disconnect it expects
+    pub async fn run(&self, addr: SocketAddr, timeout: Duration) -> Result<(), ScriptError> {
+        let mut connections: HashMap<usize, (TcpStream, FrameDecoder)> = HashMap::new();
+        for (line, step) in &self.steps {
+            let line = *line;
+            let io = |connection: usize| {
+                move |source: std::io::Error| ScriptError::Io {
+                    line,
+                    connection,
+                    source,
+                }
+            };
+            match step {
+                Step::Connect(connection) => {
+                    let stream = TcpStream::connect(addr).await.map_err(io(*connection))?;
+                    connections.insert(*connection, (stream, FrameDecoder::default()));
+                }
+                Step::Disconnect(connection) => {
+                    connections.remove(connection);
+                }
+                Step::Send(connection, message) => {
+                    let (stream, _) = connected(&mut connections, line, *connection)?;
+                    let message = finish(message);
+                    stream.write_all(&encode_frame(&message)).await.map_err(io(*connection))?;
+                }
+                Step::Expect(connection, expected) => {
+                    let (stream, decoder) = connected(&mut connections, line, *connection)?;
+                    let read = tokio::time::timeout(timeout, read_frame(stream, decoder)).await;
+                    let received = match read {
+                        Ok(Ok(frame)) => decode_frame(&frame),
+                        Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                            let connection = *connection;
+                            return Err(ScriptError::Disconnected { line, connection });
+                        }
+                        Ok(Err(e)) => return Err(io(*connection)(e)),
+                        Err(_) => return Err(ScriptError::Timeout { line, timeout }),
+                    };
+                    if !matches(expected, &received) {
+                        return Err(ScriptError::Mismatch {
+                            line,
+                            expected: expected.replace(SOH, "|"),
+                            received: received.replace(SOH, "|"),
+                        });
+                    }
+                }
+                Step::ExpectDisconnect(connection) => {
+                    let (stream, decoder) = connected(&mut connections, line, *connection)?;
+                    let read = tokio::time::timeout(timeout, read_frame(stream, decoder)).await;
+                    match read {
+                        Ok(Err(_)) => {
+                            connections.remove(connection);
+                        }
+                        Ok(Ok(_)) | Err(_) => {
+                            let connection = *connection;
+                            return Err(ScriptError::NotDisconnected { line, connection });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn connected(
+    connections: &mut HashMap<usize, (TcpStream, FrameDecoder)>, line: usize, connection: usize,
+) -> Result<&mut (TcpStream, FrameDecoder), ScriptError> {
+    connections.get_mut(&connection).ok_or(ScriptError::Disconnected { line, connection })
+}
+
+fn fields_of(message: &str) -> String {
+    let mut fields = match message.contains(SOH) {
+        true => message.to_string(),
+        false => message.replace('|', &SOH.to_string()),
+    };
+    if !fields.ends_with(SOH) {
+        fields.push(SOH);
+    }
+    fields
+}
+
+fn split(message: &str) -> Vec<(u32, &str)> {
+    message
+        .split_terminator(SOH)
+        .map(|field| match field.split_once('=') {
+            Some((tag, value)) => (tag.parse().unwrap_or(0), value),
+            None => (0, field),
+        })
+        .collect()
+}
+
+fn finish(message: &str) -> String {
+    // placeholders replaced, BodyLength and CheckSum as the fields make them
+    let fields: Vec<(u32, String)> = split(message)
+        .into_iter()
+        .filter(|(tag, _)| *tag != 9 && *tag != 10)
+        .map(|(tag, value)| (tag, time_placeholder(value).unwrap_or_else(|| value.to_string())))
+        .collect();
+    let Some(((_, begin_string), body)) = fields.split_first() else {
+        return message.to_string();
+    };
+    let body: String =
+        body.iter().map(|(tag, value)| format!("{}={}{}", tag, value, SOH)).collect();
+    let head = format!("8={}{}9={}{}", begin_string, SOH, body.len(), SOH);
+    let checksum = head.bytes().chain(body.bytes()).fold(0u32, |sum, b| sum + b as u32) % 256;
+    format!("{}{}10={:0>3}{}", head, body, checksum, SOH)
+}
+
+fn time_placeholder(value: &str) -> Option<String> {
+    let offset = value.strip_prefix("<TIME")?.strip_suffix('>')?;
+    let seconds: i64 = match offset {
+        "" => 0,
+        offset => offset.strip_prefix('+').unwrap_or(offset).parse().ok()?,
+    };
+    let time = Utc::now() + chrono::Duration::seconds(seconds);
+    Some(UtcTimestamp::new(time, TimePrecision::Millis).to_string())
+}
+
+fn matches(expected: &str, received: &str) -> bool {
+    let (mut expected, mut received) = (split(expected), split(received));
+    // stable, repeated tags stay in the order they came in
+    expected.sort_by_key(|(tag, _)| *tag);
+    received.sort_by_key(|(tag, _)| *tag);
+    expected.len() == received.len()
+        && expected.iter().zip(&received).all(|((tag, expected), (received_tag, received))| {
+            tag == received_tag
+                && (UNCOMPARED_TAGS.contains(tag)
+                    || expected.starts_with("<TIME")
+                    || expected == received)
+        })
+}
+
+#[cfg(test)]
+mod acceptance_tests {
+    use super::*;
+    use crate::test_utils::start_acceptor;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        heartbeat_interval = 30
+
+        [Session]
+        sender_comp_id = "ISLD"
+        target_comp_id = "TW"
+        socket_accept_port = 0
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let script = AcceptanceScript::parse(
+            "# logon\niCONNECT\nI8=FIX.4.3|35=A|34=1|49=TW|52=<TIME>|56=ISLD|98=0|108=30|\n\
+             E2,8=FIX.4.3|9=0|35=A|10=0\ni2,DISCONNECT\n",
+        )
+        .unwrap();
+        let steps: Vec<&Step> = script.steps().collect();
+        assert_eq!(steps[0], &Step::Connect(1));
+        assert_eq!(steps[2], &Step::Expect(2, "8=FIX.4.3\u{1}9=0\u{1}35=A\u{1}10=0\u{1}".into()));
+        assert_eq!(steps[3], &Step::Disconnect(2));
+        for invalid in ["iCONNECT\nxLOGON", "iCONNECT\néCONNECT"] {
+            assert!(matches!(
+                AcceptanceScript::parse(invalid),
+                Err(ScriptError::Syntax { line: 2, .. })
+            ));
+        }
+
+        let Step::Send(_, logon) = steps[1] else {
+            panic!("not a send")
+        };
+        let logon = finish(logon);
+        assert!(logon.starts_with("8=FIX.4.3\u{1}9=61\u{1}35=A\u{1}34=1\u{1}"));
+        assert!(!logon.contains("<TIME>"));
+        // field order, BodyLength, CheckSum and SendingTime do not matter
+        let expected = "8=FIX.4.3|9=0|49=TW|35=A|34=1|52=0|56=ISLD|98=0|108=30|10=0";
+        assert!(matches(&fields_of(expected), &logon));
+        assert!(!matches(&fields_of(&expected.replace("108=30", "108=60")), &logon));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_definitions() {
+        let mut definitions: Vec<_> = std::fs::read_dir("resources/acceptance")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "def"))
+            .collect();
+        definitions.sort();
+        assert!(!definitions.is_empty());
+        for path in definitions {
+            // every scenario starts with a session of its own
+            let (_acceptor, _events, addr) = start_acceptor(CONFIG);
+            let script = AcceptanceScript::from_file(&path).unwrap();
+            if let Err(e) = script.run(addr, Duration::from_secs(5)).await {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+}
@@ -1,18 +1,162 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use crate::data_dictionary::DataDictionary;
 use crate::message::*;
 use crate::network::SessionMap;
+use crate::quickfix_errors::{SendError, SessionRejectError};
 use crate::session;
 use crate::session::*;
 use dashmap::DashMap;
+use std::future::Future;
 use std::sync::Arc;
 
+// Where an inbound application message with DeliverToCompID(128) goes, see
+// Application::route_third_party
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThirdPartyRoute {
+    // to from_app as for any message received on the session
+    Local,
+    // to from_app as if received on another session
+    Deliver(SessionId),
+    // sent on over another session, from_app is not called
+    Forward(SessionId),
+}
+
 pub trait Application {
     fn to_app(msg: String);
-    fn from_app(&self, session_id: &SessionId, sessions: &SessionMap, msg: Message);
+    #[allow(clippy::wrong_self_convention)]
+    fn from_app(&self, ctx: &SessionContext, msg: Message);
+    // called when the session ends: on the counterparty's Logout with its Text(58), when the
+    // engine logs the session out or rejects its logon, or when it is found to be no longer
+    // connected. does nothing by default
+    fn on_logout(&self, session_id: &SessionId, reason: Option<&str>) {}
+    // called for an inbound Logon before it is answered. returning false rejects the logon
+    // with a Logout and the session does not become active
+    fn authenticate(
+        &self, session_id: &SessionId, username: Option<&str>, password: Option<&str>,
+    ) -> bool {
+        true
+    }
+    // called for an inbound application message with PossResend(97)=Y before from_app. it is
+    // sent again under a new seqnum, so only the application can tell from its own ids (e.g.
+    // ClOrdID) whether it already has it. returning true drops the message
+    fn is_poss_resend_duplicate(&self, session_id: &SessionId, msg: &Message) -> bool {
+        false
+    }
+    // called for an inbound application message with DeliverToCompID(128), i.e. addressed to
+    // a third party rather than to this engine. a hub forwards it to the session of the
+    // DeliverToCompID
+    fn route_third_party(&self, session_id: &SessionId, msg: &Message) -> ThirdPartyRoute {
+        ThirdPartyRoute::Local
+    }
+    // what the engine calls for an inbound application message. the message is on loan from
+    // the session's MessagePool and goes back to it once dropped, an application that does not
+    // keep messages can override this to let them be reused. by default it is handed on to
+    // from_app for good
+    #[allow(clippy::wrong_self_convention)]
+    fn from_app_pooled(&self, ctx: &SessionContext, msg: PooledMessage) {
+        self.from_app(ctx, msg.into_inner())
+    }
+}
+
+// What from_app is handed besides the message: the session it came in on, with the dictionary
+// and settings of the session, and a way to answer on it.
+//
+//     fn from_app(&self, ctx: &SessionContext, msg: Message) {
+//         let desk: Option<String> = ctx.settings().and_then(|settings| settings.get("desk"));
+//         let mut report = ctx.new_message("8").unwrap();
+//         ..
+//         if let Err(e) = ctx.send(report) { .. }
+//         // answered later from another task
+//         let sender = ctx.sender();
+//         tokio::spawn(async move { sender.send(..) });
+//     }
+#[derive(Debug, Clone, Copy)]
+pub struct SessionContext<'a> {
+    session_id: &'a SessionId,
+    sessions: &'a SessionMap,
+}
+
+impl<'a> SessionContext<'a> {
+    pub fn new(session_id: &'a SessionId, sessions: &'a SessionMap) -> Self {
+        Self {
+            session_id,
+            sessions,
+        }
+    }
+
+    pub fn session_id(&self) -> &'a SessionId {
+        self.session_id
+    }
+
+    pub fn sessions(&self) -> &'a SessionMap {
+        self.sessions
+    }
+
+    // None for a session that is not in the map (anymore)
+    pub fn data_dictionary(&self) -> Option<Arc<DataDictionary>> {
+        let sess = self.sessions.get_session(self.session_id)?;
+        Some(Arc::clone(sess.data_dictionary()))
+    }
+
+    pub fn settings(&self) -> Option<Arc<SessionSettings>> {
+        let sess = self.sessions.get_session(self.session_id)?;
+        Some(Arc::clone(sess.settings()))
+    }
+
+    pub fn new_message(&self, msg_type: &str) -> Result<Message, SessionRejectError> {
+        new_message(self.session_id, self.sessions, msg_type)
+    }
+
+    pub fn send(&self, msg: Message) -> Result<(), SendError> {
+        Session::sync_send_to_target(self.session_id, self.sessions, msg)
+    }
+
+    // for sending after from_app returned, e.g. from a task the application spawned
+    pub fn sender(&self) -> SessionSender {
+        SessionSender {
+            session_id: self.session_id.clone(),
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+fn new_message(
+    session_id: &SessionId, sessions: &SessionMap, msg_type: &str,
+) -> Result<Message, SessionRejectError> {
+    // see Session::new_message. no session for the comp ids is a CompID problem
+    let sess = sessions.get_session(session_id).ok_or_else(SessionRejectError::comp_id_err)?;
+    sess.new_message(msg_type)
+}
+
+// Sends on one session. Clones are cheap and share the sessions.
+#[derive(Debug, Clone)]
+pub struct SessionSender {
+    session_id: SessionId,
+    sessions: SessionMap,
+}
+
+impl SessionSender {
+    pub fn session_id(&self) -> &SessionId {
+        &self.session_id
+    }
+
+    pub fn new_message(&self, msg_type: &str) -> Result<Message, SessionRejectError> {
+        new_message(&self.session_id, &self.sessions, msg_type)
+    }
+
+    pub fn send(&self, msg: Message) -> Result<(), SendError> {
+        Session::sync_send_to_target(&self.session_id, &self.sessions, msg)
+    }
+
+    // resolves once msg is written to the socket, see Session::send_confirmed
+    pub fn send_confirmed(&self, msg: Message) -> impl Future<Output = Result<(), SendError>> {
+        Session::send_confirmed(&self.session_id, &self.sessions, msg)
+    }
 }
 
+#[derive(Default)]
 pub struct DefaultApplication;
 
 impl DefaultApplication {
@@ -27,7 +171,65 @@ impl Application for DefaultApplication {
         println!("to_app: {:?}", msg);
     }
 
-    fn from_app(&self, session_id: &SessionId, sessions: &SessionMap, msg: Message) {
-        Session::sync_send_to_target(session_id, sessions, test_logon());
+    fn from_app(&self, ctx: &SessionContext, msg: Message) {
+        let session_id = ctx.session_id();
+        let logon = ctx.sessions().get_session(session_id).map(|s| admin::logon(&s).unwrap());
+        if let Err(e) = ctx.send(logon.unwrap()) {
+            println!("could not send to {}: {}", session_id, e);
+        }
+    }
+
+    fn on_logout(&self, session_id: &SessionId, reason: Option<&str>) {
+        println!("on_logout: {} {}", session_id, reason.unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod application_tests {
+    use super::*;
+    use tokio::sync::mpsc::channel;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        desk = "equities"
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10117
+    "#;
+
+    #[test]
+    fn test_session_context() {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+
+        let ctx = SessionContext::new(&session_id, &sessions);
+        assert!(ctx.data_dictionary().is_some_and(|dd| dd.is_msg_type("D")));
+        let desk = ctx.settings().and_then(|settings| settings.get::<String>("desk"));
+        assert_eq!(desk.as_deref(), Some("equities"));
+        let order = ctx.new_message("D").unwrap();
+        assert_eq!(order.header().sender_comp_id(), Some("SENDER"));
+        ctx.send(order).unwrap();
+        let sender = ctx.sender();
+        std::thread::spawn(move || sender.send(sender.new_message("D").unwrap()))
+            .join()
+            .unwrap()
+            .unwrap();
+        for seq_num in ["34=1\x01", "34=2\x01"] {
+            assert!(rx.try_recv().unwrap().contains(seq_num));
+        }
+
+        let unknown = SessionIdBuilder::new("FIX.4.3", "SENDER", "OTHER").build().unwrap();
+        let ctx = SessionContext::new(&unknown, &sessions);
+        assert!(ctx.settings().is_none());
+        assert_eq!(ctx.new_message("D").unwrap_err().reject_reason(), Some(9));
+        assert!(matches!(ctx.send(Message::new()), Err(SendError::SessionNotFound(_))));
     }
 }
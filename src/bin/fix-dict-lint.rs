@@ -0,0 +1,30 @@
+// Reports the problems of dictionary xml files, exits with 1 if any file has one
+use std::{env, process};
+
+use fix_rs::dictionary_lint::lint_file;
+
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: fix-dict-lint <dictionary.xml>...");
+        process::exit(2);
+    }
+    let mut failed = false;
+    for path in paths {
+        match lint_file(&path) {
+            Ok(issues) => {
+                for issue in &issues {
+                    println!("{}: {}", path, issue);
+                }
+                failed |= !issues.is_empty();
+            }
+            Err(err) => {
+                println!("{}: {}", path, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,143 @@
+// Source of the current time for sessions and schedules. SendingTime, the times a session
+// last sent and received and the schedule windows all come from it, so that tests can move
+// time forward instead of waiting for it.
+use chrono::{DateTime, Duration, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    // resolves once the clock reaches at. clocks that follow the time of day sleep for what is
+    // left of it
+    fn sleep_until(&self, at: DateTime<Utc>) -> Sleep {
+        let left = (at - self.now()).to_std().unwrap_or_default();
+        Box::pin(tokio::time::sleep(left))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// clock that only moves when told to. clones share the time, and whoever sleeps on it wakes
+// once it is moved past their time
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<watch::Sender<DateTime<Utc>>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(watch::Sender::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.send_replace(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.send_modify(|now| *now += by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.borrow()
+    }
+
+    fn sleep_until(&self, at: DateTime<Utc>) -> Sleep {
+        let mut now = self.now.subscribe();
+        Box::pin(async move {
+            let _ = now.wait_for(|now| *now >= at).await;
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self(Arc::new(clock))
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl Clock for SharedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0.now()
+    }
+
+    fn sleep_until(&self, at: DateTime<Utc>) -> Sleep {
+        self.0.sleep_until(at)
+    }
+}
+
+impl std::ops::Deref for SharedClock {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedClock")
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let start = DateTime::parse_from_rfc3339("2024-03-05T16:59:58Z").unwrap().to_utc();
+        let clock = ManualClock::new(start);
+        let shared = SharedClock::new(clock.clone());
+        assert_eq!(shared.now(), start);
+        clock.advance(Duration::seconds(5));
+        assert_eq!(shared.now(), start + Duration::seconds(5));
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+        assert!(SharedClock::default().now() > start);
+    }
+
+    #[tokio::test]
+    async fn test_sleep_until() {
+        let start = DateTime::parse_from_rfc3339("2024-03-05T16:59:58Z").unwrap().to_utc();
+        let clock = ManualClock::new(start);
+        let mut sleep = SharedClock::new(clock.clone()).sleep_until(start + Duration::seconds(5));
+        let woke = |sleep: &mut Sleep| {
+            let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+            sleep.as_mut().poll(&mut cx).is_ready()
+        };
+        assert!(!woke(&mut sleep));
+        clock.advance(Duration::seconds(4));
+        assert!(!woke(&mut sleep));
+        clock.advance(Duration::seconds(1));
+        assert!(woke(&mut sleep));
+        let past = start - Duration::seconds(1);
+        tokio::time::timeout(std::time::Duration::from_secs(1), SystemClock.sleep_until(past))
+            .await
+            .unwrap();
+    }
+}
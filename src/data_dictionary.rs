@@ -1,9 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::iter::Iterator;
+#[cfg(feature = "embedded-dictionary")]
+use std::sync::{Arc, OnceLock};
 use std::{fmt, fs, path::Path, str::FromStr};
 
 use crate::quickfix_errors::*;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
 use getset::{CopyGetters, Getters};
 use indexmap::IndexSet;
 use roxmltree::{Document, Node};
@@ -14,7 +17,7 @@ type DResult<T> = Result<T, XmlError>;
 pub(crate) const HEADER_ID: &str = "header";
 pub(crate) const TRAILER_ID: &str = "trailer";
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FixType {
     Char,
     Boolean,
@@ -77,9 +80,38 @@ impl FromStr for FixType {
     }
 }
 
-impl std::fmt::Display for FixType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ftype = match self {
+impl FixType {
+    pub fn check_value(&self, value: &str) -> Result<(), InvalidFieldValue> {
+        // value has the format of the type, types without a format check pass
+        match self {
+            FixType::Char | FixType::Boolean => {
+                let parsed = match self {
+                    FixType::Char => value.parse::<crate::types::Char>().map(|_| ()),
+                    _ => value.parse::<crate::types::Bool>().map(|_| ()),
+                };
+                parsed.map_err(|_| InvalidFieldValue::new(self.as_str(), value))
+            }
+            FixType::Price | FixType::Qty | FixType::Amt => check_decimal(*self, value),
+            FixType::MultipleValueString => {
+                value.parse::<crate::types::MultipleValueString>().map(|_| ())
+            }
+            FixType::MonthYear => value.parse::<crate::types::MonthYear>().map(|_| ()),
+            FixType::LocalMktDate => value.parse::<crate::types::LocalMktDate>().map(|_| ()),
+            FixType::UtcDate => value.parse::<crate::types::UtcDateOnly>().map(|_| ()),
+            FixType::UtcTimeOnly => value.parse::<crate::types::UtcTimeOnly>().map(|_| ()),
+            FixType::UtcTimestamp => value.parse::<crate::types::UtcTimestamp>().map(|_| ()),
+            FixType::Float | FixType::PriceOffset | FixType::Percentage => {
+                match crate::types::is_fix_decimal(value) {
+                    true => Ok(()),
+                    false => Err(InvalidFieldValue::new(self.as_str(), value)),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
             FixType::Char => "CHAR",
             FixType::Boolean => "BOOLEAN",
             FixType::Data => "DATA",
@@ -105,8 +137,30 @@ impl std::fmt::Display for FixType {
             FixType::UtcTimeOnly => "UTCTIMEONLY",
             FixType::UtcTimestamp => "UTCTIMESTAMP",
             FixType::Unknown => "UNKNOWN",
-        };
-        write!(f, "{}", ftype)
+        }
+    }
+}
+
+impl std::fmt::Display for FixType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(feature = "decimal")]
+fn check_decimal(fix_type: FixType, value: &str) -> Result<(), InvalidFieldValue> {
+    // must be representable exactly, as that is how the typed accessors read it
+    match value.parse::<crate::types::Decimal>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(InvalidFieldValue::new(fix_type.as_str(), value)),
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+fn check_decimal(fix_type: FixType, value: &str) -> Result<(), InvalidFieldValue> {
+    match crate::types::is_fix_decimal(value) {
+        true => Ok(()),
+        false => Err(InvalidFieldValue::new(fix_type.as_str(), value)),
     }
 }
 
@@ -125,18 +179,110 @@ pub struct DataDictionary {
     category: HashMap<String, String>, // "D" -> "app"
     msg_fields: HashMap<String, HashSet<u32>>, // "D" -> <44, 54, ...>, "header" -> <..>
     msg_required_fields: HashMap<String, HashSet<u32>>,
+    msg_fields_order: HashMap<String, IndexSet<u32>>, // "D" -> [11, 1, ...] in xml order
+    // fields are the ones compiled into crate::field_registry, values are checked against it
+    field_registry: bool,
 }
 
 impl DataDictionary {
     pub fn from_xml<P: AsRef<Path>>(xml_file: P) -> Self {
-        let file_data = fs::read_to_string(xml_file).expect("xml file open/read error");
-        DataDictionary::from_str(&file_data).unwrap()
+        let path = xml_file.as_ref();
+        DataDictionary::try_from_xml(path)
+            .unwrap_or_else(|err| panic!("data dictionary {}: {}", path.display(), err))
+    }
+
+    pub fn try_from_xml<P: AsRef<Path>>(xml_file: P) -> DResult<Self> {
+        let bytes = fs::read(xml_file)?;
+        DataDictionary::from_str(&decode_xml(&bytes)?)
+    }
+
+    #[cfg(feature = "embedded-dictionary")]
+    pub fn embedded() -> Arc<Self> {
+        // parsed once, sessions share it
+        static EMBEDDED: OnceLock<Arc<DataDictionary>> = OnceLock::new();
+        let dictionary = EMBEDDED.get_or_init(|| {
+            let xml = include_bytes!(concat!(env!("OUT_DIR"), "/dictionary.xml"));
+            let dictionary = decode_xml(xml).and_then(|xml| DataDictionary::from_str(&xml));
+            Arc::new(dictionary.expect("embedded dictionary is checked at build time"))
+        });
+        Arc::clone(dictionary)
+    }
+
+    pub fn begin_string(&self) -> &str {
+        &self.begin_string
+    }
+
+    pub fn matches_field_registry(&self) -> bool {
+        // fields, types and enums are the ones compiled in, so crate::field_registry can stand in
+        // for the dictionary to look fields up
+        let registry = crate::field_registry::FIELDS;
+        self.begin_string == crate::field_registry::BEGIN_STRING
+            && self.field_type.len() == registry.len()
+            && registry.iter().all(|info| {
+                let values = self.field_values.get(&info.tag()).map_or(0, HashSet::len);
+                self.field_type.get(&info.tag()) == Some(&info.fix_type())
+                    && values == info.values().len()
+                    && info.values().iter().all(|(value, _)| {
+                        self.field_values.get(&info.tag()).is_some_and(|vals| vals.contains(*value))
+                    })
+            })
+    }
+
+    fn registry_field(&self, tag: u32) -> Option<&'static FieldInfo> {
+        self.field_registry.then(|| crate::field_registry::field(tag)).flatten()
     }
 
     pub fn get_field_type(&self, tag: u32) -> Option<&FixType> {
         self.field_type.get(&tag)
     }
 
+    pub fn check_field_value(&self, tag: u32, value: &str) -> Result<(), InvalidFieldValue> {
+        // value has the format of the field's type. tags the dictionary does not know pass
+        if let Some(info) = self.registry_field(tag) {
+            return info.fix_type().check_value(value);
+        }
+        match self.field_type.get(&tag) {
+            Some(fix_type) => fix_type.check_value(value),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_field_enum(&self, tag: u32, value: &str) -> Result<(), InvalidFieldValue> {
+        // every value of a MULTIPLEVALUESTRING field is one of the field's enums, when the
+        // dictionary lists them
+        if let Some(info) = self.registry_field(tag) {
+            return match info.fix_type() {
+                FixType::MultipleValueString => info.check_enum(value),
+                _ => Ok(()),
+            };
+        }
+        let allowed = match (self.field_type.get(&tag), self.field_values.get(&tag)) {
+            (Some(FixType::MultipleValueString), Some(allowed)) if !allowed.is_empty() => allowed,
+            _ => return Ok(()),
+        };
+        match value.split(' ').all(|v| allowed.contains(v)) {
+            true => Ok(()),
+            false => Err(InvalidFieldValue::new("MULTIPLEVALUESTRING", value)),
+        }
+    }
+
+    pub fn check_field_allowed(&self, tag: u32, value: &str) -> Result<(), InvalidFieldValue> {
+        // value is one of the field's enums, for fields with a single value that list them
+        if let Some(info) = self.registry_field(tag) {
+            return info.check_enum(value);
+        }
+        let fix_type = self.field_type.get(&tag).copied().unwrap_or(FixType::Unknown);
+        if let FixType::MultipleValueString = fix_type {
+            return self.check_field_enum(tag, value);
+        }
+        match self.field_values.get(&tag) {
+            Some(allowed) if !allowed.is_empty() && !allowed.contains(value) => {
+                Err(InvalidFieldValue::new(fix_type.as_str(), value))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn get_field_values(&self, tag: u32) -> Option<&HashSet<String>> {
         self.field_values.get(&tag)
     }
@@ -161,6 +307,28 @@ impl DataDictionary {
         self.fields_order.iter().copied().collect::<Vec<u32>>()
     }
 
+    pub fn get_msg_ordered_fields(&self, msg_type: &str) -> Vec<u32> {
+        // fields of the msg_type in the order they are defined in xml. header and trailer
+        // can be looked up with HEADER_ID and TRAILER_ID
+        self.msg_fields_order
+            .get(msg_type)
+            .map(|order| order.iter().copied().collect::<Vec<u32>>())
+            .unwrap_or_default()
+    }
+
+    pub fn is_msg_type(&self, msg_type: &str) -> bool {
+        self.category.contains_key(msg_type)
+    }
+
+    pub fn is_admin_msg_type(&self, msg_type: &str) -> bool {
+        self.category.get(msg_type).is_some_and(|cat| cat == "admin")
+    }
+
+    pub fn field_position(&self, msg_type: &str, tag: u32) -> Option<usize> {
+        // where the field is in the xml order of msg_type
+        self.msg_fields_order.get(msg_type)?.get_index_of(&tag)
+    }
+
     pub fn is_msg_field(&self, msg_type: &str, tag: u32) -> bool {
         self.msg_fields.get(msg_type).and_then(|val| val.get(&tag)).is_some()
     }
@@ -210,7 +378,7 @@ impl DataDictionary {
     }
 
     fn set_field_for(&mut self, msg_type: &str, fnum: u32, required: bool) -> DResult<()> {
-        let msg_fields = self.msg_fields.entry(msg_type.to_string()).or_insert_with(HashSet::new);
+        let msg_fields = self.msg_fields.entry(msg_type.to_string()).or_default();
         if msg_fields.contains(&fnum) {
             return Err(XmlError::DuplicateField(format!(
                 "field {} in message {}",
@@ -218,11 +386,9 @@ impl DataDictionary {
             )));
         }
         msg_fields.insert(fnum);
+        self.msg_fields_order.entry(msg_type.to_string()).or_default().insert(fnum);
         if required {
-            self.msg_required_fields
-                .entry(msg_type.to_owned())
-                .or_insert_with(HashSet::new)
-                .insert(fnum);
+            self.msg_required_fields.entry(msg_type.to_owned()).or_default().insert(fnum);
         }
         Ok(())
     }
@@ -452,18 +618,70 @@ impl FromStr for DataDictionary {
         let component_map: NodeMap = get_component_nodes_by_name(component_node)?;
 
         let header_node = lookup_node(HEADER_ID, &doc)?;
-        dd.add_xml_message(&HEADER_ID, &header_node, &component_map, &doc)?;
+        dd.add_xml_message(HEADER_ID, &header_node, &component_map, &doc)?;
 
         let trailer_node = lookup_node(TRAILER_ID, &doc)?;
-        dd.add_xml_message(&TRAILER_ID, &trailer_node, &component_map, &doc)?;
+        dd.add_xml_message(TRAILER_ID, &trailer_node, &component_map, &doc)?;
 
         let messages = lookup_node("messages", &doc)?;
         dd.add_all_xml_messages(&messages, &component_map, &doc)?;
 
+        dd.field_registry = dd.matches_field_registry();
         Ok(dd)
     }
 }
 
+// Field of the dictionary compiled in at build time, see crate::field_registry
+#[derive(Debug, CopyGetters)]
+pub struct FieldInfo {
+    #[getset(get_copy = "pub")]
+    tag: u32,
+    #[getset(get_copy = "pub")]
+    name: &'static str,
+    #[getset(get_copy = "pub")]
+    fix_type: FixType,
+    // (enum value, description) in xml order
+    #[getset(get_copy = "pub")]
+    values: &'static [(&'static str, &'static str)],
+}
+
+impl FieldInfo {
+    pub const fn new(
+        tag: u32, name: &'static str, fix_type: FixType,
+        values: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        Self {
+            tag,
+            name,
+            fix_type,
+            values,
+        }
+    }
+
+    pub fn description(&self, value: &str) -> Option<&'static str> {
+        self.values.iter().find(|(enum_value, _)| *enum_value == value).map(|(_, desc)| *desc)
+    }
+
+    pub fn check_value(&self, value: &str) -> Result<(), InvalidFieldValue> {
+        // format of the type and, for fields that list them, one of the enums
+        self.fix_type.check_value(value)?;
+        self.check_enum(value)
+    }
+
+    pub fn check_enum(&self, value: &str) -> Result<(), InvalidFieldValue> {
+        // one of the enums, every one of them for a MULTIPLEVALUESTRING
+        let allowed = |v: &str| self.values.is_empty() || self.description(v).is_some();
+        let all_allowed = match self.fix_type {
+            FixType::MultipleValueString => value.split(' ').all(allowed),
+            _ => allowed(value),
+        };
+        match all_allowed {
+            true => Ok(()),
+            false => Err(InvalidFieldValue::new(self.fix_type.as_str(), value)),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Getters, CopyGetters)]
 pub struct GroupInfo {
     #[getset(get_copy = "pub")]
@@ -472,7 +690,64 @@ pub struct GroupInfo {
     data_dictionary: DataDictionary,
 }
 
+impl GroupInfo {
+    // tags of an instance in the order the dictionary has them, the delimiter first
+    pub fn ordered_tags(&self) -> Vec<u32> {
+        self.data_dictionary.get_ordered_fields()
+    }
+
+    // tags every instance has to carry, in order
+    pub fn required_tags(&self) -> Vec<u32> {
+        let dd = &self.data_dictionary;
+        let required = |tag: &u32| dd.msg_required_fields.values().any(|tags| tags.contains(tag));
+        dd.fields_order.iter().copied().filter(required).collect()
+    }
+
+    // NumInGroup tags of the groups nested in an instance, in order
+    pub fn nested_group_tags(&self) -> Vec<u32> {
+        let dd = &self.data_dictionary;
+        let nested = |tag: &u32| dd.groups.values().any(|groups| groups.contains_key(tag));
+        dd.fields_order.iter().copied().filter(nested).collect()
+    }
+
+    pub fn nested_group(&self, tag: u32) -> Option<&GroupInfo> {
+        self.data_dictionary.groups.values().find_map(|groups| groups.get(&tag))
+    }
+}
+
 /********************* ALL XML PARSING RELATED CODE ********************************************/
+pub(crate) fn decode_xml(bytes: &[u8]) -> DResult<String> {
+    // BOM decides the encoding, then the encoding of the xml declaration. files that are not
+    // UTF-8 and do not declare an encoding are taken as windows-1252, as ISO-8859-1 is read
+    let (encoding, body) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        None => match declared_encoding(bytes) {
+            Some(label) => {
+                let encoding = Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| XmlError::UnsupportedEncoding(label.to_ascii_uppercase()))?;
+                (encoding, bytes)
+            }
+            None if std::str::from_utf8(bytes).is_ok() => (UTF_8, bytes),
+            None => (WINDOWS_1252, bytes),
+        },
+    };
+    let (decoded, had_errors) = encoding.decode_without_bom_handling(body);
+    if had_errors {
+        return Err(XmlError::InvalidEncoding(encoding.name()));
+    }
+    Ok(decoded.into_owned())
+}
+
+fn declared_encoding(bytes: &[u8]) -> Option<&str> {
+    // encoding="..." of <?xml ... ?>, read as ASCII which every supported encoding starts with
+    let declaration = bytes.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&declaration[..end]).ok()?;
+    let value = declaration.split("encoding").nth(1)?.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    value[1..].split(quote).next()
+}
+
 fn get_attribute<'a>(attr: &str, node: &Node<'a, '_>) -> DResult<&'a str> {
     let requested = match node.attribute(attr) {
         Some(atr) => {
@@ -861,6 +1136,33 @@ mod dictionary_tests {
         }
     }
 
+    #[test]
+    fn test_check_field_value() {
+        let mut dict = DataDictionary::default();
+        let fields = lookup_node("fields", &DOC).unwrap();
+        dict.add_fields_and_values(fields).unwrap();
+        // 44 Price, 38 Qty, 99 PriceOffset (StopPx is PRICE), 58 Text
+        assert!(dict.check_field_value(44, "101.25").is_ok());
+        assert!(dict.check_field_value(38, "100").is_ok());
+        assert!(dict.check_field_value(44, "1e2").is_err());
+        assert!(dict.check_field_value(38, "one").is_err());
+        assert!(dict.check_field_value(58, "1e2").is_ok());
+        assert!(dict.check_field_value(99999, "anything").is_ok());
+        // 18 ExecInst
+        assert!(dict.check_field_value(18, "1 G").is_ok());
+        assert!(dict.check_field_value(18, "1  G").is_err());
+        assert!(dict.check_field_enum(18, "1 G").is_ok());
+        assert!(dict.check_field_enum(18, "1 Z9").is_err());
+        assert!(dict.check_field_enum(58, "Z9").is_ok());
+        // 200 MaturityMonthYear, 75 TradeDate, 52 SendingTime
+        assert!(dict.check_field_value(200, "202212w2").is_ok());
+        assert!(dict.check_field_value(200, "2022-12").is_err());
+        assert!(dict.check_field_value(75, "20221015").is_ok());
+        assert!(dict.check_field_value(75, "15/10/2022").is_err());
+        assert!(dict.check_field_value(52, "20221015-09:30:00.123").is_ok());
+        assert!(dict.check_field_value(52, "20221015 09:30").is_err());
+    }
+
     #[test]
     fn test_field_values() {
         // testing against actual xml file
@@ -1130,6 +1432,39 @@ mod dictionary_tests {
         assert_msg("6", &dd, &[101, 102, 1, 2, 11, 12], Some(&[101, 1]));
     }
 
+    #[test]
+    fn test_msg_field_order() {
+        // fields of a message are ordered as they appear in xml, components expanded in place
+        let msg = r#"
+        <messages>
+            <message name="MsgWithCompHavingFields" msgtype="6" msgcat="app">
+                <field name="mfield2" required="N"/>
+                <component name="CompWithOnlyFields" required="Y"/>
+                <field name="mfield1" required="Y"/>
+            </message>
+        </messages>
+        "#;
+        let component = r#"
+        <components>
+            <component name="CompWithOnlyFields">
+                <field name="cfield2" required="N"/>
+                <field name="cfield1" required="Y"/>
+            </component>
+        </components>
+        "#;
+        let dd = get_dd_with_fields_and_messages(FIELDS, msg, component).unwrap();
+        assert_eq!(dd.get_msg_ordered_fields("6"), vec![102, 2, 1, 101]);
+        assert!(dd.get_msg_ordered_fields("7").is_empty());
+        assert!(dd.is_msg_type("6"));
+        assert!(!dd.is_msg_type("7"));
+
+        // header from actual xml starts with 8, 9, 35 and trailer ends with 10
+        let dd = DataDictionary::from_str(&XML).unwrap();
+        assert_eq!(dd.begin_string(), "FIX.4.3");
+        assert_eq!(dd.get_msg_ordered_fields(HEADER_ID)[..3], [8, 9, 35]);
+        assert_eq!(dd.get_msg_ordered_fields(TRAILER_ID), vec![93, 89, 10]);
+    }
+
     #[test]
     fn test_msg_with_groups() {
         // 2 groups, one is required, one is not
@@ -1477,9 +1812,9 @@ mod dictionary_tests {
         let group6_info = dd.get_msg_group("E", 96).unwrap();
         let grp6_dd = group6_info.data_dictionary();
         // verify group7
-        assert_group("E", 97, &grp6_dd, &[41], Some(&[41]), 41, &[41]);
+        assert_group("E", 97, grp6_dd, &[41], Some(&[41]), 41, &[41]);
         // verify group3
-        assert_group("E", 93, &grp6_dd, &[31, 32], None, 31, &[31, 32]);
+        assert_group("E", 93, grp6_dd, &[31, 32], None, 31, &[31, 32]);
     }
 
     #[test]
@@ -1508,9 +1843,9 @@ mod dictionary_tests {
         let group6_info = dd.get_msg_group("E", 96).unwrap();
         let grp6_dd = group6_info.data_dictionary();
         // verify group7
-        assert_group("E", 97, &grp6_dd, &[41], Some(&[41]), 41, &[41]);
+        assert_group("E", 97, grp6_dd, &[41], Some(&[41]), 41, &[41]);
         // verify group3
-        assert_group("E", 93, &grp6_dd, &[31, 32], None, 31, &[31, 32]);
+        assert_group("E", 93, grp6_dd, &[31, 32], None, 31, &[31, 32]);
     }
 
     #[test]
@@ -1582,14 +1917,155 @@ mod dictionary_tests {
         let group6_info = dd.get_msg_group("E", 96).unwrap();
         let group6_dd = group6_info.data_dictionary();
         //verify group2 (group of the comp "CompWithOnlyReqGroup")
-        assert_group("E", 92, &group6_dd, &[21, 22], Some(&[22]), 21, &[21, 22]);
+        assert_group("E", 92, group6_dd, &[21, 22], Some(&[22]), 21, &[21, 22]);
         // verify group7 (subgroup of group6)
-        assert_group("E", 97, &group6_dd, &[41, 1, 2, 91], None, 41, &[41, 1, 2, 91]);
+        assert_group("E", 97, group6_dd, &[41, 1, 2, 91], None, 41, &[41, 1, 2, 91]);
         let group7_info = group6_dd.get_msg_group("E", 97).unwrap();
         let group7_dd = group7_info.data_dictionary();
         // verify group1 (group of CompWithFieldsAndNonReqGroup)
-        assert_group("E", 91, &group7_dd, &[11, 12], None, 11, &[11, 12]);
+        assert_group("E", 91, group7_dd, &[11, 12], None, 11, &[11, 12]);
         // verify group3
-        assert_group("E", 93, &group6_dd, &[31, 32], Some(&[31]), 31, &[31, 32]);
+        assert_group("E", 93, group6_dd, &[31, 32], Some(&[31]), 31, &[31, 32]);
+
+        // the same through GroupInfo
+        assert_eq!(group6_info.ordered_tags(), [102, 92, 97, 93]);
+        assert_eq!(group6_info.required_tags(), [102, 92, 93]);
+        assert_eq!(group6_info.nested_group_tags(), [92, 97, 93]);
+        let group7_info = group6_info.nested_group(97).unwrap();
+        assert_eq!(group7_info.nested_group_tags(), [91]);
+        assert_eq!(group7_info.nested_group(91).unwrap().ordered_tags(), [11, 12]);
+        assert!(group6_info.nested_group(102).is_none());
+    }
+
+    fn tiny_xml_with_description(description: &str) -> String {
+        let xml = fs::read_to_string("resources/FIX43-tiny.xml").unwrap();
+        xml.replacen("HEARTBEAT", description, 1)
+    }
+
+    fn load_bytes(bytes: &[u8]) -> DResult<DataDictionary> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dictionary.xml");
+        fs::write(&path, bytes).unwrap();
+        DataDictionary::try_from_xml(&path)
+    }
+
+    #[test]
+    fn test_generated_groups() {
+        // group structs are generated from the same xml
+        use crate::groups::{header, new_order_list, new_order_single};
+        let dd = DataDictionary::from_str(&XML).unwrap();
+        let no_allocs = dd.get_msg_group("D", new_order_single::NoAllocs::field()).unwrap();
+        assert_eq!(no_allocs.delimiter(), new_order_single::NoAllocs::delimiter());
+        assert_eq!(
+            no_allocs.data_dictionary().get_ordered_fields(),
+            new_order_single::NoAllocs::fields()
+        );
+        // nested group, by its count tag in the parent
+        let no_orders = dd.get_msg_group("E", new_order_list::NoOrders::field()).unwrap();
+        assert_eq!(no_orders.delimiter(), 11);
+        assert!(new_order_list::NoOrders::fields().contains(&new_order_list::NoAllocs::field()));
+        assert_eq!(header::NoHops::fields(), &[628, 629, 630]);
+    }
+
+    #[test]
+    fn test_field_registry() {
+        use crate::field_registry;
+        let side = field_registry::field(54).unwrap();
+        assert_eq!((side.name(), side.fix_type()), ("Side", FixType::Char));
+        assert_eq!(side.description("2"), Some("SELL"));
+        assert!(side.check_value("1").is_ok());
+        assert!(side.check_value("Z").is_err());
+        assert!(side.check_value("12").is_err());
+        let exec_inst = field_registry::field_by_name("ExecInst").unwrap();
+        assert!(exec_inst.check_value("1 2").is_ok());
+        assert!(exec_inst.check_value("1 ?").is_err());
+        assert!(field_registry::field(0).is_none());
+        assert!(field_registry::FIELDS.windows(2).all(|pair| pair[0].tag() < pair[1].tag()));
+
+        let dictionary = DataDictionary::from_str(&XML).unwrap();
+        assert!(dictionary.matches_field_registry());
+        // values are checked against the registry
+        assert!(dictionary.field_registry);
+        assert!(dictionary.check_field_value(44, "1e2").is_err());
+        assert!(dictionary.check_field_allowed(54, "Z").is_err());
+        assert!(dictionary.check_field_enum(18, "1 Z9").is_err());
+        assert!(dictionary.check_field_enum(54, "Z").is_ok());
+        let tiny = DataDictionary::from_xml("resources/FIX43-tiny.xml");
+        assert!(!tiny.matches_field_registry() && !tiny.field_registry);
+    }
+
+    #[cfg(feature = "embedded-dictionary")]
+    #[test]
+    fn test_embedded_dictionary() {
+        let dictionary = DataDictionary::embedded();
+        assert!(Arc::ptr_eq(&dictionary, &DataDictionary::embedded()));
+        if std::env::var_os("FIX_RS_DICTIONARY").is_none() {
+            assert!(dictionary.matches_field_registry());
+        }
+    }
+
+    #[test]
+    fn test_xml_encodings() {
+        let xml = tiny_xml_with_description("CAFÉ “HEARTBEAT”");
+        // UTF-8 with BOM
+        let with_bom = [b"\xef\xbb\xbf".as_slice(), xml.as_bytes()].concat();
+        assert_eq!(load_bytes(&with_bom).unwrap().begin_string(), "FIX.4.3");
+        // UTF-16 with BOM
+        let utf16le: Vec<u8> = xml.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let utf16be: Vec<u8> = xml.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert!(load_bytes(&[b"\xff\xfe".as_slice(), &utf16le].concat()).is_ok());
+        assert!(load_bytes(&[b"\xfe\xff".as_slice(), &utf16be].concat()).is_ok());
+
+        // Latin-1 description, declared or not
+        let xml = tiny_xml_with_description("CAF\u{c9}");
+        let latin1: Vec<u8> = xml.chars().map(|c| c as u8).collect();
+        assert!(load_bytes(&latin1).is_ok());
+        let declared = [
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n".as_slice(),
+            &latin1,
+        ]
+        .concat();
+        assert!(load_bytes(&declared).is_ok());
+        // quotes of windows-1252 are control characters in ISO-8859-1
+        let cp1252: Vec<u8> =
+            tiny_xml_with_description("\u{93}CAF\u{c9}\u{94}").chars().map(|c| c as u8).collect();
+        let declared = [
+            b"<?xml version='1.0' encoding='windows-1252'?>\n".as_slice(),
+            &cp1252,
+        ]
+        .concat();
+        assert!(load_bytes(&declared).is_ok());
+        // any encoding encoding_rs has a label for
+        let xml = tiny_xml_with_description("こんにちは");
+        let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode(&xml);
+        let declared = [
+            b"<?xml version='1.0' encoding='Shift_JIS'?>\n".as_slice(),
+            &shift_jis,
+        ]
+        .concat();
+        assert!(load_bytes(&declared).is_ok());
+    }
+
+    #[test]
+    fn test_xml_read_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = DataDictionary::try_from_xml(dir.path().join("missing.xml"));
+        assert_matches!(missing, Err(XmlError::DocumentNotRead(_)));
+
+        let latin1: Vec<u8> =
+            tiny_xml_with_description("CAF\u{c9}").chars().map(|c| c as u8).collect();
+        let declared = [
+            b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".as_slice(),
+            &latin1,
+        ]
+        .concat();
+        assert_matches!(load_bytes(&declared), Err(XmlError::InvalidEncoding("UTF-8")));
+        let declared = [
+            b"<?xml version=\"1.0\" encoding=\"IBM037\"?>\n".as_slice(),
+            &latin1,
+        ]
+        .concat();
+        assert_matches!(load_bytes(&declared), Err(XmlError::UnsupportedEncoding(encoding)) if encoding == "IBM037");
+        assert_matches!(load_bytes(b"\xff\xfe<"), Err(XmlError::InvalidEncoding("UTF-16LE")));
     }
 }
@@ -0,0 +1,241 @@
+// Checks of a dictionary xml that DataDictionary either does not make or only makes at the first
+// problem, so a custom dictionary can be verified as a whole before it is deployed
+use std::collections::{HashMap, HashSet};
+use std::{fmt, fs, path::Path};
+
+use crate::data_dictionary::decode_xml;
+use crate::quickfix_errors::XmlError;
+use roxmltree::{Document, Node};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintIssue {
+    // field or group referenced by name that is not in <fields>
+    UndefinedField { location: String, name: String },
+    UndefinedComponent { location: String, name: String },
+    DuplicateEnum { field: String, value: String },
+    // group whose first entry is not a field, so its instances can not be told apart
+    GroupWithoutDelimiter { location: String, name: String },
+    UnusedComponent(String),
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::UndefinedField { location, name } => {
+                write!(f, "{}: field {} is not defined", location, name)
+            }
+            LintIssue::UndefinedComponent { location, name } => {
+                write!(f, "{}: component {} is not defined", location, name)
+            }
+            LintIssue::DuplicateEnum { field, value } => {
+                write!(f, "field {}: duplicate enum value {}", field, value)
+            }
+            LintIssue::GroupWithoutDelimiter { location, name } => {
+                write!(f, "{}: group {} does not start with a field", location, name)
+            }
+            LintIssue::UnusedComponent(name) => write!(f, "component {} is never used", name),
+        }
+    }
+}
+
+pub fn lint_file<P: AsRef<Path>>(xml_file: P) -> Result<Vec<LintIssue>, XmlError> {
+    let bytes = fs::read(xml_file)?;
+    lint_xml(&decode_xml(&bytes)?)
+}
+
+pub fn lint_xml(xml: &str) -> Result<Vec<LintIssue>, XmlError> {
+    // only a document that is not xml at all is an error, everything else is reported
+    let doc = Document::parse(xml)?;
+    let mut linter = Linter::new(&doc);
+    linter.check_enums(&doc);
+    for section in doc.root_element().children().filter(Node::is_element) {
+        match section.tag_name().name() {
+            "header" | "trailer" => linter.check_entries(section, section.tag_name().name()),
+            "messages" => {
+                for message in section.children().filter(|n| n.has_tag_name("message")) {
+                    let location = format!("message {}", message.attribute("name").unwrap_or("?"));
+                    linter.check_entries(message, &location);
+                }
+            }
+            _ => {}
+        }
+    }
+    // components are checked once each, whether they are used or not
+    let mut components: Vec<_> = linter.components.iter().map(|(k, v)| (*k, *v)).collect();
+    components.sort_by_key(|(name, _)| *name);
+    for (name, component) in components {
+        linter.check_entries(component, &format!("component {}", name));
+        if !linter.used_components.contains(name) {
+            linter.issues.push(LintIssue::UnusedComponent(name.to_string()));
+        }
+    }
+    Ok(linter.issues)
+}
+
+struct Linter<'a, 'i> {
+    fields: HashSet<&'a str>,
+    components: HashMap<&'a str, Node<'a, 'i>>,
+    used_components: HashSet<&'a str>,
+    issues: Vec<LintIssue>,
+}
+
+impl<'a, 'i> Linter<'a, 'i> {
+    fn new(doc: &'a Document<'i>) -> Self {
+        let section = |name: &str| {
+            doc.root_element().children().find(|n| n.tag_name().name().eq_ignore_ascii_case(name))
+        };
+        let fields = section("fields")
+            .into_iter()
+            .flat_map(|fields| fields.children())
+            .filter_map(|field| field.attribute("name"))
+            .collect();
+        let components = section("components")
+            .into_iter()
+            .flat_map(|components| components.children())
+            .filter_map(|component| component.attribute("name").map(|name| (name, component)))
+            .collect();
+        Self {
+            fields,
+            components,
+            used_components: HashSet::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    fn check_enums(&mut self, doc: &'a Document<'i>) {
+        for field in
+            doc.descendants().filter(|n| n.has_tag_name("field") && n.has_attribute("number"))
+        {
+            let mut values = HashSet::new();
+            for value in field.children().filter_map(|n| n.attribute("enum")) {
+                if !values.insert(value) {
+                    self.issues.push(LintIssue::DuplicateEnum {
+                        field: field.attribute("name").unwrap_or("?").to_string(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_entries(&mut self, node: Node<'a, 'i>, location: &str) {
+        for entry in node.children().filter(Node::is_element) {
+            let name = entry.attribute("name").unwrap_or_default();
+            match entry.tag_name().name() {
+                "field" => self.check_field(name, location),
+                "component" => match self.components.contains_key(name) {
+                    true => {
+                        self.used_components.insert(name);
+                    }
+                    false => self.issues.push(LintIssue::UndefinedComponent {
+                        location: location.to_string(),
+                        name: name.to_string(),
+                    }),
+                },
+                "group" => {
+                    self.check_field(name, location);
+                    if !self.starts_with_field(entry, &mut HashSet::new()) {
+                        self.issues.push(LintIssue::GroupWithoutDelimiter {
+                            location: location.to_string(),
+                            name: name.to_string(),
+                        });
+                    }
+                    self.check_entries(entry, &format!("{} group {}", location, name));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_field(&mut self, name: &str, location: &str) {
+        if !self.fields.contains(name) {
+            self.issues.push(LintIssue::UndefinedField {
+                location: location.to_string(),
+                name: name.to_string(),
+            });
+        }
+    }
+
+    fn starts_with_field(&self, node: Node<'a, 'i>, seen: &mut HashSet<&'a str>) -> bool {
+        // the first entry is the delimiter, a component delimits with its own first entry
+        let first = match node.children().find(Node::is_element) {
+            Some(first) => first,
+            None => return false,
+        };
+        match (first.tag_name().name(), first.attribute("name")) {
+            ("field", _) => true,
+            ("component", Some(name)) if seen.insert(name) => self
+                .components
+                .get(name)
+                .is_some_and(|component| self.starts_with_field(*component, seen)),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_clean_dictionaries() {
+        assert_eq!(lint_file("resources/FIX43.xml").unwrap(), vec![]);
+        assert_eq!(lint_file("resources/FIX43-tiny.xml").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_lint_issues() {
+        let xml = r#"
+        <fix type="FIX" major="4" minor="3" servicepack="0">
+            <header><field name="BeginString" required="Y"/></header>
+            <trailer><field name="CheckSum" required="Y"/></trailer>
+            <messages>
+                <message name="Order" msgtype="D" msgcat="app">
+                    <field name="Side" required="Y"/>
+                    <field name="Missing" required="N"/>
+                    <component name="NoSuchComp" required="N"/>
+                    <group name="NoLegs" required="N">
+                        <group name="NoNested" required="N">
+                            <field name="Side" required="N"/>
+                        </group>
+                    </group>
+                    <group name="NoParties" required="N">
+                        <component name="Party" required="N"/>
+                    </group>
+                </message>
+            </messages>
+            <components>
+                <component name="Party"><field name="Side" required="N"/></component>
+                <component name="Unused"><field name="Side" required="N"/></component>
+            </components>
+            <fields>
+                <field number="8" name="BeginString" type="STRING"/>
+                <field number="10" name="CheckSum" type="STRING"/>
+                <field number="54" name="Side" type="CHAR">
+                    <value enum="1" description="BUY"/>
+                    <value enum="1" description="SELL"/>
+                </field>
+                <field number="555" name="NoLegs" type="NUMINGROUP"/>
+                <field number="453" name="NoParties" type="NUMINGROUP"/>
+            </fields>
+        </fix>
+        "#;
+        let issues = lint_xml(xml).unwrap();
+        let expected = [
+            "field Side: duplicate enum value 1",
+            "message Order: field Missing is not defined",
+            "message Order: component NoSuchComp is not defined",
+            "message Order: group NoLegs does not start with a field",
+            "message Order group NoLegs: field NoNested is not defined",
+            "component Unused is never used",
+        ];
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(issues, expected);
+    }
+
+    #[test]
+    fn test_lint_not_xml() {
+        assert!(matches!(lint_xml("<fix></header>"), Err(XmlError::DocumentNotParsed(_))));
+        assert!(matches!(lint_file("resources/missing.xml"), Err(XmlError::DocumentNotRead(_))));
+    }
+}
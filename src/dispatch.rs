@@ -0,0 +1,202 @@
+use crate::data_dictionary::DataDictionary;
+use crate::message::{MessagePool, ParseOptions, PooledMessage};
+use crate::quickfix_errors::SessionRejectError;
+use crate::session::{AppDispatch, SessionId};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use tokio::sync::mpsc::{channel as tio_channel, Sender as TioSender};
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce() + Send>;
+// messages waiting for a validation thread before the sessions handing them over wait
+const VALIDATION_QUEUE_SIZE: usize = 256;
+// messages waiting for an application thread before the sessions handing them over wait
+const APP_QUEUE_SIZE: usize = 1024;
+
+// Runs a session's from_app calls one after the other, in the order they are handed over.
+// Threads stop once every lane feeding them is dropped.
+#[derive(Clone, Default)]
+pub(crate) enum AppLane {
+    // right away, on the caller's task
+    #[default]
+    Inline,
+    Queue(TioSender<Job>),
+}
+
+impl AppLane {
+    pub(crate) async fn run(&self, job: impl FnOnce() + Send + 'static) {
+        match self {
+            AppLane::Inline => job(),
+            AppLane::Queue(queue) => {
+                // a full queue holds up the session until its handler catches up. the thread
+                // outlives handlers that panic, it is only gone once the engine is
+                if queue.send(Box::new(job)).await.is_err() {
+                    println!("application thread has stopped, message not delivered");
+                }
+            }
+        }
+    }
+}
+
+// Threads shared by the sessions dispatching to the pool. A session always lands on the same
+// thread, so its messages still reach the application in order.
+pub(crate) struct AppPool {
+    threads: Vec<TioSender<Job>>,
+}
+
+impl AppPool {
+    pub(crate) fn new(size: usize) -> std::io::Result<Self> {
+        let threads = (0..size.max(1))
+            .map(|i| spawn_thread(format!("fix-app-{}", i), APP_QUEUE_SIZE))
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { threads })
+    }
+
+    pub(crate) fn lane(&self, session_id: &SessionId) -> AppLane {
+        AppLane::Queue(self.threads[thread_of(session_id, self.threads.len())].clone())
+    }
+}
+
+// Threads parsing and validating inbound messages off the session tasks, so that sessions
+// with heavy messages do not hold up the runtime threads the other sessions run on. A session
+// always lands on the same thread and waits for each message before handing over the next, so
+// its messages are still handled in order.
+#[derive(Clone)]
+pub(crate) struct ValidationPool {
+    threads: Arc<Vec<TioSender<Job>>>,
+}
+
+impl ValidationPool {
+    pub(crate) fn new(size: usize) -> std::io::Result<Self> {
+        let threads = (0..size.max(1))
+            .map(|i| spawn_thread(format!("fix-validation-{}", i), VALIDATION_QUEUE_SIZE))
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self {
+            threads: Arc::new(threads),
+        })
+    }
+
+    pub(crate) async fn parse(
+        &self, session_id: &SessionId, raw: String, dd: Arc<DataDictionary>, pool: MessagePool,
+        options: ParseOptions,
+    ) -> Result<PooledMessage, SessionRejectError> {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(pool.parse(&raw, &dd, options));
+        });
+        let thread = &self.threads[thread_of(session_id, self.threads.len())];
+        // a parse that panics, or a thread that is gone, leaves the message unparsed. it is
+        // rejected like one the parser turns down
+        if thread.send(job).await.is_err() {
+            return Err(SessionRejectError::invalid_tag_err());
+        }
+        rx.await.unwrap_or_else(|_| Err(SessionRejectError::invalid_tag_err()))
+    }
+}
+
+fn thread_of(session_id: &SessionId, threads: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    session_id.hash(&mut hasher);
+    hasher.finish() as usize % threads
+}
+
+// lanes of the sessions, the pool is only started when one of them uses it
+pub(crate) fn app_lanes<'a>(
+    dispatch: impl IntoIterator<Item = (&'a SessionId, AppDispatch)>, pool_size: usize,
+) -> std::io::Result<Vec<(SessionId, AppLane)>> {
+    let mut pool: Option<AppPool> = None;
+    dispatch
+        .into_iter()
+        .map(|(session_id, dispatch)| {
+            let lane = match dispatch {
+                AppDispatch::Inline => AppLane::Inline,
+                AppDispatch::Worker => {
+                    AppLane::Queue(spawn_thread(format!("fix-app-{}", session_id), APP_QUEUE_SIZE)?)
+                }
+                AppDispatch::Pool => match &pool {
+                    Some(pool) => pool.lane(session_id),
+                    None => pool.insert(AppPool::new(pool_size)?).lane(session_id),
+                },
+            };
+            Ok((session_id.clone(), lane))
+        })
+        .collect()
+}
+
+pub(crate) fn default_pool_size() -> usize {
+    thread::available_parallelism().map_or(4, usize::from)
+}
+
+fn spawn_thread(name: String, queue_size: usize) -> std::io::Result<TioSender<Job>> {
+    // a job that panics is reported and the thread goes on with the next one
+    let (tx, mut rx) = tio_channel::<Job>(queue_size);
+    thread::Builder::new().name(name).spawn(move || {
+        while let Some(job) = rx.blocking_recv() {
+            if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                let name = thread::current().name().unwrap_or_default().to_string();
+                println!("job panicked on {}, going on with the next one", name);
+            }
+        }
+    })?;
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod dispatch_tests {
+    use super::*;
+    use crate::session::SessionIdBuilder;
+    use std::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn test_pool_keeps_session_order() {
+        let sessions: Vec<SessionId> = (0..4)
+            .map(|i| {
+                SessionIdBuilder::new("FIX.4.3", "SENDER", &format!("T{}", i)).build().unwrap()
+            })
+            .collect();
+        let lanes = app_lanes(sessions.iter().map(|sid| (sid, AppDispatch::Pool)), 2).unwrap();
+        let (tx, rx) = channel();
+        for n in 0..50 {
+            for (sid, lane) in &lanes {
+                let (tx, sid) = (tx.clone(), sid.clone());
+                lane.run(move || tx.send((sid, n)).unwrap()).await;
+            }
+        }
+        drop(tx);
+        drop(lanes);
+        let delivered: Vec<(SessionId, i32)> = rx.iter().collect();
+        assert_eq!(delivered.len(), 200);
+        for sid in &sessions {
+            let seen: Vec<i32> =
+                delivered.iter().filter(|(s, _)| s == sid).map(|(_, n)| *n).collect();
+            assert_eq!(seen, (0..50).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lane_outlives_panic() {
+        let sid = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let lanes = app_lanes([(&sid, AppDispatch::Worker)], 1).unwrap();
+        let (tx, rx) = channel();
+        lanes[0].1.run(|| panic!("handler failed")).await;
+        lanes[0].1.run(move || tx.send("delivered").unwrap()).await;
+        assert_eq!(rx.recv_timeout(std::time::Duration::from_secs(5)), Ok("delivered"));
+    }
+
+    #[tokio::test]
+    async fn test_unparsed_is_rejected() {
+        let sid = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let (tx, rx) = tio_channel::<Job>(1);
+        drop(rx);
+        let pool = ValidationPool {
+            threads: Arc::new(vec![tx]),
+        };
+        let dd = Arc::new(DataDictionary::from_xml("resources/FIX43.xml"));
+        let raw = "8=FIX.4.3\x019=5\x0135=0\x0110=161\x01".to_string();
+        let parsed = pool.parse(&sid, raw, dd, MessagePool::new(1), ParseOptions::default()).await;
+        assert_eq!(parsed.err().and_then(|e| e.reject_reason()), Some(0));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::session::SessionId;
+use chrono::NaiveDateTime;
+use std::net::SocketAddr;
+use tokio::sync::broadcast;
+
+// events are dropped for subscribers lagging behind by more than this
+pub const EVENT_BUS_CAPACITY: usize = 256;
+
+// Lifecycle events of the engine, for monitoring. Unlike the Application callbacks nothing
+// waits on them, they are published whether anyone listens or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineEvent {
+    // session is set up and its acceptor is about to listen
+    SessionCreated(SessionId),
+    // Logon from the counterparty, before it is authenticated
+    LogonReceived {
+        session_id: SessionId,
+        source: SocketAddr,
+    },
+    // connection is gone. session_id is the session that logged on over it, if any
+    Disconnected {
+        peer: SocketAddr,
+        session_id: Option<SessionId>,
+    },
+    // MsgSeqNum higher than the one expected from the counterparty
+    SeqGapDetected {
+        session_id: SessionId,
+        expected: u64,
+        received: u64,
+    },
+    // ResendRequest of the counterparty was answered. end of 0 means everything sent
+    ResendCompleted {
+        session_id: SessionId,
+        begin: u64,
+        end: u64,
+    },
+    // session's schedule ended, an active session is logged out
+    ScheduleWindowClosed(SessionId),
+    // Logon refused for coming in outside of the schedule. local_time is now in the zone of
+    // the schedule, which is described like Sun 17:00:00-Fri 17:00:00 America/New_York
+    LogonOutsideSchedule {
+        session_id: SessionId,
+        local_time: NaiveDateTime,
+        schedule: String,
+    },
+    // seqnums started over and the message store was cleared, with reset_on_schedule at the
+    // start of a new schedule window
+    SessionReset(SessionId),
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<EngineEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { tx }
+    }
+}
+
+impl EventBus {
+    // only events published after subscribing are received
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn publish(&self, event: EngineEvent) {
+        // no subscribers is not an error, the event is just not seen
+        let _ = self.tx.send(event);
+    }
+}
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+    use crate::session::SessionIdBuilder;
+
+    #[test]
+    fn test_publish_and_subscribe() {
+        let bus = EventBus::default();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        // nobody listening yet
+        bus.publish(EngineEvent::SessionCreated(session_id.clone()));
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        bus.publish(EngineEvent::ScheduleWindowClosed(session_id.clone()));
+        let expected = EngineEvent::ScheduleWindowClosed(session_id);
+        assert_eq!(first.try_recv().unwrap(), expected);
+        assert_eq!(second.try_recv().unwrap(), expected);
+        assert!(first.try_recv().is_err());
+    }
+}
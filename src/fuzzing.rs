@@ -0,0 +1,69 @@
+// Entry points for the cargo-fuzz targets in fuzz/. They take arbitrary bytes and must not panic
+// whatever the input; a rejected message is an expected outcome, a panic is a bug. Parsing is
+// done against a small subset of FIX 4.3 (header group, nested body groups, a component) so that
+// the fuzzer reaches the group handling quickly instead of wandering the full dictionary.
+use std::sync::OnceLock;
+
+use crate::data_dictionary::DataDictionary;
+use crate::message::*;
+use crate::quickfix_errors::SessionRejectError;
+
+const TINY_DICTIONARY_XML: &str = include_str!("../resources/FIX43-tiny.xml");
+
+pub fn tiny_dictionary() -> &'static DataDictionary {
+    static DICTIONARY: OnceLock<DataDictionary> = OnceLock::new();
+    DICTIONARY.get_or_init(|| TINY_DICTIONARY_XML.parse().expect("tiny dictionary is valid"))
+}
+
+pub fn parse_message(data: &[u8]) -> Result<Message, SessionRejectError> {
+    let msg = Message::from_bytes(data, tiny_dictionary())?;
+    // serializing exercises the field ordering of whatever the parser accepted
+    let _ = msg.to_string();
+    Ok(msg)
+}
+
+pub fn extract_session_id(data: &[u8]) {
+    // the receiver routes raw messages by session id before they are parsed
+    let raw = String::from_utf8_lossy(data);
+    let _ = Message::get_session_id(&raw);
+    let _ = Message::get_reverse_session_id(&raw);
+}
+
+#[cfg(test)]
+mod fuzzing_tests {
+    use super::*;
+
+    fn soh(s: &str) -> Vec<u8> {
+        s.replace('|', &SOH.to_string()).into_bytes()
+    }
+
+    #[test]
+    fn test_tiny_dictionary_parses_valid_messages() {
+        let logon =
+            "8=FIX.4.3|9=60|35=A|49=A|56=B|34=1|52=20221006-08:43:36.522|98=0|108=30|10=000|";
+        assert!(parse_message(&soh(logon)).is_ok());
+        let list = "8=FIX.4.3|9=99|35=E|49=A|56=B|34=2|52=20221006-08:43:36.522|66=L|68=2|73=2|\
+            11=O1|67=1|78=1|79=X|80=5|55=IBM|54=1|11=O2|67=2|55=MSFT|54=2|10=000|";
+        let msg = parse_message(&soh(list)).unwrap();
+        assert_eq!(msg.get_group(73).unwrap().size(), 2);
+    }
+
+    #[test]
+    fn test_hostile_input_is_rejected() {
+        let inputs: [&[u8]; 8] = [
+            b"",
+            b"8=FIX.4.3",
+            &[0xff, 0xfe, b'=', 0x01],
+            &soh("8=FIX.4.3|9=5|"),
+            &soh("8=FIX.4.3|9=|35=D|"),
+            // group count far beyond the fields present
+            &soh("8=FIX.4.3|9=5|35=E|49=A|56=B|66=L|73=4000000000|11=O1|10=000|"),
+            // group instance without its delimiter
+            &soh("8=FIX.4.3|9=5|35=E|49=A|56=B|66=L|73=1|67=1|10=000|"),
+            &soh("8=FIX.4.3|9=5|35=E|49=A|56=B|66=L|73=x|10=000|"),
+        ];
+        for input in inputs {
+            assert!(parse_message(input).is_err(), "{:?} was accepted", input);
+        }
+    }
+}
@@ -1,54 +1,142 @@
+use crate::clock::SharedClock;
+use crate::events::{EngineEvent, EventBus};
+use crate::io::capture::{Capture, CaptureStream, Direction};
+use crate::io::inbound::ConnectionHandle;
+use crate::io::stats::ConnectionCounters;
+use crate::io::transport::{FrameReader, FrameWriter, StreamTransport, Transport};
 use crate::io::*;
-use crate::message::SOH;
+use crate::message::{decode_frame, encode_frame, split_frames, Message};
+use crate::session::{DeadLetter, DeadLetterReason, SharedDeadLetterSink};
+use std::io::IoSlice;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt, DuplexStream};
 use tokio::net::TcpListener;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::error::TryRecvError;
 use tokio::sync::mpsc::{channel as tio_channel, Receiver as TioReceiver, Sender as TioSender};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout_at, Instant};
+
+// in-process connections have no peer address, this is reported as their source instead
+pub const IN_PROCESS_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0);
+const IN_PROCESS_BUFFER_SIZE: usize = 64 * 1024;
 
 #[derive(Debug)]
 pub struct IoAcceptor {
     bind_addr: SocketAddr,
-    socket_to_app_send: TioSender<String>, // for sending message to application
-
-    _app_to_socket_send: TioBroadcastSender<String>, // used by acceptor to recv data from app
-                                                     // app_to_socket_send: TioSender<String>,           // used by app code to send data to this
+    socket_to_app_send: TioSender<InboundMessage>, // for sending message to application
+    socket_options: SocketOptions,
+    // directory accepted connections are captured to, if any
+    capture_dir: Option<PathBuf>,
+    // where closed connections are reported, if anywhere
+    events: Option<EventBus>,
+    // sees every frame read or written, if anything
+    tap: Option<SharedTap>,
+    // gets the frames a connection closed before writing, if anything
+    dead_letters: Option<SharedDeadLetterSink>,
+    // connection tasks run here, on the runtime the acceptor is started from when None
+    runtime: Option<Handle>,
+    // messages each connection holds for writing before sends to it are refused
+    send_queue_size: usize,
+    // where the connections served are registered while they are live
+    connections: ConnectionManager,
+    // times the traffic of the connections
+    clock: SharedClock,
 }
 
 impl IoAcceptor {
     pub fn create(
-        bind_addr: SocketAddr, to_send: TioSender<String>,
-    ) -> (Self, TioBroadcastSender<String>) {
-        let self_bind_addr = bind_addr.clone();
-        // dropping the receiving end
-        let (tx, _) = broadcast::channel::<String>(32);
-        let acceptor = IoAcceptor {
-            bind_addr: bind_addr,
+        bind_addr: SocketAddr, to_send: TioSender<InboundMessage>, send_queue_size: usize,
+        socket_options: SocketOptions,
+    ) -> Self {
+        IoAcceptor {
+            bind_addr,
             socket_to_app_send: to_send,
-            _app_to_socket_send: tx.clone(),
-            // app_to_socket_send: tx,
-        };
-        (acceptor, tx)
+            socket_options,
+            capture_dir: None,
+            events: None,
+            tap: None,
+            dead_letters: None,
+            runtime: None,
+            send_queue_size,
+            connections: ConnectionManager::default(),
+            clock: SharedClock::default(),
+        }
+    }
+
+    pub fn with_capture(mut self, dir: PathBuf) -> Self {
+        self.capture_dir = Some(dir);
+        self
+    }
+
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_tap(mut self, tap: SharedTap) -> Self {
+        self.tap = Some(tap);
+        self
+    }
+
+    pub fn with_dead_letters(mut self, sink: SharedDeadLetterSink) -> Self {
+        self.dead_letters = Some(sink);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    // registers connections with a manager shared with other acceptors instead of one of its own
+    pub fn with_connections(mut self, connections: ConnectionManager) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    pub fn connections(&self) -> &ConnectionManager {
+        &self.connections
+    }
+
+    fn runtime(&self) -> Handle {
+        self.runtime.clone().unwrap_or_else(Handle::current)
     }
 
-    pub fn start(&self) {
-        let bind_addr = self.bind_addr.clone();
+    pub fn start(&self) -> std::io::Result<SocketAddr> {
+        // binding is done before spawning so that bind errors reach the caller and the actual
+        // address is known when bind_addr has port 0
+        let std_listener = std::net::TcpListener::bind(self.bind_addr)?;
+        std_listener.set_nonblocking(true)?;
+        // the listener is registered with the runtime it is polled on
+        let runtime = self.runtime();
+        let _guard = runtime.enter();
+        let listener = TcpListener::from_std(std_listener)?;
+        let local_addr = listener.local_addr()?;
+        println!("listening on {}", local_addr);
         let socket_to_app_send = self.socket_to_app_send.clone();
-        let app_to_socket_send = self._app_to_socket_send.clone();
-        tokio::spawn(async move {
+        let send_queue_size = self.send_queue_size;
+        let socket_options = self.socket_options;
+        let capture_dir = self.capture_dir.clone();
+        let events = self.events.clone();
+        let tap = self.tap.clone();
+        let dead_letters = self.dead_letters.clone();
+        let connections = self.connections.clone();
+        let clock = self.clock.clone();
+        let conn_runtime = runtime.clone();
+        runtime.spawn(async move {
             loop {
-                let listener = match TcpListener::bind(bind_addr).await {
-                    Ok(listener) => {
-                        println!("listening on {}", bind_addr);
-                        listener
-                    }
-                    Err(e) => {
-                        println!("Error in bind: {:?}", e);
-                        continue;
-                    }
-                };
-                let (stream, _) = match listener.accept().await {
+                let (stream, remote_addr) = match listener.accept().await {
                     Ok((stream, remote_addr)) => {
                         println!("accepted connection from {}", remote_addr);
                         (stream, remote_addr)
@@ -58,53 +146,392 @@ impl IoAcceptor {
                         continue;
                     }
                 };
-                let (owned_read, owned_write) = stream.into_split();
-                start_socket_listener_task(owned_read, socket_to_app_send.clone());
-                start_app_listner_task(owned_write, app_to_socket_send.subscribe());
+                if let Err(e) = socket_options.apply(&stream) {
+                    println!("Error in setting socket options: {:?}", e);
+                }
+                let to_app = socket_to_app_send.clone();
+                let events = events.clone();
+                let tap = tap.clone();
+                let dead_letters = dead_letters.clone();
+                let connections = connections.clone();
+                let clock = clock.clone();
+                match capture(&capture_dir, remote_addr) {
+                    Some(capture) => {
+                        let stream = CaptureStream::new(stream, capture);
+                        let transport = StreamTransport::new(stream, remote_addr);
+                        serve_connection(
+                            &conn_runtime,
+                            transport,
+                            to_app,
+                            send_queue_size,
+                            socket_options,
+                            events,
+                            tap,
+                            dead_letters,
+                            connections,
+                            clock,
+                        );
+                    }
+                    None => serve_connection(
+                        &conn_runtime,
+                        stream,
+                        to_app,
+                        send_queue_size,
+                        socket_options,
+                        events,
+                        tap,
+                        dead_letters,
+                        connections,
+                        clock,
+                    ),
+                }
             }
         });
+        Ok(local_addr)
+    }
+
+    // Serves a connection made over some other transport than the acceptor's listener, the
+    // same way as an accepted socket
+    pub fn serve<T: Transport>(&self, transport: T) {
+        serve_connection(
+            &self.runtime(),
+            transport,
+            self.socket_to_app_send.clone(),
+            self.send_queue_size,
+            self.socket_options,
+            self.events.clone(),
+            self.tap.clone(),
+            self.dead_letters.clone(),
+            self.connections.clone(),
+            self.clock.clone(),
+        );
+    }
+
+    // Connection from within the process over an in-memory pipe. The returned end is the
+    // counterparty's, what it writes is read by the engine and what the engine sends can be
+    // read from it.
+    pub fn connect_in_process(&self) -> DuplexStream {
+        let (local, remote) = tokio::io::duplex(IN_PROCESS_BUFFER_SIZE);
+        self.serve(local);
+        remote
     }
 }
 
-fn start_socket_listener_task(read_half: OwnedReadHalf, to_app: TioSender<String>) {
-    tokio::spawn(async move {
-        let mut buf: Vec<u8> = Vec::with_capacity(1024);
-        let mut buf_reader = BufReader::new(read_half);
+fn capture(capture_dir: &Option<PathBuf>, remote_addr: SocketAddr) -> Option<Capture> {
+    let dir = capture_dir.as_ref()?;
+    match Capture::create(dir, remote_addr) {
+        Ok(capture) => {
+            println!("capturing connection from {} to {}", remote_addr, capture.path().display());
+            Some(capture)
+        }
+        Err(e) => {
+            println!("could not capture connection from {}: {:?}", remote_addr, e);
+            None
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serve_connection<T: Transport>(
+    runtime: &Handle, transport: T, to_app: TioSender<InboundMessage>, send_queue_size: usize,
+    socket_options: SocketOptions, events: Option<EventBus>, tap: Option<SharedTap>,
+    dead_letters: Option<SharedDeadLetterSink>, connections: ConnectionManager, clock: SharedClock,
+) {
+    let remote_addr = transport.peer_addr();
+    let (mut reader, writer) = transport.into_split();
+    reader.set_max_message_size(socket_options.max_message_size);
+    let flush_interval = socket_options.flush_interval;
+    let idle_timeout = socket_options.idle_timeout;
+    // every connection has a queue of its own, so a slow counterparty only holds up itself
+    let (outbound, from_app) = tio_channel::<String>(send_queue_size.max(1));
+    let connection = ConnectionHandle::new(outbound).with_clock(clock.clone());
+    let counters = Arc::clone(connection.counters());
+    let stop_writer = Arc::new(Notify::new());
+    let writer = start_app_listner_task(
+        runtime,
+        writer,
+        from_app,
+        Arc::clone(&stop_writer),
+        flush_interval,
+        tap.clone(),
+        dead_letters.map(|sink| (sink, clock)),
+        counters,
+    );
+    let id = connections.register(remote_addr, connection.clone(), writer, stop_writer);
+    start_socket_listener_task(
+        runtime,
+        reader,
+        remote_addr,
+        to_app,
+        connection,
+        connections,
+        id,
+        events,
+        tap,
+        idle_timeout,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_socket_listener_task<R: FrameReader>(
+    runtime: &Handle, mut reader: R, remote_addr: SocketAddr, to_app: TioSender<InboundMessage>,
+    connection: ConnectionHandle, connections: ConnectionManager, id: ConnectionId,
+    events: Option<EventBus>, tap: Option<SharedTap>, idle_timeout: Option<Duration>,
+) {
+    runtime.spawn(async move {
         loop {
-            read_message(&mut buf_reader, &mut buf).await;
-            let raw_msg = String::from_utf8_lossy(&buf[..buf.len()]).to_string();
-            to_app.send(raw_msg).await.unwrap();
-            buf.clear();
+            // a peer that vanished without closing the connection is noticed by the silence
+            let idle = async {
+                match idle_timeout {
+                    Some(timeout) => tokio::time::sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let frame = tokio::select! {
+                read = reader.read_frame() => match read {
+                    Ok(frame) => frame,
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => {
+                        println!("dropping connection from {}: {}", remote_addr, e);
+                        break;
+                    }
+                },
+                _ = connection.disconnected() => break,
+                _ = idle => {
+                    let timeout = idle_timeout.unwrap_or_default();
+                    println!("dropping connection from {}: nothing read for {:?}", remote_addr, timeout);
+                    break;
+                }
+            };
+            connection.counters().received(frame.len());
+            let raw_msg = decode_frame(&frame);
+            if let Some(tap) = &tap {
+                let session_id = match connection.bound_session() {
+                    Some(session_id) => Some(session_id.clone()),
+                    None => Message::get_reverse_session_id(&raw_msg).ok(),
+                };
+                tap.on_frame(session_id.as_ref(), Direction::Inbound, &frame);
+            }
+            let inbound = InboundMessage::new(raw_msg, remote_addr, connection.clone());
+            // once a session has logged on over the connection its messages go straight to it,
+            // without working out the session from every message
+            let to_session = connection.next_hop(&to_app);
+            if to_session.send(inbound).await.is_err() {
+                break;
+            }
+        }
+        println!("connection closed");
+        connections.remove(id);
+        if let Some(events) = events {
+            events.publish(EngineEvent::Disconnected {
+                peer: remote_addr,
+                session_id: connection.bound_session().cloned(),
+            });
         }
     });
 }
 
-fn start_app_listner_task(
-    mut write_half: OwnedWriteHalf, mut from_app: TioBroadcastReceiver<String>,
-) {
-    tokio::spawn(async move {
+#[allow(clippy::too_many_arguments)]
+fn start_app_listner_task<W: FrameWriter>(
+    runtime: &Handle, mut writer: W, mut from_app: TioReceiver<String>, stop: Arc<Notify>,
+    flush_interval: Duration, tap: Option<SharedTap>,
+    dead_letters: Option<(SharedDeadLetterSink, SharedClock)>, counters: Arc<ConnectionCounters>,
+) -> JoinHandle<()> {
+    runtime.spawn(async move {
         println!("starting internal msg receiv");
-        // if there is message to be sent out to remote socket then read and send
-        while let Ok(msg) = from_app.recv().await {
-            println!("sending {}", &msg);
-            let _res = write_half.write_all(msg.as_bytes()).await.unwrap();
-            println!("sent {}", &msg);
+        // also when the task is aborted
+        let _stopped = WriterStopped(Arc::clone(&counters));
+        let mut batch: Vec<String> = Vec::new();
+        // whatever is queued behind a message goes out with it in one write. once told to stop
+        // nothing more is taken, what is queued is still written
+        while let Some(msg) = tokio::select! {
+            msg = from_app.recv() => msg,
+            _ = stop.notified() => {
+                from_app.close();
+                from_app.recv().await
+            }
+        } {
+            batch.push(msg);
+            let closed = collect_batch(&mut from_app, &mut batch, flush_interval).await;
+            println!("sending {} message(s)", batch.len());
+            // every frame is encoded once, the tap sees the bytes that were written
+            let frames: Vec<_> = batch
+                .iter()
+                .flat_map(|msg| split_frames(msg))
+                .map(|frame| (frame, encode_frame(frame)))
+                .collect();
+            let encoded: Vec<&[u8]> = frames.iter().map(|(_, bytes)| bytes.as_ref()).collect();
+            if let Err(e) = writer.write_frames(&encoded).await {
+                println!("Error in writing to socket: {:?}", e);
+                break;
+            }
+            counters.sent(frames.len(), batch.iter().map(String::len).sum());
+            counters.written(batch.len());
+            if let Some(tap) = &tap {
+                for (frame, bytes) in frames.iter() {
+                    let session_id = Message::get_session_id(frame).ok();
+                    tap.on_frame(session_id.as_ref(), Direction::Outbound, bytes);
+                }
+            }
+            drop(frames);
+            batch.clear();
+            if closed {
+                break;
+            }
         }
-    });
+        // a batch the write failed for and what is still queued behind it never go out
+        from_app.close();
+        while let Ok(msg) = from_app.try_recv() {
+            batch.push(msg);
+        }
+        if let Some((sink, clock)) = &dead_letters {
+            for frame in batch.iter().flat_map(|msg| split_frames(msg)) {
+                if let Ok(session_id) = Message::get_session_id(frame) {
+                    sink.dead_letter(&DeadLetter {
+                        session_id,
+                        raw: frame.to_string(),
+                        reason: DeadLetterReason::Unwritten,
+                        time: clock.now(),
+                    });
+                }
+            }
+        }
+        if let Err(e) = writer.close().await {
+            println!("Error in closing connection: {:?}", e);
+        }
+    })
 }
 
-async fn read_message<R: AsyncBufReadExt + Unpin>(reader: &mut R, buf: &mut Vec<u8>) {
+struct WriterStopped(Arc<ConnectionCounters>);
+
+impl Drop for WriterStopped {
+    fn drop(&mut self) {
+        self.0.writer_stopped();
+    }
+}
+
+async fn collect_batch(
+    from_app: &mut TioReceiver<String>, batch: &mut Vec<String>, flush_interval: Duration,
+) -> bool {
+    // true if the writer has to stop after writing what was collected, because the channel is
+    // gone
+    let deadline = Instant::now() + flush_interval;
     loop {
-        let bytes_read = reader.read_until(SOH as u8, buf).await.unwrap();
-        // println!("bytes received: {:?}", &buf);
-        let slice_start = buf.len() - bytes_read;
-        let slice_end = buf.len();
-        // last read data
-        let byte_slice = &buf[slice_start..slice_end];
-        if byte_slice.starts_with(&[49, 48, 61]) {
-            // b"10="
-            // checksum tag found, break
-            break;
+        let received = match from_app.try_recv() {
+            Err(TryRecvError::Empty) if !flush_interval.is_zero() => {
+                match timeout_at(deadline, from_app.recv()).await {
+                    Ok(received) => received.ok_or(TryRecvError::Disconnected),
+                    Err(_) => return false,
+                }
+            }
+            received => received,
+        };
+        match received {
+            Ok(msg) => batch.push(msg),
+            Err(TryRecvError::Empty) => return false,
+            Err(_) => return true,
         }
     }
 }
+
+pub(crate) async fn write_batch<W: AsyncWrite + Unpin>(
+    writer: &mut W, batch: &[&[u8]],
+) -> std::io::Result<()> {
+    let mut slices: Vec<IoSlice> = batch.iter().map(|msg| IoSlice::new(msg)).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        let written = writer.write_vectored(slices).await?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut slices, written);
+    }
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod acceptor_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_batched_write() {
+        let (tx, mut rx) = tio_channel::<String>(8);
+        for msg in ["8=FIX.4.3\u{1}", "35=0\u{1}", "10=000\u{1}"] {
+            tx.try_send(msg.to_string()).unwrap();
+        }
+        let mut batch = vec![rx.recv().await.unwrap()];
+        assert!(!collect_batch(&mut rx, &mut batch, Duration::ZERO).await);
+        assert_eq!(batch.len(), 3);
+        let mut written: Vec<u8> = Vec::new();
+        let encoded: Vec<&[u8]> = batch.iter().map(String::as_bytes).collect();
+        write_batch(&mut written, &encoded).await.unwrap();
+        assert_eq!(written, b"8=FIX.4.3\x0135=0\x0110=000\x01");
+
+        // with a flush interval, messages queued while waiting join the batch
+        let mut batch = Vec::new();
+        let sender = tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send("late".to_string()).await.unwrap();
+        });
+        assert!(!collect_batch(&mut rx, &mut batch, Duration::from_millis(200)).await);
+        assert_eq!(batch, vec!["late"]);
+
+        drop(tx);
+        assert!(collect_batch(&mut rx, &mut batch, Duration::ZERO).await);
+    }
+
+    struct BrokenWriter;
+
+    impl FrameWriter for BrokenWriter {
+        async fn write_frames(&mut self, _frames: &[&[u8]]) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        async fn close(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unwritten_dead_letters() {
+        let mut msg = Message::new();
+        let header = msg.header_mut();
+        header.set_begin_string("FIX.4.3");
+        header.set_msg_type("0");
+        header.set_sender_comp_id("SENDER");
+        header.set_target_comp_id("TARGET");
+        msg.set_body_len();
+        msg.set_checksum();
+        let heartbeat = msg.to_string();
+
+        let letters = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = {
+            let letters = Arc::clone(&letters);
+            SharedDeadLetterSink::new(move |letter: &DeadLetter| {
+                letters.lock().unwrap().push(letter.clone())
+            })
+        };
+        let (tx, rx) = tio_channel::<String>(8);
+        // two frames in one send and one more behind them
+        tx.try_send(heartbeat.repeat(2)).unwrap();
+        tx.try_send(heartbeat.clone()).unwrap();
+        let counters = Arc::new(ConnectionCounters::default());
+        let writer = start_app_listner_task(
+            &Handle::current(),
+            BrokenWriter,
+            rx,
+            Arc::new(Notify::new()),
+            Duration::ZERO,
+            None,
+            Some((sink, SharedClock::default())),
+            counters,
+        );
+        writer.await.unwrap();
+        let letters = letters.lock().unwrap();
+        assert_eq!(letters.len(), 3);
+        assert!(letters.iter().all(|letter| letter.raw == heartbeat
+            && letter.reason == DeadLetterReason::Unwritten
+            && letter.session_id.to_string().contains("SENDER")));
+    }
+}
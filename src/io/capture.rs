@@ -0,0 +1,268 @@
+use crate::io::frame::FrameDecoder;
+use crate::message::decode_frame;
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, IoSlice, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::oneshot;
+
+pub const CAPTURE_FILE_EXTENSION: &str = "fixcap";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    fn as_byte(self) -> u8 {
+        match self {
+            Direction::Inbound => b'I',
+            Direction::Outbound => b'O',
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'I' => Some(Direction::Inbound),
+            b'O' => Some(Direction::Outbound),
+            _ => None,
+        }
+    }
+}
+
+// Bytes as they went over the wire in one read or write of a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    pub direction: Direction,
+    pub timestamp: DateTime<Utc>,
+    pub bytes: Vec<u8>,
+}
+
+// Capture file of one connection. Every read and write is a record of direction ('I' or 'O'),
+// nanoseconds since the epoch as a big endian i64, length as a big endian u32 and the bytes.
+// Reads and writes of the connection only queue their record, a blocking task of the runtime
+// writes them to the file and flushes whenever it has caught up, so that a capture survives
+// the process going down. Created from within a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    path: PathBuf,
+    records: std::sync::mpsc::Sender<CaptureOp>,
+}
+
+#[derive(Debug)]
+enum CaptureOp {
+    Record(Direction, i64, Vec<u8>),
+    // answered once everything queued before is in the file
+    Flush(oneshot::Sender<()>),
+}
+
+impl Capture {
+    pub fn create(dir: &Path, peer: SocketAddr) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let name = format!(
+            "{}_{}_{}.{}",
+            peer.ip().to_string().replace(':', "."),
+            peer.port(),
+            Utc::now().format("%Y%m%d-%H%M%S%.6f"),
+            CAPTURE_FILE_EXTENSION
+        );
+        let path = dir.join(name);
+        let file = File::create(&path)?;
+        let (records, queued) = std::sync::mpsc::channel();
+        let writer_path = path.clone();
+        tokio::task::spawn_blocking(move || write_records(&writer_path, file, queued));
+        Ok(Self { path, records })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // resolves once what was recorded so far is written to the file
+    pub async fn flushed(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.records.send(CaptureOp::Flush(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    fn record(&self, direction: Direction, bytes: &[u8]) {
+        // capturing is for debugging, traffic goes on when the capture cannot be written
+        let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let _ = self.records.send(CaptureOp::Record(direction, nanos, bytes.to_vec()));
+    }
+}
+
+fn write_records(path: &Path, file: File, queued: std::sync::mpsc::Receiver<CaptureOp>) {
+    // runs until every Capture of the connection is dropped
+    let mut file = BufWriter::new(file);
+    while let Ok(op) = queued.recv() {
+        let mut flushes = Vec::new();
+        let mut result = Ok(());
+        for op in std::iter::once(op).chain(queued.try_iter()) {
+            match op {
+                CaptureOp::Record(direction, nanos, bytes) => {
+                    result = result
+                        .and_then(|_| file.write_all(&[direction.as_byte()]))
+                        .and_then(|_| file.write_all(&nanos.to_be_bytes()))
+                        .and_then(|_| file.write_all(&(bytes.len() as u32).to_be_bytes()))
+                        .and_then(|_| file.write_all(&bytes));
+                }
+                CaptureOp::Flush(done) => flushes.push(done),
+            }
+        }
+        if let Err(e) = result.and_then(|_| file.flush()) {
+            println!("could not write capture {}: {}", path.display(), e);
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+// Stream that records everything read from and written to it in a capture
+#[derive(Debug)]
+pub struct CaptureStream<S> {
+    inner: S,
+    capture: Capture,
+}
+
+impl<S> CaptureStream<S> {
+    pub fn new(inner: S, capture: Capture) -> Self {
+        Self { inner, capture }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CaptureStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        let read = &buf.filled()[filled..];
+        if let (Poll::Ready(Ok(())), false) = (&poll, read.is_empty()) {
+            this.capture.record(Direction::Inbound, read);
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CaptureStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            this.capture.record(Direction::Outbound, &buf[..written]);
+        }
+        poll
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(written)) = poll {
+            let bytes: Vec<u8> =
+                bufs.iter().flat_map(|buf| buf.iter().copied()).take(written).collect();
+            this.capture.record(Direction::Outbound, &bytes);
+        }
+        poll
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+pub fn read_capture<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<CaptureRecord>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+    let mut direction = [0u8; 1];
+    loop {
+        match reader.read_exact(&mut direction) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(records),
+            Err(e) => return Err(e),
+        }
+        let direction = Direction::from_byte(direction[0])
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid capture record"))?;
+        let mut nanos = [0u8; 8];
+        reader.read_exact(&mut nanos)?;
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let mut bytes = vec![0u8; u32::from_be_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        records.push(CaptureRecord {
+            direction,
+            timestamp: DateTime::from_timestamp_nanos(i64::from_be_bytes(nanos)),
+            bytes,
+        });
+    }
+}
+
+// Messages of one direction of a capture, framed by the decoder the engine reads with and fed
+// to it in the chunks they arrived in, so that framing problems show up as they did live
+pub fn replay<P: AsRef<Path>>(path: P, direction: Direction) -> std::io::Result<Vec<String>> {
+    let mut decoder = FrameDecoder::default();
+    let mut messages = Vec::new();
+    for record in read_capture(path)?.into_iter().filter(|r| r.direction == direction) {
+        decoder.extend(&record.bytes);
+        while let Some(frame) = decoder.next_frame()? {
+            messages.push(decode_frame(&frame));
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod capture_tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const HEARTBEAT: &str = "8=FIX.4.3\u{1}9=5\u{1}35=0\u{1}10=163\u{1}";
+
+    #[tokio::test]
+    async fn test_capture_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let peer: SocketAddr = "127.0.0.1:9880".parse().unwrap();
+        let capture = Capture::create(dir.path(), peer).unwrap();
+        assert!(capture.path().starts_with(dir.path()));
+        let (local, mut remote) = tokio::io::duplex(1024);
+        let mut stream = CaptureStream::new(local, capture.clone());
+
+        // inbound message split over two reads, then two messages in one
+        let (first, second) = HEARTBEAT.split_at(12);
+        let mut buf = [0u8; 1024];
+        remote.write_all(first.as_bytes()).await.unwrap();
+        let _ = stream.read(&mut buf).await.unwrap();
+        remote.write_all(format!("{}{}", second, HEARTBEAT).as_bytes()).await.unwrap();
+        let _ = stream.read(&mut buf).await.unwrap();
+        stream.write_all(HEARTBEAT.as_bytes()).await.unwrap();
+
+        capture.flushed().await;
+        let records = read_capture(capture.path()).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].bytes, first.as_bytes());
+        assert_eq!(records[2].direction, Direction::Outbound);
+        assert!(records[0].timestamp <= records[2].timestamp);
+        assert_eq!(replay(capture.path(), Direction::Inbound).unwrap(), vec![HEARTBEAT; 2]);
+        assert_eq!(replay(capture.path(), Direction::Outbound).unwrap(), vec![HEARTBEAT]);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::io::inbound::ConnectionHandle;
+use crate::io::stats::ConnectionStats;
+use crate::session::SessionId;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+pub type ConnectionId = u64;
+
+// Point in time view of a live connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    pub peer: SocketAddr,
+    // session whose logon was accepted over the connection, if any yet, see SessionId::key
+    pub session_id: Option<String>,
+    pub stats: ConnectionStats,
+}
+
+// how long the writer of a connection that is gone gets to write what is still queued
+const WRITER_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Connection {
+    peer: SocketAddr,
+    handle: ConnectionHandle,
+    // task writing the connection's outbound queue
+    writer: JoinHandle<()>,
+    // tells the writer to write what is queued and stop
+    stop_writer: Arc<Notify>,
+}
+
+// Every live connection of an engine, from the moment it is served until its reader stops.
+// Connections can be looked up by the session bound to them and closed from outside, and
+// close_all waits for them to be gone, e.g. before the engine shuts down.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionManager {
+    connections: Arc<DashMap<ConnectionId, Connection>>,
+    next_id: Arc<AtomicU64>,
+    // notified whenever a connection goes away
+    removed: Arc<Notify>,
+}
+
+impl ConnectionManager {
+    pub(crate) fn register(
+        &self, peer: SocketAddr, handle: ConnectionHandle, writer: JoinHandle<()>,
+        stop_writer: Arc<Notify>,
+    ) -> ConnectionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        handle.registered(self, id);
+        self.connections.insert(
+            id,
+            Connection {
+                peer,
+                handle,
+                writer,
+                stop_writer,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn remove(&self, id: ConnectionId) {
+        // the writer still writes what is queued, e.g. a Logout sent right before the
+        // connection was closed, and refuses what is sent after so that sessions report
+        // Disconnected instead of queueing messages nobody will write. one stuck on a peer that
+        // does not read is stopped
+        if let Some((_, connection)) = self.connections.remove(&id) {
+            connection.stop_writer.notify_one();
+            let mut writer = connection.writer;
+            tokio::spawn(async move {
+                if tokio::time::timeout(WRITER_DRAIN_TIMEOUT, &mut writer).await.is_err() {
+                    writer.abort();
+                }
+            });
+        }
+        self.removed.notify_waiters();
+    }
+
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<_> = self.connections.iter().map(|c| info(*c.key(), &c)).collect();
+        connections.sort_by_key(|connection| connection.id);
+        connections
+    }
+
+    pub fn connection(&self, id: ConnectionId) -> Option<ConnectionInfo> {
+        self.connections.get(&id).map(|c| info(id, &c))
+    }
+
+    // connections bound to the session, more than one only until the older ones are noticed
+    // to be gone
+    pub fn session_connections(&self, session_id: &SessionId) -> Vec<ConnectionInfo> {
+        let session_id = session_id.key();
+        self.connections()
+            .into_iter()
+            .filter(|c| c.session_id.as_ref() == Some(&session_id))
+            .collect()
+    }
+
+    // stops reading the connection, its reader then removes it. false if it is already gone
+    pub fn close(&self, id: ConnectionId) -> bool {
+        self.connections.get(&id).map(|c| c.handle.disconnect()).is_some()
+    }
+
+    // closes every connection and waits until all of them are gone, false if some are still
+    // there after the timeout
+    pub async fn close_all(&self, timeout: Duration) -> bool {
+        for connection in self.connections.iter() {
+            connection.handle.disconnect();
+        }
+        let gone = async {
+            loop {
+                let removed = self.removed.notified();
+                if self.connections.is_empty() {
+                    return;
+                }
+                removed.await;
+            }
+        };
+        tokio::time::timeout(timeout, gone).await.is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
+
+fn info(id: ConnectionId, connection: &Connection) -> ConnectionInfo {
+    ConnectionInfo {
+        id,
+        peer: connection.peer,
+        session_id: connection.handle.bound_session().map(SessionId::key),
+        stats: connection.handle.counters().stats(),
+    }
+}
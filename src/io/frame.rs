@@ -0,0 +1,353 @@
+use crate::message::{next_message_start, SOH};
+use std::io::{Error, ErrorKind};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+// what a message starts with, whatever its FIX version
+const BEGIN_STRING: &[u8] = b"8=FIX";
+const BODY_LENGTH: &[u8] = b"9=";
+const CHECKSUM: &[u8] = b"10=";
+// "10=" three digits and SOH
+const CHECKSUM_LEN: usize = 7;
+const READ_CHUNK_SIZE: usize = 4096;
+// BodyLength comes from the peer, beyond this the buffer grows as the bytes actually arrive
+const MAX_PREALLOCATION: usize = 1024 * 1024;
+
+enum FrameLen {
+    Complete(usize),
+    // total length is known once BodyLength is read
+    Incomplete(Option<usize>),
+    Garbled,
+}
+
+// Splits the byte stream of a connection into FIX messages using BodyLength(9). Bytes are
+// appended as they are read off the socket, so one read can hold several messages and the
+// end of a message can arrive with a later read. Whatever is left after the last complete
+// message is kept for the next one. Bytes that do not frame as a message are logged and dropped
+// up to the next BeginString(8), as garbled messages are to be ignored. That includes a message
+// whose BodyLength runs past the start of the next one. A message announcing more
+// than max_message_size bytes is an error, the connection is not worth keeping.
+// Taken messages are not removed from the front one by one, start moves past them and the
+// buffer is compacted once they make up half of it. The length of the message at the front is
+// kept once known, so that it is not worked out again with every read until it is complete,
+// and so is how far it was looked through for a message starting inside it.
+#[derive(Debug, Default)]
+pub(crate) struct FrameDecoder {
+    buf: Vec<u8>,
+    start: usize,
+    front_len: Option<usize>,
+    front_checked: usize,
+    max_message_size: Option<usize>,
+}
+
+impl FrameDecoder {
+    pub(crate) fn with_max_message_size(max_message_size: Option<usize>) -> Self {
+        Self {
+            max_message_size,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn set_max_message_size(&mut self, max_message_size: Option<usize>) {
+        self.max_message_size = max_message_size;
+    }
+
+    pub(crate) fn extend(&mut self, bytes: &[u8]) {
+        if self.start > 0 && self.start * 2 >= self.buf.len() {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    // bytes read that are not taken as a message yet
+    fn pending(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    fn take_front(&mut self, len: usize) {
+        self.start += len;
+        self.front_len = None;
+        self.front_checked = 0;
+    }
+
+    pub(crate) fn next_frame(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        loop {
+            self.discard_to_next_message(0);
+            let mut frame_len = match self.front_len {
+                Some(len) if self.pending().len() < len => FrameLen::Incomplete(Some(len)),
+                _ => frame_len(self.pending()),
+            };
+            if let FrameLen::Incomplete(Some(len)) = frame_len {
+                // a BodyLength too large for the message would hold up the ones after it
+                if self.next_after_checksum(len).is_some() {
+                    frame_len = FrameLen::Garbled;
+                }
+            }
+            if let FrameLen::Complete(len) | FrameLen::Incomplete(Some(len)) = frame_len {
+                if self.max_message_size.is_some_and(|max| len > max) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("message of {} bytes is larger than allowed", len),
+                    ));
+                }
+            }
+            match frame_len {
+                FrameLen::Complete(len) => {
+                    let frame = self.pending()[..len].to_vec();
+                    self.take_front(len);
+                    return Ok(Some(frame));
+                }
+                FrameLen::Incomplete(len) => {
+                    // whole message is allocated for once, instead of growing with every read
+                    if let Some(len) = len {
+                        let len = len.min(MAX_PREALLOCATION);
+                        self.buf.reserve(len.saturating_sub(self.pending().len()));
+                    }
+                    self.front_len = len;
+                    return Ok(None);
+                }
+                FrameLen::Garbled => self.discard_to_next_message(1),
+            }
+        }
+    }
+
+    fn discard_to_next_message(&mut self, from: usize) {
+        // drops the bytes before the next BeginString at or after from
+        let pending = self.pending();
+        let end = next_message_start(pending, from).unwrap_or_else(|| {
+            // the next message may start with the last bytes read
+            let started = (1..BEGIN_STRING.len())
+                .rev()
+                .find(|len| pending.ends_with(&BEGIN_STRING[..*len]))
+                .unwrap_or(0);
+            (pending.len() - started).max(from.min(pending.len()))
+        });
+        if end > 0 {
+            let printable = String::from_utf8_lossy(&pending[..end]).replace(SOH, "|");
+            println!("discarding {} bytes of garbled data: {}", end, printable);
+            self.take_front(end);
+        }
+    }
+
+    fn next_after_checksum(&mut self, len: usize) -> Option<usize> {
+        // start of a message right after a CheckSum field, before the frame at the start of
+        // the buffer should end. a message can only start there if that frame was garbled.
+        // what was looked through with an earlier read is not again, but for a BeginString
+        // that may have been cut short
+        let within = len.min(self.pending().len());
+        let mut from = self.front_checked.max(1);
+        self.front_checked = within.saturating_sub(BEGIN_STRING.len() - 1);
+        let pending = self.pending();
+        while let Some(start) = next_message_start(&pending[..within], from) {
+            let before = &pending[..start];
+            let checksum = before.len().checked_sub(CHECKSUM_LEN + 1).map(|at| &before[at..]);
+            let after_checksum = checksum.is_some_and(|field| {
+                field[0] == SOH as u8
+                    && field[1..].starts_with(CHECKSUM)
+                    && field[4..CHECKSUM_LEN].iter().all(u8::is_ascii_digit)
+            });
+            if after_checksum {
+                return Some(start);
+            }
+            from = start + 1;
+        }
+        None
+    }
+}
+
+fn frame_len(buf: &[u8]) -> FrameLen {
+    let body_len_start = match find_soh(buf, 0) {
+        Some(end) => end + 1,
+        None => return FrameLen::Incomplete(None),
+    };
+    let body_len_field = &buf[body_len_start..];
+    if body_len_field.len() < BODY_LENGTH.len() {
+        return FrameLen::Incomplete(None);
+    }
+    if !body_len_field.starts_with(BODY_LENGTH) {
+        return FrameLen::Garbled;
+    }
+    let body_start = match find_soh(buf, body_len_start) {
+        Some(end) => end + 1,
+        None => return FrameLen::Incomplete(None),
+    };
+    let digits = &buf[body_len_start + BODY_LENGTH.len()..body_start - 1];
+    let body_len = match std::str::from_utf8(digits).ok().and_then(|d| d.parse::<usize>().ok()) {
+        Some(body_len) => body_len,
+        None => return FrameLen::Garbled,
+    };
+    let checksum_start = body_start.saturating_add(body_len);
+    let frame_len = checksum_start.saturating_add(CHECKSUM_LEN);
+    if buf.len() < frame_len {
+        return FrameLen::Incomplete(Some(frame_len));
+    }
+    let checksum = &buf[checksum_start..checksum_start + CHECKSUM_LEN];
+    match checksum.starts_with(CHECKSUM) && checksum[CHECKSUM_LEN - 1] == SOH as u8 {
+        true => FrameLen::Complete(frame_len),
+        false => FrameLen::Garbled,
+    }
+}
+
+fn find_soh(buf: &[u8], from: usize) -> Option<usize> {
+    buf[from..].iter().position(|b| *b == SOH as u8).map(|pos| from + pos)
+}
+
+// Next complete message from the reader, reading more as needed. Cancel safe, what was read
+// stays in the decoder.
+pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R, decoder: &mut FrameDecoder,
+) -> std::io::Result<Vec<u8>> {
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+    loop {
+        if let Some(frame) = decoder.next_frame()? {
+            return Ok(frame);
+        }
+        let bytes_read = reader.read(&mut chunk).await?;
+        if bytes_read == 0 {
+            // eof, peer closed the connection
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        decoder.extend(&chunk[..bytes_read]);
+    }
+}
+
+#[cfg(test)]
+mod frame_tests {
+    use super::*;
+
+    const HEARTBEAT: &str = "8=FIX.4.3\u{1}9=5\u{1}35=0\u{1}10=163\u{1}";
+    const TEST_REQUEST: &str = "8=FIX.4.3\u{1}9=14\u{1}35=1\u{1}112=TEST\u{1}10=068\u{1}";
+
+    fn frames(decoder: &mut FrameDecoder) -> Vec<String> {
+        std::iter::from_fn(|| decoder.next_frame().unwrap())
+            .map(|frame| String::from_utf8(frame).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_back_to_back_messages() {
+        let mut decoder = FrameDecoder::default();
+        let burst = format!("{}{}{}", HEARTBEAT, TEST_REQUEST, &HEARTBEAT[..12]);
+        decoder.extend(burst.as_bytes());
+        assert_eq!(frames(&mut decoder), vec![HEARTBEAT, TEST_REQUEST]);
+        // rest of the partial message arrives with the next read
+        decoder.extend(&HEARTBEAT.as_bytes()[12..]);
+        assert_eq!(frames(&mut decoder), vec![HEARTBEAT]);
+        assert!(decoder.pending().is_empty());
+    }
+
+    #[test]
+    fn test_taken_messages_are_compacted() {
+        let mut decoder = FrameDecoder::default();
+        let burst = HEARTBEAT.repeat(3);
+        decoder.extend(burst.as_bytes());
+        assert_eq!(frames(&mut decoder).len(), 3);
+        // nothing is moved while messages are taken, only once more is read
+        assert_eq!(decoder.buf.len(), burst.len());
+        decoder.extend(&TEST_REQUEST.as_bytes()[..16]);
+        assert_eq!(decoder.buf, &TEST_REQUEST.as_bytes()[..16]);
+        assert!(frames(&mut decoder).is_empty());
+        assert_eq!(decoder.front_len, Some(TEST_REQUEST.len()));
+        decoder.extend(&TEST_REQUEST.as_bytes()[16..]);
+        assert_eq!(frames(&mut decoder), vec![TEST_REQUEST]);
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let mut decoder = FrameDecoder::default();
+        let mut received = Vec::new();
+        for byte in TEST_REQUEST.bytes() {
+            decoder.extend(&[byte]);
+            received.extend(frames(&mut decoder));
+        }
+        assert_eq!(received, vec![TEST_REQUEST]);
+    }
+
+    #[test]
+    fn test_garbled_data_is_skipped() {
+        let mut decoder = FrameDecoder::default();
+        // junk before a message, BodyLength that does not match and a missing BodyLength
+        let wrong_len = HEARTBEAT.replace("9=5", "9=7");
+        let no_len = HEARTBEAT.replace("9=5\u{1}", "");
+        let stream = format!("junk\u{1}{}{}{}{}", wrong_len, no_len, TEST_REQUEST, HEARTBEAT);
+        decoder.extend(stream.as_bytes());
+        assert_eq!(frames(&mut decoder), vec![TEST_REQUEST, HEARTBEAT]);
+    }
+
+    #[test]
+    fn test_resync_after_long_body_length() {
+        // the garbled message would wait for 500 more bytes, the ones after it are taken
+        // instead of waiting with them
+        let mut decoder = FrameDecoder::with_max_message_size(Some(200));
+        let too_long = HEARTBEAT.replace("9=5", "9=500");
+        let stream = format!("{}{}{}", too_long, TEST_REQUEST, HEARTBEAT);
+        decoder.extend(stream.as_bytes());
+        assert_eq!(frames(&mut decoder), vec![TEST_REQUEST, HEARTBEAT]);
+        assert!(decoder.pending().is_empty());
+        // an 8=FIX that does not follow a CheckSum is no reason to give up on a message
+        let text = "8=FIX.4.3\u{1}9=22\u{1}35=1\u{1}58=x\u{1}8=FIX\u{1}10=000\u{1}";
+        decoder.extend(&text.as_bytes()[..30]);
+        assert!(frames(&mut decoder).is_empty());
+        assert_eq!(decoder.pending().len(), 30);
+    }
+
+    #[test]
+    fn test_resync_byte_at_a_time() {
+        let mut decoder = FrameDecoder::default();
+        let mut received = Vec::new();
+        for byte in format!("8=junk\u{1}8=F\u{1}{}", TEST_REQUEST).bytes() {
+            decoder.extend(&[byte]);
+            received.extend(frames(&mut decoder));
+        }
+        assert_eq!(received, vec![TEST_REQUEST]);
+    }
+
+    #[test]
+    fn test_resync_carries_offset() {
+        // the message waited for is looked through once, not from its start with every read
+        let mut decoder = FrameDecoder::default();
+        let too_long = HEARTBEAT.replace("9=5", "9=500");
+        decoder.extend(too_long.as_bytes());
+        assert!(frames(&mut decoder).is_empty());
+        assert_eq!(decoder.front_checked, too_long.len() - 4);
+        let mut received = Vec::new();
+        for byte in TEST_REQUEST.bytes() {
+            decoder.extend(&[byte]);
+            received.extend(frames(&mut decoder));
+        }
+        assert_eq!(received, vec![TEST_REQUEST]);
+        assert_eq!(decoder.front_checked, 0);
+    }
+
+    #[test]
+    fn test_preallocate_from_body_length() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&TEST_REQUEST.as_bytes()[..16]);
+        assert!(frames(&mut decoder).is_empty());
+        assert!(decoder.buf.capacity() >= TEST_REQUEST.len());
+    }
+
+    #[test]
+    fn test_max_message_size() {
+        let mut decoder = FrameDecoder::with_max_message_size(Some(HEARTBEAT.len()));
+        decoder.extend(HEARTBEAT.as_bytes());
+        assert_eq!(frames(&mut decoder), vec![HEARTBEAT]);
+        // refused as soon as BodyLength is known, before the body arrives
+        decoder.extend(&TEST_REQUEST.as_bytes()[..16]);
+        let err = decoder.next_frame().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut decoder = FrameDecoder::with_max_message_size(Some(HEARTBEAT.len()));
+        decoder.extend(TEST_REQUEST.as_bytes());
+        assert!(decoder.next_frame().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let stream = format!("{}{}", HEARTBEAT, TEST_REQUEST);
+        let mut reader = stream.as_bytes();
+        let mut decoder = FrameDecoder::default();
+        assert_eq!(read_frame(&mut reader, &mut decoder).await.unwrap(), HEARTBEAT.as_bytes());
+        assert_eq!(read_frame(&mut reader, &mut decoder).await.unwrap(), TEST_REQUEST.as_bytes());
+        assert!(read_frame(&mut reader, &mut decoder).await.is_err());
+    }
+}
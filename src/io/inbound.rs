@@ -0,0 +1,192 @@
+use crate::clock::SharedClock;
+use crate::io::connections::{ConnectionId, ConnectionManager};
+use crate::io::stats::ConnectionCounters;
+use crate::session::SessionId;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc::Sender as TioSender;
+use tokio::sync::Notify;
+
+// Raw message read off a connection together with where it came from, so that the engine can
+// make decisions based on the peer (e.g. logon authentication) and drop the connection.
+#[derive(Debug, Clone)]
+pub struct InboundMessage {
+    pub raw: String,
+    pub source: SocketAddr,
+    connection: ConnectionHandle,
+}
+
+impl InboundMessage {
+    pub(crate) fn new(raw: String, source: SocketAddr, connection: ConnectionHandle) -> Self {
+        Self {
+            raw,
+            source,
+            connection,
+        }
+    }
+
+    // closes the connection the message was read from. nothing more is read from it, what is
+    // already queued for it is still written
+    pub fn disconnect(&self) {
+        self.connection.close();
+    }
+
+    // hands everything read from the connection from now on straight to the session's inbox,
+    // once its logon is accepted. only the first binding of a connection counts
+    pub(crate) fn bind(&self, session_id: &SessionId, session_inbox: &TioSender<InboundMessage>) {
+        self.connection.bind(session_id, session_inbox);
+    }
+
+    // session the connection is bound to, if its logon was accepted
+    pub(crate) fn bound_session(&self) -> Option<&SessionId> {
+        self.connection.bound_session()
+    }
+
+    pub(crate) fn connection(&self) -> &ConnectionHandle {
+        &self.connection
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionHandle {
+    closed: Arc<Notify>,
+    session_inbox: Arc<OnceLock<(SessionId, TioSender<InboundMessage>)>>,
+    // messages handed to the dispatcher that are not in a session inbox yet
+    routing: Arc<AtomicUsize>,
+    // queue of the connection's writer, only what is sent for this connection goes on it
+    outbound: TioSender<String>,
+    counters: Arc<ConnectionCounters>,
+    // manager the connection is registered with while it is live
+    registration: Arc<OnceLock<(ConnectionManager, ConnectionId)>>,
+}
+
+impl ConnectionHandle {
+    pub(crate) fn new(outbound: TioSender<String>) -> Self {
+        Self {
+            closed: Arc::default(),
+            session_inbox: Arc::default(),
+            routing: Arc::default(),
+            outbound,
+            counters: Arc::default(),
+            registration: Arc::default(),
+        }
+    }
+
+    // traffic is timed by clock instead of the system clock
+    pub(crate) fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.counters = Arc::new(ConnectionCounters::new(clock));
+        self
+    }
+
+    pub(crate) fn outbound(&self) -> &TioSender<String> {
+        &self.outbound
+    }
+
+    pub(crate) fn counters(&self) -> &Arc<ConnectionCounters> {
+        &self.counters
+    }
+
+    pub(crate) fn disconnect(&self) {
+        // notify_one keeps a permit when the reader is not waiting yet, so it is not lost
+        self.closed.notify_one();
+    }
+
+    pub(crate) fn registered(&self, manager: &ConnectionManager, id: ConnectionId) {
+        let _ = self.registration.set((manager.clone(), id));
+    }
+
+    pub(crate) fn close(&self) {
+        // closed by the manager of the connection, which lets the writer write what is queued
+        // once the reader is gone. one that was never registered only stops reading
+        match self.registration.get() {
+            Some((manager, id)) => {
+                manager.close(*id);
+            }
+            None => self.disconnect(),
+        }
+    }
+
+    pub(crate) async fn disconnected(&self) {
+        self.closed.notified().await;
+    }
+
+    pub(crate) fn bind(&self, session_id: &SessionId, session_inbox: &TioSender<InboundMessage>) {
+        let _ = self.session_inbox.set((session_id.clone(), session_inbox.clone()));
+    }
+
+    pub(crate) fn bound(&self) -> Option<&TioSender<InboundMessage>> {
+        self.session_inbox.get().map(|(_, inbox)| inbox)
+    }
+
+    pub(crate) fn bound_session(&self) -> Option<&SessionId> {
+        self.session_inbox.get().map(|(session_id, _)| session_id)
+    }
+
+    // where the next message read goes. a bound connection goes straight to its session only
+    // once the dispatcher has passed on everything read before, so that nothing overtakes it
+    pub(crate) fn next_hop<'a>(
+        &'a self, dispatcher: &'a TioSender<InboundMessage>,
+    ) -> &'a TioSender<InboundMessage> {
+        match self.bound() {
+            Some(inbox) if self.routing.load(Ordering::Acquire) == 0 => inbox,
+            _ => {
+                self.routing.fetch_add(1, Ordering::AcqRel);
+                dispatcher
+            }
+        }
+    }
+
+    // the dispatcher is done with a message of the connection, passed on or dropped
+    pub(crate) fn routed(&self) {
+        let _ =
+            self.routing.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod inbound_tests {
+    use super::*;
+    use crate::session::SessionIdBuilder;
+    use tokio::sync::mpsc::channel;
+
+    #[test]
+    fn test_bind() {
+        let (outbound, _outbound_rx) = channel(1);
+        let connection = ConnectionHandle::new(outbound);
+        assert!(connection.bound().is_none());
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let other_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "OTHER").build().unwrap();
+        let (first, mut first_rx) = channel(1);
+        let (second, _second_rx) = channel(1);
+        let source = "127.0.0.1:9880".parse().unwrap();
+        let inbound = InboundMessage::new("raw".to_string(), source, connection.clone());
+        inbound.bind(&session_id, &first);
+        inbound.bind(&other_id, &second);
+        assert_eq!(connection.bound_session(), Some(&session_id));
+        connection.bound().unwrap().try_send(inbound).unwrap();
+        assert_eq!(first_rx.try_recv().unwrap().raw, "raw");
+    }
+
+    #[test]
+    fn test_next_hop_keeps_order() {
+        let (outbound, _outbound_rx) = channel(1);
+        let connection = ConnectionHandle::new(outbound);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let (dispatcher, _dispatcher_rx) = channel(8);
+        let (inbox, _inbox_rx) = channel(8);
+        assert!(connection.next_hop(&dispatcher).same_channel(&dispatcher));
+
+        // the logon is accepted while a message read after it is still with the dispatcher
+        assert!(connection.next_hop(&dispatcher).same_channel(&dispatcher));
+        connection.bind(&session_id, &inbox);
+        connection.routed();
+        assert!(connection.next_hop(&dispatcher).same_channel(&dispatcher));
+        connection.routed();
+        connection.routed();
+
+        // nothing in between anymore, later messages can go straight to the session
+        assert!(connection.next_hop(&dispatcher).same_channel(&inbox));
+        assert!(connection.next_hop(&dispatcher).same_channel(&inbox));
+    }
+}
@@ -1,6 +1,23 @@
-pub(crate) use tokio::sync::{broadcast, mpsc};
+pub(crate) use tokio::sync::broadcast;
 
 pub(crate) mod acceptor;
+pub mod capture;
+pub mod connections;
+pub mod connector;
+pub(crate) mod frame;
+pub mod inbound;
+pub mod socket_options;
+pub mod stats;
+pub mod tap;
+pub mod transport;
+
+pub use connections::{ConnectionId, ConnectionInfo, ConnectionManager};
+pub use connector::{ProxyKind, ProxySettings};
+pub use inbound::InboundMessage;
+pub use socket_options::SocketOptions;
+pub use stats::ConnectionStats;
+pub use tap::{MessageTap, SharedTap};
+pub use transport::{FrameReader, FrameWriter, StreamTransport, Transport};
 
 pub type TioBroadcastSender<T> = broadcast::Sender<T>;
 pub type TioBroadcastReceiver<T> = broadcast::Receiver<T>;
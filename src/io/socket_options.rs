@@ -0,0 +1,162 @@
+use crate::session::*;
+use socket2::SockRef;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+// TCP tuning applied to every stream of a session. Nagle is disabled unless configured
+// otherwise since trading links care about latency more than packet count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    pub nodelay: bool,
+    pub keepalive: bool,
+    pub send_buffer_size: Option<usize>,
+    pub recv_buffer_size: Option<usize>,
+    // how long the writer keeps collecting queued messages before writing them out together.
+    // zero writes as soon as the queue is drained
+    pub flush_interval: Duration,
+    // connection is dropped when a message announces more bytes than this. unlimited when None
+    pub max_message_size: Option<usize>,
+    // connection is dropped when nothing is read from it for this long, e.g. when the peer's
+    // host went away without closing it. independent of heartbeats, never when None
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: false,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            flush_interval: Duration::ZERO,
+            max_message_size: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    pub fn from_settings(settings: &Properties, session_id: &SessionId) -> Self {
+        let default = Self::default();
+        Self {
+            nodelay: settings
+                .get_optional_config(session_id, SOCKET_NODELAY_SETTING)
+                .unwrap_or(default.nodelay),
+            keepalive: settings
+                .get_optional_config(session_id, SOCKET_KEEPALIVE_SETTING)
+                .unwrap_or(default.keepalive),
+            send_buffer_size: settings
+                .get_optional_config(session_id, SOCKET_SEND_BUFFER_SIZE_SETTING),
+            recv_buffer_size: settings
+                .get_optional_config(session_id, SOCKET_RECEIVE_BUFFER_SIZE_SETTING),
+            flush_interval: settings
+                .get_optional_config(session_id, SOCKET_FLUSH_INTERVAL_MS_SETTING)
+                .map(Duration::from_millis)
+                .unwrap_or(default.flush_interval),
+            max_message_size: settings.get_optional_config(session_id, MAX_MESSAGE_SIZE_SETTING),
+            idle_timeout: settings
+                .get_optional_config(session_id, SOCKET_IDLE_TIMEOUT_SETTING)
+                .map(Duration::from_secs),
+        }
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        // sessions accepted on one port share the socket, so it gets the most demanding
+        // value any of them asked for
+        Self {
+            nodelay: self.nodelay || other.nodelay,
+            keepalive: self.keepalive || other.keepalive,
+            send_buffer_size: self.send_buffer_size.max(other.send_buffer_size),
+            recv_buffer_size: self.recv_buffer_size.max(other.recv_buffer_size),
+            flush_interval: self.flush_interval.min(other.flush_interval),
+            // the largest limit, so that no session's messages are refused on a shared port
+            max_message_size: match (self.max_message_size, other.max_message_size) {
+                (Some(size), Some(other_size)) => Some(size.max(other_size)),
+                _ => None,
+            },
+            // the longest as well, a session that waits longer would lose its connection
+            idle_timeout: match (self.idle_timeout, other.idle_timeout) {
+                (Some(timeout), Some(other_timeout)) => Some(timeout.max(other_timeout)),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+        let sock = SockRef::from(stream);
+        sock.set_keepalive(self.keepalive)?;
+        if let Some(size) = self.send_buffer_size {
+            sock.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            sock.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod socket_options_tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        socket_keepalive = true
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 0
+        socket_nodelay = false
+        socket_send_buffer_size = 65536
+        socket_flush_interval_ms = 5
+        max_message_size = 4096
+        socket_idle_timeout = 90
+    "#;
+
+    #[test]
+    fn test_from_settings() {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let options = SocketOptions::from_settings(&settings, &session_id);
+        assert!(!options.nodelay);
+        assert!(options.keepalive);
+        assert_eq!(options.send_buffer_size, Some(65536));
+        assert_eq!(options.recv_buffer_size, None);
+        assert_eq!(options.flush_interval, Duration::from_millis(5));
+        assert_eq!(options.max_message_size, Some(4096));
+        assert_eq!(options.idle_timeout, Some(Duration::from_secs(90)));
+
+        let merged = options.merge(SocketOptions::default());
+        assert!(merged.nodelay);
+        assert!(merged.keepalive);
+        assert_eq!(merged.send_buffer_size, Some(65536));
+        assert_eq!(merged.flush_interval, Duration::ZERO);
+        assert_eq!(merged.max_message_size, None);
+        assert_eq!(merged.idle_timeout, None);
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let options = SocketOptions {
+            nodelay: true,
+            keepalive: true,
+            send_buffer_size: Some(65536),
+            recv_buffer_size: Some(65536),
+            ..Default::default()
+        };
+        options.apply(&stream).unwrap();
+        assert!(stream.nodelay().unwrap());
+        let sock = SockRef::from(&stream);
+        assert!(sock.keepalive().unwrap());
+        // the kernel may round the requested size up
+        assert!(sock.send_buffer_size().unwrap() >= 65536);
+        assert!(sock.recv_buffer_size().unwrap() >= 65536);
+    }
+}
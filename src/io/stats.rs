@@ -0,0 +1,168 @@
+use crate::clock::{Clock, SharedClock};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use tokio::sync::watch;
+
+// Traffic of one connection so far, as shown in the status of the session logged on over it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionStats {
+    pub connected_at: DateTime<Utc>,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub messages_in: u64,
+    pub messages_out: u64,
+    pub last_received_time: Option<DateTime<Utc>>,
+    pub last_sent_time: Option<DateTime<Utc>>,
+}
+
+// Counted by the connection's reader and writer tasks as they go. Outbound bytes are the ones
+// handed to the writer, before a MessageEncoding transcodes them. Times are taken from the
+// clock of the engine the connection is served by.
+#[derive(Debug)]
+pub(crate) struct ConnectionCounters {
+    clock: SharedClock,
+    connected_at: DateTime<Utc>,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+    last_received_time: AtomicTime,
+    last_sent_time: AtomicTime,
+    // sends put on the writer's queue, and how far the writer got with them
+    queued: AtomicU64,
+    write_progress: watch::Sender<WriteProgress>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct WriteProgress {
+    written: u64,
+    // the writer stopped, nothing queued after written goes out anymore
+    stopped: bool,
+}
+
+// time in microseconds since the epoch, so that it can be set without a lock. NONE until set
+#[derive(Debug)]
+struct AtomicTime(AtomicI64);
+
+impl AtomicTime {
+    const NONE: i64 = i64::MIN;
+
+    fn set(&self, time: DateTime<Utc>) {
+        self.0.store(time.timestamp_micros(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Option<DateTime<Utc>> {
+        match self.0.load(Ordering::Relaxed) {
+            Self::NONE => None,
+            micros => DateTime::from_timestamp_micros(micros),
+        }
+    }
+}
+
+impl Default for AtomicTime {
+    fn default() -> Self {
+        Self(AtomicI64::new(Self::NONE))
+    }
+}
+
+impl Default for ConnectionCounters {
+    fn default() -> Self {
+        Self::new(SharedClock::default())
+    }
+}
+
+impl ConnectionCounters {
+    pub(crate) fn new(clock: SharedClock) -> Self {
+        Self {
+            connected_at: clock.now(),
+            clock,
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            messages_in: AtomicU64::new(0),
+            messages_out: AtomicU64::new(0),
+            last_received_time: AtomicTime::default(),
+            last_sent_time: AtomicTime::default(),
+            queued: AtomicU64::new(0),
+            write_progress: watch::channel(WriteProgress::default()).0,
+        }
+    }
+
+    pub(crate) fn received(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.messages_in.fetch_add(1, Ordering::Relaxed);
+        self.last_received_time.set(self.clock.now());
+    }
+
+    pub(crate) fn sent(&self, messages: usize, bytes: usize) {
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.messages_out.fetch_add(messages as u64, Ordering::Relaxed);
+        self.last_sent_time.set(self.clock.now());
+    }
+
+    // sends are numbered in the order they are queued, the writer takes them in the same order
+    pub(crate) fn queued(&self) -> u64 {
+        self.queued.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub(crate) fn written(&self, sends: usize) {
+        self.write_progress.send_modify(|progress| progress.written += sends as u64);
+    }
+
+    pub(crate) fn writer_stopped(&self) {
+        self.write_progress.send_modify(|progress| progress.stopped = true);
+    }
+
+    pub(crate) async fn wait_written(&self, send: u64) -> bool {
+        // false if the writer stopped before it got to the send
+        let mut progress = self.write_progress.subscribe();
+        let progress = progress.wait_for(|progress| progress.written >= send || progress.stopped);
+        progress.await.is_ok_and(|progress| progress.written >= send)
+    }
+
+    pub(crate) fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            connected_at: self.connected_at,
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            messages_in: self.messages_in.load(Ordering::Relaxed),
+            messages_out: self.messages_out.load(Ordering::Relaxed),
+            last_received_time: self.last_received_time.get(),
+            last_sent_time: self.last_sent_time.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_written() {
+        let counters = ConnectionCounters::default();
+        assert_eq!(counters.queued(), 1);
+        assert_eq!(counters.queued(), 2);
+        counters.written(1);
+        assert!(counters.wait_written(1).await);
+        let second = counters.wait_written(2);
+        counters.writer_stopped();
+        assert!(!second.await);
+    }
+
+    #[test]
+    fn test_times_from_clock() {
+        use crate::clock::ManualClock;
+        let start = DateTime::parse_from_rfc3339("2024-03-05T10:00:00Z").unwrap().to_utc();
+        let clock = ManualClock::new(start);
+        let counters = ConnectionCounters::new(SharedClock::new(clock.clone()));
+        assert_eq!(counters.stats().last_received_time, None);
+        clock.advance(chrono::Duration::milliseconds(1500));
+        counters.received(10);
+        clock.advance(chrono::Duration::seconds(1));
+        counters.sent(1, 20);
+        let stats = counters.stats();
+        assert_eq!(stats.connected_at, start);
+        assert_eq!(stats.last_received_time, Some(start + chrono::Duration::milliseconds(1500)));
+        assert_eq!(stats.last_sent_time, Some(start + chrono::Duration::milliseconds(2500)));
+    }
+}
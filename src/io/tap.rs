@@ -0,0 +1,51 @@
+use crate::io::capture::Direction;
+use crate::session::SessionId;
+use std::sync::Arc;
+
+// Sees every frame as the bytes that went over the wire: inbound ones as read, before they are
+// parsed, outbound ones as written. The session is the one the frame is for, None when its
+// header does not name one. Compliance capture can be fed from here without serializing parsed
+// messages again. It is called on the connection's tasks, so it should hand the bytes off
+// rather than block. Closures with the same signature implement it as well.
+//
+//     let acceptor = SocketAcceptor::new(settings, app).with_tap(
+//         move |sid: Option<&SessionId>, direction: Direction, bytes: &[u8]| {
+//             archive.send((sid.cloned(), direction, bytes.to_vec()))
+//         },
+//     );
+pub trait MessageTap: Send + Sync {
+    fn on_frame(&self, session_id: Option<&SessionId>, direction: Direction, bytes: &[u8]);
+}
+
+impl<F> MessageTap for F
+where
+    F: Fn(Option<&SessionId>, Direction, &[u8]) + Send + Sync,
+{
+    fn on_frame(&self, session_id: Option<&SessionId>, direction: Direction, bytes: &[u8]) {
+        self(session_id, direction, bytes)
+    }
+}
+
+// shared handle to a tap, so that holders can still derive Debug
+#[derive(Clone)]
+pub struct SharedTap(Arc<dyn MessageTap>);
+
+impl SharedTap {
+    pub fn new(tap: impl MessageTap + 'static) -> Self {
+        Self(Arc::new(tap))
+    }
+}
+
+impl std::ops::Deref for SharedTap {
+    type Target = dyn MessageTap;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for SharedTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedTap")
+    }
+}
@@ -0,0 +1,199 @@
+use crate::io::acceptor::{write_batch, IN_PROCESS_ADDR};
+use crate::io::frame::{read_frame, FrameDecoder};
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+
+// Connection the session engine exchanges messages over. The engine reads and writes on
+// separate tasks, so a transport is split into its reading and writing halves before use.
+// TCP, unix domain sockets and in-memory pipes are transports as they are, any other stream
+// (e.g. TLS from tokio-rustls or tokio-native-tls) is one when wrapped in StreamTransport.
+pub trait Transport: Send + 'static {
+    type Reader: FrameReader;
+    type Writer: FrameWriter;
+
+    // reported as the source of messages read from the transport
+    fn peer_addr(&self) -> SocketAddr;
+    fn into_split(self) -> (Self::Reader, Self::Writer);
+}
+
+pub trait FrameReader: Send + 'static {
+    // next complete message. has to be cancel safe, the engine stops waiting on it when the
+    // connection is dropped
+    fn read_frame(&mut self) -> impl Future<Output = std::io::Result<Vec<u8>>> + Send;
+    // messages announcing more bytes than this are a read error. readers that frame messages
+    // some other way can ignore it
+    fn set_max_message_size(&mut self, max_message_size: Option<usize>) {}
+}
+
+pub trait FrameWriter: Send + 'static {
+    // messages, already encoded, are written in order and flushed before returning
+    fn write_frames(
+        &mut self, frames: &[&[u8]],
+    ) -> impl Future<Output = std::io::Result<()>> + Send;
+    fn close(&mut self) -> impl Future<Output = std::io::Result<()>> + Send;
+}
+
+#[derive(Debug)]
+pub struct StreamReader<R> {
+    reader: R,
+    decoder: FrameDecoder,
+}
+
+impl<R> StreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: FrameDecoder::default(),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send + 'static> FrameReader for StreamReader<R> {
+    async fn read_frame(&mut self) -> std::io::Result<Vec<u8>> {
+        read_frame(&mut self.reader, &mut self.decoder).await
+    }
+
+    fn set_max_message_size(&mut self, max_message_size: Option<usize>) {
+        self.decoder.set_max_message_size(max_message_size);
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamWriter<W> {
+    writer: W,
+}
+
+impl<W> StreamWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send + 'static> FrameWriter for StreamWriter<W> {
+    async fn write_frames(&mut self, frames: &[&[u8]]) -> std::io::Result<()> {
+        write_batch(&mut self.writer, frames).await
+    }
+
+    async fn close(&mut self) -> std::io::Result<()> {
+        self.writer.shutdown().await
+    }
+}
+
+// Any byte stream as a transport, for streams that do not come with their own split
+#[derive(Debug)]
+pub struct StreamTransport<S> {
+    stream: S,
+    peer_addr: SocketAddr,
+}
+
+impl<S> StreamTransport<S> {
+    pub fn new(stream: S, peer_addr: SocketAddr) -> Self {
+        Self { stream, peer_addr }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + 'static> Transport for StreamTransport<S> {
+    type Reader = StreamReader<ReadHalf<S>>;
+    type Writer = StreamWriter<WriteHalf<S>>;
+
+    fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    fn into_split(self) -> (Self::Reader, Self::Writer) {
+        let (reader, writer) = tokio::io::split(self.stream);
+        (StreamReader::new(reader), StreamWriter::new(writer))
+    }
+}
+
+impl Transport for TcpStream {
+    type Reader = StreamReader<tokio::net::tcp::OwnedReadHalf>;
+    type Writer = StreamWriter<tokio::net::tcp::OwnedWriteHalf>;
+
+    fn peer_addr(&self) -> SocketAddr {
+        TcpStream::peer_addr(self).unwrap_or(IN_PROCESS_ADDR)
+    }
+
+    fn into_split(self) -> (Self::Reader, Self::Writer) {
+        let (reader, writer) = TcpStream::into_split(self);
+        (StreamReader::new(reader), StreamWriter::new(writer))
+    }
+}
+
+// unix domain sockets have no SocketAddr, they are reported like in-process connections
+#[cfg(unix)]
+impl Transport for tokio::net::UnixStream {
+    type Reader = StreamReader<tokio::net::unix::OwnedReadHalf>;
+    type Writer = StreamWriter<tokio::net::unix::OwnedWriteHalf>;
+
+    fn peer_addr(&self) -> SocketAddr {
+        IN_PROCESS_ADDR
+    }
+
+    fn into_split(self) -> (Self::Reader, Self::Writer) {
+        let (reader, writer) = tokio::net::UnixStream::into_split(self);
+        (StreamReader::new(reader), StreamWriter::new(writer))
+    }
+}
+
+impl Transport for DuplexStream {
+    type Reader = StreamReader<ReadHalf<DuplexStream>>;
+    type Writer = StreamWriter<WriteHalf<DuplexStream>>;
+
+    fn peer_addr(&self) -> SocketAddr {
+        IN_PROCESS_ADDR
+    }
+
+    fn into_split(self) -> (Self::Reader, Self::Writer) {
+        let (reader, writer) = tokio::io::split(self);
+        (StreamReader::new(reader), StreamWriter::new(writer))
+    }
+}
+
+#[cfg(test)]
+mod transport_tests {
+    use super::*;
+
+    const HEARTBEAT: &str = "8=FIX.4.3\u{1}9=5\u{1}35=0\u{1}10=163\u{1}";
+
+    async fn exchange<T: Transport>(local: T, remote: T) {
+        let (_, mut local_writer) = local.into_split();
+        let (mut remote_reader, _) = remote.into_split();
+        local_writer.write_frames(&[HEARTBEAT.as_bytes(), HEARTBEAT.as_bytes()]).await.unwrap();
+        assert_eq!(remote_reader.read_frame().await.unwrap(), HEARTBEAT.as_bytes());
+        assert_eq!(remote_reader.read_frame().await.unwrap(), HEARTBEAT.as_bytes());
+        local_writer.close().await.unwrap();
+        assert!(remote_reader.read_frame().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (remote, remote_addr) = listener.accept().await.unwrap();
+        assert_eq!(Transport::peer_addr(&local), listener.local_addr().unwrap());
+        assert_eq!(Transport::peer_addr(&remote), remote_addr);
+        exchange(local, remote).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix() {
+        let (local, remote) = tokio::net::UnixStream::pair().unwrap();
+        exchange(local, remote).await;
+    }
+
+    #[tokio::test]
+    async fn test_in_memory() {
+        let (local, remote) = tokio::io::duplex(1024);
+        exchange(local, remote).await;
+        let (local, remote) = tokio::io::duplex(1024);
+        let peer_addr: SocketAddr = "10.0.0.1:9880".parse().unwrap();
+        let (local, remote) =
+            (StreamTransport::new(local, peer_addr), StreamTransport::new(remote, peer_addr));
+        assert_eq!(local.peer_addr(), peer_addr);
+        exchange(local, remote).await;
+    }
+}
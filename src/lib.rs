@@ -0,0 +1,25 @@
+#![allow(dead_code, unused_variables)]
+
+include!(concat!(env!("OUT_DIR"), "/mod.rs"));
+
+pub mod acceptance;
+pub mod application;
+pub mod clock;
+pub mod data_dictionary;
+pub mod dictionary_lint;
+pub mod dispatch;
+pub mod events;
+pub mod fuzzing;
+pub mod io;
+pub mod market_data;
+pub mod message;
+pub mod network;
+pub mod orders;
+pub mod quickfix_errors;
+pub mod replication;
+pub mod router;
+pub mod session;
+pub mod simulator;
+#[cfg(test)]
+mod test_utils;
+pub mod types;
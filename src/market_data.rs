@@ -0,0 +1,520 @@
+use crate::field_values::{MDEntryType, MDReqRejReason, MDUpdateAction, SubscriptionRequestType};
+use crate::fields::{
+    MDEntryID, MDEntryPx, MDEntrySize, MDReqID, MarketDepth, NoMDEntries, NoMDEntryTypes,
+    NoRelatedSym, Symbol, Text,
+};
+use crate::groups::{
+    market_data_incremental_refresh, market_data_request, market_data_snapshot_full_refresh,
+};
+use crate::message::*;
+use crate::network::SessionMap;
+use crate::quickfix_errors::{SendError, SessionRejectError};
+use crate::session::{Session, SessionId};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+type SessResult<T> = Result<T, SessionRejectError>;
+type SubscribeHandler = Box<
+    dyn Fn(&SessionId, &SessionMap, &MDSubscription) -> Result<(), MDReqRejReason> + Send + Sync,
+>;
+type UnsubscribeHandler = Box<dyn Fn(&SessionId, &MDSubscription) + Send + Sync>;
+
+// One instance of NoMDEntries(268) of a MarketDataSnapshotFullRefresh(W) or
+// MarketDataIncrementalRefresh(X). Prices and sizes are kept as they go on the wire, so they
+// can be given as whatever numeric type the application uses.
+//
+//     let bid = MDEntry::new(MDEntryType::Bid).px(101.25).size(500);
+//     let gone = MDEntry::update(MDUpdateAction::Delete, MDEntryType::Offer).id("OFFER7");
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MDEntry {
+    // only in incremental refreshes
+    pub update_action: Option<MDUpdateAction>,
+    pub entry_type: Option<MDEntryType>,
+    pub entry_id: Option<String>,
+    // incremental refreshes name the instrument per entry, snapshots once for all of them
+    pub symbol: Option<String>,
+    pub px: Option<String>,
+    pub size: Option<String>,
+    // any other field of the instance
+    pub fields: Vec<(u32, String)>,
+}
+
+impl MDEntry {
+    pub fn new(entry_type: MDEntryType) -> Self {
+        Self {
+            entry_type: Some(entry_type),
+            ..Default::default()
+        }
+    }
+
+    pub fn update(action: MDUpdateAction, entry_type: MDEntryType) -> Self {
+        Self {
+            update_action: Some(action),
+            ..Self::new(entry_type)
+        }
+    }
+
+    pub fn id(mut self, entry_id: &str) -> Self {
+        self.entry_id = Some(entry_id.to_string());
+        self
+    }
+
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = Some(symbol.to_string());
+        self
+    }
+
+    pub fn px(mut self, px: impl Display) -> Self {
+        self.px = Some(px.to_string());
+        self
+    }
+
+    pub fn size(mut self, size: impl Display) -> Self {
+        self.size = Some(size.to_string());
+        self
+    }
+
+    pub fn with_field(mut self, tag: u32, value: &str) -> Self {
+        self.fields.push((tag, value.to_string()));
+        self
+    }
+
+    const TAGS: [u32; 6] = [279, 269, 278, 55, 270, 271];
+
+    fn write_to(&self, instance: &mut FieldMap) {
+        let typed = [
+            (MDUpdateAction::field(), self.update_action.map(|a| a.value().to_string())),
+            (MDEntryType::field(), self.entry_type.map(|t| t.value().to_string())),
+            (MDEntryID::field(), self.entry_id.clone()),
+            (Symbol::field(), self.symbol.clone()),
+            (MDEntryPx::field(), self.px.clone()),
+            (MDEntrySize::field(), self.size.clone()),
+        ];
+        for (tag, value) in typed {
+            if let Some(value) = value {
+                instance.set_field(StringField::new(tag, &value));
+            }
+        }
+        for (tag, value) in &self.fields {
+            instance.set_field(StringField::new(*tag, value));
+        }
+    }
+
+    fn read_from(instance: &FieldMap) -> Self {
+        Self {
+            update_action: instance.get_field::<MDUpdateAction>(MDUpdateAction::field()).ok(),
+            entry_type: instance.get_field::<MDEntryType>(MDEntryType::field()).ok(),
+            entry_id: instance.get_field(MDEntryID::field()).ok(),
+            symbol: instance.get_field(Symbol::field()).ok(),
+            px: instance.get_field(MDEntryPx::field()).ok(),
+            size: instance.get_field(MDEntrySize::field()).ok(),
+            fields: instance
+                .iter()
+                .into_iter()
+                .filter(|field| !Self::TAGS.contains(&field.tag()))
+                .map(|field| (field.tag(), field.value().to_string()))
+                .collect(),
+        }
+    }
+}
+
+pub fn snapshot(
+    session: &Session, md_req_id: Option<&str>, symbol: &str, entries: &[MDEntry],
+) -> SessResult<Message> {
+    // full book of one instrument, answering md_req_id or unsolicited
+    let mut msg = session.new_message("W")?;
+    if let Some(md_req_id) = md_req_id {
+        msg.set_field(StringField::new(MDReqID::field(), md_req_id));
+    }
+    msg.set_field(StringField::new(Symbol::field(), symbol));
+    let delimiter = market_data_snapshot_full_refresh::NoMDEntries::delimiter();
+    let group = msg.set_group(NoMDEntries::field(), entries.len() as u32, delimiter);
+    for (i, entry) in entries.iter().enumerate() {
+        group[i].set_field_order(market_data_snapshot_full_refresh::NoMDEntries::fields());
+        entry.write_to(&mut group[i]);
+    }
+    Ok(msg)
+}
+
+pub fn incremental_refresh(
+    session: &Session, md_req_id: Option<&str>, entries: &[MDEntry],
+) -> SessResult<Message> {
+    let mut msg = session.new_message("X")?;
+    if let Some(md_req_id) = md_req_id {
+        msg.set_field(StringField::new(MDReqID::field(), md_req_id));
+    }
+    let delimiter = market_data_incremental_refresh::NoMDEntries::delimiter();
+    let group = msg.set_group(NoMDEntries::field(), entries.len() as u32, delimiter);
+    for (i, entry) in entries.iter().enumerate() {
+        group[i].set_field_order(market_data_incremental_refresh::NoMDEntries::fields());
+        entry.write_to(&mut group[i]);
+    }
+    Ok(msg)
+}
+
+pub fn md_entries(msg: &Message) -> Vec<MDEntry> {
+    // entries of a received W or X, empty for any other message
+    match msg.get_group(NoMDEntries::field()) {
+        Some(group) => (0..group.size() as usize).map(|i| MDEntry::read_from(&group[i])).collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn market_data_request(
+    session: &Session, subscription: &MDSubscription, request_type: SubscriptionRequestType,
+) -> SessResult<Message> {
+    // the initiator side of a subscription, also to end one
+    let mut msg = session.new_message("V")?;
+    msg.set_field(StringField::new(MDReqID::field(), &subscription.md_req_id));
+    msg.set_field(StringField::new(SubscriptionRequestType::field(), request_type.value()));
+    msg.set_field(StringField::new(MarketDepth::field(), &subscription.market_depth.to_string()));
+    let entry_types = &subscription.entry_types;
+    let delimiter = market_data_request::NoMDEntryTypes::delimiter();
+    let group = msg.set_group(NoMDEntryTypes::field(), entry_types.len() as u32, delimiter);
+    for (i, entry_type) in entry_types.iter().enumerate() {
+        group[i].set_field_order(market_data_request::NoMDEntryTypes::fields());
+        group[i].set_field(StringField::new(MDEntryType::field(), entry_type.value()));
+    }
+    let symbols = &subscription.symbols;
+    let delimiter = market_data_request::NoRelatedSym::delimiter();
+    let group = msg.set_group(NoRelatedSym::field(), symbols.len() as u32, delimiter);
+    for (i, symbol) in symbols.iter().enumerate() {
+        group[i].set_field_order(market_data_request::NoRelatedSym::fields());
+        group[i].set_field(StringField::new(Symbol::field(), symbol));
+    }
+    Ok(msg)
+}
+
+pub fn market_data_request_reject(
+    session: &Session, md_req_id: &str, reason: MDReqRejReason,
+) -> SessResult<Message> {
+    let mut msg = session.new_message("Y")?;
+    msg.set_field(StringField::new(MDReqID::field(), md_req_id));
+    msg.set_field(StringField::new(MDReqRejReason::field(), reason.value()));
+    msg.set_field(StringField::new(Text::field(), reason.description()));
+    Ok(msg)
+}
+
+// What a MarketDataRequest(V) asks for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MDSubscription {
+    pub md_req_id: String,
+    pub symbols: Vec<String>,
+    pub entry_types: Vec<MDEntryType>,
+    // 0 is the full book, 1 the top of it
+    pub market_depth: u32,
+    // false for a snapshot only, nothing is kept for it
+    pub updates: bool,
+}
+
+impl MDSubscription {
+    pub fn new(md_req_id: &str, symbols: &[&str], entry_types: &[MDEntryType]) -> Self {
+        Self {
+            md_req_id: md_req_id.to_string(),
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            entry_types: entry_types.to_vec(),
+            market_depth: 0,
+            updates: true,
+        }
+    }
+
+    fn from_request(msg: &Message) -> (Self, Option<SubscriptionRequestType>) {
+        let instances = |tag: u32| {
+            let group = msg.get_group(tag);
+            (0..group.map_or(0, |g| g.size() as usize)).map(move |i| &group.unwrap()[i])
+        };
+        let request_type = msg.get_field(SubscriptionRequestType::field()).ok();
+        let subscription = Self {
+            md_req_id: msg.get_field(MDReqID::field()).unwrap_or_default(),
+            symbols: instances(NoRelatedSym::field())
+                .filter_map(|instance| instance.get_field(Symbol::field()).ok())
+                .collect(),
+            entry_types: instances(NoMDEntryTypes::field())
+                .filter_map(|instance| instance.get_field(MDEntryType::field()).ok())
+                .collect(),
+            market_depth: msg.get_field(MarketDepth::field()).unwrap_or_default(),
+            updates: request_type == Some(SubscriptionRequestType::SnapshotPlusUpdates),
+        };
+        (subscription, request_type)
+    }
+}
+
+// Keeps the market data subscriptions of every session by MDReqID(262), and hands requests to
+// the application. The subscribe handler sends the snapshot, usually with snapshot(), and
+// returning an MDReqRejReason refuses the request with a MarketDataRequestReject(Y).
+// Incremental refreshes then go to every session subscribed to the symbol with publish.
+//
+//     let mut subscriptions = MarketDataSubscriptions::new();
+//     subscriptions.on_subscribe(|sid, sessions, subscription| { .. });
+//     // in Application::from_app, for msg type V
+//     subscriptions.handle_request(ctx.session_id(), ctx.sessions(), &msg);
+//     // as the book changes
+//     subscriptions.publish(sessions, "EUR/USD", &[MDEntry::update(..).px(..)]);
+#[derive(Default)]
+pub struct MarketDataSubscriptions {
+    subscriptions: Mutex<HashMap<SessionId, Vec<MDSubscription>>>,
+    on_subscribe: Option<SubscribeHandler>,
+    on_unsubscribe: Option<UnsubscribeHandler>,
+}
+
+impl MarketDataSubscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_subscribe(
+        &mut self,
+        handler: impl Fn(&SessionId, &SessionMap, &MDSubscription) -> Result<(), MDReqRejReason>
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self {
+        self.on_subscribe = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_unsubscribe(
+        &mut self, handler: impl Fn(&SessionId, &MDSubscription) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_unsubscribe = Some(Box::new(handler));
+        self
+    }
+
+    pub fn handle_request(
+        &self, session_id: &SessionId, sessions: &SessionMap, msg: &Message,
+    ) -> Result<(), SendError> {
+        let (subscription, request_type) = MDSubscription::from_request(msg);
+        let md_req_id = subscription.md_req_id.clone();
+        let reason = match request_type {
+            Some(SubscriptionRequestType::DisablePreviousSnapshotPlusUpdateRequest) => {
+                let removed = self.remove(session_id, &md_req_id);
+                if let (Some(removed), Some(handler)) = (removed, &self.on_unsubscribe) {
+                    handler(session_id, &removed);
+                }
+                return Ok(());
+            }
+            None => Some(MDReqRejReason::UnsupportedSubscriptionrequesttype),
+            Some(_) if subscription.updates && self.is_subscribed(session_id, &md_req_id) => {
+                Some(MDReqRejReason::DuplicateMdreqid)
+            }
+            // the lock is not held while the application handles the request
+            Some(_) => self
+                .on_subscribe
+                .as_ref()
+                .and_then(|handler| handler(session_id, sessions, &subscription).err()),
+        };
+        let Some(reason) = reason else {
+            if subscription.updates {
+                let mut subscriptions = self.subscriptions.lock().unwrap();
+                subscriptions.entry(session_id.clone()).or_default().push(subscription);
+            }
+            return Ok(());
+        };
+        let reject = sessions
+            .get_session(session_id)
+            .map(|sess| market_data_request_reject(&sess, &md_req_id, reason));
+        match reject {
+            Some(Ok(reject)) => Session::sync_send_to_target(session_id, sessions, reject),
+            _ => Err(SendError::SessionNotFound(session_id.to_string())),
+        }
+    }
+
+    pub fn subscriptions(&self, session_id: &SessionId) -> Vec<MDSubscription> {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.get(session_id).cloned().unwrap_or_default()
+    }
+
+    pub fn subscribers(&self, symbol: &str) -> Vec<(SessionId, MDSubscription)> {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions
+            .iter()
+            .flat_map(|(session_id, subscribed)| {
+                subscribed
+                    .iter()
+                    .filter(|subscription| subscription.symbols.iter().any(|s| s == symbol))
+                    .map(|subscription| (session_id.clone(), subscription.clone()))
+            })
+            .collect()
+    }
+
+    pub fn publish(
+        &self, sessions: &SessionMap, symbol: &str, entries: &[MDEntry],
+    ) -> Vec<(SessionId, SendError)> {
+        // an incremental refresh to every subscription of the symbol, with the entries of the
+        // types it asked for. what could not be sent is returned, the rest went out
+        let mut failed = Vec::new();
+        for (session_id, subscription) in self.subscribers(symbol) {
+            let entries: Vec<MDEntry> = entries
+                .iter()
+                .filter(|entry| {
+                    entry.entry_type.is_none_or(|t| subscription.entry_types.contains(&t))
+                })
+                .map(|entry| MDEntry {
+                    symbol: entry.symbol.clone().or_else(|| Some(symbol.to_string())),
+                    ..entry.clone()
+                })
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            let md_req_id = Some(subscription.md_req_id.as_str());
+            let refresh = sessions
+                .get_session(&session_id)
+                .map(|sess| incremental_refresh(&sess, md_req_id, &entries));
+            let result = match refresh {
+                Some(Ok(refresh)) => Session::sync_send_to_target(&session_id, sessions, refresh),
+                _ => Err(SendError::SessionNotFound(session_id.to_string())),
+            };
+            if let Err(e) = result {
+                failed.push((session_id, e));
+            }
+        }
+        failed
+    }
+
+    pub fn remove_session(&self, session_id: &SessionId) {
+        // e.g. on logout, a new logon has to subscribe again
+        self.subscriptions.lock().unwrap().remove(session_id);
+    }
+
+    fn is_subscribed(&self, session_id: &SessionId, md_req_id: &str) -> bool {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.get(session_id).is_some_and(|subscribed| {
+            subscribed.iter().any(|subscription| subscription.md_req_id == md_req_id)
+        })
+    }
+
+    fn remove(&self, session_id: &SessionId, md_req_id: &str) -> Option<MDSubscription> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let subscribed = subscriptions.get_mut(session_id)?;
+        let pos = subscribed.iter().position(|subscription| subscription.md_req_id == md_req_id)?;
+        Some(subscribed.remove(pos))
+    }
+}
+
+#[cfg(test)]
+mod market_data_tests {
+    use super::*;
+    use crate::session::{Properties, SessionIdBuilder};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10117
+    "#;
+
+    fn test_sessions() -> (SessionId, SessionMap, Receiver<String>) {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, rx) = channel::<String>(16);
+        session.set_responder(Some(tx));
+        (session_id.clone(), SessionMap::from_iter([(session_id, session)]), rx)
+    }
+
+    fn sent(sessions: &SessionMap, session_id: &SessionId, rx: &mut Receiver<String>) -> Message {
+        let dd = Arc::clone(sessions.get_session(session_id).unwrap().data_dictionary());
+        Message::from_str(&rx.try_recv().unwrap(), &dd).unwrap()
+    }
+
+    #[test]
+    fn test_refresh_round_trip() {
+        let (session_id, sessions, mut rx) = test_sessions();
+        let book = [
+            MDEntry::new(MDEntryType::Bid).px(1.0825).size(1000000),
+            MDEntry::new(MDEntryType::Offer).px(1.0827).size(500000).with_field(346, "3"),
+        ];
+        let msg =
+            snapshot(&sessions.get_session(&session_id).unwrap(), Some("REQ1"), "EUR/USD", &book);
+        Session::sync_send_to_target(&session_id, &sessions, msg.unwrap()).unwrap();
+        let received = sent(&sessions, &session_id, &mut rx);
+        assert_eq!(received.get_field::<String>(262).unwrap(), "REQ1");
+        assert_eq!(received.get_field::<String>(55).unwrap(), "EUR/USD");
+        assert_eq!(md_entries(&received), book);
+
+        let changes = [
+            MDEntry::update(MDUpdateAction::Change, MDEntryType::Bid).id("B1").px(1.0826),
+            MDEntry::update(MDUpdateAction::Delete, MDEntryType::Offer).id("O1"),
+        ];
+        let msg = incremental_refresh(&sessions.get_session(&session_id).unwrap(), None, &changes);
+        Session::sync_send_to_target(&session_id, &sessions, msg.unwrap()).unwrap();
+        let received = sent(&sessions, &session_id, &mut rx);
+        assert!(received.get_field::<String>(262).is_err());
+        assert_eq!(md_entries(&received), changes);
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let (session_id, sessions, mut rx) = test_sessions();
+        let unsubscribed = Arc::new(Mutex::new(Vec::new()));
+        let mut subscriptions = MarketDataSubscriptions::new();
+        let removed = Arc::clone(&unsubscribed);
+        subscriptions
+            .on_subscribe(|sid, sessions, subscription| {
+                if subscription.symbols.iter().any(|symbol| symbol == "UNKNOWN") {
+                    return Err(MDReqRejReason::UnknownSymbol);
+                }
+                let bid = MDEntry::new(MDEntryType::Bid).px(150).size(10);
+                let md_req_id = Some(subscription.md_req_id.as_str());
+                let msg =
+                    snapshot(&sessions.get_session(sid).unwrap(), md_req_id, "USD/JPY", &[bid]);
+                Session::sync_send_to_target(sid, sessions, msg.unwrap()).unwrap();
+                Ok(())
+            })
+            .on_unsubscribe(move |_, subscription| {
+                removed.lock().unwrap().push(subscription.md_req_id.clone())
+            });
+        let request = |subscription: &MDSubscription, request_type| {
+            let session = sessions.get_session(&session_id).unwrap();
+            market_data_request(&session, subscription, request_type).unwrap()
+        };
+        let book =
+            MDSubscription::new("REQ1", &["USD/JPY"], &[MDEntryType::Bid, MDEntryType::Offer]);
+        let subscribe = request(&book, SubscriptionRequestType::SnapshotPlusUpdates);
+
+        subscriptions.handle_request(&session_id, &sessions, &subscribe).unwrap();
+        assert_eq!(sent(&sessions, &session_id, &mut rx).get_msg_type().unwrap(), "W");
+        assert_eq!(subscriptions.subscriptions(&session_id), vec![book.clone()]);
+        assert_eq!(subscriptions.subscribers("USD/JPY"), vec![(session_id.clone(), book.clone())]);
+
+        // the same MDReqID again, and a symbol the application does not know
+        subscriptions.handle_request(&session_id, &sessions, &subscribe).unwrap();
+        let reject = sent(&sessions, &session_id, &mut rx);
+        assert_eq!(reject.get_msg_type().unwrap(), "Y");
+        assert_eq!(reject.get_field::<String>(281).unwrap(), "1");
+        let unknown = MDSubscription::new("REQ2", &["UNKNOWN"], &[MDEntryType::Bid]);
+        let unknown = request(&unknown, SubscriptionRequestType::SnapshotPlusUpdates);
+        subscriptions.handle_request(&session_id, &sessions, &unknown).unwrap();
+        let reject = sent(&sessions, &session_id, &mut rx);
+        assert_eq!(reject.get_field::<String>(262).unwrap(), "REQ2");
+        assert_eq!(reject.get_field::<String>(281).unwrap(), "0");
+
+        // only the entry types asked for, named by the subscription's symbol
+        let update = [
+            MDEntry::update(MDUpdateAction::New, MDEntryType::Offer).px(150.02).size(5),
+            MDEntry::update(MDUpdateAction::New, MDEntryType::Trade).px(150.01).size(1),
+        ];
+        assert!(subscriptions.publish(&sessions, "USD/JPY", &update).is_empty());
+        let refresh = sent(&sessions, &session_id, &mut rx);
+        assert_eq!(refresh.get_msg_type().unwrap(), "X");
+        assert_eq!(refresh.get_field::<String>(262).unwrap(), "REQ1");
+        assert_eq!(md_entries(&refresh), vec![update[0].clone().symbol("USD/JPY")]);
+        subscriptions.publish(&sessions, "EUR/USD", &update);
+        assert!(rx.try_recv().is_err());
+
+        let unsubscribe =
+            request(&book, SubscriptionRequestType::DisablePreviousSnapshotPlusUpdateRequest);
+        subscriptions.handle_request(&session_id, &sessions, &unsubscribe).unwrap();
+        assert!(subscriptions.subscriptions(&session_id).is_empty());
+        assert_eq!(*unsubscribed.lock().unwrap(), ["REQ1"]);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,263 @@
+// Field by field comparison of messages, for tests to assert on and for showing what a
+// counterparty sent differently. Fields are compared by tag regardless of order, fields of
+// repeating groups instance by instance.
+use crate::message::{FieldMap, Message, Tag};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Body,
+    Trailer,
+}
+
+// Where a field is: its section, unknown for a field that is in none, and the NumInGroup tag and
+// instance index of every repeating group it is nested in, outermost first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    pub section: Option<Section>,
+    pub groups: Vec<(Tag, usize)>,
+    pub tag: Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldDiff {
+    Missing {
+        path: FieldPath,
+        expected: String,
+    },
+    Extra {
+        path: FieldPath,
+        actual: String,
+    },
+    Changed {
+        path: FieldPath,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageDiff {
+    pub diffs: Vec<FieldDiff>,
+}
+
+impl FieldDiff {
+    pub fn path(&self) -> &FieldPath {
+        match self {
+            FieldDiff::Missing { path, .. } => path,
+            FieldDiff::Extra { path, .. } => path,
+            FieldDiff::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl MessageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.diffs.is_empty()
+    }
+
+    // leaves out differences in the given tags, e.g. SendingTime(52), BodyLength(9) and
+    // CheckSum(10) that differ from run to run
+    pub fn ignoring(mut self, tags: &[Tag]) -> Self {
+        self.diffs.retain(|diff| !tags.contains(&diff.path().tag));
+        self
+    }
+}
+
+// every field of expected that actual lacks or has another value, and every field only actual
+// has
+pub fn diff(expected: &Message, actual: &Message) -> MessageDiff {
+    let mut diffs = Vec::new();
+    let sections = [
+        (Section::Header, &*expected.header, &*actual.header),
+        (Section::Body, &expected.body, &actual.body),
+        (Section::Trailer, &*expected.trailer, &*actual.trailer),
+    ];
+    for (section, expected, actual) in sections {
+        diff_maps(Some(section), &[], expected, actual, &mut diffs);
+    }
+    MessageDiff { diffs }
+}
+
+// only the given fields are looked for, in any section of actual. fields of repeating groups
+// can not be named this way
+pub fn diff_fields(expected: &[(Tag, &str)], actual: &Message) -> MessageDiff {
+    let sections = [
+        (Section::Header, &*actual.header),
+        (Section::Body, &actual.body),
+        (Section::Trailer, &*actual.trailer),
+    ];
+    let diffs = expected
+        .iter()
+        .filter_map(|(tag, expected)| {
+            let found = sections
+                .iter()
+                .find_map(|(section, map)| map.fields.get(tag).map(|field| (*section, field)));
+            let path = |section| FieldPath {
+                section,
+                groups: Vec::new(),
+                tag: *tag,
+            };
+            match found {
+                None => Some(FieldDiff::Missing {
+                    path: path(None),
+                    expected: expected.to_string(),
+                }),
+                Some((section, field)) if field.value() != *expected => Some(FieldDiff::Changed {
+                    path: path(Some(section)),
+                    expected: expected.to_string(),
+                    actual: field.value().to_string(),
+                }),
+                Some(_) => None,
+            }
+        })
+        .collect();
+    MessageDiff { diffs }
+}
+
+fn diff_maps(
+    section: Option<Section>, groups: &[(Tag, usize)], expected: &FieldMap, actual: &FieldMap,
+    diffs: &mut Vec<FieldDiff>,
+) {
+    let mut tags: Vec<Tag> = expected.fields.keys().chain(actual.fields.keys()).copied().collect();
+    tags.sort_unstable();
+    tags.dedup();
+    let no_fields = FieldMap::default();
+    for tag in tags {
+        let path = FieldPath {
+            section,
+            groups: groups.to_vec(),
+            tag,
+        };
+        match (expected.fields.get(&tag), actual.fields.get(&tag)) {
+            (Some(expected), Some(actual)) if expected.value() != actual.value() => {
+                diffs.push(FieldDiff::Changed {
+                    path,
+                    expected: expected.value().to_string(),
+                    actual: actual.value().to_string(),
+                })
+            }
+            (Some(expected), None) => diffs.push(FieldDiff::Missing {
+                path,
+                expected: expected.value().to_string(),
+            }),
+            (None, Some(actual)) => diffs.push(FieldDiff::Extra {
+                path,
+                actual: actual.value().to_string(),
+            }),
+            _ => {}
+        }
+        let instances = |map: &FieldMap| map.get_group(tag).map_or(0, |group| group.fields.len());
+        for index in 0..instances(expected).max(instances(actual)) {
+            let mut nested = groups.to_vec();
+            nested.push((tag, index));
+            let expected = instance(expected, tag, index).unwrap_or(&no_fields);
+            let actual = instance(actual, tag, index).unwrap_or(&no_fields);
+            diff_maps(section, &nested, expected, actual, diffs);
+        }
+    }
+}
+
+fn instance(map: &FieldMap, tag: Tag, index: usize) -> Option<&FieldMap> {
+    map.get_group(tag).and_then(|group| group.fields.get(index))
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.section {
+            Some(Section::Header) => write!(f, "header ")?,
+            Some(Section::Body) => write!(f, "body ")?,
+            Some(Section::Trailer) => write!(f, "trailer ")?,
+            None => {}
+        }
+        for (tag, index) in &self.groups {
+            write!(f, "{}[{}].", tag, index)?;
+        }
+        write!(f, "{}", self.tag)
+    }
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldDiff::Missing { path, expected } => write!(f, "missing {} = {:?}", path, expected),
+            FieldDiff::Extra { path, actual } => write!(f, "extra {} = {:?}", path, actual),
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => write!(f, "changed {}: expected {:?}, got {:?}", path, expected, actual),
+        }
+    }
+}
+
+impl fmt::Display for MessageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diff) in self.diffs.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diff)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::message::StringField;
+
+    fn order() -> Message {
+        let mut msg = Message::new();
+        msg.header_mut().set_msg_type("D");
+        msg.header_mut().set_sender_comp_id("SENDER");
+        msg.set_field(StringField::new(11, "ORDER1"));
+        msg.set_field(StringField::new(38, "100"));
+        let parties = msg.set_group(453, 2, 448);
+        parties[0].set_field(StringField::new(448, "BROKER"));
+        parties[1].set_field(StringField::new(448, "DESK"));
+        parties[1].set_field(StringField::new(452, "1"));
+        msg
+    }
+
+    #[test]
+    fn test_diff() {
+        assert!(diff(&order(), &order()).is_empty());
+
+        let mut actual = Message::new();
+        actual.header_mut().set_msg_type("D");
+        actual.header_mut().set_sender_comp_id("OTHER");
+        actual.set_field(StringField::new(11, "ORDER1"));
+        actual.set_field(StringField::new(58, "note"));
+        let parties = actual.set_group(453, 2, 448);
+        parties[1].set_field(StringField::new(448, "DESK"));
+        let diffs = diff(&order(), &actual).diffs;
+        let shown: Vec<String> = diffs.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            shown,
+            [
+                "changed header 49: expected \"SENDER\", got \"OTHER\"",
+                "missing body 38 = \"100\"",
+                "extra body 58 = \"note\"",
+                "missing body 453[0].448 = \"BROKER\"",
+                "missing body 453[1].452 = \"1\"",
+            ]
+        );
+        assert_eq!(diffs[4].path().groups, [(453, 1)]);
+        let diff = diff(&order(), &actual).ignoring(&[49, 58]);
+        assert_eq!(diff.diffs.len(), 3);
+    }
+
+    #[test]
+    fn test_diff_fields() {
+        let msg = order();
+        assert!(diff_fields(&[(35, "D"), (11, "ORDER1")], &msg).is_empty());
+        let diff = diff_fields(&[(49, "OTHER"), (44, "10.5")], &msg);
+        assert_eq!(
+            diff.to_string(),
+            "changed header 49: expected \"OTHER\", got \"SENDER\"\nmissing 44 = \"10.5\""
+        );
+    }
+}
@@ -0,0 +1,431 @@
+// Wire handling of DATA fields. Their value is taken by the length in the preceding length field,
+// so it may hold SOH, and the Encoded* fields are in the charset named by MessageEncoding(347).
+// Inside the engine every frame is UTF-8: inbound frames are decoded from the MessageEncoding
+// charset and outbound frames encoded to it, with the length fields, BodyLength and CheckSum
+// following the bytes that actually go on the wire.
+use encoding_rs::{Encoding, UTF_8};
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::Tag;
+
+const SOH: u8 = 0x01;
+
+// (length tag, data tag) of every DATA field, the length always goes right before the data
+pub const DATA_FIELDS: [(Tag, Tag); 16] = [
+    (90, 91),
+    (93, 89),
+    (95, 96),
+    (212, 213),
+    (348, 349),
+    (350, 351),
+    (352, 353),
+    (354, 355),
+    (356, 357),
+    (358, 359),
+    (360, 361),
+    (362, 363),
+    (364, 365),
+    (445, 446),
+    (618, 619),
+    (621, 622),
+];
+
+// data fields whose value is in the MessageEncoding charset
+pub const ENCODED_FIELDS: [Tag; 12] = [349, 351, 353, 355, 357, 359, 361, 363, 365, 446, 619, 622];
+
+pub fn data_tag(length_tag: Tag) -> Option<Tag> {
+    DATA_FIELDS.iter().find(|(len, _)| *len == length_tag).map(|(_, data)| *data)
+}
+
+pub fn length_tag(data_tag: Tag) -> Option<Tag> {
+    DATA_FIELDS.iter().find(|(_, data)| *data == data_tag).map(|(len, _)| *len)
+}
+
+// spans of the tag=value fields of a frame, without their SOH. a data field runs for the length
+// its length field declares if an SOH follows there, otherwise to the next SOH like any field
+pub(crate) fn split_fields(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut fields = Vec::with_capacity(16);
+    let mut expected: Option<(Tag, usize)> = None;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let mut end = bytes[pos..].iter().position(|b| *b == SOH).map_or(bytes.len(), |i| pos + i);
+        let eq = bytes[pos..end].iter().position(|b| *b == b'=').map(|i| pos + i);
+        let tag = eq.and_then(|eq| parse_num::<Tag>(&bytes[pos..eq]));
+        if let (Some(eq), Some(tag), Some((data, len))) = (eq, tag, expected) {
+            let data_end = eq + 1 + len;
+            if tag == data && bytes.get(data_end) == Some(&SOH) {
+                end = data_end;
+            }
+        }
+        expected = match (eq, tag.and_then(data_tag)) {
+            (Some(eq), Some(data)) => {
+                parse_num::<usize>(&bytes[eq + 1..end]).map(|len| (data, len))
+            }
+            _ => None,
+        };
+        fields.push(pos..end);
+        pos = end + 1;
+    }
+    fields
+}
+
+// byte offset and text of the first tag field of a frame
+pub(crate) fn find_field(frame: &str, tag: Tag) -> Option<(usize, &str)> {
+    let prefix = format!("{}=", tag);
+    split_fields(frame.as_bytes())
+        .into_iter()
+        .find(|span| frame.as_bytes()[span.clone()].starts_with(prefix.as_bytes()))
+        .and_then(|span| Some((span.start, frame.get(span)?)))
+}
+
+// a sent frame made ready to go out again without parsing it: PossDupFlag(43) set, SendingTime(52)
+// set to sending_time with the one it had moved to OrigSendingTime(122), BodyLength and CheckSum
+// redone. the three go right after MsgType, every other field is copied as it is
+pub(crate) fn resend_frame(frame: &str, sending_time: &str) -> String {
+    let fields: Vec<(Option<Tag>, &str)> = split_fields(frame.as_bytes())
+        .into_iter()
+        .map(|span| {
+            let field = &frame[span];
+            (field.split_once('=').and_then(|(tag, _)| tag.parse().ok()), field)
+        })
+        .collect();
+    let value = |tag: Tag| {
+        let (_, field) = fields.iter().find(|(t, _)| *t == Some(tag))?;
+        field.split_once('=').map(|(_, value)| value)
+    };
+    let mut body = String::with_capacity(frame.len() + 48);
+    for (tag, field) in &fields {
+        if matches!(tag, Some(8 | 9 | 10 | 43 | 52 | 122)) {
+            continue;
+        }
+        body.push_str(field);
+        body.push('\x01');
+        if *tag == Some(35) {
+            body.push_str("43=Y\x0152=");
+            body.push_str(sending_time);
+            body.push('\x01');
+            if let Some(orig) = value(52) {
+                body.push_str("122=");
+                body.push_str(orig);
+                body.push('\x01');
+            }
+        }
+    }
+    let begin_string = value(8).unwrap_or_default();
+    let mut out = format!("8={}\x019={}\x01{}", begin_string, body.len(), body);
+    let checksum = out.bytes().fold(0u32, |sum, b| sum + b as u32) % 256;
+    out.push_str(&format!("10={:0>3}\x01", checksum));
+    out
+}
+
+// where the next message starts at or after from: a BeginString(8) of FIX or FIXT at the start
+// of a field. a stream or a batch can be picked up from there after a garbled message
+pub fn next_message_start(bytes: &[u8], from: usize) -> Option<usize> {
+    (from..bytes.len())
+        .find(|&i| bytes[i..].starts_with(b"8=FIX") && (i == 0 || bytes[i - 1] == SOH))
+}
+
+// frames written back to back, as a batch is queued for its single write, split where
+// BodyLength(9) says their CheckSum(10) is, so that data holding "\x0110=" does not end one. what
+// does not frame that way is left as one
+pub fn split_frames(frames: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut rest = frames;
+    while let Some(len) = frame_len(rest.as_bytes()) {
+        if len >= rest.len() || !rest.is_char_boundary(len) {
+            break;
+        }
+        split.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    split.push(rest);
+    split
+}
+
+// length of the frame at the start of bytes, up to and including the SOH after its CheckSum
+fn frame_len(bytes: &[u8]) -> Option<usize> {
+    let body_len_start = bytes.iter().position(|b| *b == SOH)? + 1;
+    let body_len_end = body_len_start + bytes[body_len_start..].iter().position(|b| *b == SOH)?;
+    let body_len = parse_num::<usize>(bytes[body_len_start..body_len_end].strip_prefix(b"9=")?)?;
+    let body_start = body_len_end + 1;
+    let checksum = bytes.get(body_start + body_len..)?;
+    if !checksum.starts_with(b"10=") {
+        return None;
+    }
+    Some(body_start + body_len + checksum.iter().position(|b| *b == SOH)? + 1)
+}
+
+// the charset of a frame when it is not UTF-8
+fn frame_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    split_fields(bytes)
+        .into_iter()
+        .map(|span| &bytes[span])
+        .find_map(|field| field.strip_prefix(b"347="))
+        .and_then(Encoding::for_label)
+        .filter(|encoding| *encoding != UTF_8)
+}
+
+pub fn decode_frame(bytes: &[u8]) -> String {
+    match frame_encoding(bytes) {
+        Some(encoding) => {
+            let decoded = transcode(bytes, |value| {
+                Cow::Owned(encoding.decode_without_bom_handling(value).0.into_owned().into_bytes())
+            });
+            String::from_utf8_lossy(&decoded).into_owned()
+        }
+        None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+pub fn encode_frame(frame: &str) -> Cow<'_, [u8]> {
+    let frames = split_frames(frame);
+    if frames.len() > 1 {
+        // each frame of a batch has its own MessageEncoding
+        return Cow::Owned(
+            frames.into_iter().flat_map(|frame| encode_frame(frame).into_owned()).collect(),
+        );
+    }
+    match frame_encoding(frame.as_bytes()) {
+        Some(encoding) => Cow::Owned(transcode(frame.as_bytes(), |value| {
+            match encoding.encode(&String::from_utf8_lossy(value)).0 {
+                Cow::Borrowed(bytes) => Cow::Owned(bytes.to_vec()),
+                Cow::Owned(bytes) => Cow::Owned(bytes),
+            }
+        })),
+        None => Cow::Borrowed(frame.as_bytes()),
+    }
+}
+
+// rewrites the encoded fields of a frame. BodyLength and CheckSum are moved by the difference the
+// rewrite makes, so a frame that was wrong before stays exactly as wrong
+fn transcode(bytes: &[u8], convert: impl Fn(&[u8]) -> Cow<'_, [u8]>) -> Vec<u8> {
+    let spans = split_fields(bytes);
+    let mut fields: Vec<(Option<Tag>, Vec<u8>)> = spans
+        .iter()
+        .map(|span| {
+            let field = &bytes[span.clone()];
+            let eq = field.iter().position(|b| *b == b'=');
+            let tag = eq.and_then(|eq| parse_num::<Tag>(&field[..eq]));
+            match (tag, eq) {
+                (Some(tag), Some(eq)) if ENCODED_FIELDS.contains(&tag) => {
+                    let mut converted = field[..=eq].to_vec();
+                    converted.extend_from_slice(&convert(&field[eq + 1..]));
+                    (Some(tag), converted)
+                }
+                _ => (tag, field.to_vec()),
+            }
+        })
+        .collect();
+
+    for i in 1..fields.len() {
+        let (prev, current) = fields.split_at_mut(i);
+        let (len_tag, len_field) = &mut prev[i - 1];
+        let (data, data_field) = &current[0];
+        if let (Some(len_tag), Some(data)) = (*len_tag, *data) {
+            if ENCODED_FIELDS.contains(&data) && length_tag(data) == Some(len_tag) {
+                let value_len = data_field.len() - data.to_string().len() - 1;
+                *len_field = format!("{}={}", len_tag, value_len).into_bytes();
+            }
+        }
+    }
+
+    let old: Vec<(Option<Tag>, &[u8])> =
+        spans.iter().zip(&fields).map(|(span, (tag, _))| (*tag, &bytes[span.clone()])).collect();
+    let (old_len, old_sum) = measure(old.iter().map(|(tag, field)| (*tag, *field)));
+    let (new_len, _) = measure(fields.iter().map(|(tag, field)| (*tag, field.as_slice())));
+    if let Some((_, field)) = fields.iter_mut().find(|(tag, _)| *tag == Some(9)) {
+        if let Some(len) = parse_num::<i64>(&field[2..]) {
+            *field = format!("9={}", len + new_len - old_len).into_bytes();
+        }
+    }
+    let (_, new_sum) = measure(fields.iter().map(|(tag, field)| (*tag, field.as_slice())));
+    if let Some((_, field)) = fields.iter_mut().find(|(tag, _)| *tag == Some(10)) {
+        if let Some(checksum) = parse_num::<i64>(&field[3..]) {
+            let checksum = (checksum + new_sum - old_sum).rem_euclid(256);
+            *field = format!("10={:0>3}", checksum).into_bytes();
+        }
+    }
+
+    let mut out = Vec::with_capacity(bytes.len() + 16);
+    for (_, field) in &fields {
+        out.extend_from_slice(field);
+        out.push(SOH);
+    }
+    if bytes.last() != Some(&SOH) {
+        out.pop();
+    }
+    out
+}
+
+// body length and byte sum of the fields, each with its SOH, as BodyLength and CheckSum count them
+fn measure<'a>(fields: impl Iterator<Item = (Option<Tag>, &'a [u8])>) -> (i64, i64) {
+    fields.fold((0, 0), |(len, sum), (tag, field)| {
+        let field_len = field.len() as i64 + 1;
+        let field_sum = field.iter().map(|b| *b as i64).sum::<i64>() + SOH as i64;
+        match tag {
+            Some(8 | 9) => (len, sum + field_sum),
+            Some(10) => (len, sum),
+            _ => (len + field_len, sum + field_sum),
+        }
+    })
+}
+
+fn parse_num<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+    use crate::data_dictionary::DataDictionary;
+    use crate::message::{Message, StringField};
+    use lazy_static::lazy_static;
+
+    lazy_static! {
+        static ref DD: DataDictionary = DataDictionary::from_xml("resources/FIX43.xml");
+    }
+
+    fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+        bytes.windows(needle.len()).any(|w| w == needle)
+    }
+
+    fn logout_with_text(text: &str) -> String {
+        logout_in("SHIFT_JIS", text)
+    }
+
+    fn logout_in(encoding: &str, text: &str) -> String {
+        let mut msg = Message::new();
+        let header = msg.header_mut();
+        header.set_begin_string("FIX.4.3");
+        header.set_msg_type("5");
+        header.set_sender_comp_id("ACCEPTOR");
+        header.set_target_comp_id("INITIATOR");
+        header.set_seq_num(2);
+        header.set_field(StringField::new(52, "20240305-10:00:00.000"), &DD).unwrap();
+        header.set_field(StringField::new(347, encoding), &DD).unwrap();
+        msg.set_field(StringField::new(58, "bye"));
+        msg.set_field(StringField::new(355, text));
+        msg.set_body_len();
+        msg.set_checksum();
+        msg.to_string()
+    }
+
+    #[test]
+    fn test_shift_jis_round_trip() {
+        let raw = logout_with_text("こんにちは");
+        // set_body_len sized the text as utf-8
+        assert!(raw.contains("\u{1}354=15\u{1}355=こんにちは\u{1}"));
+
+        let wire = encode_frame(&raw);
+        let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode("こんにちは");
+        let field = [b"\x01354=10\x01355=".as_slice(), &shift_jis, b"\x01"].concat();
+        assert!(contains(&wire, &field));
+        let body_len = wire.len() - wire.windows(4).position(|w| w == b"\x0135=").unwrap() - 8;
+        assert!(contains(&wire, format!("\x019={}\x01", body_len).as_bytes()));
+        let checksum = wire[..wire.len() - 7].iter().map(|b| *b as u32).sum::<u32>() % 256;
+        assert!(wire.ends_with(format!("10={:0>3}\x01", checksum).as_bytes()));
+
+        assert_eq!(decode_frame(&wire), raw);
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let parsed = Message::from_str(&decode_frame(&wire), &dd).unwrap();
+        assert_eq!(parsed.get_field::<String>(355).unwrap(), "こんにちは");
+    }
+
+    #[test]
+    fn test_resend_frame() {
+        // data fields, SOH and all, are copied as they are
+        let raw = logout_with_text("a\u{1}b").replace("347=SHIFT_JIS", "347=UTF-8");
+        let resent = resend_frame(&raw, "20240305-10:05:00.000");
+        assert!(resent.starts_with("8=FIX.4.3\u{1}9="));
+        assert!(resent.contains(
+            "\u{1}35=5\u{1}43=Y\u{1}52=20240305-10:05:00.000\u{1}122=20240305-10:00:00.000\u{1}"
+        ));
+        assert!(resent.contains("\u{1}354=3\u{1}355=a\u{1}b\u{1}"));
+
+        // BodyLength and CheckSum are those of the new frame, resending again keeps the first
+        // SendingTime out of it
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let parsed = Message::from_str(&resent, &dd).unwrap();
+        let mut expected = parsed.clone();
+        expected.set_body_len();
+        expected.set_checksum();
+        assert_eq!(parsed.header().get_field::<u32>(9), expected.header().get_field::<u32>(9));
+        assert_eq!(parsed.trailer().checksum(), expected.trailer().checksum());
+        let again = resend_frame(&resent, "20240305-10:06:00.000");
+        assert!(again.contains("\u{1}122=20240305-10:05:00.000\u{1}"));
+        assert_eq!(again.matches("\u{1}43=Y").count(), 1);
+    }
+
+    #[test]
+    fn test_utf8_untouched() {
+        let raw = logout_in("UTF-8", "grüße");
+        assert!(matches!(encode_frame(&raw), Cow::Borrowed(_)));
+        assert_eq!(decode_frame(raw.as_bytes()), raw);
+    }
+
+    #[test]
+    fn test_batch() {
+        let plain = logout_in("UTF-8", "grüße");
+        let encoded = logout_with_text("こんにちは");
+        let batch = [plain.as_str(), &encoded, &plain].concat();
+        assert_eq!(split_frames(&batch), [plain.as_str(), &encoded, &plain]);
+        assert_eq!(split_frames(&plain), [plain.as_str()]);
+        let wire = [plain.as_bytes(), &encode_frame(&encoded), plain.as_bytes()].concat();
+        assert_eq!(encode_frame(&batch), wire);
+    }
+
+    #[test]
+    fn test_split_by_body_len() {
+        // CheckSum inside a data field does not end the frame
+        let mut msg = Message::new();
+        let header = msg.header_mut();
+        header.set_begin_string("FIX.4.3");
+        header.set_msg_type("B");
+        msg.set_field(StringField::new(148, "news"));
+        msg.set_field(StringField::new(96, "a\u{1}10=000\u{1}b"));
+        msg.set_body_len();
+        msg.set_checksum();
+        let news = msg.to_string();
+        let plain = logout_in("UTF-8", "grüße");
+        let batch = [news.as_str(), &plain, &news].concat();
+        assert_eq!(split_frames(&batch), [news.as_str(), &plain, &news]);
+        // a wrong BodyLength leaves the rest as it is
+        let garbled = batch.replacen("\u{1}9=", "\u{1}9=1", 1);
+        assert_eq!(split_frames(&garbled), [garbled.as_str()]);
+        assert_eq!(split_frames(""), [""]);
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let raw = logout_with_text("bye").replace("52=20240305-10:00:00.000", "52=10:00");
+        let err = Message::from_str(&raw, &dd).unwrap_err();
+        assert_eq!(err.ref_tag_id(), Some(52));
+        assert_eq!(err.offset(), raw.find("52=10:00"));
+        assert_eq!(err.raw_field(), Some("52=10:00"));
+        let position = format!("at byte {} (\"52=10:00\")", err.offset().unwrap());
+        assert!(err.to_string().ends_with(&position));
+
+        let raw = logout_with_text("bye").replace("58=bye", "5x=bye");
+        let err = Message::from_str(&raw, &dd).unwrap_err();
+        assert_eq!((err.offset(), err.raw_field()), (raw.find("5x=bye"), Some("5x=bye")));
+
+        // the message after a garbled one is found from where the error is
+        let next = logout_with_text("next");
+        let batch = format!("{}{}", raw, next);
+        let start = next_message_start(batch.as_bytes(), err.offset().unwrap()).unwrap();
+        assert_eq!(&batch[start..], next);
+        assert_eq!(next_message_start(b"58=8=FIX\x01", 0), None);
+    }
+
+    #[test]
+    fn test_split_data_with_soh() {
+        let frame = b"8=FIX.4.3\x0195=3\x0196=a\x01b\x0110=000\x01";
+        let fields: Vec<&[u8]> = split_fields(frame).into_iter().map(|r| &frame[r]).collect();
+        assert_eq!(fields, [&b"8=FIX.4.3"[..], b"95=3", b"96=a\x01b", b"10=000"]);
+        // a length that does not end at an SOH is not trusted
+        let frame = b"95=9\x0196=a\x01b\x01";
+        assert_eq!(split_fields(frame).len(), 3);
+    }
+}
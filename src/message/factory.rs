@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::data_dictionary::{DataDictionary, HEADER_ID, TRAILER_ID};
+use crate::message::*;
+use crate::quickfix_errors::SessionRejectError;
+
+// Creates empty messages for a begin string and msg type. The header is stamped with
+// BeginString(8) and MsgType(35) and header, body and trailer get the field order defined
+// in the data dictionary so that serialized output follows the spec's ordering.
+#[derive(Debug, Default, Clone)]
+pub struct MessageFactory {
+    dictionaries: HashMap<String, Arc<DataDictionary>>,
+}
+
+impl MessageFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_dictionary(dd: Arc<DataDictionary>) -> Self {
+        let mut factory = Self::new();
+        factory.add_dictionary(dd);
+        factory
+    }
+
+    pub fn add_dictionary(&mut self, dd: Arc<DataDictionary>) {
+        self.dictionaries.insert(dd.begin_string().to_string(), dd);
+    }
+
+    pub fn get_dictionary(&self, begin_string: &str) -> Option<&Arc<DataDictionary>> {
+        self.dictionaries.get(begin_string)
+    }
+
+    pub fn create(&self, begin_string: &str, msg_type: &str) -> SessResult<Message> {
+        let dd = self
+            .get_dictionary(begin_string)
+            .ok_or_else(SessionRejectError::value_out_of_range_err)?;
+        if !dd.is_msg_type(msg_type) {
+            return Err(SessionRejectError::invalid_msg_type_err());
+        }
+        let mut message = Message {
+            header: Header::new(FieldMap::with_field_order(&dd.get_msg_ordered_fields(HEADER_ID))),
+            body: FieldMap::with_field_order(&dd.get_msg_ordered_fields(msg_type)),
+            trailer: Trailer::new(FieldMap::trailer(&dd.get_msg_ordered_fields(TRAILER_ID))),
+        };
+        message.header_mut().set_begin_string(begin_string);
+        message.header_mut().set_msg_type(msg_type);
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod factory_tests {
+    use super::*;
+    use crate::session::FIX43_BEGIN_STR;
+    use lazy_static::lazy_static;
+
+    lazy_static! {
+        static ref DD: Arc<DataDictionary> =
+            Arc::new(DataDictionary::from_xml("resources/FIX43.xml"));
+    }
+
+    #[test]
+    fn test_create_sets_header() {
+        let factory = MessageFactory::with_dictionary(Arc::clone(&DD));
+        let msg = factory.create(FIX43_BEGIN_STR, "D").unwrap();
+        assert_eq!(msg.header().get_field::<String>(8).unwrap(), FIX43_BEGIN_STR);
+        assert_eq!(msg.header().get_field::<String>(35).unwrap(), "D");
+        assert_eq!(msg.get_msg_type().unwrap(), "D");
+    }
+
+    #[test]
+    fn test_create_uses_dictionary_field_order() {
+        let factory = MessageFactory::with_dictionary(Arc::clone(&DD));
+        let mut msg = factory.create(FIX43_BEGIN_STR, "A").unwrap();
+        // set fields in reverse of the xml order
+        msg.header_mut().set_seq_num(1);
+        msg.header_mut().set_target_comp_id("TARGET");
+        msg.header_mut().set_sender_comp_id("SENDER");
+        msg.set_field(StringField::new(108, "30"));
+        msg.set_field(StringField::new(98, "0"));
+        msg.set_body_len();
+        msg.set_checksum();
+        let tags: Vec<u32> = msg
+            .header()
+            .iter()
+            .into_iter()
+            .chain(msg.body().iter())
+            .chain(msg.trailer().iter())
+            .map(|fld| fld.tag())
+            .collect();
+        assert_eq!(tags, vec![8, 9, 35, 49, 56, 34, 98, 108, 10]);
+    }
+
+    #[test]
+    fn test_create_unknown_msg_type() {
+        let factory = MessageFactory::with_dictionary(Arc::clone(&DD));
+        assert!(factory.create(FIX43_BEGIN_STR, "ZZ").is_err());
+        // header and trailer are not message types
+        assert!(factory.create(FIX43_BEGIN_STR, HEADER_ID).is_err());
+    }
+
+    #[test]
+    fn test_create_unknown_begin_string() {
+        let factory = MessageFactory::with_dictionary(Arc::clone(&DD));
+        assert!(factory.create("FIX.4.4", "D").is_err());
+        assert!(MessageFactory::new().create(FIX43_BEGIN_STR, "D").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+// Standard header and trailer of a message. Fields are read like those of any FieldMap, and the
+// fields every message has through named accessors. set_field refuses tags the session's
+// dictionary knows to be outside the header or trailer, so that a body field does not end up
+// there by mistake. Tags the dictionary does not know, like user defined ones, are taken as
+// they are.
+//
+//     let seq_num = msg.header().seq_num();
+//     msg.header_mut().set_on_behalf_of_comp_id("DESK");
+//     msg.header_mut().set_field(StringField::new(5000, "custom"), session.data_dictionary())?;
+use crate::data_dictionary::DataDictionary;
+use crate::fields::*;
+use crate::message::{FieldMap, SessResult, StringField, Tag};
+use crate::quickfix_errors::SessionRejectError;
+use crate::types::UtcTimestamp;
+use std::ops::Deref;
+
+#[derive(Debug, Default, Clone)]
+pub struct Header(FieldMap);
+
+// one of the named setters, for filling in ids from a list
+pub(crate) type SetId = fn(&mut Header, &str);
+
+#[derive(Debug, Default, Clone)]
+pub struct Trailer(FieldMap);
+
+impl Header {
+    pub(crate) fn new(fields: FieldMap) -> Self {
+        Self(fields)
+    }
+
+    pub fn set_field(&mut self, field: StringField, dd: &DataDictionary) -> SessResult<()> {
+        set_checked(&mut self.0, field, dd, DataDictionary::is_header_field)
+    }
+
+    pub fn remove_field(&mut self, tag: Tag) -> Option<StringField> {
+        self.0.remove_field(tag)
+    }
+
+    // fields as they are, for the parser
+    pub(super) fn fields_mut(&mut self) -> &mut FieldMap {
+        &mut self.0
+    }
+
+    pub fn begin_string(&self) -> Option<&str> {
+        self.value(BeginString::field())
+    }
+
+    pub fn msg_type(&self) -> Option<&str> {
+        self.value(MsgType::field())
+    }
+
+    pub fn sender_comp_id(&self) -> Option<&str> {
+        self.value(SenderCompID::field())
+    }
+
+    pub fn target_comp_id(&self) -> Option<&str> {
+        self.value(TargetCompID::field())
+    }
+
+    pub fn seq_num(&self) -> Option<u64> {
+        self.get_field(MsgSeqNum::field()).ok()
+    }
+
+    pub fn sending_time(&self) -> Option<UtcTimestamp> {
+        self.get_field(SendingTime::field()).ok()
+    }
+
+    pub fn on_behalf_of_comp_id(&self) -> Option<&str> {
+        self.value(OnBehalfOfCompID::field())
+    }
+
+    pub fn deliver_to_comp_id(&self) -> Option<&str> {
+        self.value(DeliverToCompID::field())
+    }
+
+    pub fn last_msg_seq_num_processed(&self) -> Option<u64> {
+        self.get_field(LastMsgSeqNumProcessed::field()).ok()
+    }
+
+    pub fn poss_dup(&self) -> bool {
+        self.value(PossDupFlag::field()) == Some("Y")
+    }
+
+    pub fn set_begin_string(&mut self, begin_string: &str) {
+        self.0.set_field(StringField::new(BeginString::field(), begin_string));
+    }
+
+    pub fn set_body_length(&mut self, body_length: usize) {
+        let body_length = itoa::Buffer::new().format(body_length).to_string();
+        self.0.set_field(StringField::new(BodyLength::field(), &body_length));
+    }
+
+    pub fn set_msg_type(&mut self, msg_type: &str) {
+        self.0.set_field(StringField::new(MsgType::field(), msg_type));
+    }
+
+    pub fn set_sender_comp_id(&mut self, sender_comp_id: &str) {
+        self.0.set_field(StringField::new(SenderCompID::field(), sender_comp_id));
+    }
+
+    pub fn set_target_comp_id(&mut self, target_comp_id: &str) {
+        self.0.set_field(StringField::new(TargetCompID::field(), target_comp_id));
+    }
+
+    pub fn set_sender_sub_id(&mut self, sender_sub_id: &str) {
+        self.0.set_field(StringField::new(SenderSubID::field(), sender_sub_id));
+    }
+
+    pub fn set_sender_location_id(&mut self, sender_location_id: &str) {
+        self.0.set_field(StringField::new(SenderLocationID::field(), sender_location_id));
+    }
+
+    pub fn set_target_sub_id(&mut self, target_sub_id: &str) {
+        self.0.set_field(StringField::new(TargetSubID::field(), target_sub_id));
+    }
+
+    pub fn set_target_location_id(&mut self, target_location_id: &str) {
+        self.0.set_field(StringField::new(TargetLocationID::field(), target_location_id));
+    }
+
+    pub fn set_on_behalf_of_comp_id(&mut self, on_behalf_of_comp_id: &str) {
+        self.0.set_field(StringField::new(OnBehalfOfCompID::field(), on_behalf_of_comp_id));
+    }
+
+    pub fn set_on_behalf_of_sub_id(&mut self, on_behalf_of_sub_id: &str) {
+        self.0.set_field(StringField::new(OnBehalfOfSubID::field(), on_behalf_of_sub_id));
+    }
+
+    pub fn set_on_behalf_of_location_id(&mut self, on_behalf_of_location_id: &str) {
+        let field = StringField::new(OnBehalfOfLocationID::field(), on_behalf_of_location_id);
+        self.0.set_field(field);
+    }
+
+    pub fn set_deliver_to_comp_id(&mut self, deliver_to_comp_id: &str) {
+        self.0.set_field(StringField::new(DeliverToCompID::field(), deliver_to_comp_id));
+    }
+
+    pub fn set_seq_num(&mut self, seq_num: u64) {
+        self.0
+            .set_field(StringField::new(MsgSeqNum::field(), itoa::Buffer::new().format(seq_num)));
+    }
+
+    pub fn set_sending_time(&mut self, sending_time: &UtcTimestamp) {
+        self.0.set_field(StringField::new(SendingTime::field(), &sending_time.to_string()));
+    }
+
+    pub fn set_poss_resend(&mut self, poss_resend: bool) {
+        let value = if poss_resend { "Y" } else { "N" };
+        self.0.set_field(StringField::new(PossResend::field(), value));
+    }
+
+    // SendingTime(52) of the message as first sent, as it was written then
+    pub fn set_orig_sending_time(&mut self, orig_sending_time: &str) {
+        self.0.set_field(StringField::new(OrigSendingTime::field(), orig_sending_time));
+    }
+
+    pub fn set_last_msg_seq_num_processed(&mut self, seq_num: u64) {
+        let seq_num = itoa::Buffer::new().format(seq_num).to_string();
+        self.0.set_field(StringField::new(LastMsgSeqNumProcessed::field(), &seq_num));
+    }
+
+    pub fn set_poss_dup(&mut self, poss_dup: bool) {
+        self.0.set_field(StringField::new(PossDupFlag::field(), if poss_dup { "Y" } else { "N" }));
+    }
+
+    pub fn reverse_routing(&mut self, request: &Header) {
+        // a response goes back through the hub: whoever the request was sent on behalf of gets
+        // it delivered, and it is sent on behalf of whoever the request was delivered to
+        let reverse_ids = [
+            (OnBehalfOfCompID::field(), DeliverToCompID::field()),
+            (OnBehalfOfSubID::field(), DeliverToSubID::field()),
+            (OnBehalfOfLocationID::field(), DeliverToLocationID::field()),
+            (DeliverToCompID::field(), OnBehalfOfCompID::field()),
+            (DeliverToSubID::field(), OnBehalfOfSubID::field()),
+            (DeliverToLocationID::field(), OnBehalfOfLocationID::field()),
+        ];
+        for (from_tag, to_tag) in reverse_ids {
+            if let Some(value) = request.value(from_tag) {
+                self.0.set_field(StringField::new(to_tag, value));
+            }
+        }
+    }
+
+    fn value(&self, tag: Tag) -> Option<&str> {
+        self.0.fields.get(&tag).map(StringField::value)
+    }
+}
+
+impl Trailer {
+    pub(crate) fn new(fields: FieldMap) -> Self {
+        Self(fields)
+    }
+
+    pub fn set_field(&mut self, field: StringField, dd: &DataDictionary) -> SessResult<()> {
+        set_checked(&mut self.0, field, dd, DataDictionary::is_trailer_field)
+    }
+
+    // fields as they are, for the parser
+    pub(super) fn fields_mut(&mut self) -> &mut FieldMap {
+        &mut self.0
+    }
+
+    pub fn checksum(&self) -> Option<u32> {
+        self.get_field(CheckSum::field()).ok()
+    }
+
+    pub fn set_checksum(&mut self, checksum: u32) {
+        self.0.set_field(StringField::new(CheckSum::field(), &format!("{:0>3}", checksum)));
+    }
+}
+
+fn set_checked(
+    map: &mut FieldMap, field: StringField, dd: &DataDictionary,
+    is_part: fn(&DataDictionary, Tag) -> bool,
+) -> SessResult<()> {
+    let tag = field.tag();
+    if dd.get_field_type(tag).is_some() && !is_part(dd, tag) {
+        return Err(SessionRejectError::tag_not_defined_for_msg().with_tag(tag));
+    }
+    map.set_field(field);
+    Ok(())
+}
+
+// raw access to the fields, unchecked
+impl Deref for Header {
+    type Target = FieldMap;
+
+    fn deref(&self) -> &FieldMap {
+        &self.0
+    }
+}
+
+impl Deref for Trailer {
+    type Target = FieldMap;
+
+    fn deref(&self) -> &FieldMap {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod header_tests {
+    use crate::data_dictionary::DataDictionary;
+    use crate::message::{Message, StringField};
+    use crate::types::{TimePrecision, UtcTimestamp};
+
+    #[test]
+    fn test_accessors() {
+        let mut msg = Message::new();
+        let header = msg.header_mut();
+        header.set_begin_string("FIX.4.3");
+        header.set_msg_type("D");
+        header.set_sender_comp_id("SENDER");
+        header.set_target_comp_id("TARGET");
+        header.set_seq_num(42);
+        let now = chrono::DateTime::parse_from_rfc3339("2024-03-05T10:00:00Z").unwrap();
+        let now = UtcTimestamp::new(now.to_utc(), TimePrecision::Millis);
+        header.set_sending_time(&now);
+        header.set_poss_dup(true);
+        let header = msg.header();
+        assert_eq!((header.begin_string(), header.msg_type()), (Some("FIX.4.3"), Some("D")));
+        assert_eq!(header.sender_comp_id(), Some("SENDER"));
+        assert_eq!(header.target_comp_id(), Some("TARGET"));
+        assert_eq!(header.seq_num(), Some(42));
+        assert_eq!(header.sending_time().map(|t| t.to_string()), Some(now.to_string()));
+        assert!(header.poss_dup());
+        // raw tag access still works
+        assert_eq!(header.get_field::<u32>(34).unwrap(), 42);
+
+        msg.set_body_len();
+        msg.set_checksum();
+        let checksum = msg.trailer().get_field::<u32>(10).ok();
+        assert_eq!(msg.trailer().checksum(), checksum);
+    }
+
+    #[test]
+    fn test_set_field() {
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let mut msg = Message::new();
+        // OnBehalfOfCompID(115) is a header field, ClOrdID(11) is not
+        assert!(msg.header_mut().set_field(StringField::new(115, "DESK"), &dd).is_ok());
+        let err = msg.header_mut().set_field(StringField::new(11, "ORDER1"), &dd).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(2), Some(11)));
+        assert!(msg.header().get_field::<String>(11).is_err());
+        // tags outside the dictionary
+        assert!(msg.header_mut().set_field(StringField::new(5000, "custom"), &dd).is_ok());
+        assert!(msg.trailer_mut().set_field(StringField::new(93, "3"), &dd).is_ok());
+        assert!(msg.trailer_mut().set_field(StringField::new(115, "DESK"), &dd).is_err());
+    }
+
+    #[test]
+    fn test_reverse_routing() {
+        let mut request = Message::new();
+        let header = request.header_mut();
+        header.set_on_behalf_of_comp_id("CLIENT");
+        header.set_on_behalf_of_sub_id("TRADER1");
+        header.set_deliver_to_comp_id("BROKER");
+        let mut response = Message::new();
+        response.header_mut().reverse_routing(request.header());
+        let header = response.header();
+        assert_eq!(header.deliver_to_comp_id(), Some("CLIENT"));
+        assert_eq!(header.get_field::<String>(129).unwrap(), "TRADER1");
+        assert_eq!(header.on_behalf_of_comp_id(), Some("BROKER"));
+        assert!(header.get_field::<String>(117).is_err());
+        assert!(header.get_field::<String>(145).is_err());
+    }
+}
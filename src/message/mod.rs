@@ -0,0 +1,1489 @@
+use getset::{CopyGetters, Getters, MutGetters};
+use smallvec::SmallVec;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+use crate::clock::Clock;
+use crate::data_dictionary::{DataDictionary, FixType, HEADER_ID};
+use crate::fields::*;
+use crate::quickfix_errors::{InvalidMessage, SessionRejectError};
+use crate::session::{SessionId, SessionIdBuilder};
+use crate::types::{Bool, Char, MultipleValueString, TimePrecision, UtcTimestamp};
+
+pub mod diff;
+pub mod encoding;
+pub mod factory;
+pub mod header;
+pub mod pool;
+pub mod store;
+pub mod wire;
+
+pub use diff::{diff, diff_fields, FieldDiff, MessageDiff};
+pub use encoding::{decode_frame, encode_frame, next_message_start, split_frames};
+pub(crate) use encoding::{find_field, resend_frame};
+pub use factory::*;
+pub(crate) use header::SetId;
+pub use header::{Header, Trailer};
+pub use pool::{MessagePool, PooledMessage};
+pub use store::{
+    recover_seq_nums, FileStore, MemoryStore, MessageCursor, MessageStore, RetentionPolicy,
+    SeqNumCheckpoint, SharedMessageStore, StoredMessage, DEFAULT_SEQNUM_CHECKPOINT_INTERVAL,
+    DEFAULT_SEQNUM_CHECKPOINT_MESSAGES,
+};
+pub use wire::WireBuilder;
+
+type SessResult<T> = Result<T, SessionRejectError>;
+
+/*
+derive a macro which will create impl fns for each of the items in this enum
+ and then delete this comment
+ */
+#[derive(Debug)]
+pub enum Type {
+    Int(i64),
+    Length(u32),
+    TagNum(u32),
+    DayOfMonth(u32),
+    SeqNum(u64),
+    NumInGroup(u32),
+    Float(f64),
+    Price(f64),
+    PriceOffset(f64),
+    Amt(f64),
+    Percent(f64),
+    Qty(f64),
+    Char(char),
+    Bool(bool),
+    Str(String),
+    Currency(String),
+    Country(String),
+    Exchange(String),
+    LocalMktDate(String),
+    MonthYear(String),
+    MultiValueStr(String),
+    UtcDate(String),
+    UtcTimeOnly(String),
+    UtcTimestamp(String),
+}
+
+impl Type {
+    fn from_dictionary(tag: Tag, value: &str, dd: &DataDictionary) -> SessResult<Self> {
+        // format is checked before the enums, so that a malformed value is not reported as out
+        // of range. tags the dictionary does not know are read as Str
+        dd.check_field_value(tag, value)
+            .map_err(|_| SessionRejectError::incorrect_data_format_err().with_tag(tag))?;
+        dd.check_field_allowed(tag, value)
+            .map_err(|_| SessionRejectError::value_out_of_range_err().with_tag(tag))?;
+        let fix_type = dd.get_field_type(tag).copied().unwrap_or(FixType::Unknown);
+        let typed = match fix_type {
+            FixType::Int => Type::Int(parse_typed(tag, value)?),
+            FixType::Length => Type::Length(parse_typed(tag, value)?),
+            FixType::Tagnum => Type::TagNum(parse_typed(tag, value)?),
+            FixType::Seqnum => Type::SeqNum(parse_typed(tag, value)?),
+            FixType::NumInGroup => Type::NumInGroup(parse_typed(tag, value)?),
+            FixType::Float => Type::Float(parse_typed(tag, value)?),
+            FixType::Price => Type::Price(parse_typed(tag, value)?),
+            FixType::PriceOffset => Type::PriceOffset(parse_typed(tag, value)?),
+            FixType::Amt => Type::Amt(parse_typed(tag, value)?),
+            FixType::Percentage => Type::Percent(parse_typed(tag, value)?),
+            FixType::Qty => Type::Qty(parse_typed(tag, value)?),
+            FixType::Char => Type::Char(parse_typed::<Char>(tag, value)?.value()),
+            FixType::Boolean => Type::Bool(parse_typed::<Bool>(tag, value)?.value()),
+            FixType::Currency => Type::Currency(value.to_string()),
+            FixType::Country => Type::Country(value.to_string()),
+            FixType::Exchange => Type::Exchange(value.to_string()),
+            FixType::LocalMktDate => Type::LocalMktDate(value.to_string()),
+            FixType::MonthYear => Type::MonthYear(value.to_string()),
+            FixType::MultipleValueString => Type::MultiValueStr(value.to_string()),
+            FixType::UtcDate => Type::UtcDate(value.to_string()),
+            FixType::UtcTimeOnly => Type::UtcTimeOnly(value.to_string()),
+            FixType::UtcTimestamp => Type::UtcTimestamp(value.to_string()),
+            FixType::Str | FixType::Data | FixType::Unknown => Type::Str(value.to_string()),
+        };
+        Ok(typed)
+    }
+}
+
+fn parse_typed<T: FromStr>(tag: Tag, value: &str) -> SessResult<T> {
+    value
+        .parse::<T>()
+        .map_err(|_| SessionRejectError::incorrect_data_format_err().with_tag(tag))
+}
+
+type Tag = u32;
+pub const SOH: char = '\u{01}';
+// pub const SOH: char = '|';
+
+// Value of a field. One and two byte values, which most enumerated fields such as Side(54),
+// OrdType(40) and the Y/N of booleans have, are kept inline instead of on the heap.
+#[derive(Clone)]
+enum FieldValue {
+    Inline { len: u8, bytes: [u8; 2] },
+    Heap(String),
+}
+
+impl FieldValue {
+    fn new(value: &str) -> Self {
+        match value.as_bytes() {
+            [] => FieldValue::Inline {
+                len: 0,
+                bytes: [0; 2],
+            },
+            [b] => FieldValue::Inline {
+                len: 1,
+                bytes: [*b, 0],
+            },
+            [b1, b2] => FieldValue::Inline {
+                len: 2,
+                bytes: [*b1, *b2],
+            },
+            _ => FieldValue::Heap(value.to_string()),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            // the bytes are the whole chars of a str
+            FieldValue::Inline { len, bytes } => {
+                std::str::from_utf8(&bytes[..*len as usize]).expect("inline value is utf-8")
+            }
+            FieldValue::Heap(value) => value,
+        }
+    }
+}
+
+impl Default for FieldValue {
+    fn default() -> Self {
+        FieldValue::new("")
+    }
+}
+
+impl std::fmt::Debug for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[derive(Debug, Default, Clone, CopyGetters)]
+pub struct StringField {
+    #[getset(get_copy = "pub")]
+    tag: Tag,
+
+    value: FieldValue,
+}
+
+impl StringField {
+    pub fn new(tag: Tag, value: &str) -> Self {
+        Self {
+            tag,
+            value: FieldValue::new(value),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        self.value.as_str()
+    }
+
+    // appends tag=value<SOH> to buf, without formatting the field into a String first
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(itoa::Buffer::new().format(self.tag).as_bytes());
+        buf.push(b'=');
+        buf.extend_from_slice(self.value().as_bytes());
+        buf.push(SOH as u8);
+    }
+
+    // bytes write_to appends
+    pub fn encoded_len(&self) -> usize {
+        itoa::Buffer::new().format(self.tag).len() + self.value().len() + 2
+    }
+
+    // sum of the bytes write_to appends, as counted into CheckSum(10)
+    fn byte_sum(&self) -> u32 {
+        let tag = itoa::Buffer::new().format(self.tag).bytes().map(u32::from).sum::<u32>();
+        let value = self.value().bytes().map(u32::from).sum::<u32>();
+        tag + value + b'=' as u32 + SOH as u32
+    }
+}
+
+impl Display for StringField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}{}", self.tag, self.value(), SOH)
+    }
+}
+
+// trailer fields in the order they go on the wire, CheckSum(10) always being the last field
+const TRAILER_FIELD_ORDER: [Tag; 3] = [93, 89, 10];
+
+// Fields of a FieldMap in the order they were set, at most one per tag. A header, trailer or
+// group instance has few enough fields that scanning them beats hashing, and up to 8 of them
+// are kept inline. A list that grows past FIELD_INDEX_THRESHOLD, e.g. a large body, gets an
+// index by tag.
+#[derive(Debug, Default, Clone)]
+struct FieldList {
+    fields: SmallVec<[StringField; 8]>,
+    index: Option<HashMap<Tag, usize>>,
+}
+
+const FIELD_INDEX_THRESHOLD: usize = 16;
+
+impl FieldList {
+    fn position(&self, tag: &Tag) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(tag).copied(),
+            None => self.fields.iter().position(|field| field.tag() == *tag),
+        }
+    }
+
+    fn insert(&mut self, field: StringField) {
+        if let Some(position) = self.position(&field.tag()) {
+            self.fields[position] = field;
+            return;
+        }
+        if let Some(index) = self.index.as_mut() {
+            index.insert(field.tag(), self.fields.len());
+        }
+        self.fields.push(field);
+        if self.index.is_none() && self.fields.len() > FIELD_INDEX_THRESHOLD {
+            let index = self.fields.iter().enumerate().map(|(pos, field)| (field.tag(), pos));
+            self.index = Some(index.collect());
+        }
+    }
+
+    fn get(&self, tag: &Tag) -> Option<&StringField> {
+        self.position(tag).map(|position| &self.fields[position])
+    }
+
+    fn remove(&mut self, tag: &Tag) -> Option<StringField> {
+        let position = self.position(tag)?;
+        if let Some(index) = self.index.as_mut() {
+            index.remove(tag);
+            index.values_mut().filter(|pos| **pos > position).for_each(|pos| *pos -= 1);
+        }
+        Some(self.fields.remove(position))
+    }
+
+    fn contains_key(&self, tag: &Tag) -> bool {
+        self.position(tag).is_some()
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &Tag> {
+        self.fields.iter().map(|field| &field.tag)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &StringField> {
+        self.fields.iter()
+    }
+
+    fn clear(&mut self) {
+        self.fields.clear();
+        self.index = None;
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FieldMap {
+    fields: FieldList,
+    group: HashMap<Tag, Group>,
+    field_order: Vec<Tag>,
+    // goes after every other field, ordered or not
+    last_field: Option<Tag>,
+}
+
+impl FieldMap {
+    #[inline]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_field_order(field_order: &[u32]) -> Self {
+        Self {
+            field_order: field_order.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn trailer(field_order: &[u32]) -> Self {
+        Self {
+            field_order: field_order.to_vec(),
+            last_field: Some(CheckSum::field()),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_field(&mut self, field: StringField) {
+        self.fields.insert(field);
+    }
+
+    pub fn remove_field(&mut self, tag: Tag) -> Option<StringField> {
+        self.fields.remove(&tag)
+    }
+
+    pub fn get_field<T: FromStr>(&self, tag: u32) -> Result<T, String> {
+        if let Some(field) = self.fields.get(&tag) {
+            return field.value().parse::<T>().map_err(|_| "could not parse".to_string());
+        }
+        Err("not found".to_string())
+    }
+
+    pub fn get_multiple_values(&self, tag: Tag) -> Result<Vec<String>, String> {
+        self.get_field::<MultipleValueString>(tag).map(MultipleValueString::into_values)
+    }
+
+    pub fn get_typed(&self, tag: Tag, dd: &DataDictionary) -> SessResult<Type> {
+        // value read as the type the dictionary declares for the field
+        match self.fields.get(&tag) {
+            Some(field) => Type::from_dictionary(tag, field.value(), dd),
+            None => Err(SessionRejectError::required_tag_missing_err().with_tag(tag)),
+        }
+    }
+
+    pub fn set_multiple_values<S: Into<String>>(
+        &mut self, tag: Tag, values: impl IntoIterator<Item = S>,
+    ) {
+        let value = MultipleValueString::new(values).to_string();
+        self.set_field(StringField::new(tag, &value));
+    }
+
+    pub fn set_group(&mut self, tag: Tag, value: u32, rep_grp_delimiter: Tag) -> &mut Group {
+        let grp_field = StringField::new(tag, value.to_string().as_str());
+        self.set_field(grp_field);
+        // instances are created as they are indexed or added, not for the whole count up front
+        self.group.entry(tag).or_insert_with(|| Group::new(rep_grp_delimiter, tag, value))
+    }
+
+    pub fn get_group(&self, tag: Tag) -> Option<&Group> {
+        self.group.get(&tag)
+    }
+
+    pub fn set_field_order(&mut self, f_order: &[Tag]) {
+        self.field_order = f_order.to_vec();
+    }
+
+    fn set_data_lengths(&mut self) {
+        // length field of every data field, those of group instances included
+        for (len_tag, data_tag) in encoding::DATA_FIELDS {
+            if let Some(data) = self.fields.get(&data_tag) {
+                let len = data.value().len().to_string();
+                self.set_field(StringField::new(len_tag, &len));
+            }
+        }
+        for group in self.group.values_mut() {
+            for instance in group.fields.iter_mut() {
+                instance.set_data_lengths();
+            }
+        }
+    }
+
+    // drops the fields and groups, the field order stays
+    pub fn clear(&mut self) {
+        self.fields.clear();
+        self.group.clear();
+    }
+
+    // appends the fields, groups included, in the order they go on the wire
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        for field in self.iter() {
+            field.write_to(buf);
+        }
+    }
+
+    pub fn iter(&self) -> FieldMapIter<'_> {
+        let mut map_iter = FieldMapIter::default();
+        map_iter.fieldmap_to_vec(self);
+        map_iter
+    }
+
+    fn is_ordered_field(&self, tag: Tag) -> bool {
+        self.field_order.contains(&tag)
+    }
+
+    fn index_comparator(&self, tag1: Tag, tag2: Tag) -> Ordering {
+        let field_index = |field: Tag| {
+            self.field_order
+                .iter()
+                .position(|needle| *needle == field)
+                .map_or(usize::MAX, |pos| pos)
+        };
+        field_index(tag1).cmp(&field_index(tag2))
+    }
+}
+
+impl std::fmt::Display for FieldMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf);
+        f.write_str(std::str::from_utf8(&buf).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FieldMapIter<'a> {
+    vec_str_field: Vec<&'a StringField>,
+}
+
+impl<'a> FieldMapIter<'a> {
+    fn fieldmap_to_vec(&mut self, field_map: &'a FieldMap) {
+        let mut temp_vec: Vec<&StringField> = field_map.fields.values().collect();
+        if !field_map.field_order.is_empty() || field_map.last_field.is_some() {
+            temp_vec.sort_by_cached_key(|&field| {
+                let position = field_map
+                    .field_order
+                    .iter()
+                    .position(|needle| *needle == field.tag())
+                    .map_or(usize::MAX, |pos| pos);
+                (field_map.last_field == Some(field.tag()), position)
+            })
+        }
+        // a length field goes right before its data field, wherever the data field ends up
+        for (len_tag, data_tag) in encoding::DATA_FIELDS {
+            let len_pos = temp_vec.iter().position(|field| field.tag() == len_tag);
+            if let Some(len_pos) = len_pos {
+                if field_map.fields.contains_key(&data_tag) {
+                    let len_field = temp_vec.remove(len_pos);
+                    let data_pos = temp_vec.iter().position(|f| f.tag() == data_tag).unwrap();
+                    temp_vec.insert(data_pos, len_field);
+                }
+            }
+        }
+        for str_field in temp_vec {
+            let tag = str_field.tag();
+            self.vec_str_field.push(str_field);
+            if let Some(grp) = field_map.get_group(tag) {
+                for grp_field_map in grp.fields.iter() {
+                    self.fieldmap_to_vec(grp_field_map);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for FieldMapIter<'a> {
+    type Item = &'a StringField;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec_str_field.into_iter()
+    }
+}
+
+// instances inline up to 4, more than most groups have
+type Instances = SmallVec<[FieldMap; 4]>;
+
+#[derive(Debug, Default, Clone, CopyGetters, Getters)]
+pub struct Group {
+    #[getset(get_copy)]
+    delim: u32,
+
+    #[getset(get_copy)]
+    tag: Tag,
+
+    #[getset(get_copy)]
+    value: u32,
+
+    fields: Instances,
+}
+
+impl Group {
+    pub fn new(delimiter: Tag, tag: Tag, value: u32) -> Self {
+        Self {
+            delim: delimiter,
+            tag,
+            value,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_group(&mut self, grp: FieldMap) {
+        self.fields.push(grp);
+    }
+
+    pub fn size(&self) -> u32 {
+        self.fields.len() as u32
+    }
+
+    fn correct_count(&mut self, count: u32) {
+        self.fields.truncate(count as usize);
+        self.value = count;
+    }
+}
+
+impl Index<usize> for Group {
+    type Output = FieldMap;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        self.fields.index(idx)
+    }
+}
+
+impl IndexMut<usize> for Group {
+    // creates the instances up to idx that are not there yet, within the group's count
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        if idx >= self.fields.len() && idx < self.value as usize {
+            self.fields.resize_with(idx + 1, FieldMap::new);
+        }
+        self.fields.index_mut(idx)
+    }
+}
+
+// How forgiving parsing is about what counterparties get wrong
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    // NumInGroup that disagrees with the instances that follow is set to their number instead
+    // of rejecting the message
+    pub correct_num_in_group: bool,
+    // header fields found among the body fields are moved to the header instead of being
+    // rejected. BeginString, BodyLength, MsgType first and the trailer last are always enforced
+    pub relaxed_field_order: bool,
+    // fields of a group instance in another order than the dictionary's are accepted instead of
+    // rejecting the message. the delimiter still has to start every instance
+    pub lenient_group_field_order: bool,
+    // messages with more fields than this are rejected before any of them are looked at
+    pub max_fields: Option<usize>,
+    // NumInGroup above this is rejected, whatever instances follow
+    pub max_group_instances: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, MutGetters, Getters)]
+#[getset(get = "pub", get_mut = "pub")]
+pub struct Message {
+    pub header: Header,
+    pub body: FieldMap,
+    trailer: Trailer,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self {
+            header: Header::new(FieldMap::with_field_order(&[8, 9, 35])),
+            trailer: Trailer::new(FieldMap::trailer(&TRAILER_FIELD_ORDER)),
+            ..Default::default()
+        }
+    }
+
+    // back to what new returns, keeping the memory of the fields for the next message
+    pub fn clear(&mut self) {
+        let reset = |map: &mut FieldMap, order: &[Tag]| {
+            map.clear();
+            map.field_order.clear();
+            map.field_order.extend_from_slice(order);
+        };
+        reset(self.header.fields_mut(), &[8, 9, 35]);
+        reset(&mut self.body, &[]);
+        reset(self.trailer.fields_mut(), &TRAILER_FIELD_ORDER);
+        self.body.last_field = None;
+        self.header.fields_mut().last_field = None;
+        self.trailer.fields_mut().last_field = Some(CheckSum::field());
+    }
+
+    pub fn set_field(&mut self, fld: StringField) {
+        self.body.set_field(fld);
+    }
+
+    pub fn get_field<T: FromStr>(&self, tag: Tag) -> Result<T, String> {
+        self.body.get_field(tag)
+    }
+
+    pub fn get_multiple_values(&self, tag: Tag) -> Result<Vec<String>, String> {
+        self.body.get_multiple_values(tag)
+    }
+
+    pub fn get_typed(&self, tag: Tag, dd: &DataDictionary) -> SessResult<Type> {
+        self.body.get_typed(tag, dd)
+    }
+
+    pub fn set_multiple_values<S: Into<String>>(
+        &mut self, tag: Tag, values: impl IntoIterator<Item = S>,
+    ) {
+        self.body.set_multiple_values(tag, values)
+    }
+
+    pub fn set_group(&mut self, tag: Tag, value: u32, rep_grp_delimiter: Tag) -> &mut Group {
+        self.body.set_group(tag, value, rep_grp_delimiter)
+    }
+
+    pub fn get_group(&self, tag: Tag) -> Option<&Group> {
+        self.body.get_group(tag)
+    }
+
+    fn add_group(&mut self, tag: Tag, grp: Group) {
+        self.body.group.insert(tag, grp);
+    }
+
+    fn calc_checksum(&self) -> u32 {
+        let mut byte_sum = 0u32;
+        for sfield in
+            self.header.iter().into_iter().chain(self.body.iter()).chain(self.trailer.iter())
+        {
+            if sfield.tag() != 10 {
+                byte_sum += sfield.byte_sum();
+            }
+        }
+        byte_sum % 256
+    }
+
+    pub fn set_checksum(&mut self) {
+        let checksum = self.calc_checksum();
+        self.trailer.set_checksum(checksum);
+    }
+
+    fn calc_body_len(&self) -> usize {
+        self.header
+            .iter()
+            .into_iter()
+            .chain(self.body.iter())
+            .chain(self.trailer.iter())
+            .filter_map(|sfield| {
+                if sfield.tag() != 8 && sfield.tag() != 9 && sfield.tag() != 10 {
+                    Some(sfield.encoded_len())
+                } else {
+                    None
+                }
+            })
+            .sum()
+    }
+
+    pub fn set_body_len(&mut self) {
+        // data fields are sized here so that a value set as text goes out with its length
+        for field_map in [
+            self.header.fields_mut(),
+            &mut self.body,
+            self.trailer.fields_mut(),
+        ] {
+            field_map.set_data_lengths();
+        }
+        let body_len = self.calc_body_len();
+        self.header.set_body_length(body_len)
+    }
+
+    pub fn get_msg_type(&self) -> Result<String, String> {
+        self.header.get_field::<String>(35)
+    }
+
+    // appends the message as it goes on the wire, fields are copied into buf as they are
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        self.header.write_to(buf);
+        self.body.write_to(buf);
+        self.trailer.write_to(buf);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256);
+        self.write_to(&mut buf);
+        buf
+    }
+
+    pub fn set_sending_time(&mut self, clock: &dyn Clock) {
+        // stamped from the clock of the session sending it, see crate::clock
+        self.header.set_sending_time(&UtcTimestamp::new(clock.now(), TimePrecision::Millis));
+    }
+
+    pub fn from_str(s: &str, dd: &DataDictionary) -> SessResult<Self> {
+        Message::from_str_with(s, dd, ParseOptions::default())
+    }
+
+    pub fn from_str_with(s: &str, dd: &DataDictionary, options: ParseOptions) -> SessResult<Self> {
+        let mut message = Message::new();
+        message.parse_into(s, dd, options)?;
+        Ok(message)
+    }
+
+    // parses s into self, which is expected to be empty like a new or cleared message
+    fn parse_into(
+        &mut self, s: &str, dd: &DataDictionary, options: ParseOptions,
+    ) -> SessResult<()> {
+        let mut vdeq: VecDeque<StringField> = VecDeque::with_capacity(16);
+        let spans = encoding::split_fields(s.as_bytes());
+        if let Some(max) = options.max_fields.filter(|max| spans.len() > *max) {
+            // told at the first field over the limit
+            let over = spans[max].clone();
+            let err = SessionRejectError::value_out_of_range_err();
+            return Err(err.with_position(over.start, &s[over]));
+        }
+        for span in spans {
+            // errors tell where in s the field is
+            let offset = span.start;
+            let field = &s[span];
+            let at = |err: SessionRejectError| err.with_position(offset, field);
+            let (tag, value) = match field.split_once('=') {
+                Some((t, v)) => {
+                    let parse_result = t.parse::<u32>();
+                    if parse_result.is_err() {
+                        return Err(at(SessionRejectError::invalid_tag_err()));
+                    }
+                    if v.is_empty() {
+                        return Err(at(SessionRejectError::tag_without_value_err()));
+                    }
+                    let tag = parse_result.unwrap();
+                    dd.check_field_value(tag, v).map_err(|_| {
+                        at(SessionRejectError::incorrect_data_format_err().with_tag(tag))
+                    })?;
+                    dd.check_field_enum(tag, v).map_err(|_| {
+                        at(SessionRejectError::value_out_of_range_err().with_tag(tag))
+                    })?;
+                    (tag, v)
+                }
+                None => return Err(at(SessionRejectError::invalid_tag_err())),
+            };
+            vdeq.push_back(StringField::new(tag, value));
+        }
+
+        from_vec(vdeq, self, dd, options).map_err(|err| err.locate_in(s))
+    }
+
+    pub fn from_bytes(bytes: &[u8], dd: &DataDictionary) -> SessResult<Self> {
+        let s = std::str::from_utf8(bytes)
+            .map_err(|_| SessionRejectError::incorrect_data_format_err())?;
+        Message::from_str(s, dd)
+    }
+
+    pub fn get_session_id(s: &str) -> Result<SessionId, InvalidMessage> {
+        Ok(SessionIdBuilder::default()
+            .begin_string(required_field_value(BeginString::field(), s)?)
+            .sender_compid(required_field_value(SenderCompID::field(), s)?)
+            .sender_subid(extract_field_value(SenderSubID::field(), s)?.unwrap_or_default())
+            .sender_locationid(
+                extract_field_value(SenderLocationID::field(), s)?.unwrap_or_default(),
+            )
+            .target_compid(required_field_value(TargetCompID::field(), s)?)
+            .target_subid(extract_field_value(TargetSubID::field(), s)?.unwrap_or_default())
+            .target_locationid(
+                extract_field_value(TargetLocationID::field(), s)?.unwrap_or_default(),
+            )
+            .build()
+            .expect("required session id fields are set"))
+    }
+
+    pub fn raw_field(raw: &str, tag: Tag) -> Option<&str> {
+        // value of a field of a raw message that is not parsed, e.g. because it does not parse
+        extract_field_value(tag, raw).ok().flatten()
+    }
+
+    pub fn get_reverse_session_id(s: &str) -> Result<SessionId, InvalidMessage> {
+        // sender values from message is put into target & vice-versa
+        Ok(SessionIdBuilder::default()
+            .begin_string(required_field_value(BeginString::field(), s)?)
+            .sender_compid(required_field_value(TargetCompID::field(), s)?)
+            .sender_subid(extract_field_value(TargetSubID::field(), s)?.unwrap_or_default())
+            .sender_locationid(
+                extract_field_value(TargetLocationID::field(), s)?.unwrap_or_default(),
+            )
+            .target_compid(required_field_value(SenderCompID::field(), s)?)
+            .target_subid(extract_field_value(SenderSubID::field(), s)?.unwrap_or_default())
+            .target_locationid(
+                extract_field_value(SenderLocationID::field(), s)?.unwrap_or_default(),
+            )
+            .build()
+            .expect("required session id fields are set"))
+    }
+}
+
+// Implemented by the generated message structs in crate::messages, one per message in the
+// dictionary. The struct wraps a Message of its msg type and derefs to it.
+pub trait TypedMessage: Sized {
+    const MSG_TYPE: &'static str;
+    const MSG_NAME: &'static str;
+
+    // msg type is not checked, callers are expected to have matched on MSG_TYPE
+    fn from_message(message: Message) -> Self;
+    fn message(&self) -> &Message;
+    fn into_message(self) -> Message;
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let buf = self.to_bytes();
+        f.write_str(std::str::from_utf8(&buf).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+fn extract_field_value(tag: Tag, s: &str) -> Result<Option<&str>, InvalidMessage> {
+    // value of the first occurrence of tag in raw message. BeginString has to be the first field,
+    // any other tag is searched for after an SOH so that e.g. 58= is not taken for 8=
+    let field_start = if tag == BeginString::field() {
+        if !s.starts_with("8=") {
+            return Ok(None);
+        }
+        0
+    } else {
+        match s.find(&format!("{}{}=", SOH, tag)) {
+            Some(indx) => indx + 1,
+            None => return Ok(None),
+        }
+    };
+    // tag and '=' are ascii so this is a char boundary
+    let value_start = field_start + tag.to_string().len() + 1;
+    let value_len = s[value_start..].find(SOH).ok_or(InvalidMessage::MessageDoesNotHaveSOH)?;
+    Ok(Some(&s[value_start..value_start + value_len]))
+}
+
+fn required_field_value(tag: Tag, s: &str) -> Result<&str, InvalidMessage> {
+    match extract_field_value(tag, s)? {
+        Some(value) if !value.is_empty() => Ok(value),
+        _ => Err(InvalidMessage::HeaderTagMissing(tag)),
+    }
+}
+
+fn from_vec(
+    mut v: VecDeque<StringField>, message: &mut Message, dd: &DataDictionary, options: ParseOptions,
+) -> SessResult<()> {
+    parse_header(&mut v, message.header.fields_mut(), dd, options)?;
+    parse_body(&mut v, message, dd, options)?;
+    parse_trailer(&mut v, message.trailer.fields_mut(), dd)
+}
+
+fn parse_group(
+    v: &mut VecDeque<StringField>, msg_type: &str, fld: &StringField, fmap: &mut FieldMap,
+    dd: &DataDictionary, options: ParseOptions,
+) -> SessResult<()> {
+    let rg = dd
+        .get_msg_group(msg_type, fld.tag())
+        .ok_or_else(SessionRejectError::tag_not_defined_for_msg)?;
+    let rg_dd = rg.data_dictionary();
+    let field_order = rg_dd.get_ordered_fields();
+    let group_count_tag = fld.tag();
+    let declared_count = match fld.value().parse::<u32>() {
+        Ok(c) => c,
+        Err(e) => return Err(SessionRejectError::incorrect_data_format_err()),
+    };
+    if options.max_group_instances.is_some_and(|max| declared_count > max) {
+        return Err(SessionRejectError::value_out_of_range_err().with_tag(group_count_tag));
+    }
+    let lenient = options.correct_num_in_group;
+    if declared_count as usize > v.len() && !lenient {
+        // every instance starts with the delimiter, so there cannot be more instances than
+        // fields left
+        return Err(SessionRejectError::incorrect_num_in_grp_count());
+    }
+    let delimiter = rg.delimiter();
+    // instances are added as their delimiters come, whatever count was declared
+    let group = fmap.set_group(fld.tag(), declared_count, delimiter);
+    let mut actual_count: i32 = -1;
+    let mut previous_offset: i32 = -1;
+    while let Some(next_field) = v.pop_front() {
+        if next_field.tag() == delimiter {
+            actual_count += 1;
+            if actual_count as u32 >= declared_count && !lenient {
+                // incorrect NumInGroups
+                return Err(SessionRejectError::incorrect_num_in_grp_count());
+            }
+            group.add_group(FieldMap::new());
+            // resetting previous offset
+            previous_offset = -1;
+            let group_instance = &mut group[actual_count as usize];
+            group_instance.set_field_order(&field_order);
+            if rg_dd.is_msg_group(msg_type, next_field.tag()) {
+                parse_group(v, msg_type, &next_field, group_instance, rg_dd, options)?;
+            } else {
+                group_instance.set_field(next_field);
+            }
+        } else if rg_dd.is_msg_group(msg_type, next_field.tag()) {
+            if actual_count < 0 {
+                // delimiter not found but other tag is encountered
+                return Err(SessionRejectError::required_tag_missing_err());
+            }
+            let group_instance = &mut group[actual_count as usize];
+            parse_group(v, msg_type, &next_field, group_instance, rg_dd, options)?;
+        } else if rg_dd.is_msg_field(msg_type, next_field.tag()) {
+            if actual_count < 0 {
+                // means first field not found i.e. delimiter
+                return Err(SessionRejectError::required_tag_missing_err());
+            }
+            // verify the order of fields
+            let offset = match field_order.iter().position(|f| *f == next_field.tag()) {
+                Some(pos) => pos as i32,
+                None => return Err(SessionRejectError::tag_not_defined_for_msg()),
+            };
+            let group_instance = &mut group[actual_count as usize];
+            if options.lenient_group_field_order {
+                // in any order, but a field the instance already has belongs to one whose
+                // delimiter is missing
+                if group_instance.fields.contains_key(&next_field.tag()) {
+                    return Err(SessionRejectError::repeating_grp_out_of_order());
+                }
+            } else if offset < previous_offset {
+                // means the field is out of order
+                return Err(SessionRejectError::repeating_grp_out_of_order());
+            }
+            group_instance.set_field(next_field);
+            previous_offset = offset;
+        } else {
+            // its not a group field, push back and come out
+            v.push_front(next_field);
+            break;
+        }
+    }
+    let actual_count = (actual_count + 1) as u32;
+    if actual_count != declared_count {
+        if !lenient {
+            // means actual repeating groups are less then declared count
+            return Err(SessionRejectError::incorrect_num_in_grp_count());
+        }
+        println!(
+            "correcting NumInGroup({}) from {} to {} instances received",
+            group_count_tag, declared_count, actual_count
+        );
+        group.correct_count(actual_count);
+        fmap.set_field(StringField::new(group_count_tag, &actual_count.to_string()));
+    }
+    // every instance has to carry the fields the dictionary requires of the group
+    let required: Vec<Tag> =
+        field_order.iter().copied().filter(|tag| rg_dd.is_msg_req_field(msg_type, *tag)).collect();
+    let group = &fmap.group[&group_count_tag];
+    for (idx, instance) in group.fields.iter().enumerate() {
+        if let Some(missing) = required.iter().find(|tag| !instance.fields.contains_key(tag)) {
+            return Err(SessionRejectError::required_tag_missing_err()
+                .with_tag(*missing)
+                .with_group_instance(group_count_tag, idx));
+        }
+    }
+    Ok(())
+}
+
+fn parse_header(
+    v: &mut VecDeque<StringField>, header: &mut FieldMap, dd: &DataDictionary,
+    options: ParseOptions,
+) -> SessResult<()> {
+    // the first of BeginString, BodyLength, MsgType not where it belongs is the one reported
+    let leading = [BeginString::field(), BodyLength::field(), MsgType::field()];
+    for (idx, expected) in leading.into_iter().enumerate() {
+        match v.get(idx).map(StringField::tag) {
+            Some(tag) if tag == expected => {}
+            Some(_) if v.iter().any(|fld| fld.tag() == expected) => {
+                return Err(SessionRejectError::tag_specified_out_of_order().with_tag(expected));
+            }
+            _ => return Err(SessionRejectError::required_tag_missing_err().with_tag(expected)),
+        }
+    }
+    while let Some(fld) = v.pop_front() {
+        if !dd.is_header_field(fld.tag()) {
+            // start of body
+            v.push_front(fld);
+            return Ok(());
+        } else if dd.is_msg_group(HEADER_ID, fld.tag()) {
+            parse_group(v, HEADER_ID, &fld, header, dd, options)?;
+        } else {
+            header.set_field(fld);
+        }
+    }
+    Ok(())
+}
+
+fn parse_body(
+    v: &mut VecDeque<StringField>, msg: &mut Message, dd: &DataDictionary, options: ParseOptions,
+) -> SessResult<()> {
+    let msg_type = match msg.get_msg_type() {
+        Ok(s) => s,
+        Err(_) => return Err(SessionRejectError::required_tag_missing_err()),
+    };
+    while let Some(fld) = v.pop_front() {
+        if dd.is_header_field(fld.tag()) {
+            // a header field already in the header, like a second MsgType, is never moved there
+            if !options.relaxed_field_order || msg.header.fields.contains_key(&fld.tag()) {
+                return Err(SessionRejectError::tag_specified_out_of_order().with_tag(fld.tag()));
+            }
+            if dd.is_msg_group(HEADER_ID, fld.tag()) {
+                parse_group(v, HEADER_ID, &fld, msg.header.fields_mut(), dd, options)?;
+            } else {
+                msg.header.fields_mut().set_field(fld);
+            }
+            continue;
+        }
+        if dd.is_trailer_field(fld.tag()) {
+            v.push_front(fld);
+            return Ok(());
+        }
+        if dd.is_msg_group(msg_type.as_str(), fld.tag()) {
+            parse_group(v, &msg_type, &fld, &mut msg.body, dd, options)?;
+        } else {
+            msg.set_field(fld);
+        }
+    }
+    Ok(())
+}
+
+fn parse_trailer(
+    v: &mut VecDeque<StringField>, trailer: &mut FieldMap, dd: &DataDictionary,
+) -> SessResult<()> {
+    // Signature(89) comes right after SignatureLength(93) and CheckSum(10) is the last field
+    let mut previous: Option<Tag> = None;
+    while let Some(fld) = v.pop_front() {
+        let tag = fld.tag();
+        let out_of_order = !dd.is_trailer_field(tag)
+            || (tag == CheckSum::field() && !v.is_empty())
+            || ((tag == Signature::field()) != (previous == Some(SignatureLength::field())));
+        if out_of_order {
+            return Err(SessionRejectError::tag_specified_out_of_order().with_tag(tag));
+        }
+        previous = Some(tag);
+        trailer.set_field(fld);
+    }
+    if previous == Some(SignatureLength::field()) {
+        return Err(SessionRejectError::required_tag_missing_err().with_tag(Signature::field()));
+    }
+    Ok(())
+}
+
+// pub const SAMPLE_MSG: &str = "8=FIX.4.2|9=251|35=D|49=AFUNDMGR|56=ABROKER|34=2|52=2003061501:14:49|11=12345|1=111111|63=0|64=20030621|21=3|110=1000|111=50000|55=IBM|48=459200101|22=1|54=1|60=2003061501:14:49|38=5000|40=1|44=15.75|15=USD|59=0|10=127|";
+
+#[cfg(test)]
+mod message_test {
+    use super::*;
+    #[cfg(test)]
+    use crate::data_dictionary::*;
+    use assert_matches::assert_matches;
+    use lazy_static::*;
+
+    const MSG_STR: &str = "8=FIX.4.3|9=73|35=A|34=0|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|98=0|108=30|10=061|";
+    lazy_static! {
+        static ref DD: DataDictionary = DataDictionary::from_xml("resources/FIX43.xml");
+    }
+
+    fn soh_replaced_str(s: &str) -> String {
+        let mut buff = [0u8; 1];
+        s.replace('|', SOH.encode_utf8(&mut buff))
+    }
+
+    #[test]
+    fn msg_test_simple_no_group() {
+        let msg = Message::from_str(&soh_replaced_str(MSG_STR), &DD);
+        assert!(msg.is_ok());
+        let msg = msg.unwrap();
+        assert_eq!(msg.get_msg_type().unwrap(), "A");
+        assert_eq!(msg.header().get_field::<String>(8).unwrap(), "FIX.4.3");
+    }
+
+    #[test]
+    fn msg_test_inline_values() {
+        for value in ["", "1", "Y", "10", "é", "100", "日", "ORDER1"] {
+            let field = StringField::new(54, value);
+            assert_eq!(field.value(), value);
+            assert_eq!(field.to_string(), format!("54={}\u{1}", value));
+            let inline = matches!(field.value, FieldValue::Inline { .. });
+            assert_eq!(inline, value.len() <= 2, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn msg_test_compact_storage() {
+        // fields keep the order they were first set in, a field set again is replaced
+        let mut map = FieldMap::new();
+        for (tag, value) in [(55, "IBM"), (54, "1"), (38, "100"), (54, "2")] {
+            map.set_field(StringField::new(tag, value));
+        }
+        assert_eq!(map.to_string(), "55=IBM\u{1}54=2\u{1}38=100\u{1}");
+        assert_eq!(map.remove_field(55).unwrap().value(), "IBM");
+        assert!(map.get_field::<String>(55).is_err());
+
+        // instances beyond the inline ones
+        let group = map.set_group(268, 6, 269);
+        for i in 0..6 {
+            group[i].set_field(StringField::new(269, &i.to_string()));
+        }
+        assert_eq!(group.size(), 6);
+        assert!(group.fields.spilled());
+        assert_eq!(group[5].get_field::<u32>(269).unwrap(), 5);
+    }
+
+    #[test]
+    fn msg_test_lazy_group_instances() {
+        // nothing is allocated for the declared count, only for instances used
+        let mut msg = Message::new();
+        let group = msg.set_group(453, u32::MAX, 448);
+        assert_eq!((group.size(), group.value()), (0, u32::MAX));
+        group[1].set_field(StringField::new(448, "DESK"));
+        assert_eq!(group.size(), 2);
+        assert!(group[0].get_field::<String>(448).is_err());
+
+        let md = "8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+        let lenient = ParseOptions {
+            correct_num_in_group: true,
+            ..Default::default()
+        };
+        let raw = soh_replaced_str(&md.replace("268=2", "268=4294967295"));
+        let msg = Message::from_str_with(&raw, &DD, lenient).unwrap();
+        assert_eq!(msg.get_group(268).unwrap().size(), 2);
+    }
+
+    #[test]
+    fn msg_test_write_to() {
+        let raw = soh_replaced_str("8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|");
+        let mut msg = Message::from_str(&raw, &DD).unwrap();
+        // the same bytes as formatting field by field, appended to what is in the buffer
+        let formatted: String = [msg.header(), &msg.body, msg.trailer()]
+            .iter()
+            .flat_map(|map| map.iter())
+            .map(ToString::to_string)
+            .collect();
+        let mut buf = b"prefix".to_vec();
+        msg.write_to(&mut buf);
+        assert_eq!(&buf[6..], formatted.as_bytes());
+        assert_eq!(msg.to_string(), formatted);
+
+        let field = StringField::new(270, "8490.07");
+        assert_eq!(field.encoded_len(), field.to_string().len());
+        let byte_sum: u32 = field.to_string().bytes().map(u32::from).sum();
+        assert_eq!(field.byte_sum(), byte_sum);
+        msg.set_body_len();
+        msg.set_checksum();
+        let sent = msg.to_string();
+        let body_start = sent.find("35=").unwrap();
+        let trailer_start = sent.rfind("10=").unwrap();
+        assert_eq!(msg.header().get_field::<usize>(9).unwrap(), trailer_start - body_start);
+        let checksum = sent[..trailer_start].bytes().map(u32::from).sum::<u32>() % 256;
+        assert_eq!(msg.trailer().get_field::<u32>(10).unwrap(), checksum);
+    }
+
+    #[test]
+    fn msg_test_large_field_list() {
+        // past the threshold fields are found by index, still in the order they were set
+        let mut fields = FieldMap::new();
+        for tag in (5000..5040).rev() {
+            fields.set_field(StringField::new(tag, &tag.to_string()));
+        }
+        assert!(fields.fields.index.is_some());
+        fields.set_field(StringField::new(5020, "changed"));
+        assert_eq!(fields.remove_field(5030).unwrap().value(), "5030");
+        assert!(fields.remove_field(5030).is_none());
+        assert_eq!(fields.get_field::<String>(5020).unwrap(), "changed");
+        assert_eq!(fields.get_field::<u32>(5000).unwrap(), 5000);
+        let tags: Vec<Tag> = fields.fields.keys().copied().collect();
+        let expected: Vec<Tag> = (5000..5040).rev().filter(|tag| *tag != 5030).collect();
+        assert_eq!(tags, expected);
+        assert!(tags.iter().all(|tag| fields.fields.get(tag).unwrap().tag() == *tag));
+        fields.clear();
+        assert!(fields.fields.index.is_none() && fields.get_field::<u32>(5000).is_err());
+    }
+
+    #[test]
+    fn msg_test_data_len_in_group() {
+        // EncodedText(355) of a group instance gets its EncodedTextLen(354) like a body one
+        let mut msg = Message::new();
+        msg.set_field(StringField::new(355, "caf\u{e9}"));
+        let mut instance = FieldMap::new();
+        instance.set_field(StringField::new(269, "0"));
+        instance.set_field(StringField::new(355, "bid\u{1}ask"));
+        msg.body.set_group(268, 1, 269).add_group(instance);
+        msg.set_body_len();
+        assert_eq!(msg.get_field::<usize>(354).unwrap(), 5);
+        let instance = &msg.body.get_group(268).unwrap()[0];
+        assert_eq!(instance.get_field::<usize>(354).unwrap(), 7);
+    }
+
+    #[test]
+    fn msg_test_with_header_group() {
+        // header having a group, verify that its parsed
+        // header with NoHops repeating group
+        let msg_with_header: &str =  "8=FIX.4.3|9=73|35=A|34=0|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|627=1|628=hopcompid|629=20221006-08:43:36.522|630=0|98=0|108=30|10=061|";
+        let msg = Message::from_str(&soh_replaced_str(msg_with_header), &DD);
+        assert!(msg.is_ok());
+        let msg = msg.unwrap();
+        assert!(msg.header().get_group(627).is_some());
+        let header_group = msg.header().get_group(627).unwrap();
+        assert_eq!(header_group.delim(), 628);
+        assert_eq!(header_group.size(), 1);
+        assert_eq!(header_group[0].get_field::<String>(628).unwrap(), "hopcompid");
+        assert_eq!(header_group[0].get_field::<String>(629).unwrap(), "20221006-08:43:36.522");
+        assert_eq!(header_group[0].get_field::<u32>(630).unwrap(), 0);
+    }
+
+    #[test]
+    fn msg_test_with_body_group() {
+        // message body having groups
+        let msg_body_with_repeating_group = "8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+        let msg = Message::from_str(&soh_replaced_str(msg_body_with_repeating_group), &DD);
+        assert!(msg.is_ok());
+        let msg = msg.unwrap();
+        assert_eq!(msg.get_msg_type().unwrap(), "W");
+        assert!(msg.get_group(268).is_some());
+        let md_entries_grp = msg.get_group(268).unwrap();
+        assert_eq!(md_entries_grp.delim(), 269);
+        assert_eq!(md_entries_grp.size(), 2);
+        assert_eq!(md_entries_grp[0].get_field::<u32>(269).unwrap(), 0);
+        assert_eq!(md_entries_grp[0].get_field::<f32>(270).unwrap(), 8490.07);
+        assert_eq!(md_entries_grp[0].get_field::<u32>(271).unwrap(), 10);
+
+        assert_eq!(md_entries_grp[1].get_field::<u32>(269).unwrap(), 1);
+        assert_eq!(md_entries_grp[1].get_field::<f32>(270).unwrap(), 8519.57);
+        assert_eq!(md_entries_grp[1].get_field::<u32>(271).unwrap(), 20);
+    }
+
+    #[test]
+    fn msg_test_with_group_and_subgroups() {
+        // body having repeating groups having subgroups
+        let new_order_list = "8=FIX.4.4|9=108|35=E|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|66=list_id|394=1|73=2|11=ClientOrderId1|67=1|78=2|79=AllocAct11|80=10|79=AllocAct12|80=20|54=1|11=ClientOrderId2|67=2|78=1|79=AllocAct21|80=30|54=2|10=075";
+        let msg = Message::from_str(&soh_replaced_str(new_order_list), &DD);
+        assert!(msg.is_ok());
+        let msg = msg.unwrap();
+        assert_eq!(msg.get_msg_type().unwrap(), "E");
+        assert!(msg.get_group(73).is_some());
+        let no_orders_grp = msg.get_group(73).unwrap();
+        assert_eq!(no_orders_grp.delim(), 11);
+        assert_eq!(no_orders_grp.size(), 2);
+        assert_eq!(no_orders_grp[0].get_field::<String>(11).unwrap(), "ClientOrderId1");
+        assert_eq!(no_orders_grp[0].get_field::<u32>(67).unwrap(), 1);
+
+        let no_alloc_subgrp = no_orders_grp[0].get_group(78);
+        assert!(no_alloc_subgrp.is_some());
+        let no_alloc_subgrp = no_alloc_subgrp.unwrap();
+        assert_eq!(no_alloc_subgrp.size(), 2);
+        assert_eq!(no_alloc_subgrp[0].get_field::<String>(79).unwrap(), "AllocAct11");
+        assert_eq!(no_alloc_subgrp[0].get_field::<u32>(80).unwrap(), 10);
+
+        assert_eq!(no_alloc_subgrp[1].get_field::<String>(79).unwrap(), "AllocAct12");
+        assert_eq!(no_alloc_subgrp[1].get_field::<u32>(80).unwrap(), 20);
+
+        assert_eq!(no_orders_grp[1].get_field::<String>(11).unwrap(), "ClientOrderId2");
+        assert_eq!(no_orders_grp[1].get_field::<u32>(67).unwrap(), 2);
+
+        let no_alloc_subgrp2 = no_orders_grp[1].get_group(78);
+        assert!(no_alloc_subgrp2.is_some());
+        let no_alloc_subgrp2 = no_alloc_subgrp2.unwrap();
+        assert_eq!(no_alloc_subgrp2.size(), 1);
+        assert_eq!(no_alloc_subgrp2[0].get_field::<String>(79).unwrap(), "AllocAct21");
+        assert_eq!(no_alloc_subgrp2[0].get_field::<u32>(80).unwrap(), 30);
+    }
+
+    #[test]
+    fn msg_test_session_id() {
+        let sid = Message::get_session_id(&soh_replaced_str(MSG_STR)).unwrap();
+        assert_eq!(sid.to_string(), "FIX.4.3:BANZAI->FIXIMULATOR");
+        let reverse = Message::get_reverse_session_id(&soh_replaced_str(MSG_STR)).unwrap();
+        assert_eq!(reverse.to_string(), "FIX.4.3:FIXIMULATOR->BANZAI");
+        // 58= is not mistaken for BeginString and sub id is picked up
+        let with_text = "8=FIX.4.3|9=5|35=0|49=A|50=DESK|56=B|58=8=X|10=000|";
+        let sid = Message::get_session_id(&soh_replaced_str(with_text)).unwrap();
+        assert_eq!(sid.to_string(), "FIX.4.3:A/DESK->B");
+    }
+
+    #[test]
+    fn msg_test_garbled_session_id() {
+        let garbled = [
+            "",
+            "9=5|35=0|49=A|56=B|10=000|",
+            "8=FIX.4.3|9=5|35=0|56=B|10=000|",
+            "8=FIX.4.3|9=5|35=0|49=|56=B|10=000|",
+            // last field is not terminated
+            "8=FIX.4.3|9=5|35=0|49=A|56=B",
+            "8=FIX.4.3",
+        ];
+        for raw in garbled {
+            assert!(Message::get_session_id(&soh_replaced_str(raw)).is_err(), "{}", raw);
+            assert!(Message::get_reverse_session_id(&soh_replaced_str(raw)).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn msg_test_incorrect_data_format() {
+        let order = "8=FIX.4.3|9=73|35=D|34=2|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|11=ORDER1|21=1|55=IBM|54=1|60=20221006-08:43:36.522|38=100|40=2|44=15.75|10=061|";
+        assert!(Message::from_str(&soh_replaced_str(order), &DD).is_ok());
+        for (good, bad) in [
+            ("44=15.75", "44=1.5e1"),
+            ("44=15.75", "44=+15"),
+            ("38=100", "38=ten"),
+        ] {
+            let raw = order.replace(good, bad);
+            let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+            assert_eq!(err.reject_reason(), Some(6));
+            assert_eq!(err.ref_tag_id(), good[..2].parse().ok());
+        }
+        let raw = order.replace("21=1|", "21=1|200=2022-12|");
+        let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Session Level Reject Reason - IncorrectDataFormatForValue for tag 200 at byte {} \
+                 (\"200=2022-12\")",
+                raw.find("200=").unwrap()
+            )
+        );
+        let raw = order.replace("40=2|", "40=2|18=1 G|");
+        let msg = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap();
+        assert_eq!(msg.get_multiple_values(18).unwrap(), vec!["1", "G"]);
+        let raw = order.replace("40=2|", "40=2|18=1 Z9|");
+        let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(5), Some(18)));
+    }
+
+    #[test]
+    fn msg_test_get_typed() {
+        let order = "8=FIX.4.3|9=73|35=D|34=2|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|11=ORDER1|21=1|55=IBM|54=1|60=20221006-08:43:36.522|38=100|40=2|44=15.75|114=Y|10=061|";
+        let mut msg = Message::from_str(&soh_replaced_str(order), &DD).unwrap();
+        assert_matches!(msg.get_typed(54, &DD), Ok(Type::Char('1')));
+        assert_matches!(msg.get_typed(114, &DD), Ok(Type::Bool(true)));
+        assert_matches!(msg.get_typed(38, &DD), Ok(Type::Qty(q)) if q == 100.0);
+        assert_matches!(msg.get_typed(44, &DD), Ok(Type::Price(p)) if p == 15.75);
+        assert_matches!(msg.get_typed(55, &DD), Ok(Type::Str(s)) if s == "IBM");
+        assert_matches!(msg.get_typed(60, &DD), Ok(Type::UtcTimestamp(_)));
+        let err = msg.get_typed(58, &DD).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(1), Some(58)));
+        for (tag, value, reason) in [
+            (114, "y", 6),
+            (114, "Yes", 6),
+            (54, "12", 6),
+            (54, "\u{7}", 6),
+            (54, "Z", 5),
+        ] {
+            msg.set_field(StringField::new(tag, value));
+            let err = msg.get_typed(tag, &DD).unwrap_err();
+            assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(reason), Some(tag)));
+        }
+        let raw = order.replace("114=Y", "114=n");
+        let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(6), Some(114)));
+    }
+
+    #[test]
+    fn msg_test_correct_num_in_group() {
+        let md = "8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+        let lenient = ParseOptions {
+            correct_num_in_group: true,
+            ..Default::default()
+        };
+        for declared in ["268=1", "268=3", "268=500"] {
+            let raw = soh_replaced_str(&md.replace("268=2", declared));
+            let err = Message::from_str(&raw, &DD).unwrap_err();
+            assert_eq!(err.reject_reason(), Some(16));
+            let msg = Message::from_str_with(&raw, &DD, lenient).unwrap();
+            let md_entries_grp = msg.get_group(268).unwrap();
+            assert_eq!((md_entries_grp.size(), md_entries_grp.value()), (2, 2));
+            assert_eq!(md_entries_grp[1].get_field::<u32>(271).unwrap(), 20);
+            assert_eq!(msg.get_field::<u32>(268).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn msg_test_limits() {
+        let md = "8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+        let raw = soh_replaced_str(md);
+        let limited = |max_fields, max_group_instances| ParseOptions {
+            max_fields,
+            max_group_instances,
+            ..Default::default()
+        };
+        assert!(Message::from_str_with(&raw, &DD, limited(Some(17), Some(2))).is_ok());
+        let err = Message::from_str_with(&raw, &DD, limited(Some(16), None)).unwrap_err();
+        assert_eq!((err.reject_reason(), err.raw_field()), (Some(5), Some("10=075")));
+        let err = Message::from_str_with(&raw, &DD, limited(None, Some(1))).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(5), Some(268)));
+
+        // a hostile count is rejected before anything is allocated for it, even when lenient
+        let hostile = soh_replaced_str(&md.replace("268=2", "268=4294967295"));
+        let options = ParseOptions {
+            correct_num_in_group: true,
+            ..limited(None, Some(100))
+        };
+        let err = Message::from_str_with(&hostile, &DD, options).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(5), Some(268)));
+    }
+
+    #[test]
+    fn msg_test_group_field_order() {
+        let md = "8=FIX.4.4|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+        let lenient = ParseOptions {
+            lenient_group_field_order: true,
+            ..Default::default()
+        };
+        // MDEntrySize(271) before MDEntryPx(270) in the second instance
+        let swapped = soh_replaced_str(&md.replace("270=8519.57|271=20|", "271=20|270=8519.57|"));
+        let err = Message::from_str(&swapped, &DD).unwrap_err();
+        assert_eq!(err.reject_reason(), Some(15));
+        let msg = Message::from_str_with(&swapped, &DD, lenient).unwrap();
+        let entries = msg.get_group(268).unwrap();
+        assert_eq!(entries[1].get_field::<u32>(271).unwrap(), 20);
+        assert_eq!(entries[1].get_field::<f64>(270).unwrap(), 8519.57);
+
+        // the delimiter still has to come first
+        let late_delimiter =
+            soh_replaced_str(&md.replace("269=1|270=8519.57|", "270=8519.57|269=1|"));
+        let err = Message::from_str_with(&late_delimiter, &DD, lenient).unwrap_err();
+        assert_eq!(err.reject_reason(), Some(15));
+    }
+
+    #[test]
+    fn msg_test_group_required_fields() {
+        let list_status = "8=FIX.4.3|9=100|35=N|34=2|49=A|52=20180425-17:51:40.787|56=B|66=L1|429=1|82=2|431=1|83=1|68=2|73=2|11=o1|14=0|39=0|151=10|84=0|6=0|11=o2|14=0|39=0|151=5|84=0|6=0|10=000|";
+        assert!(Message::from_str(&soh_replaced_str(list_status), &DD).is_ok());
+        // LeavesQty(151) missing from the second order
+        let raw = soh_replaced_str(&list_status.replace("151=5|", ""));
+        let err = Message::from_str(&raw, &DD).unwrap_err();
+        assert_eq!(err.reject_reason(), Some(1));
+        assert_eq!(err.ref_tag_id(), Some(151));
+        assert_eq!(err.group_instance(), Some((73, 1)));
+        assert!(err.to_string().ends_with("for tag 151 in instance 2 of group 73"));
+    }
+
+    #[test]
+    fn msg_test_trailer_with_more_fields() {
+        // trailer having all the fields of trailer and verify that it is parsed correctly
+        let msg = "8=FIX.4.3|9=73|35=A|34=0|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|98=0|108=30|93=3|89=abc|10=061|";
+        let msg = Message::from_str(&soh_replaced_str(msg), &DD).unwrap();
+        assert_eq!(msg.trailer().get_field::<u32>(93).unwrap(), 3);
+        assert_eq!(msg.trailer().get_field::<String>(89).unwrap(), "abc");
+        assert!(msg.to_string().ends_with(&soh_replaced_str("|93=3|89=abc|10=061|")));
+
+        for trailer in [
+            "10=061|93=3|89=abc|",
+            "93=3|10=061|89=abc|",
+            "89=abc|93=3|10=061|",
+        ] {
+            let raw = format!("{}{}", &MSG_STR[..MSG_STR.len() - 7], trailer);
+            let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+            assert_eq!(err.reject_reason(), Some(14));
+        }
+        let raw = format!("{}93=3|", &MSG_STR[..MSG_STR.len() - 7]);
+        let err = Message::from_str(&soh_replaced_str(&raw), &DD).unwrap_err();
+        assert_eq!((err.reject_reason(), err.ref_tag_id()), (Some(1), Some(89)));
+    }
+
+    #[test]
+    fn msg_test_field_order() {
+        let strict = ParseOptions::default();
+        let relaxed = ParseOptions {
+            relaxed_field_order: true,
+            ..Default::default()
+        };
+        let reject = |raw: &str, options| {
+            let err = Message::from_str_with(&soh_replaced_str(raw), &DD, options).unwrap_err();
+            (err.reject_reason(), err.ref_tag_id())
+        };
+        let swapped = MSG_STR.replace("8=FIX.4.3|9=73|", "9=73|8=FIX.4.3|");
+        assert_eq!(reject(&swapped, strict), (Some(14), Some(8)));
+        assert_eq!(reject(&MSG_STR.replace("9=73|", ""), strict), (Some(1), Some(9)));
+
+        // SenderSubID(50) after the body fields
+        let late_header = MSG_STR.replace("108=30|", "108=30|50=desk|");
+        assert_eq!(reject(&late_header, strict), (Some(14), Some(50)));
+        let msg = Message::from_str_with(&soh_replaced_str(&late_header), &DD, relaxed).unwrap();
+        assert_eq!(msg.header().get_field::<String>(50).unwrap(), "desk");
+        let second_msg_type = MSG_STR.replace("108=30|", "108=30|35=0|");
+        assert_eq!(reject(&second_msg_type, relaxed), (Some(14), Some(35)));
+
+        let late_body = MSG_STR.replace("108=30|", "108=30|93=3|89=abc|58=late|");
+        assert_eq!(reject(&late_body, relaxed), (Some(14), Some(58)));
+    }
+
+    #[test]
+    fn msg_test_trailer_order() {
+        // CheckSum goes last however the trailer was filled in
+        let mut msg = Message::new();
+        msg.trailer_mut().set_field(StringField::new(10, "000"), &DD).unwrap();
+        msg.trailer_mut().set_field(StringField::new(89, "abc"), &DD).unwrap();
+        msg.trailer_mut().set_field(StringField::new(93, "3"), &DD).unwrap();
+        assert_eq!(msg.trailer().to_string(), soh_replaced_str("93=3|89=abc|10=000|"));
+        let mut trailer = FieldMap::trailer(&[]);
+        trailer.set_field(StringField::new(10, "000"));
+        trailer.set_field(StringField::new(5000, "custom"));
+        assert_eq!(trailer.to_string(), soh_replaced_str("5000=custom|10=000|"));
+    }
+
+    fn msg_test_invalid_checksum() {}
+
+    fn msg_test_invalid_body_length() {}
+
+    fn msg_test_soh_in_data_field() {}
+
+    fn msg_test_soh_in_non_data_field() {}
+}
@@ -0,0 +1,129 @@
+// Messages kept for reuse by the parser, so that the receive path does not allocate the maps
+// of every message it parses anew. A message taken from the pool goes back to it, cleared,
+// when its PooledMessage is dropped.
+use crate::data_dictionary::DataDictionary;
+use crate::message::{Message, ParseOptions, SessResult};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct MessagePool {
+    free: Arc<Mutex<Vec<Message>>>,
+    // messages kept at most, any more are dropped when they come back
+    capacity: usize,
+}
+
+impl MessagePool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            free: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    // an empty message, as Message::new returns it
+    pub fn get(&self) -> PooledMessage {
+        // not Message::default, that leaves out the field order of header and trailer
+        #[allow(clippy::unwrap_or_default)]
+        let message = self.free.lock().unwrap().pop().unwrap_or_else(Message::new);
+        PooledMessage {
+            message: Some(message),
+            pool: self.clone(),
+        }
+    }
+
+    pub fn parse(
+        &self, s: &str, dd: &DataDictionary, options: ParseOptions,
+    ) -> SessResult<PooledMessage> {
+        // a message that does not parse goes straight back
+        let mut message = self.get();
+        message.parse_into(s, dd, options)?;
+        Ok(message)
+    }
+
+    // messages waiting to be reused
+    pub fn available(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    fn release(&self, mut message: Message) {
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.capacity {
+            message.clear();
+            free.push(message);
+        }
+    }
+}
+
+// Message on loan from a MessagePool, derefs to the message. into_inner keeps the message for
+// good, it is then not returned.
+#[derive(Debug)]
+pub struct PooledMessage {
+    message: Option<Message>,
+    pool: MessagePool,
+}
+
+impl PooledMessage {
+    pub fn into_inner(mut self) -> Message {
+        self.message.take().expect("message is there until dropped")
+    }
+}
+
+impl Deref for PooledMessage {
+    type Target = Message;
+
+    fn deref(&self) -> &Message {
+        self.message.as_ref().expect("message is there until dropped")
+    }
+}
+
+impl DerefMut for PooledMessage {
+    fn deref_mut(&mut self) -> &mut Message {
+        self.message.as_mut().expect("message is there until dropped")
+    }
+}
+
+impl Drop for PooledMessage {
+    fn drop(&mut self) {
+        if let Some(message) = self.message.take() {
+            self.pool.release(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod pool_tests {
+    use super::*;
+    use crate::message::SOH;
+
+    const SNAPSHOT: &str = "8=FIX.4.3|9=108|35=W|34=2|49=GEMINI|52=20180425-17:51:40.787|56=TRADEBOTMD002|55=BTCUSD|262=2|268=2|269=0|270=8490.07|271=10|269=1|270=8519.57|271=20|10=075|";
+    const LOGON: &str = "8=FIX.4.3|9=73|35=A|34=1|49=BANZAI|52=20221006-08:43:36.522|56=FIXIMULATOR|98=0|108=30|10=061|";
+
+    #[test]
+    fn test_reuse() {
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let raw = |s: &str| s.replace('|', &SOH.to_string());
+        let pool = MessagePool::new(1);
+        let snapshot = pool.parse(&raw(SNAPSHOT), &dd, ParseOptions::default()).unwrap();
+        assert_eq!(pool.available(), 0);
+        drop(snapshot);
+        assert_eq!(pool.available(), 1);
+
+        // nothing of the message before is left in the reused one
+        let logon = pool.parse(&raw(LOGON), &dd, ParseOptions::default()).unwrap();
+        assert_eq!(pool.available(), 0);
+        let parsed = Message::from_str(&raw(LOGON), &dd).unwrap();
+        assert!(crate::message::diff(&parsed, &logon).is_empty());
+        assert!(logon.get_group(268).is_none());
+
+        // a message that does not parse goes back, one kept for good does not, and the pool
+        // keeps no more than its capacity
+        let extra = pool.get();
+        assert!(pool.parse("8=FIX.4.3", &dd, ParseOptions::default()).is_err());
+        assert_eq!(pool.available(), 1);
+        let kept = logon.into_inner();
+        assert_eq!(kept.get_msg_type().unwrap(), "A");
+        drop(extra);
+        assert_eq!(pool.available(), 1);
+    }
+}
@@ -0,0 +1,803 @@
+use crate::session::*;
+use crate::types::UtcTimestamp;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+// Keeps what a session sent, by MsgSeqNum, for answering ResendRequests, the messages queued
+// for the counterparty while the session was down, until they are delivered, the seqnums of
+// the application messages received, to tell redelivered ones apart, and the session's next
+// seqnums as of its last checkpoint, see SeqNumCheckpoint.
+pub trait MessageStore: Send + std::fmt::Debug {
+    fn set(&mut self, seq_num: u64, raw: StoredMessage) -> io::Result<()>;
+    // stored messages with begin <= seqnum <= end, in seqnum order, read as the cursor advances
+    fn iter_range(&self, begin: u64, end: u64) -> MessageCursor<'_>;
+    fn get_range(&self, begin: u64, end: u64) -> io::Result<Vec<(u64, StoredMessage)>> {
+        self.iter_range(begin, end).collect()
+    }
+    fn enqueue(&mut self, raw: &str) -> io::Result<()>;
+    // queued messages, oldest first. they stay queued until dequeued
+    fn queued(&self) -> io::Result<Vec<String>>;
+    // drops the oldest count queued messages once they are delivered
+    fn dequeue(&mut self, count: usize) -> io::Result<()>;
+    // stores that do not keep received seqnums deliver every PossDup message again
+    fn set_received(&mut self, seq_num: u64) -> io::Result<()> {
+        let _ = seq_num;
+        Ok(())
+    }
+    fn is_received(&self, seq_num: u64) -> bool {
+        let _ = seq_num;
+        false
+    }
+    // the received seqnums kept, in order
+    fn received(&self) -> Vec<u64> {
+        Vec::new()
+    }
+    fn reset(&mut self) -> io::Result<()>;
+    // number of sent messages stored
+    fn stored_count(&self) -> usize {
+        self.iter_range(1, u64::MAX).count()
+    }
+    // drops the sent messages with seqnum <= through, see RetentionPolicy
+    fn purge(&mut self, through: u64) -> io::Result<()> {
+        let _ = through;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "store cannot purge messages"))
+    }
+    // (next sender, next target) seqnums of the last checkpoint, none before the first one
+    fn seq_nums(&self) -> Option<(u64, u64)> {
+        None
+    }
+    fn set_seq_nums(&mut self, next_sender: u64, next_target: u64) -> io::Result<()> {
+        let _ = (next_sender, next_target);
+        Ok(())
+    }
+    // highest seqnum of the sent messages stored
+    fn last_stored(&self) -> Option<u64> {
+        self.iter_range(1, u64::MAX).filter_map(Result::ok).last().map(|(seq_num, _)| seq_num)
+    }
+    // highest seqnum of the application messages received
+    fn last_received(&self) -> Option<u64> {
+        None
+    }
+}
+
+// received seqnums kept for telling PossDup messages apart. once more than twice as many are
+// kept, those below the low-water mark of the highest one minus RECEIVED_WINDOW are dropped,
+// a PossDup message that old is delivered again
+pub const RECEIVED_WINDOW: u64 = 10_000;
+
+// the received seqnums below the low-water mark, none while fewer than twice RECEIVED_WINDOW
+// are kept
+fn trim_received(received: &mut BTreeSet<u64>) -> Option<BTreeSet<u64>> {
+    if received.len() as u64 <= 2 * RECEIVED_WINDOW {
+        return None;
+    }
+    let low_water = received.last()?.saturating_sub(RECEIVED_WINDOW);
+    let kept = received.split_off(&low_water);
+    Some(std::mem::replace(received, kept))
+}
+
+// A sent message as it went out. Stores that keep messages in memory hand out the same bytes
+// for every resend instead of a copy
+pub type StoredMessage = Arc<[u8]>;
+
+// Lazy iterator over (seqnum, message) pairs of a store, so that dumping or resending a large
+// range does not load all of it at once.
+//
+//     let store = FileStore::open("store", &session_id)?;
+//     for stored in store.iter_range(1000, 2000) {
+//         let (seq_num, raw) = stored?;
+//         println!("{} {}", seq_num, String::from_utf8_lossy(&raw).replace(SOH, "|"));
+//     }
+pub struct MessageCursor<'a> {
+    inner: Box<dyn Iterator<Item = io::Result<(u64, StoredMessage)>> + 'a>,
+}
+
+impl<'a> MessageCursor<'a> {
+    pub fn new(inner: impl Iterator<Item = io::Result<(u64, StoredMessage)>> + 'a) -> Self {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(std::iter::empty())
+    }
+}
+
+impl Iterator for MessageCursor<'_> {
+    type Item = io::Result<(u64, StoredMessage)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl std::fmt::Debug for MessageCursor<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MessageCursor")
+    }
+}
+
+// store shared between clones of a session
+#[derive(Debug, Clone)]
+pub struct SharedMessageStore(Arc<Mutex<dyn MessageStore>>);
+
+impl SharedMessageStore {
+    pub fn new(store: impl MessageStore + 'static) -> Self {
+        Self(Arc::new(Mutex::new(store)))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, dyn MessageStore + 'static> {
+        // a panic while holding the lock cannot leave the store half updated in a way later
+        // callers could not deal with, so poisoning is ignored
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for SharedMessageStore {
+    fn default() -> Self {
+        Self::new(MemoryStore::new())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    messages: BTreeMap<u64, StoredMessage>,
+    queue: VecDeque<String>,
+    received: BTreeSet<u64>,
+    seq_nums: Option<(u64, u64)>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageStore for MemoryStore {
+    fn set(&mut self, seq_num: u64, raw: StoredMessage) -> io::Result<()> {
+        self.messages.insert(seq_num, raw);
+        Ok(())
+    }
+
+    fn iter_range(&self, begin: u64, end: u64) -> MessageCursor<'_> {
+        if begin > end {
+            return MessageCursor::empty();
+        }
+        let range = self.messages.range(begin..=end);
+        MessageCursor::new(range.map(|(seq_num, raw)| Ok((*seq_num, Arc::clone(raw)))))
+    }
+
+    fn enqueue(&mut self, raw: &str) -> io::Result<()> {
+        self.queue.push_back(raw.to_string());
+        Ok(())
+    }
+
+    fn queued(&self) -> io::Result<Vec<String>> {
+        Ok(self.queue.iter().cloned().collect())
+    }
+
+    fn dequeue(&mut self, count: usize) -> io::Result<()> {
+        self.queue.drain(..count.min(self.queue.len()));
+        Ok(())
+    }
+
+    fn set_received(&mut self, seq_num: u64) -> io::Result<()> {
+        self.received.insert(seq_num);
+        trim_received(&mut self.received);
+        Ok(())
+    }
+
+    fn is_received(&self, seq_num: u64) -> bool {
+        self.received.contains(&seq_num)
+    }
+
+    fn received(&self) -> Vec<u64> {
+        self.received.iter().copied().collect()
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.messages.clear();
+        self.queue.clear();
+        self.received.clear();
+        self.seq_nums = None;
+        Ok(())
+    }
+
+    fn stored_count(&self) -> usize {
+        self.messages.len()
+    }
+
+    fn purge(&mut self, through: u64) -> io::Result<()> {
+        self.messages = self.messages.split_off(&through.saturating_add(1));
+        Ok(())
+    }
+
+    fn seq_nums(&self) -> Option<(u64, u64)> {
+        self.seq_nums
+    }
+
+    fn set_seq_nums(&mut self, next_sender: u64, next_target: u64) -> io::Result<()> {
+        self.seq_nums = Some((next_sender, next_target));
+        Ok(())
+    }
+
+    fn last_stored(&self) -> Option<u64> {
+        self.messages.keys().next_back().copied()
+    }
+
+    fn last_received(&self) -> Option<u64> {
+        self.received.last().copied()
+    }
+}
+
+// seqnum -> (offset, length) of the message in the body file
+type Offsets = BTreeMap<u64, (u64, usize)>;
+
+// Store that survives restarts. Each session gets four files in the store directory:
+// <session>.body with one "seqnum<TAB>message" line per sent message, <session>.queue with
+// one queued message per line, <session>.received with one received seqnum per line and
+// <session>.seqnums with the "sender target" seqnums of the last checkpoint. Messages never
+// contain a newline, fields end with SOH.
+#[derive(Debug)]
+pub struct FileStore {
+    body_path: PathBuf,
+    body: File,
+    offsets: Offsets,
+    queue_path: PathBuf,
+    queue: VecDeque<String>,
+    received_path: PathBuf,
+    received_file: File,
+    received: BTreeSet<u64>,
+    seq_nums_path: PathBuf,
+    seq_nums: Option<(u64, u64)>,
+}
+
+impl FileStore {
+    pub fn open(dir: impl AsRef<Path>, session_id: &SessionId) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(file_name(session_id));
+        let body_path = path.with_extension("body");
+        let queue_path = path.with_extension("queue");
+        let received_path = path.with_extension("received");
+        let seq_nums_path = path.with_extension("seqnums");
+        let (body, offsets) = Self::open_body(&body_path)?;
+        let queue = match fs::read_to_string(&queue_path) {
+            Ok(queued) => queued.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(e) => return Err(e),
+        };
+        let mut received_file =
+            OpenOptions::new().read(true).append(true).create(true).open(&received_path)?;
+        let mut received = String::new();
+        received_file.read_to_string(&mut received)?;
+        // a seqnum cut short by a crash does not parse, or is one never received. either way
+        // the message is delivered again rather than dropped
+        let received = received
+            .split_inclusive('\n')
+            .filter_map(|line| line.strip_suffix('\n')?.parse::<u64>().ok())
+            .collect();
+        let seq_nums = match fs::read_to_string(&seq_nums_path) {
+            Ok(seq_nums) => parse_seq_nums(&seq_nums),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            body_path,
+            body,
+            offsets,
+            queue_path,
+            queue,
+            received_path,
+            received_file,
+            received,
+            seq_nums_path,
+            seq_nums,
+        })
+    }
+
+    fn open_body(body_path: &Path) -> io::Result<(File, Offsets)> {
+        let mut body = OpenOptions::new().read(true).append(true).create(true).open(body_path)?;
+        let mut contents = String::new();
+        body.read_to_string(&mut contents)?;
+        let mut offsets = BTreeMap::new();
+        let mut offset = 0;
+        for line in contents.split_inclusive('\n') {
+            // a line without newline was cut short by a crash and is ignored
+            if let Some((seq_num, raw)) = line.strip_suffix('\n').and_then(|l| l.split_once('\t')) {
+                if let Ok(seq_num) = seq_num.parse::<u64>() {
+                    let raw_offset = offset + seq_num.to_string().len() as u64 + 1;
+                    offsets.insert(seq_num, (raw_offset, raw.len()));
+                }
+            }
+            offset += line.len() as u64;
+        }
+        Ok((body, offsets))
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<StoredMessage> {
+        let mut body = &self.body;
+        let mut raw = vec![0; len];
+        body.seek(SeekFrom::Start(offset))?;
+        body.read_exact(&mut raw)?;
+        Ok(raw.into())
+    }
+
+    fn write_received(&mut self) -> io::Result<()> {
+        // the seqnums kept after a trim replace the received file, written aside and renamed
+        // over it like the body file on purge
+        let tmp_path = self.received_path.with_extension("received.tmp");
+        let mut kept = io::BufWriter::new(File::create(&tmp_path)?);
+        for seq_num in self.received.iter() {
+            writeln!(kept, "{}", seq_num)?;
+        }
+        kept.into_inner().map_err(io::IntoInnerError::into_error)?.sync_data()?;
+        fs::rename(&tmp_path, &self.received_path)?;
+        self.received_file = OpenOptions::new().append(true).open(&self.received_path)?;
+        Ok(())
+    }
+
+    fn write_queue(&self) -> io::Result<()> {
+        let mut file = File::create(&self.queue_path)?;
+        for raw in self.queue.iter() {
+            writeln!(file, "{}", raw)?;
+        }
+        file.sync_data()
+    }
+}
+
+impl MessageStore for FileStore {
+    fn set(&mut self, seq_num: u64, raw: StoredMessage) -> io::Result<()> {
+        let offset = self.body.seek(SeekFrom::End(0))?;
+        let mut line = Vec::with_capacity(raw.len() + 24);
+        write_line(&mut line, seq_num, &raw)?;
+        self.body.write_all(&line)?;
+        let raw_offset = offset + seq_num.to_string().len() as u64 + 1;
+        self.offsets.insert(seq_num, (raw_offset, raw.len()));
+        Ok(())
+    }
+
+    fn iter_range(&self, begin: u64, end: u64) -> MessageCursor<'_> {
+        if begin > end {
+            return MessageCursor::empty();
+        }
+        let range = self.offsets.range(begin..=end);
+        MessageCursor::new(range.map(|(seq_num, (offset, len))| {
+            let raw = self.read_at(*offset, *len)?;
+            Ok((*seq_num, raw))
+        }))
+    }
+
+    fn enqueue(&mut self, raw: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().append(true).create(true).open(&self.queue_path)?;
+        writeln!(file, "{}", raw)?;
+        // queued messages exist nowhere else, so they are on disk before enqueue returns
+        file.sync_data()?;
+        self.queue.push_back(raw.to_string());
+        Ok(())
+    }
+
+    fn queued(&self) -> io::Result<Vec<String>> {
+        Ok(self.queue.iter().cloned().collect())
+    }
+
+    fn dequeue(&mut self, count: usize) -> io::Result<()> {
+        self.queue.drain(..count.min(self.queue.len()));
+        self.write_queue()
+    }
+
+    fn set_received(&mut self, seq_num: u64) -> io::Result<()> {
+        writeln!(self.received_file, "{}", seq_num)?;
+        self.received.insert(seq_num);
+        if trim_received(&mut self.received).is_some() {
+            self.write_received()?;
+        }
+        Ok(())
+    }
+
+    fn is_received(&self, seq_num: u64) -> bool {
+        self.received.contains(&seq_num)
+    }
+
+    fn received(&self) -> Vec<u64> {
+        self.received.iter().copied().collect()
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.body.set_len(0)?;
+        self.offsets.clear();
+        self.received_file.set_len(0)?;
+        self.received.clear();
+        self.queue.clear();
+        self.seq_nums = None;
+        match fs::remove_file(&self.seq_nums_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        self.write_queue()
+    }
+
+    fn stored_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn purge(&mut self, through: u64) -> io::Result<()> {
+        // the messages kept are written to a new body file that then replaces the old one, a
+        // crash in between leaves the old one as it was
+        let kept_path = self.body_path.with_extension("body.tmp");
+        let mut kept = io::BufWriter::new(File::create(&kept_path)?);
+        for stored in self.iter_range(through.saturating_add(1), u64::MAX) {
+            let (seq_num, raw) = stored?;
+            write_line(&mut kept, seq_num, &raw)?;
+        }
+        kept.into_inner().map_err(io::IntoInnerError::into_error)?.sync_data()?;
+        fs::rename(&kept_path, &self.body_path)?;
+        (self.body, self.offsets) = Self::open_body(&self.body_path)?;
+        Ok(())
+    }
+
+    fn seq_nums(&self) -> Option<(u64, u64)> {
+        self.seq_nums
+    }
+
+    fn set_seq_nums(&mut self, next_sender: u64, next_target: u64) -> io::Result<()> {
+        // written aside and renamed over the old one, a crash leaves either checkpoint whole
+        let tmp_path = self.seq_nums_path.with_extension("seqnums.tmp");
+        let mut file = File::create(&tmp_path)?;
+        writeln!(file, "{} {}", next_sender, next_target)?;
+        file.sync_data()?;
+        fs::rename(&tmp_path, &self.seq_nums_path)?;
+        self.seq_nums = Some((next_sender, next_target));
+        Ok(())
+    }
+
+    fn last_stored(&self) -> Option<u64> {
+        self.offsets.keys().next_back().copied()
+    }
+
+    fn last_received(&self) -> Option<u64> {
+        self.received.last().copied()
+    }
+}
+
+// "seqnum<TAB>message" line of the body and archive files
+fn write_line(out: &mut impl Write, seq_num: u64, raw: &[u8]) -> io::Result<()> {
+    write!(out, "{}\t", seq_num)?;
+    out.write_all(raw)?;
+    out.write_all(b"\n")
+}
+
+fn parse_seq_nums(seq_nums: &str) -> Option<(u64, u64)> {
+    let (sender, target) = seq_nums.trim().split_once(' ')?;
+    Some((sender.parse().ok()?, target.parse().ok()?))
+}
+
+fn file_name(session_id: &SessionId) -> String {
+    session_id
+        .key()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// How many sent messages a session's store keeps, and for how long. Older ones are dropped,
+// so that a resend of them is gap filled. With archive_path set they are first written to
+// <session>.<first>-<last>.archive there, one "seqnum<TAB>message" line each like the body
+// file. Archives are written uncompressed, compressing them is left to whatever collects them:
+// an archive is never written to again once purge returns, so it can be compressed or moved
+// away right after.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_messages: Option<usize>,
+    // by SendingTime(52)
+    pub max_age: Option<Duration>,
+    pub archive_path: Option<PathBuf>,
+}
+
+impl RetentionPolicy {
+    pub fn from_settings(settings: &Properties, session_id: &SessionId) -> Self {
+        Self {
+            max_messages: settings.get_optional_config(session_id, STORE_MAX_MESSAGES_SETTING),
+            max_age: settings
+                .get_optional_config(session_id, STORE_MAX_AGE_SETTING)
+                .map(Duration::from_secs),
+            archive_path: settings
+                .get_optional_config::<String>(session_id, STORE_ARCHIVE_PATH_SETTING)
+                .map(PathBuf::from),
+        }
+    }
+
+    pub fn keeps_everything(&self) -> bool {
+        self.max_messages.is_none() && self.max_age.is_none()
+    }
+
+    pub fn purge(
+        &self, store: &mut dyn MessageStore, session_id: &SessionId, now: DateTime<Utc>,
+    ) -> io::Result<usize> {
+        // number of messages purged. the oldest ones go first, a message is only purged with
+        // all before it
+        if self.keeps_everything() {
+            return Ok(0);
+        }
+        let excess = self
+            .max_messages
+            .map_or(0, |max_messages| store.stored_count().saturating_sub(max_messages));
+        let cutoff = self.max_age.and_then(|age| chrono::Duration::from_std(age).ok());
+        let cutoff = cutoff.map(|age| now - age);
+        let mut purged = Vec::new();
+        for stored in store.iter_range(1, u64::MAX) {
+            let (seq_num, raw) = stored?;
+            let expired =
+                cutoff.is_some_and(|cutoff| sending_time(&raw).is_some_and(|t| t < cutoff));
+            if purged.len() >= excess && !expired {
+                break;
+            }
+            purged.push((seq_num, raw));
+        }
+        let (Some((first, _)), Some((last, _))) = (purged.first(), purged.last()) else {
+            return Ok(0);
+        };
+        if let Some(dir) = &self.archive_path {
+            fs::create_dir_all(dir)?;
+            let name = format!("{}.{}-{}.archive", file_name(session_id), first, last);
+            let mut archive = io::BufWriter::new(File::create(dir.join(name))?);
+            for (seq_num, raw) in purged.iter() {
+                write_line(&mut archive, *seq_num, raw)?;
+            }
+            archive.into_inner().map_err(io::IntoInnerError::into_error)?.sync_data()?;
+        }
+        store.purge(*last)?;
+        Ok(purged.len())
+    }
+}
+
+// How often a session writes its next seqnums to the store. A checkpoint is written once
+// messages seqnum changes have piled up since the last one, or once interval has passed with
+// any pending, whichever comes first. The default batches the writes, what a crash loses of
+// them is made up for by recover_seq_nums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqNumCheckpoint {
+    pub messages: u64,
+    pub interval: Option<Duration>,
+}
+
+pub const DEFAULT_SEQNUM_CHECKPOINT_MESSAGES: u64 = 100;
+pub const DEFAULT_SEQNUM_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+impl Default for SeqNumCheckpoint {
+    fn default() -> Self {
+        Self {
+            messages: DEFAULT_SEQNUM_CHECKPOINT_MESSAGES,
+            interval: Some(DEFAULT_SEQNUM_CHECKPOINT_INTERVAL),
+        }
+    }
+}
+
+impl SeqNumCheckpoint {
+    pub fn from_settings(settings: &Properties, session_id: &SessionId) -> Self {
+        Self {
+            messages: settings
+                .get_optional_config(session_id, SEQNUM_CHECKPOINT_MESSAGES_SETTING)
+                .unwrap_or(DEFAULT_SEQNUM_CHECKPOINT_MESSAGES)
+                .max(1),
+            interval: settings
+                .get_optional_config(session_id, SEQNUM_CHECKPOINT_INTERVAL_MS_SETTING)
+                .map(Duration::from_millis)
+                .or(Some(DEFAULT_SEQNUM_CHECKPOINT_INTERVAL)),
+        }
+    }
+
+    pub fn is_due(&self, pending: u64, since_last: Duration) -> bool {
+        pending >= self.messages
+            || pending > 0 && self.interval.is_some_and(|interval| since_last >= interval)
+    }
+}
+
+// Seqnums a session starts with, those of the last checkpoint moved past every message stored
+// or received after it. Every sent message is stored before the next one goes out, so the
+// sender seqnum never falls behind what the counterparty has seen. The target one can, by the
+// admin messages received since the checkpoint, the counterparty's next message then shows up
+// as a gap and the session sends a ResendRequest for it.
+pub fn recover_seq_nums(store: &dyn MessageStore) -> (u64, u64) {
+    let (sender, target) = store.seq_nums().unwrap_or((1, 1));
+    let sender = store.last_stored().map_or(sender, |last| sender.max(last + 1));
+    let target = store.last_received().map_or(target, |last| target.max(last + 1));
+    (sender, target)
+}
+
+pub(crate) fn sending_time(raw: &[u8]) -> Option<DateTime<Utc>> {
+    let (_, rest) = std::str::from_utf8(raw).ok()?.split_once("\x0152=")?;
+    let value = rest.split('\x01').next()?;
+    value.parse::<UtcTimestamp>().ok().map(|timestamp| timestamp.value())
+}
+
+#[cfg(test)]
+mod store_tests {
+    use super::*;
+    use crate::session::SessionIdBuilder;
+
+    fn stored(raw: &str) -> StoredMessage {
+        raw.as_bytes().into()
+    }
+
+    fn check_store(store: &mut dyn MessageStore) {
+        for seq_num in [1, 2, 4] {
+            store.set(seq_num, stored(&format!("msg{}", seq_num))).unwrap();
+        }
+        let range = store.get_range(2, 4).unwrap();
+        assert_eq!(range, vec![(2, stored("msg2")), (4, stored("msg4"))]);
+        assert!(store.get_range(4, 2).unwrap().is_empty());
+        let mut cursor = store.iter_range(1, u64::MAX);
+        assert_eq!(cursor.next().unwrap().unwrap(), (1, stored("msg1")));
+        assert_eq!(cursor.map(|stored| stored.unwrap().0).collect::<Vec<_>>(), vec![2, 4]);
+
+        for raw in ["queued1", "queued2", "queued3"] {
+            store.enqueue(raw).unwrap();
+        }
+        store.dequeue(1).unwrap();
+        assert_eq!(store.queued().unwrap(), vec!["queued2", "queued3"]);
+
+        store.set_received(7).unwrap();
+        assert!(store.is_received(7));
+        assert!(!store.is_received(6));
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new();
+        check_store(&mut store);
+        // every read hands out the stored bytes, not a copy of them
+        let (_, first) = store.iter_range(1, 1).next().unwrap().unwrap();
+        let (_, second) = store.iter_range(1, 1).next().unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        store.reset().unwrap();
+        assert!(store.get_range(1, u64::MAX).unwrap().is_empty());
+        assert!(store.queued().unwrap().is_empty());
+        assert!(!store.is_received(7));
+    }
+
+    #[test]
+    fn test_file_store_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut store = FileStore::open(dir.path(), &session_id).unwrap();
+        check_store(&mut store);
+        // replaced message is read back from its latest line
+        store.set(2, stored("msg2 again")).unwrap();
+        drop(store);
+
+        let mut store = FileStore::open(dir.path(), &session_id).unwrap();
+        let range = store.get_range(1, 2).unwrap();
+        assert_eq!(range, vec![(1, stored("msg1")), (2, stored("msg2 again"))]);
+        assert_eq!(store.queued().unwrap(), vec!["queued2", "queued3"]);
+        assert!(store.is_received(7));
+        store.reset().unwrap();
+        drop(store);
+
+        let store = FileStore::open(dir.path(), &session_id).unwrap();
+        assert!(store.get_range(1, u64::MAX).unwrap().is_empty());
+        assert!(store.queued().unwrap().is_empty());
+        assert!(!store.is_received(7));
+    }
+
+    #[test]
+    fn test_qualified_file_stores() {
+        // sessions differing only by qualifier keep their own files
+        let dir = tempfile::tempdir().unwrap();
+        let plain = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let orders = "FIX.4.3:SENDER->TARGET:orders".parse::<SessionId>().unwrap();
+        assert_ne!(file_name(&plain), file_name(&orders));
+        let mut store = FileStore::open(dir.path(), &plain).unwrap();
+        store.set(1, stored("plain")).unwrap();
+        store.set_seq_nums(2, 3).unwrap();
+        let mut store = FileStore::open(dir.path(), &orders).unwrap();
+        store.set(1, stored("orders")).unwrap();
+        drop(store);
+
+        let store = FileStore::open(dir.path(), &plain).unwrap();
+        assert_eq!(store.get_range(1, 1).unwrap(), vec![(1, stored("plain"))]);
+        let store = FileStore::open(dir.path(), &orders).unwrap();
+        assert_eq!(store.get_range(1, 1).unwrap(), vec![(1, stored("orders"))]);
+        assert_eq!(store.seq_nums(), None);
+    }
+
+    #[test]
+    fn test_received_trimmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut file_store = FileStore::open(dir.path(), &session_id).unwrap();
+        let mut memory_store = MemoryStore::new();
+        let last = 2 * RECEIVED_WINDOW + 1;
+        for seq_num in 1..=last {
+            file_store.set_received(seq_num).unwrap();
+            memory_store.set_received(seq_num).unwrap();
+        }
+        drop(file_store);
+        let file_store = FileStore::open(dir.path(), &session_id).unwrap();
+        for store in [&file_store as &dyn MessageStore, &memory_store] {
+            assert!(!store.is_received(last - RECEIVED_WINDOW - 1));
+            assert!(store.is_received(last - RECEIVED_WINDOW));
+            assert_eq!(store.last_received(), Some(last));
+        }
+    }
+
+    #[test]
+    fn test_seq_num_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut store = FileStore::open(dir.path(), &session_id).unwrap();
+        assert_eq!(recover_seq_nums(&store), (1, 1));
+        store.set_seq_nums(3, 5).unwrap();
+        // sent and received after the checkpoint
+        (1..=4).for_each(|seq_num| store.set(seq_num, stored("msg")).unwrap());
+        store.set_received(2).unwrap();
+        drop(store);
+
+        let mut store = FileStore::open(dir.path(), &session_id).unwrap();
+        assert_eq!(store.seq_nums(), Some((3, 5)));
+        assert_eq!(recover_seq_nums(&store), (5, 5));
+        store.set_received(7).unwrap();
+        assert_eq!(recover_seq_nums(&store), (5, 8));
+        store.reset().unwrap();
+        drop(store);
+        let store = FileStore::open(dir.path(), &session_id).unwrap();
+        assert_eq!(recover_seq_nums(&store), (1, 1));
+
+        let checkpoint = SeqNumCheckpoint {
+            messages: 100,
+            interval: Some(Duration::from_millis(500)),
+        };
+        assert!(!checkpoint.is_due(99, Duration::from_millis(10)));
+        assert!(checkpoint.is_due(100, Duration::ZERO));
+        assert!(checkpoint.is_due(1, Duration::from_millis(500)));
+        assert!(!checkpoint.is_due(0, Duration::from_secs(5)));
+        assert!(!SeqNumCheckpoint::default().is_due(1, Duration::ZERO));
+        assert!(SeqNumCheckpoint::default().is_due(1, DEFAULT_SEQNUM_CHECKPOINT_INTERVAL));
+    }
+
+    #[test]
+    fn test_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let at = |minute: u64| format!("20240305-10:{:02}:00.000", minute);
+        let text = |seq_num: u64| format!("8=FIX.4.3\x0134={}\x0152={}\x01", seq_num, at(seq_num));
+        let raw = |seq_num: u64| stored(&text(seq_num));
+        let now = at(10).parse::<UtcTimestamp>().unwrap().value();
+
+        let mut store = MemoryStore::new();
+        (1..=5).for_each(|seq_num| store.set(seq_num, raw(seq_num)).unwrap());
+        let policy = RetentionPolicy {
+            max_messages: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(policy.purge(&mut store, &session_id, now).unwrap(), 2);
+        assert_eq!(store.stored_count(), 3);
+        assert_eq!(store.get_range(1, 3).unwrap(), vec![(3, raw(3))]);
+
+        // messages older than 7 minutes go, with the ones before them
+        let mut store = FileStore::open(dir.path(), &session_id).unwrap();
+        (1..=5).for_each(|seq_num| store.set(seq_num, raw(seq_num)).unwrap());
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(7 * 60)),
+            archive_path: Some(dir.path().join("archive")),
+            ..Default::default()
+        };
+        assert_eq!(policy.purge(&mut store, &session_id, now).unwrap(), 2);
+        assert_eq!(policy.purge(&mut store, &session_id, now).unwrap(), 0);
+        store.set(6, raw(6)).unwrap();
+        drop(store);
+
+        let store = FileStore::open(dir.path(), &session_id).unwrap();
+        let kept: Vec<u64> = store.iter_range(1, u64::MAX).map(|s| s.unwrap().0).collect();
+        assert_eq!(kept, vec![3, 4, 5, 6]);
+        let archive = format!("{}.1-2.archive", file_name(&session_id));
+        let archive = dir.path().join("archive").join(archive);
+        let archived = fs::read_to_string(&archive).unwrap();
+        assert_eq!(archived, format!("1\t{}\n2\t{}\n", text(1), text(2)));
+    }
+}
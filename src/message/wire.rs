@@ -0,0 +1,149 @@
+// Messages built straight into their wire format, for latency sensitive paths like order
+// entry. Values are kept in one buffer, at the slot the generated layout of the message type
+// has for their tag, and encode writes them in that order without going through a FieldMap.
+// Tags outside the layout, like those of repeating groups, go after the layout ones in the
+// order they are set, header tags like OnBehalfOfCompID(115) go in the header.
+//
+//     let mut order = NewOrderSingle::builder();
+//     order.set(11, "ORDER1").set(21, '1').set(55, "IBM").set(54, '1').set(40, '1');
+//     session.send_wire(&order)?;
+use crate::data_dictionary::{DataDictionary, HEADER_ID};
+use crate::message::{Tag, SOH};
+use smallvec::SmallVec;
+use std::fmt::{Display, Write};
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub struct WireBuilder {
+    msg_type: &'static str,
+    layout: &'static [Tag],
+    // where in values the value of each layout tag is, if it is set
+    slots: Vec<Option<Range<usize>>>,
+    extra: Vec<(Tag, Range<usize>)>,
+    values: String,
+}
+
+impl WireBuilder {
+    pub fn new(msg_type: &'static str, layout: &'static [Tag]) -> Self {
+        Self {
+            msg_type,
+            layout,
+            slots: vec![None; layout.len()],
+            extra: Vec::new(),
+            values: String::with_capacity(128),
+        }
+    }
+
+    pub fn msg_type(&self) -> &'static str {
+        self.msg_type
+    }
+
+    // a layout tag set again takes the new value, any other tag is added once more
+    pub fn set(&mut self, tag: Tag, value: impl Display) -> &mut Self {
+        let start = self.values.len();
+        write!(self.values, "{}", value).expect("writing to a String");
+        let range = start..self.values.len();
+        match self.layout.iter().position(|slot_tag| *slot_tag == tag) {
+            Some(slot) => self.slots[slot] = Some(range),
+            None => self.extra.push((tag, range)),
+        }
+        self
+    }
+
+    // value of the tag, the first one set for a tag outside the layout
+    pub fn get(&self, tag: Tag) -> Option<&str> {
+        let range = match self.layout.iter().position(|slot_tag| *slot_tag == tag) {
+            Some(slot) => self.slots[slot].clone(),
+            None => self.extra.iter().find(|(t, _)| *t == tag).map(|(_, range)| range.clone()),
+        };
+        range.map(|range| &self.values[range])
+    }
+
+    // appends the message to out: BeginString, BodyLength, MsgType, the header fields, the body
+    // and CheckSum. header fields set on the builder go with the given ones in the dictionary's
+    // header order, a given one takes the place of a builder one with the same tag
+    pub fn encode(
+        &self, begin_string: &str, header: &[(Tag, &str)], dd: &DataDictionary, out: &mut String,
+    ) {
+        let extra = || self.extra.iter().map(|(tag, range)| (*tag, &self.values[range.clone()]));
+        let mut header_fields: SmallVec<[(Tag, &str); 16]> = header.iter().copied().collect();
+        header_fields.extend(extra().filter(|(tag, _)| {
+            dd.is_header_field(*tag) && header.iter().all(|(given, _)| given != tag)
+        }));
+        header_fields
+            .sort_by_key(|(tag, _)| dd.field_position(HEADER_ID, *tag).unwrap_or(usize::MAX));
+        let layout = self.layout.iter().zip(&self.slots).filter_map(|(tag, slot)| {
+            slot.as_ref().map(|range| (*tag, &self.values[range.clone()]))
+        });
+        let fields = || {
+            std::iter::once((35, self.msg_type))
+                .chain(header_fields.iter().copied())
+                .chain(layout.clone())
+                .chain(extra().filter(|(tag, _)| !dd.is_header_field(*tag)))
+        };
+        // BodyLength is known before anything is written, the fields go out in one pass
+        let body_len: usize = fields().map(|(tag, value)| field_len(tag, value)).sum();
+        let start = out.len();
+        out.reserve(body_len + begin_string.len() + 32);
+        write_field(out, 8, begin_string);
+        write_field(out, 9, itoa::Buffer::new().format(body_len));
+        for (tag, value) in fields() {
+            write_field(out, tag, value);
+        }
+        let checksum = out[start..].bytes().fold(0u32, |sum, b| sum + b as u32) % 256;
+        write!(out, "10={:0>3}{}", checksum, SOH).expect("writing to a String");
+    }
+}
+
+fn field_len(tag: Tag, value: &str) -> usize {
+    itoa::Buffer::new().format(tag).len() + value.len() + 2
+}
+
+fn write_field(out: &mut String, tag: Tag, value: &str) {
+    out.push_str(itoa::Buffer::new().format(tag));
+    out.push('=');
+    out.push_str(value);
+    out.push(SOH);
+}
+
+#[cfg(test)]
+mod wire_tests {
+    use crate::data_dictionary::DataDictionary;
+    use crate::message::Message;
+    use crate::messages::NewOrderSingle;
+
+    #[test]
+    fn test_encode() {
+        let mut order = NewOrderSingle::builder();
+        // set in any order, written in the dictionary's
+        order.set(55, "IBM").set(40, '2').set(44, 15.75).set(11, "ORDER1").set(21, '1');
+        order.set(54, '1').set(60, "20221006-08:43:37.100").set(38, 100).set(55, "MSFT");
+        order.set(5000, "custom");
+        assert_eq!(order.get(55), Some("MSFT"));
+        assert_eq!(order.get(5000), Some("custom"));
+        assert_eq!(order.get(58), None);
+
+        // a header tag set on the builder goes in the header, a given one wins over it
+        order.set(115, "CLIENT").set(56, "OTHER");
+
+        let dd = DataDictionary::from_xml("resources/FIX43.xml");
+        let header = [(49, "BANZAI"), (56, "FIXIMULATOR"), (34, "2")];
+        let mut out = String::from("before");
+        order.encode("FIX.4.3", &header, &dd, &mut out);
+        let raw = out.strip_prefix("before").unwrap();
+        let position = |tag: &str| raw.find(&format!("\u{1}{}=", tag)).unwrap();
+        assert!(position("11") < position("21") && position("21") < position("55"));
+        assert!(position("40") < position("44") && position("44") < position("5000"));
+        assert!(raw.contains("\u{1}56=FIXIMULATOR\u{1}115=CLIENT\u{1}34=2\u{1}"));
+        assert!(!raw.contains("OTHER"));
+
+        // what the parser makes of it encodes the same, BodyLength and CheckSum included
+        let parsed = Message::from_str(raw, &dd).unwrap();
+        assert_eq!(parsed.get_field::<String>(55).unwrap(), "MSFT");
+        let mut expected = parsed.clone();
+        expected.set_body_len();
+        expected.set_checksum();
+        assert_eq!(parsed.header().get_field::<u32>(9), expected.header().get_field::<u32>(9));
+        assert_eq!(parsed.trailer().get_field::<u32>(10), expected.trailer().get_field::<u32>(10));
+    }
+}
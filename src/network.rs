@@ -3,66 +3,104 @@
 
 use dashmap::iter::Iter;
 use dashmap::{mapref::entry::Entry, DashMap};
-use getset::{Getters, Setters};
+use getset::{CopyGetters, Getters, Setters};
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::io::{Error, Read, Write};
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs};
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::str::{self, FromStr};
 use std::sync::atomic::AtomicBool;
 use std::sync::{mpsc::Receiver, mpsc::Sender, Arc, Mutex};
-use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, DuplexStream};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::runtime::Handle;
 use tokio::sync::mpsc::{channel as tio_channel, Receiver as TioReceiver, Sender as TioSender};
 use tokio::{
     self, io::AsyncBufReadExt, io::BufReader, net::TcpListener, net::TcpStream, task::JoinHandle,
 };
 
-use crate::application::Application;
+use crate::application::{Application, SessionContext, ThirdPartyRoute};
+use crate::clock::{Clock, SharedClock};
+use crate::dispatch::{app_lanes, default_pool_size, AppLane, ValidationPool};
+use crate::events::{EngineEvent, EventBus};
+use crate::fields::{
+    BeginSeqNo, BeginString, EndSeqNo, MsgSeqNum, Password, SenderCompID, TargetCompID, Text,
+    Username,
+};
 use crate::io::acceptor::IoAcceptor;
 use crate::io::*;
 use crate::{data_dictionary::*, io, session};
 // use crate::message::store::*;
 
 use crate::message::*;
-use crate::session::*;
+use crate::orders::OrderTracker;
+use crate::quickfix_errors::{FollowError, SendError, SessionRejectError};
+use crate::replication::{self, ReplicationFeed};
+use crate::session::session_schedule::SessionSchedule;
 use crate::session::*;
 
+// used when socket_accept_host is not configured
 pub(crate) const SOCKET_ACCEPT_HOST_IP: &str = "127.0.0.1";
+// on_logout reason when the session is found without a connection to write to
+const DISCONNECTED_REASON: &str = "disconnected";
+const SCHEDULE_ENDED_REASON: &str = "session schedule ended";
 // pub trait Connecter {
 //     fn start(&self) -> Vec<thread::JoinHandle<()>>;
 //     fn stop();
 // }
 type SessionRef<'a> = dashmap::mapref::one::Ref<'a, SessionId, Session>;
+type SessionRefMut<'a> = dashmap::mapref::one::RefMut<'a, SessionId, Session>;
 
 #[derive(Debug, Default, PartialEq)]
 pub enum ConnectionType {
     #[default]
-    ACCEPTOR,
-    INITIATOR,
+    Acceptor,
+    Initiator,
+}
+
+// the variants were renamed, the old names still work for a while
+impl ConnectionType {
+    #[deprecated(note = "use ConnectionType::Acceptor")]
+    pub const ACCEPTOR: Self = Self::Acceptor;
+    #[deprecated(note = "use ConnectionType::Initiator")]
+    pub const INITIATOR: Self = Self::Initiator;
 }
 
 impl FromStr for ConnectionType {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.eq_ignore_ascii_case(ACCEPTOR_CONN_TYPE) {
-            Ok(ConnectionType::ACCEPTOR)
+            Ok(ConnectionType::Acceptor)
         } else if s.eq_ignore_ascii_case(INITIATOR_CONN_TYPE) {
-            Ok(ConnectionType::INITIATOR)
+            Ok(ConnectionType::Initiator)
         } else {
             Err("invalid connection type")
         }
     }
 }
 
-#[derive(Debug, Getters, Clone)]
+#[derive(Debug, Getters, CopyGetters, Clone)]
 struct SocketDescriptor {
     #[getset(get)]
     addr: SocketAddr,
 
     #[getset(get)]
     accepted_connections: HashMap<SessionId, Session>,
+
+    // a connection to the socket can be for any of its sessions, so its outbound queue is sized
+    // for the largest
+    #[getset(get_copy)]
+    send_queue_size: usize,
+
+    #[getset(get_copy)]
+    socket_options: SocketOptions,
+
+    // raw traffic of the socket's connections is captured here when any session asks for it
+    #[getset(get)]
+    capture_dir: Option<PathBuf>,
 }
 
 impl SocketDescriptor {
@@ -70,10 +108,21 @@ impl SocketDescriptor {
         Self {
             addr: sock,
             accepted_connections: HashMap::new(),
+            send_queue_size: 0,
+            socket_options: SocketOptions::default(),
+            capture_dir: None,
         }
     }
 
-    fn accept_session(&mut self, session_id: SessionId, session: Session) {
+    fn accept_session(&mut self, session_id: SessionId, session: Session, settings: &Properties) {
+        let capture_dir =
+            settings.get_optional_config::<PathBuf>(&session_id, CAPTURE_PATH_SETTING);
+        self.capture_dir = self.capture_dir.take().or(capture_dir);
+        self.send_queue_size = self.send_queue_size.max(session.send_queue_size());
+        self.socket_options = match self.accepted_connections.is_empty() {
+            true => session.socket_options(),
+            false => self.socket_options.merge(session.socket_options()),
+        };
         self.accepted_connections.insert(session_id, session);
     }
 
@@ -104,6 +153,16 @@ impl PartialEq<SocketAddr> for SocketDescriptor {
 
 impl Eq for SocketDescriptor {}
 
+// messages waiting to be handled by a session's task before its connection's reader is held up
+const SESSION_INBOX_SIZE: usize = 64;
+// parsed messages a session keeps for reuse
+const MESSAGE_POOL_SIZE: usize = SESSION_INBOX_SIZE;
+// how often a session task looks whether its schedule has ended
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// how often a session with a retention policy purges its message store
+const STORE_PURGE_INTERVAL: Duration = Duration::from_secs(60);
+const QUEUE_EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, Default)]
 pub struct SessionMap {
     id_to_session: Arc<DashMap<SessionId, Session>>,
@@ -114,20 +173,26 @@ impl SessionMap {
         self.id_to_session.insert(session_id, session);
     }
 
-    pub fn get_session(&self, session_id: &SessionId) -> Option<SessionRef> {
+    pub fn get_session(&self, session_id: &SessionId) -> Option<SessionRef<'_>> {
         self.id_to_session.get(session_id)
     }
 
-    pub fn from_iter<I: IntoIterator<Item = (SessionId, Session)>>(it: I) -> Self {
-        Self {
-            id_to_session: Arc::new(DashMap::from_iter(it)),
-        }
+    pub fn get_session_mut(&self, session_id: &SessionId) -> Option<SessionRefMut<'_>> {
+        self.id_to_session.get_mut(session_id)
     }
 
-    pub fn entry(&self, session_id: &SessionId) -> Entry<SessionId, Session> {
+    pub fn entry(&self, session_id: &SessionId) -> Entry<'_, SessionId, Session> {
         self.id_to_session.entry(session_id.clone())
     }
 
+    pub fn session_ids(&self) -> Vec<SessionId> {
+        self.id_to_session.iter().map(|sref| sref.key().clone()).collect()
+    }
+
+    pub fn statuses(&self) -> Vec<SessionStatus> {
+        self.id_to_session.iter().map(|sref| sref.value().status()).collect()
+    }
+
     pub fn key_values_map(&self) -> HashMap<SessionId, Session> {
         self.id_to_session
             .iter()
@@ -136,150 +201,835 @@ impl SessionMap {
     }
 }
 
+impl FromIterator<(SessionId, Session)> for SessionMap {
+    fn from_iter<I: IntoIterator<Item = (SessionId, Session)>>(it: I) -> Self {
+        Self {
+            id_to_session: Arc::new(DashMap::from_iter(it)),
+        }
+    }
+}
+
 #[derive(Debug, Getters, Setters)]
 #[getset(get)]
 pub struct SocketAcceptor<A: Application + Send + Sync> {
     settings: Properties,
     connection_type: ConnectionType,
+    // for applications sending on the sessions outside of their callbacks
+    #[getset(get = "pub")]
     session_map: SessionMap,
-    // sock_descriptors: Arc<Mutex<HashMap<SocketAddr, bool>>>,
     // #[getset(set)]
     // receiver: Option<TioReceiver<String>>, // receive raw string msg from socket handling task
     #[getset(set)]
     app: Arc<A>,
+    // checked for every inbound Logon before the application's credentials check
+    authenticator: Option<SharedAuthenticator>,
+    // given every frame of every connection, as read and as written
+    tap: Option<SharedTap>,
+    // addresses the acceptor is actually listening on, once started
+    #[getset(get = "pub")]
+    local_addrs: Vec<SocketAddr>,
+    // by configured accept address, kept for serving in-process connections
+    io_acceptors: HashMap<SocketAddr, IoAcceptor>,
+    // lifecycle events of the acceptor's sessions and connections
+    #[getset(get = "pub")]
+    events: EventBus,
+    // live connections on any of the acceptor's addresses
+    #[getset(get = "pub")]
+    connections: ConnectionManager,
+    // runtime the engine's tasks are spawned on, the one it is started from when None
+    runtime: Option<Handle>,
+    // address standbys connect to for the changes of the session stores, see replication
+    replication: Option<(SocketAddr, ReplicationFeed)>,
+    // address actually listened on for standbys, once started
+    #[getset(get = "pub")]
+    replication_addr: Option<SocketAddr>,
+    // of the sessions and the connections they are served over
+    clock: SharedClock,
 }
 
 impl<A: Application + Send + Sync + 'static> SocketAcceptor<A> {
+    // panics when a session cannot be created, try_new returns the error instead
     pub fn new(settings: Properties, app: A) -> Self {
-        let session_map = create_sessions(&settings);
-        // let socket_desc = create_socket_descriptors(&settings);
+        Self::try_new(settings, app).unwrap_or_else(|e| panic!("could not create sessions: {}", e))
+    }
+
+    pub fn try_new(settings: Properties, app: A) -> std::io::Result<Self> {
+        let session_map = create_sessions(&settings)?;
         let connection_type: ConnectionType =
             settings.get_default_config(CONNECTION_TYPE_SETTING).unwrap();
-        Self {
+        Ok(Self {
             settings,
             connection_type,
             session_map: SessionMap::from_iter(session_map),
-            // sock_descriptors: Arc::new(Mutex::new(socket_desc)),
             // receiver: None,
             app: Arc::new(app),
+            authenticator: None,
+            tap: None,
+            local_addrs: Vec::new(),
+            io_acceptors: HashMap::new(),
+            events: EventBus::default(),
+            connections: ConnectionManager::default(),
+            runtime: None,
+            replication: None,
+            replication_addr: None,
+            clock: SharedClock::default(),
+        })
+    }
+
+    // replaces the system clock, e.g. with a ManualClock for tests that move time forward
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        let clock = SharedClock::new(clock);
+        for mut session in self.session_map.id_to_session.iter_mut() {
+            session.set_clock(clock.clone());
         }
+        self.clock = clock;
+        self
     }
 
-    fn set_session_responder(
-        &mut self, session_id: &SessionId, msg_sender: TioBroadcastSender<String>,
-    ) {
-        self.session_map().entry(session_id).and_modify(|session| {
-            session.set_responder(Some(msg_sender));
-        });
+    // every session feeds its order messages to the tracker, which the application can then ask
+    // where its orders stand
+    pub fn with_order_tracker(self, tracker: Arc<OrderTracker>) -> Self {
+        for mut session in self.session_map.id_to_session.iter_mut() {
+            session.set_order_tracker(Some(Arc::clone(&tracker)));
+        }
+        self
+    }
+
+    // undeliverable outbound messages of every session go to the sink, in place of the one
+    // dead_letter_path sets up
+    pub fn with_dead_letter_sink(self, sink: impl DeadLetterSink + 'static) -> Self {
+        let sink = SharedDeadLetterSink::new(sink);
+        for mut session in self.session_map.id_to_session.iter_mut() {
+            session.set_dead_letter_sink(sink.clone());
+        }
+        self
+    }
+
+    // primary of warm standbys: they connect to addr for the changes of every session's store
+    pub fn with_replication(mut self, addr: SocketAddr) -> Self {
+        let feed = ReplicationFeed::default();
+        for mut session in self.session_map.id_to_session.iter_mut() {
+            let store = feed.replicate(session.key(), session.message_store().clone());
+            session.set_message_store(store);
+        }
+        self.replication = Some((addr, feed));
+        self
+    }
+
+    // standby of the primary replicating on addr, until the primary is gone. the acceptor
+    // should be started only after take_over, and only if the error says it can
+    pub async fn follow_primary(&self, primary: SocketAddr) -> FollowError {
+        replication::follow_primary(primary, self.session_map()).await
+    }
+
+    // sessions go on with the seqnums of the primary they followed
+    pub fn take_over(&self) {
+        replication::take_over(self.session_map());
+    }
+
+    // runs the engine on the given runtime, e.g. a dedicated worker pool, instead of the one
+    // start_accepting_connections is called from. it can then be started from outside any
+    // runtime, and several engines in one process each get their own
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    // snapshot of every session, ordered by session id
+    pub fn status(&self) -> Vec<SessionStatus> {
+        let mut status = self.session_map().statuses();
+        status.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        status
+    }
+
+    // subscribe before starting the acceptor to see every session being created
+    pub fn subscribe(&self) -> TioBroadcastReceiver<EngineEvent> {
+        self.events.subscribe()
     }
 
-    pub fn start_accepting_connections(&mut self) {
+    // manual intervention on one session, the others keep running. the application is told
+    // about a session that ends this way like about any other logout
+    pub fn disconnect(&self, session_id: &SessionId, reason: &str) -> Result<(), SendError> {
+        Session::disconnect(session_id, self.session_map(), reason)?;
+        self.app.on_logout(session_id, Some(reason));
+        Ok(())
+    }
+
+    pub fn reset(&self, session_id: &SessionId) -> Result<(), SendError> {
+        let was_active = self.session_map().get_session(session_id).is_some_and(|s| s.is_active());
+        let result = Session::reset(session_id, self.session_map());
+        if was_active {
+            self.app.on_logout(session_id, Some(SESSION_RESET_REASON));
+        }
+        result
+    }
+
+    pub fn with_authenticator(mut self, authenticator: impl Authenticator + 'static) -> Self {
+        self.authenticator = Some(SharedAuthenticator::new(authenticator));
+        self
+    }
+
+    pub fn with_tap(mut self, tap: impl MessageTap + 'static) -> Self {
+        self.tap = Some(SharedTap::new(tap));
+        self
+    }
+
+    pub fn start_accepting_connections(&mut self) -> std::io::Result<()> {
+        let runtime = self.runtime.clone().unwrap_or_else(Handle::current);
         let s_ids = self.session_map().key_values_map();
         let mut socket_to_descriptor: HashMap<SocketAddr, SocketDescriptor> = HashMap::new();
-        let mut io_acceptors: HashMap<SocketDescriptor, IoAcceptor> = HashMap::new();
         for (session_id, session) in s_ids.iter() {
-            let socket_port = self
-                .settings()
-                .get_optional_config::<u16>(session_id, SOCKET_ACCEPT_PORT_SETTING)
-                .unwrap();
-            let socket_addr_str = format!("{}:{}", SOCKET_ACCEPT_HOST_IP, socket_port);
-            let socket_addr = socket_addr_str.parse::<SocketAddr>().unwrap();
+            let socket_addr = accept_addr(self.settings(), session_id)?;
             socket_to_descriptor
                 .entry(socket_addr)
                 .or_insert_with(|| SocketDescriptor::new(socket_addr))
-                .accept_session(session_id.clone(), session.clone());
+                .accept_session(session_id.clone(), session.clone(), self.settings());
         }
 
-        let (socket_to_app_tx, socket_to_app_rx) = tio_channel::<String>(64);
-        start_receiver_task(socket_to_app_rx, Arc::clone(self.app()), self.session_map().clone());
+        let pool_size = self
+            .settings
+            .get_default_config(APP_POOL_SIZE_SETTING)
+            .unwrap_or_else(|_| default_pool_size());
+        let lanes =
+            app_lanes(s_ids.iter().map(|(sid, sess)| (sid, sess.app_dispatch())), pool_size)?;
+        // started only when some session validates on it
+        let validation = s_ids.values().any(|sess| sess.validation_pool()).then(|| {
+            let size = self
+                .settings
+                .get_default_config(VALIDATION_POOL_SIZE_SETTING)
+                .unwrap_or_else(|_| default_pool_size());
+            ValidationPool::new(size)
+        });
+        let validation = validation.transpose()?;
+        let session_tasks = lanes
+            .into_iter()
+            .map(|(session_id, lane)| {
+                self.events.publish(EngineEvent::SessionCreated(session_id.clone()));
+                let session_task = start_session_task(
+                    &runtime,
+                    session_id.clone(),
+                    Arc::clone(self.app()),
+                    self.authenticator.clone(),
+                    self.session_map().clone(),
+                    self.events.clone(),
+                    lane,
+                    validation.clone().filter(|_| s_ids[&session_id].validation_pool()),
+                );
+                (session_id, session_task)
+            })
+            .collect();
+        let (socket_to_app_tx, socket_to_app_rx) = tio_channel::<InboundMessage>(64);
+        start_dispatcher_task(&runtime, socket_to_app_rx, session_tasks);
         for (s_addr, s_desc) in socket_to_descriptor.iter() {
-            let (io_acceptor, app_to_socket_tx) =
-                IoAcceptor::create(*s_addr, socket_to_app_tx.clone());
-            // update app_to_socket_tx in all the session accepted by this socket_descriptor
-            for (accepted_sid, _) in s_desc.accepted_connections() {
-                self.set_session_responder(accepted_sid, app_to_socket_tx.clone());
+            let mut io_acceptor = IoAcceptor::create(
+                *s_addr,
+                socket_to_app_tx.clone(),
+                s_desc.send_queue_size(),
+                s_desc.socket_options(),
+            );
+            if let Some(dir) = s_desc.capture_dir() {
+                io_acceptor = io_acceptor.with_capture(dir.clone());
+            }
+            if let Some(tap) = &self.tap {
+                io_acceptor = io_acceptor.with_tap(tap.clone());
             }
-            io_acceptor.start();
-            io_acceptors.insert(s_desc.clone(), io_acceptor);
+            // what a connection closes on before writing goes to the sink of its session
+            let sessions = self.session_map().clone();
+            io_acceptor = io_acceptor.with_dead_letters(SharedDeadLetterSink::new(
+                move |letter: &DeadLetter| {
+                    if let Some(sess) = sessions.get_session(&letter.session_id) {
+                        sess.dead_letter_sink().dead_letter(letter);
+                    }
+                },
+            ));
+            io_acceptor = io_acceptor
+                .with_clock(self.clock.clone())
+                .with_events(self.events.clone())
+                .with_connections(self.connections.clone())
+                .with_runtime(runtime.clone());
+            let local_addr = io_acceptor.start()?;
+            self.local_addrs.push(local_addr);
+            self.io_acceptors.insert(*s_addr, io_acceptor);
+        }
+        if let Some((addr, feed)) = &self.replication {
+            let sessions = self.session_map().clone();
+            self.replication_addr = Some(feed.start(&runtime, *addr, sessions)?);
         }
+        Ok(())
     }
-}
 
-//     pub fn initialize(&mut self) {
-//         let session_socket = create_socket_session(self.settings());
-//         let (raw_tx, raw_rx) = tio_channel::<String>(64);
-//         for (sock_addr, id_set) in session_socket {
-//             let (msg_tx, msg_rx) = tio_channel::<String>(16);
-//             for sid in id_set {
-//                 self.set_session_responder(&sid, msg_tx.clone())
-//             }
-//             let tx = raw_tx.clone();
-//             let socket_descriptor = Arc::clone(self.sock_descriptors());
-//             start_acceptor_task(sock_addr, socket_descriptor, tx, msg_rx);
-//         }
+    pub fn serve<T: Transport>(&self, session_id: &SessionId, transport: T) -> std::io::Result<()> {
+        // connection over a transport other than the acceptor's socket (e.g. a unix domain
+        // socket or TLS), handled like the ones accepted for the session's port
+        self.io_acceptor(session_id)?.serve(transport);
+        Ok(())
+    }
 
-//         start_receiver_task(raw_rx, Arc::clone(self.app()), Arc::clone(self.session_map()));
-//     }
-// }
+    pub fn connect_in_process(&self, session_id: &SessionId) -> std::io::Result<DuplexStream> {
+        // counterparty end of an in-memory connection to the session's acceptor, for tests and
+        // for applications running next to the engine
+        Ok(self.io_acceptor(session_id)?.connect_in_process())
+    }
 
-// fn start_acceptor_task(
-//     sock_addr: SocketAddr, socket_descriptor: Arc<Mutex<HashMap<SocketAddr, bool>>>,
-//     tx: TioSender<String>, msg_rx: TioReceiver<String>,
-// ) {
-//     tokio::spawn(async move {
-//         let listener = TcpListener::bind(sock_addr).await.unwrap();
-//         let local_addr = listener.local_addr().unwrap();
-//         socket_descriptor.lock().unwrap().insert(local_addr, true);
-//         println!("Port binding done");
-//         // let mut msg_rx = Arc::new(msg_rx);
-//         let (stream, _) = listener.accept().await.unwrap();
-//         println!("Accepted connection");
-//         let local_addr = stream.local_addr().unwrap();
-//         // let (owned_read_half, owned_write_half) = stream.into_split();
-//         // let responder = Arc::new(Mutex::new(owned_write_half));
-//         // connections.insert(local_addr, Arc::clone(&responder));
-//         handle_message_io(stream, &tx, msg_rx).await;
-//     });
-// }
+    fn io_acceptor(&self, session_id: &SessionId) -> std::io::Result<&IoAcceptor> {
+        // only there once the acceptor is started
+        let addr = accept_addr(self.settings(), session_id)?;
+        self.io_acceptors.get(&addr).ok_or_else(|| {
+            Error::new(std::io::ErrorKind::NotConnected, format!("not accepting {}", session_id))
+        })
+    }
+}
+
+// Routes inbound messages to the task of the session they are for. Every session has its own
+// task handling its messages in order, so sessions are processed concurrently and the routing
+// table belongs to this task instead of being looked up in the shared map. Messages of a
+// connection reach the session in the order they were read, also when its logon binds the
+// connection to the session while later messages are still on their way through here.
+fn start_dispatcher_task(
+    runtime: &Handle, mut rx: TioReceiver<InboundMessage>,
+    session_tasks: HashMap<SessionId, TioSender<InboundMessage>>,
+) {
+    runtime.spawn(async move {
+        while let Some(inbound) = rx.recv().await {
+            let connection = inbound.connection().clone();
+            dispatch(inbound, &session_tasks).await;
+            connection.routed();
+        }
+    });
+}
 
-fn start_receiver_task<A: Application + Send + Sync + 'static>(
-    mut rx: TioReceiver<String>, app: Arc<A>, sessions: SessionMap,
+async fn dispatch(
+    inbound: InboundMessage, session_tasks: &HashMap<SessionId, TioSender<InboundMessage>>,
 ) {
-    std::thread::spawn(move || {
-        while let Some(s) = rx.blocking_recv() {
-            println!("received: {}", s);
-            let session_id: SessionId = Message::get_reverse_session_id(&s);
+    let session_id: SessionId = match Message::get_reverse_session_id(&inbound.raw) {
+        Ok(sid) => sid,
+        Err(e) => {
+            println!("ignoring garbled message: {}", e);
+            return;
+        }
+    };
+    // a BeginString no session uses goes to the session of the comp ids, to be rejected
+    let session_task = session_tasks.get(&session_id).or_else(|| {
+        session_tasks
+            .iter()
+            .find(|(sid, _)| sid.eq_ignoring_begin_string(&session_id))
+            .map(|(_, session_task)| session_task)
+    });
+    match session_task {
+        Some(session_task) => {
+            if session_task.send(inbound).await.is_err() {
+                println!("session task for {} has stopped", session_id);
+            }
+        }
+        None => println!("ignoring message for unknown session {}", session_id),
+    }
+}
 
-            let dd = sessions
+// The task handles the inbound messages and timers of its session one at a time. It does not
+// own the session though: sends from the application (Session::sync_send_to_target and the
+// like), SocketAcceptor::disconnect/reset and the replication of a standby change it from
+// their own threads. Each of them holds the session's entry in the SessionMap for the whole
+// change, so a change is never seen half done, but it can land between two inbound messages.
+#[allow(clippy::too_many_arguments)]
+fn start_session_task<A: Application + Send + Sync + 'static>(
+    runtime: &Handle, session_id: SessionId, app: Arc<A>,
+    authenticator: Option<SharedAuthenticator>, sessions: SessionMap, events: EventBus,
+    lane: AppLane, validation: Option<ValidationPool>,
+) -> TioSender<InboundMessage> {
+    let (mut dd, schedule) = sessions
+        .get_session(&session_id)
+        .map(|sess| (Arc::clone(sess.data_dictionary()), Arc::clone(sess.schedule())))
+        .expect("session task for unknown session");
+    let (tx, mut rx) = tio_channel::<InboundMessage>(SESSION_INBOX_SIZE);
+    let inbox = tx.clone();
+    let reset_on_schedule =
+        sessions.get_session(&session_id).is_some_and(|sess| sess.reset_on_schedule());
+    let keeps_everything =
+        sessions.get_session(&session_id).is_none_or(|sess| sess.retention().keeps_everything());
+    let checkpoint_interval =
+        sessions.get_session(&session_id).and_then(|sess| sess.seq_num_checkpoint().interval);
+    let send_scheduled = sessions
+        .get_session(&session_id)
+        .map(|sess| sess.send_scheduled())
+        .expect("session task for unknown session");
+    let expires_queued =
+        sessions.get_session(&session_id).is_some_and(|sess| sess.queue_max_age().is_some());
+    let mut disconnects = events.subscribe();
+    runtime.spawn(async move {
+        let mut in_session_time = schedule.is_session_time();
+        let mut window_start = schedule.window_start();
+        let mut schedule_check = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+        let mut checkpoint_check =
+            tokio::time::interval(checkpoint_interval.unwrap_or(SCHEDULE_CHECK_INTERVAL));
+        let mut store_purge = tokio::time::interval(STORE_PURGE_INTERVAL);
+        let mut queue_expiry = tokio::time::interval(QUEUE_EXPIRY_INTERVAL);
+        let pool = MessagePool::new(MESSAGE_POOL_SIZE);
+        loop {
+            // sleeps until the earliest scheduled send, woken again when one is scheduled
+            let send_due = sessions
                 .get_session(&session_id)
-                .map(|sess| Arc::clone(sess.data_dictionary()))
-                .unwrap();
-            if let Ok(message) = Message::from_str(&s, &dd) {
-                println!("msg parsed");
-                if let Ok(_) = Session::verify(&message, &sessions) {
-                    app.from_app(&session_id, &sessions, message);
-                } else {
-                    // Session::send(test_logon(), session_id.clone(), Arc::clone(&sessions));
-                    Session::sync_send_to_target(&session_id, &sessions, test_logon());
+                .and_then(|sess| sess.next_send_time().map(|at| sess.clock().sleep_until(at)));
+            tokio::select! {
+                inbound = rx.recv() => {
+                    let Some(inbound) = inbound else { break };
+                    if let Some(mut sess) = sessions.get_session_mut(&session_id) {
+                        sess.reply_over(&inbound);
+                        // while no one is logged on, what comes in is parsed with the dictionary
+                        // of its BeginString if the session accepts it. the session switches to
+                        // it only once a Logon in it is accepted
+                        if !sess.is_active() {
+                            let switch_dd = raw_begin_string(&inbound.raw)
+                                .and_then(|begin_string| sess.dictionary_for(begin_string));
+                            dd = Arc::clone(switch_dd.unwrap_or(sess.data_dictionary()));
+                        }
+                    }
+                    let options = sessions
+                        .get_session(&session_id)
+                        .map(|sess| sess.parse_options())
+                        .unwrap_or_default();
+                    let parsed = match &validation {
+                        Some(validation) => {
+                            let (raw, dd) = (inbound.raw.clone(), Arc::clone(&dd));
+                            validation.parse(&session_id, raw, dd, pool.clone(), options).await
+                        }
+                        None => pool.parse(&inbound.raw, &dd, options),
+                    };
+                    let logged_on = handle_inbound(
+                        &session_id,
+                        &dd,
+                        &app,
+                        &lane,
+                        parsed,
+                        &authenticator,
+                        &sessions,
+                        &events,
+                        &inbound,
+                    )
+                    .await;
+                    if logged_on {
+                        inbound.bind(&session_id, &inbox);
+                        if let Some(mut sess) = sessions.get_session_mut(&session_id) {
+                            sess.set_connection(Some(&inbound));
+                        }
+                    }
                 }
-            } else {
-                // Session::send(test_logon(), session_id.clone(), Arc::clone(&sessions));
+                event = disconnects.recv() => {
+                    // a lagging receiver can miss a disconnect, the peer then shows until the
+                    // next logon
+                    if let Ok(EngineEvent::Disconnected { session_id: Some(sid), .. }) = event {
+                        if sid == session_id {
+                            if let Some(mut sess) = sessions.get_session_mut(&session_id) {
+                                sess.set_connection(None);
+                            }
+                        }
+                    }
+                }
+                _ = send_scheduled.notified() => {}
+                _ = async {
+                    match send_due {
+                        Some(sleep) => sleep.await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    if let Err(e) = Session::send_due(&session_id, &sessions) {
+                        println!("could not send scheduled message for {}: {}", session_id, e);
+                    }
+                }
+                _ = checkpoint_check.tick(), if checkpoint_interval.is_some() => {
+                    // seqnums left unwritten by a quiet session are written once their time is up
+                    if let Some(mut sess) = sessions.get_session_mut(&session_id) {
+                        sess.checkpoint_seq_nums();
+                    }
+                }
+                _ = store_purge.tick(), if !keeps_everything => {
+                    let (session_id, sessions) = (session_id.clone(), sessions.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = Session::purge_store(&session_id, &sessions).await {
+                            println!("could not purge message store of {}: {}", session_id, e);
+                        }
+                    });
+                }
+                _ = queue_expiry.tick(), if expires_queued => {
+                    let expired = sessions.get_session_mut(&session_id).map(|mut sess| sess.expire_queued());
+                    if let Some(Err(e)) = expired {
+                        println!("could not expire queued messages of {}: {}", session_id, e);
+                    }
+                }
+                _ = schedule_check.tick(), if !schedule.is_non_stop() => {
+                    let was_session_time = in_session_time;
+                    in_session_time = schedule.is_session_time();
+                    if was_session_time && !in_session_time {
+                        close_schedule_window(&session_id, &schedule, app.as_ref(), &sessions, &events);
+                    }
+                    // a window with a new start is a new session, also right after the last
+                    // one. days off within a window do not start it over
+                    if let Some(start) = schedule.window_start() {
+                        if window_start.replace(start) != Some(start) && reset_on_schedule {
+                            reset_for_schedule(&session_id, app.as_ref(), &sessions, &events);
+                        }
+                    }
+                }
+            }
+        }
+    });
+    tx
+}
+
+fn close_schedule_window<A: Application>(
+    session_id: &SessionId, schedule: &SessionSchedule, app: &A, sessions: &SessionMap,
+    events: &EventBus,
+) {
+    // only the breaks between the windows of a split session log it out, at the end of the
+    // schedule the session is left as it is
+    events.publish(EngineEvent::ScheduleWindowClosed(session_id.clone()));
+    let is_active = sessions.get_session(session_id).is_some_and(|sess| sess.is_active());
+    if !is_active || !schedule.is_between_windows() {
+        return;
+    }
+    if let Err(e) = Session::terminate(session_id, sessions, SCHEDULE_ENDED_REASON) {
+        println!("could not log out {}: {}", session_id, e);
+    }
+    app.on_logout(session_id, Some(SCHEDULE_ENDED_REASON));
+}
+
+fn reset_for_schedule<A: Application>(
+    session_id: &SessionId, app: &A, sessions: &SessionMap, events: &EventBus,
+) {
+    // a session still logged on from the previous window is logged out first
+    let was_active = sessions.get_session(session_id).is_some_and(|sess| sess.is_active());
+    if let Err(e) = Session::reset(session_id, sessions) {
+        println!("could not reset {}: {}", session_id, e);
+    }
+    if was_active {
+        app.on_logout(session_id, Some(SESSION_RESET_REASON));
+    }
+    events.publish(EngineEvent::SessionReset(session_id.clone()));
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_inbound<A: Application + Send + Sync + 'static>(
+    session_id: &SessionId, dd: &DataDictionary, app: &Arc<A>, lane: &AppLane,
+    parsed: Result<PooledMessage, SessionRejectError>, authenticator: &Option<SharedAuthenticator>,
+    sessions: &SessionMap, events: &EventBus, inbound: &InboundMessage,
+) -> bool {
+    // true when the message is a Logon that was accepted
+    println!("received: {}", inbound.raw);
+    let message = match parsed {
+        Ok(message) => message,
+        Err(e) => {
+            // garbled messages, e.g. with a wrong checksum, have no reject reason and are
+            // dropped. the others are rejected once the session is logged on
+            let active = sessions.get_session(session_id).is_some_and(|sess| sess.is_active());
+            if e.reject_reason().is_none() || !active {
+                println!("dropping message for {} that does not parse: {}", session_id, e);
+            } else if let Err(send_err) =
+                Session::reject_inbound(session_id, sessions, &inbound.raw, &e)
+            {
+                println!("could not reject message for {}: {}", session_id, send_err);
+            }
+            return false;
+        }
+    };
+    println!("msg parsed");
+    if !is_for_session(&message, session_id) {
+        // a connection bound to a session is not routed by comp ids anymore
+        println!("ignoring message not for {}", session_id);
+        return false;
+    }
+    let msg_type = message.get_msg_type().unwrap_or_default();
+    let begin_string =
+        message.header().get_field::<String>(BeginString::field()).unwrap_or_default();
+    let current = sessions.get_session(session_id).map(|sess| sess.begin_string().clone());
+    let current = current.unwrap_or_else(|| session_id.begin_string().clone());
+    // only a Logon switches a session accepting any BeginString to another
+    let switch = begin_string != current
+        && msg_type == "A"
+        && sessions
+            .get_session(session_id)
+            .is_some_and(|sess| sess.dictionary_for(&begin_string).is_some());
+    if begin_string != current && !switch {
+        reject_begin_string(session_id, sessions, app.as_ref(), inbound, &begin_string, &current);
+        return false;
+    }
+    if let Some(mut sess) = sessions.get_session_mut(session_id) {
+        let now = sess.clock().now();
+        sess.state_mut().set_last_received_time(Some(now));
+    }
+    if Session::verify(&message, sessions).is_err() {
+        // session ref has to be released before sending takes it mutably
+        let logon = sessions.get_session(session_id).map(|sess| admin::logon(&sess));
+        if let Some(Ok(logon)) = logon {
+            let send_result = Session::sync_send_to_target(session_id, sessions, logon);
+            if let Err(SendError::Disconnected) = send_result {
+                app.on_logout(session_id, Some(DISCONNECTED_REASON));
+            }
+        }
+        return false;
+    }
+    // ref is released at the end of and_then, terminate takes it mutably
+    let seq_num_err = sessions
+        .get_session(session_id)
+        .and_then(|sess| sess.check_target_seq_num(&message).err());
+    if let Some(reason) = seq_num_err {
+        if let Err(e) = Session::terminate(session_id, sessions, &reason) {
+            println!("could not log out {}: {}", session_id, e);
+        }
+        app.on_logout(session_id, Some(&reason));
+        return false;
+    }
+    // only a message that passed the checks says what the peer processed
+    if let Some(last_processed) = message.header().last_msg_seq_num_processed() {
+        if let Some(mut sess) = sessions.get_session_mut(session_id) {
+            sess.state_mut().set_peer_last_msg_seq_num_processed(Some(last_processed));
+        }
+    }
+    let expected =
+        sessions.get_session(session_id).map(|sess| sess.state().next_target_msg_seq_num());
+    let received = message.header().get_field::<u64>(MsgSeqNum::field()).unwrap_or_default();
+    if let Some(expected) = expected.filter(|expected| received > *expected) {
+        events.publish(EngineEvent::SeqGapDetected {
+            session_id: session_id.clone(),
+            expected,
+            received,
+        });
+        // Logon, Logout, ResendRequest and SequenceReset are handled past a gap, the others are
+        // dropped and come again with the resend
+        if !matches!(msg_type.as_str(), "A" | "5" | "2" | "4") {
+            request_resend(session_id, sessions, app.as_ref(), received);
+            return false;
+        }
+    }
+    let decision = match msg_type.as_str() {
+        "A" => {
+            events.publish(EngineEvent::LogonReceived {
+                session_id: session_id.clone(),
+                source: inbound.source,
+            });
+            match outside_schedule(session_id, sessions, events) {
+                Some(reason) => LogonDecision::Reject(reason),
+                // seqnums and sent messages do not carry over to another BeginString
+                None if switch && !is_reset_logon(&message) => LogonDecision::Reject(format!(
+                    "BeginString {} needs ResetSeqNumFlag to switch from {}",
+                    begin_string, current
+                )),
+                None => authenticate(app.as_ref(), authenticator, session_id, inbound, &message),
+            }
+        }
+        _ => LogonDecision::Accept,
+    };
+    if switch && decision == LogonDecision::Accept {
+        // before the Logon is answered, the answer goes out in the new BeginString
+        if let Some(mut sess) = sessions.get_session_mut(session_id) {
+            sess.switch_begin_string(&begin_string);
+        }
+    }
+    match decision {
+        LogonDecision::Reject(reason) => {
+            if let Err(e) = Session::reject_logon(session_id, sessions, &reason) {
+                println!("could not reject logon for {}: {}", session_id, e);
             }
+            app.on_logout(session_id, Some(&reason));
+        }
+        LogonDecision::Disconnect => {
+            println!("disconnecting {} on logon", inbound.source);
+            inbound.disconnect();
+            app.on_logout(session_id, Some("logon rejected: disconnected"));
+        }
+        LogonDecision::Accept if dd.is_admin_msg_type(&msg_type) => {
+            let result = Session::next_admin(session_id, sessions, &message);
+            Session::correlate(session_id, sessions, &message);
+            if msg_type == "2" && result.is_ok() {
+                // same defaults as the session answering it
+                events.publish(EngineEvent::ResendCompleted {
+                    session_id: session_id.clone(),
+                    begin: message.get_field::<u64>(BeginSeqNo::field()).unwrap_or(1),
+                    end: message.get_field::<u64>(EndSeqNo::field()).unwrap_or(0),
+                });
+            }
+            if result.is_ok() && msg_type != "5" {
+                // seqnums the Logon or SequenceReset did not account for
+                request_resend(session_id, sessions, app.as_ref(), received);
+            }
+            if msg_type == "5" {
+                // counterparty's reason, if it gave one
+                let text = message.get_field::<String>(Text::field()).ok();
+                app.on_logout(session_id, text.as_deref());
+            } else if let Err(SendError::Disconnected) = result {
+                app.on_logout(session_id, Some(DISCONNECTED_REASON));
+            }
+            return msg_type == "A" && result.is_ok();
+        }
+        LogonDecision::Accept => {
+            let duplicate =
+                sessions.get_session(session_id).is_some_and(|s| s.is_duplicate(&message));
+            if duplicate {
+                println!("dropping duplicate of message {} for {}", received, session_id);
+                return false;
+            }
+            if Session::is_poss_resend(&message)
+                && app.is_poss_resend_duplicate(session_id, &message)
+            {
+                println!("dropping resent message {} for {}", received, session_id);
+                Session::skip_app(session_id, sessions, &message);
+                return false;
+            }
+            Session::next_app(session_id, sessions, &message);
+            Session::correlate(session_id, sessions, &message);
+            let route = match message.header().deliver_to_comp_id() {
+                Some(_) => app.route_third_party(session_id, &message),
+                None => ThirdPartyRoute::Local,
+            };
+            let session_id = match route {
+                ThirdPartyRoute::Local => session_id.clone(),
+                ThirdPartyRoute::Deliver(to) => to,
+                ThirdPartyRoute::Forward(to) => {
+                    if let Err(e) = Session::forward(session_id, sessions, &to, &message) {
+                        println!(
+                            "could not forward {} from {} to {}: {}",
+                            received, session_id, to, e
+                        );
+                    }
+                    return false;
+                }
+            };
+            let (app, sessions) = (Arc::clone(app), sessions.clone());
+            lane.run(move || {
+                app.from_app_pooled(&SessionContext::new(&session_id, &sessions), message)
+            })
+            .await;
+        }
+    }
+    false
+}
 
-            // app.from_app(s);
+fn request_resend<A: Application>(
+    session_id: &SessionId, sessions: &SessionMap, app: &A, received: u64,
+) {
+    match Session::request_resend(session_id, sessions, received) {
+        Ok(()) => {}
+        Err(SendError::Disconnected) => app.on_logout(session_id, Some(DISCONNECTED_REASON)),
+        Err(e) => println!("could not request resend for {}: {}", session_id, e),
+    }
+}
+
+fn reject_begin_string<A: Application>(
+    session_id: &SessionId, sessions: &SessionMap, app: &A, inbound: &InboundMessage,
+    begin_string: &str, expected: &str,
+) {
+    // logout like a rejected logon. a connection other than the one the session is logged on
+    // over is dropped instead, the logout would go out to the session's counterparty. the
+    // application only hears of it for a session that was logged on
+    let reason = format!("BeginString {} does not match {}", begin_string, expected);
+    let active = sessions.get_session(session_id).is_some_and(|sess| sess.is_active());
+    let own_connection = inbound.bound_session() == Some(session_id);
+    if own_connection || !active {
+        if let Err(e) = Session::terminate(session_id, sessions, &reason) {
+            println!("could not log out {}: {}", session_id, e);
+        }
+        if active {
+            app.on_logout(session_id, Some(&reason));
         }
+    } else {
+        inbound.disconnect();
+    }
+}
+
+fn raw_begin_string(raw: &str) -> Option<&str> {
+    // BeginString(8) is always the first field
+    raw.strip_prefix("8=")?.split('\x01').next()
+}
+
+fn is_for_session(msg: &Message, session_id: &SessionId) -> bool {
+    // the counterparty's SenderCompID is our TargetCompID and the other way around
+    let header = msg.header();
+    header.get_field::<String>(SenderCompID::field()).as_deref() == Ok(session_id.target_compid())
+        && header.get_field::<String>(TargetCompID::field()).as_deref()
+            == Ok(session_id.sender_compid())
+}
+
+fn outside_schedule(
+    session_id: &SessionId, sessions: &SessionMap, events: &EventBus,
+) -> Option<String> {
+    // reason for refusing a logon outside of the session's hours. times are the schedule's
+    // local ones, a window that looks right in another zone is the usual cause
+    let schedule = sessions.get_session(session_id).map(|sess| Arc::clone(sess.schedule()))?;
+    if schedule.is_session_time() {
+        return None;
+    }
+    let local_time = schedule.local_now();
+    let reason = format!(
+        "logon rejected: outside session hours {}, local time is {}",
+        schedule,
+        local_time.format("%Y-%m-%d %H:%M:%S")
+    );
+    println!("{}: {}", session_id, reason);
+    events.publish(EngineEvent::LogonOutsideSchedule {
+        session_id: session_id.clone(),
+        local_time,
+        schedule: schedule.to_string(),
     });
+    Some(reason)
+}
+
+fn authenticate<A: Application>(
+    app: &A, authenticator: &Option<SharedAuthenticator>, session_id: &SessionId,
+    inbound: &InboundMessage, logon: &Message,
+) -> LogonDecision {
+    if let Some(authenticator) = authenticator {
+        let decision = authenticator.authenticate(session_id, logon, inbound.source);
+        if decision != LogonDecision::Accept {
+            return decision;
+        }
+    }
+    let username = logon.get_field::<String>(Username::field()).ok();
+    let password = logon.get_field::<String>(Password::field()).ok();
+    if app.authenticate(session_id, username.as_deref(), password.as_deref()) {
+        LogonDecision::Accept
+    } else {
+        LogonDecision::Reject("logon rejected: authentication failed".to_string())
+    }
+}
+
+fn accept_addr(settings: &Properties, session_id: &SessionId) -> std::io::Result<SocketAddr> {
+    let port: u16 =
+        settings.get_optional_config(session_id, SOCKET_ACCEPT_PORT_SETTING).ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} not set for {}", SOCKET_ACCEPT_PORT_SETTING, session_id),
+            )
+        })?;
+    let host: String = settings
+        .get_optional_config(session_id, SOCKET_ACCEPT_HOST_SETTING)
+        .unwrap_or_else(|| SOCKET_ACCEPT_HOST_IP.to_string());
+    resolve_socket_addr(&host, port)
 }
 
-fn create_sessions(settings: &Properties) -> HashMap<SessionId, Session> {
+pub(crate) fn resolve_socket_addr(host: &str, port: u16) -> std::io::Result<SocketAddr> {
+    // host can be a hostname, an ipv4 or ipv6 address (optionally in brackets), or 0.0.0.0/::
+    // to listen on all interfaces. first resolved address is used
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+        Error::new(std::io::ErrorKind::AddrNotAvailable, format!("could not resolve {}", host))
+    })
+}
+
+fn create_sessions(settings: &Properties) -> std::io::Result<HashMap<SessionId, Session>> {
     let mut session_map = HashMap::new();
     let connection_type: ConnectionType =
         settings.get_default_config(CONNECTION_TYPE_SETTING).unwrap();
+    // sessions naming the same dictionary share it. nothing is shared with other engines in
+    // the process, each is built from its own settings only
+    let mut dictionaries: HashMap<Option<String>, Arc<DataDictionary>> = HashMap::new();
     for session_id in settings.session_ids() {
-        let session = Session::with_settings(session_id, settings);
+        let path = settings.get_optional_config::<String>(session_id, DATA_DICTIONARY_FILE_PATH);
+        let data_dictionary = dictionaries
+            .entry(path)
+            .or_insert_with(|| Session::load_dictionary(session_id, settings))
+            .clone();
+        let session = Session::with_dictionary(session_id, settings, data_dictionary)?;
         session_map.insert(session_id.clone(), session);
     }
-    session_map
+    Ok(session_map)
 }
 
 // fn create_socket_session(settings: &Properties) -> HashMap<SocketAddr, HashSet<SessionId>> {
@@ -288,11 +1038,11 @@ fn create_sessions(settings: &Properties) -> HashMap<SessionId, Session> {
 //         settings.default_property(CONNECTION_TYPE_SETTING).unwrap();
 //     for session_id in settings.session_ids() {
 //         let (host, port): (String, u16) = match connection_type {
-//             ConnectionType::ACCEPTOR => (
+//             ConnectionType::Acceptor => (
 //                 SOCKET_ACCEPT_HOST_IP.to_string(),
 //                 settings.get_or_default(session_id, SOCKET_ACCEPT_PORT_SETTING).unwrap(),
 //             ),
-//             ConnectionType::INITIATOR => (
+//             ConnectionType::Initiator => (
 //                 settings.get_or_default(session_id, SOCKET_CONNECT_HOST_SETTING).unwrap(),
 //                 settings.get_or_default(session_id, SOCKET_CONNECT_PORT_SETTING).unwrap(),
 //             ),
@@ -313,28 +1063,6 @@ fn create_sessions(settings: &Properties) -> HashMap<SessionId, Session> {
 //     result_map
 // }
 
-fn create_socket_descriptors(settings: &Properties) -> HashMap<SocketAddr, bool> {
-    let mut descriptor = HashMap::new();
-    let connection_type: ConnectionType =
-        settings.get_default_config(CONNECTION_TYPE_SETTING).unwrap();
-    for session_id in settings.session_ids() {
-        let (host, port): (String, u16) = match connection_type {
-            ConnectionType::ACCEPTOR => (
-                SOCKET_ACCEPT_HOST_IP.to_string(),
-                settings.get_optional_config(session_id, SOCKET_ACCEPT_PORT_SETTING).unwrap(),
-            ),
-            ConnectionType::INITIATOR => (
-                settings.get_optional_config(session_id, SOCKET_CONNECT_HOST_SETTING).unwrap(),
-                settings.get_optional_config(session_id, SOCKET_CONNECT_PORT_SETTING).unwrap(),
-            ),
-        };
-        let addr_str = format!("{}:{}", host, port);
-        let sock_address = addr_str.parse::<SocketAddr>().unwrap();
-        descriptor.insert(sock_address, false);
-    }
-    descriptor
-}
-
 // fn start_internal_msg_receiver_task(mut write_stream: OwnedWriteHalf, mut rx: TioReceiver<String>) {
 //     tokio::spawn(async move {
 //         println!("starting internal msg receiv");
@@ -380,4 +1108,1357 @@ fn create_socket_descriptors(settings: &Properties) -> HashMap<SocketAddr, bool>
 // }
 
 #[cfg(test)]
-mod networkio_tests {}
+mod networkio_tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        heartbeat_interval = 30
+
+        [Session]
+        sender_comp_id = "ACCEPTOR"
+        target_comp_id = "INITIATOR"
+        socket_accept_port = 0
+    "#;
+
+    fn seq_num(msg: &Message) -> u64 {
+        msg.header().get_field::<u64>(34).unwrap()
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_connection_type() {
+        assert_eq!("ACCEPTOR".parse(), Ok(ConnectionType::ACCEPTOR));
+        assert_eq!("initiator".parse(), Ok(ConnectionType::Initiator));
+        assert_eq!(ConnectionType::INITIATOR, ConnectionType::Initiator);
+    }
+
+    fn msg_type(msg: &Message) -> String {
+        msg.get_msg_type().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logon_heartbeat_and_app_message() {
+        let (acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(msg_type(&logon), "A");
+        assert_eq!(seq_num(&logon), 1);
+
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        let heartbeat = initiator.recv().await;
+        assert_eq!(msg_type(&heartbeat), "0");
+        assert_eq!(seq_num(&heartbeat), 2);
+        assert_eq!(heartbeat.get_field::<String>(112).unwrap(), "PING");
+
+        // plain heartbeat is consumed by the session without a reply
+        initiator.send_admin(|s| admin::heartbeat(s, None)).await;
+
+        let mut order = initiator.session().new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        initiator.send(order).await;
+        match recv_event(&mut events).await {
+            AppEvent::FromApp(sid, msg) => {
+                assert_eq!(sid, session_id);
+                assert_eq!(msg_type(&msg), "D");
+                assert_eq!(seq_num(&msg), 4);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.state().next_target_msg_seq_num(), 5);
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+        assert_eq!(initiator.session().state().next_target_msg_seq_num(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reject_invalid_message() {
+        let (acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        // OrderQty(38) is not a number
+        let mut order = initiator.session().new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        order.set_field(StringField::new(38, "many"));
+        initiator.send(order).await;
+        let reject = initiator.recv().await;
+        assert_fields(&reject, &[(35, "3"), (45, "2"), (371, "38"), (372, "D"), (373, "6")]);
+
+        // its seqnum is used up, the next message is the one expected
+        let mut order = initiator.session().new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER2"));
+        initiator.send(order).await;
+        match recv_event(&mut events).await {
+            AppEvent::FromApp(_, msg) => assert_eq!(seq_num(&msg), 3),
+            event => panic!("unexpected event {:?}", event),
+        }
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.state().next_target_msg_seq_num(), 4);
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_third_party_forward() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            socket_accept_port = 0
+
+            [Session]
+            sender_comp_id = "HUB"
+            target_comp_id = "CLIENT"
+
+            [Session]
+            sender_comp_id = "HUB"
+            target_comp_id = "BROKER"
+        "#;
+        let sid = |target| SessionIdBuilder::new("FIX.4.3", "HUB", target).build().unwrap();
+        let (app, mut events) = TestApplication::new();
+        let app = app.with_third_party_route(ThirdPartyRoute::Forward(sid("BROKER")));
+        let (acceptor, _) = start_acceptor_with(config, app);
+        let mut client = TestInitiator::connect_in_process(&acceptor, config, &sid("CLIENT"));
+        let mut broker = TestInitiator::connect_in_process(&acceptor, config, &sid("BROKER"));
+        for initiator in [&mut client, &mut broker] {
+            initiator.send_admin(admin::logon).await;
+            assert_eq!(msg_type(&initiator.recv().await), "A");
+        }
+
+        let mut order = client.session().new_message("D").unwrap();
+        order.header_mut().set_deliver_to_comp_id("BROKER");
+        order.set_field(StringField::new(11, "ORDER1"));
+        client.send(order).await;
+        let forwarded = broker.recv().await;
+        assert_fields(&forwarded, &[(35, "D"), (49, "HUB"), (56, "BROKER"), (115, "CLIENT")]);
+        assert_fields(&forwarded, &[(34, "2"), (11, "ORDER1")]);
+        assert!(forwarded.header().deliver_to_comp_id().is_none());
+        // the hub does not see it as its own
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_in_process_connection() {
+        let (acceptor, mut events, _) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect_in_process(&acceptor, CONFIG, &session_id);
+
+        initiator.send_admin(admin::logon).await;
+        assert_eq!(msg_type(&initiator.recv().await), "A");
+        let mut order = initiator.session().new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        initiator.send(order).await;
+        match recv_event(&mut events).await {
+            AppEvent::FromApp(sid, msg) => {
+                assert_eq!(sid, session_id);
+                assert_eq!(msg.get_field::<String>(11).unwrap(), "ORDER1");
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        let unknown = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "OTHER").build().unwrap();
+        assert!(acceptor.connect_in_process(&unknown).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_capture_connection() {
+        use crate::io::capture::{replay, Direction};
+        let dir = tempfile::tempdir().unwrap();
+        let config = format!("{}capture_path = {:?}\n", CONFIG, dir.path());
+        let (_acceptor, _events, addr) = start_acceptor(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        let captures: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(captures.len(), 1);
+        let path = captures[0].as_ref().unwrap().path();
+        // records reach the file after the traffic they are of
+        let mut inbound = Vec::new();
+        let mut outbound = Vec::new();
+        for _ in 0..100 {
+            inbound = replay(&path, Direction::Inbound).unwrap();
+            outbound = replay(&path, Direction::Outbound).unwrap();
+            if (inbound.len(), outbound.len()) == (1, 1) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!((inbound.len(), outbound.len()), (1, 1));
+        assert!(inbound[0].contains("\u{1}35=A\u{1}49=INITIATOR\u{1}"));
+        assert!(outbound[0].contains("\u{1}35=A\u{1}49=ACCEPTOR\u{1}"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_message_tap() {
+        use crate::io::capture::Direction;
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let tapped = Arc::clone(&frames);
+        let (app, _events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app).with_tap(
+            move |sid: Option<&SessionId>, direction: Direction, bytes: &[u8]| {
+                let raw = String::from_utf8_lossy(bytes).to_string();
+                tapped.lock().unwrap().push((sid.cloned(), direction, raw));
+            },
+        );
+        let (_acceptor, addr) = run_acceptor(acceptor);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        initiator.recv().await;
+
+        // outbound frames are tapped once written, which can be after the initiator read them
+        for _ in 0..100 {
+            if frames.lock().unwrap().len() == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let frames = frames.lock().unwrap();
+        let directions: Vec<_> = frames.iter().map(|(_, direction, _)| *direction).collect();
+        use Direction::*;
+        assert_eq!(directions, [Inbound, Outbound, Inbound, Outbound]);
+        // the logon is tapped before the connection belongs to the session
+        assert!(frames.iter().all(|(sid, _, _)| sid.as_ref() == Some(&session_id)));
+        assert!(frames[0].2.contains("\u{1}35=A\u{1}49=INITIATOR\u{1}"));
+        assert!(frames[3].2.contains("\u{1}35=0\u{1}49=ACCEPTOR\u{1}"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_back_to_back_messages() {
+        let (_acceptor, _events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+
+        let mut burst = vec![admin::logon(initiator.session()).unwrap()];
+        for req_id in ["PING1", "PING2", "PING3"] {
+            burst.push(admin::test_request(initiator.session(), req_id).unwrap());
+        }
+        initiator.send_all(burst).await;
+        assert_eq!(msg_type(&initiator.recv().await), "A");
+        for req_id in ["PING1", "PING2", "PING3"] {
+            let heartbeat = initiator.recv().await;
+            assert_eq!(heartbeat.get_field::<String>(112).unwrap(), req_id);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_burst_keeps_order_across_binding() {
+        // the connection is bound to the session while most of the burst is still on its way
+        // through the dispatcher, none of it may overtake the rest. the answers all fit the queue
+        let config = CONFIG
+            .replace("socket_accept_port = 0", "socket_accept_port = 0\nsend_queue_size = 512");
+        let (_acceptor, _events, addr) = start_acceptor(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, &config, &session_id).await;
+
+        let req_ids: Vec<String> = (0..200).map(|i| format!("PING{}", i)).collect();
+        let mut burst = vec![admin::logon(initiator.session()).unwrap()];
+        for req_id in &req_ids {
+            burst.push(admin::test_request(initiator.session(), req_id).unwrap());
+        }
+        initiator.send_all(burst).await;
+        assert_eq!(msg_type(&initiator.recv().await), "A");
+        for req_id in &req_ids {
+            let heartbeat = initiator.recv().await;
+            assert_eq!(msg_type(&heartbeat), "0");
+            assert_eq!(&heartbeat.get_field::<String>(112).unwrap(), req_id);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logon_authentication() {
+        let config = CONFIG.replace(
+            "socket_accept_port = 0",
+            "socket_accept_port = 0\nlogon_username = \"trader\"\nlogon_password = \"secret\"",
+        );
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        for (password, accepted) in [("secret", true), ("wrong", false)] {
+            let (app, mut events) = TestApplication::new();
+            let (acceptor, addr) =
+                start_acceptor_with(&config, app.with_credentials("trader", password));
+            let mut initiator = TestInitiator::connect(addr, &config, &session_id).await;
+            initiator.send_admin(admin::logon).await;
+            let reply = initiator.recv().await;
+            let session = acceptor.session_map().get_session(&session_id).unwrap();
+            assert_eq!(session.is_active(), accepted);
+            if accepted {
+                assert_eq!(msg_type(&reply), "A");
+            } else {
+                assert_eq!(msg_type(&reply), "5");
+                assert!(reply.get_field::<String>(58).unwrap().contains("authentication"));
+                assert_matches::assert_matches!(recv_event(&mut events).await, AppEvent::Logout(_, Some(reason)) if reason.contains("authentication"));
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logon_authenticator() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let decisions = [
+            ("TOKEN1", LogonDecision::Accept),
+            ("EXPIRED", LogonDecision::Reject("token expired".to_string())),
+            ("FORGED", LogonDecision::Disconnect),
+        ];
+        for (token, expected) in decisions {
+            let (app, _events) = TestApplication::new();
+            let authenticator = |_: &SessionId, logon: &Message, source: SocketAddr| {
+                assert!(source.ip().is_loopback());
+                match logon.get_field::<String>(96).unwrap().as_str() {
+                    "TOKEN1" => LogonDecision::Accept,
+                    "EXPIRED" => LogonDecision::Reject("token expired".to_string()),
+                    _ => LogonDecision::Disconnect,
+                }
+            };
+            let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app)
+                .with_authenticator(authenticator);
+            let (acceptor, addr) = run_acceptor(acceptor);
+            let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+            let mut logon = admin::logon(initiator.session()).unwrap();
+            logon.set_field(StringField::new(95, &token.len().to_string()));
+            logon.set_field(StringField::new(96, token));
+            initiator.send(logon).await;
+            match expected {
+                LogonDecision::Accept => assert_eq!(msg_type(&initiator.recv().await), "A"),
+                LogonDecision::Reject(reason) => {
+                    let logout = initiator.recv().await;
+                    assert_eq!(msg_type(&logout), "5");
+                    assert_eq!(logout.get_field::<String>(58).unwrap(), reason);
+                }
+                LogonDecision::Disconnect => assert!(initiator.is_disconnected().await),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_socket_addr() {
+        let addr = resolve_socket_addr("0.0.0.0", 9880).unwrap();
+        assert_eq!(addr, "0.0.0.0:9880".parse::<SocketAddr>().unwrap());
+        for host in ["::", "[::]"] {
+            let addr = resolve_socket_addr(host, 9880).unwrap();
+            assert_eq!(addr, "[::]:9880".parse::<SocketAddr>().unwrap());
+        }
+        let addr = resolve_socket_addr("::1", 9880).unwrap();
+        assert!(addr.is_ipv6() && addr.ip().is_loopback());
+        assert!(resolve_socket_addr("localhost", 9880).unwrap().ip().is_loopback());
+        assert!(resolve_socket_addr("not a host", 9880).is_err());
+    }
+
+    #[test]
+    fn test_accept_addr_setting() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            socket_accept_host = "::1"
+
+            [Session]
+            sender_comp_id = "ACCEPTOR"
+            target_comp_id = "INITIATOR"
+            socket_accept_port = 9880
+
+            [Session]
+            sender_comp_id = "ACCEPTOR"
+            target_comp_id = "OTHER"
+            socket_accept_port = 9881
+            socket_accept_host = "0.0.0.0"
+        "#;
+        let settings = Properties::from_str(config);
+        let sid = |target| SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", target).build().unwrap();
+        assert_eq!(
+            accept_addr(&settings, &sid("INITIATOR")).unwrap(),
+            "[::1]:9880".parse().unwrap()
+        );
+        assert_eq!(accept_addr(&settings, &sid("OTHER")).unwrap(), "0.0.0.0:9881".parse().unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logout() {
+        let (_acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::logout(s, None)).await;
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(seq_num(&logout), 2);
+        assert_matches::assert_matches!(recv_event(&mut events).await, AppEvent::Logout(sid, None) if sid == session_id);
+
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::logout(s, Some("end of day"))).await;
+        initiator.recv().await;
+        assert_matches::assert_matches!(
+            recv_event(&mut events).await,
+            AppEvent::Logout(_, Some(reason)) if reason == "end of day"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reset_seq_num_flag() {
+        let (acceptor, _events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::logout(s, None)).await;
+        initiator.recv().await;
+
+        // the next Logon has ResetSeqNumFlag(141)=Y and goes out as seqnum 1 again
+        initiator.session_mut().state_mut().set_next_sender_msg_seq_num(1);
+        initiator.session_mut().state_mut().set_next_target_msg_seq_num(1);
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_fields(&logon, &[(35, "A"), (34, "1"), (141, "Y")]);
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.state().next_sender_msg_seq_num(), 2);
+        assert_eq!(session.state().next_target_msg_seq_num(), 2);
+        assert_eq!(session.message_store().lock().stored_count(), 1);
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logout_on_seq_num_too_low() {
+        let (acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send_admin(|s| admin::heartbeat(s, None)).await;
+        initiator.session_mut().state_mut().set_next_sender_msg_seq_num(2);
+        initiator.send_admin(|s| admin::heartbeat(s, None)).await;
+
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        let text = logout.get_field::<String>(58).unwrap();
+        assert_eq!(text, "MsgSeqNum too low, expecting 3 but received 2");
+        assert_matches::assert_matches!(
+            recv_event(&mut events).await,
+            AppEvent::Logout(_, Some(reason)) if reason == text
+        );
+        assert!(!acceptor.session_map().get_session(&session_id).unwrap().is_active());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_last_processed_after_checks() {
+        let (acceptor, _events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        let processed = |last_processed: u64| {
+            move |s: &Session| {
+                let mut msg = admin::test_request(s, "PING")?;
+                msg.header_mut().set_last_msg_seq_num_processed(last_processed);
+                Ok(msg)
+            }
+        };
+        let peer_processed = || {
+            let session = acceptor.session_map().get_session(&session_id).unwrap();
+            session.state().peer_last_msg_seq_num_processed()
+        };
+
+        initiator.send_admin(processed(1)).await;
+        initiator.recv().await;
+        assert_eq!(peer_processed(), Some(1));
+        // a seqnum too low logs out, what the message says it processed is not taken
+        initiator.session_mut().state_mut().set_next_sender_msg_seq_num(2);
+        initiator.send_admin(processed(2)).await;
+        assert_eq!(msg_type(&initiator.recv().await), "5");
+        assert_eq!(peer_processed(), Some(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_engine_events() {
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app);
+        let mut events = acceptor.subscribe();
+        let (_acceptor, addr) = run_acceptor(acceptor);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        assert_eq!(
+            recv_engine_event(&mut events).await,
+            EngineEvent::SessionCreated(session_id.clone())
+        );
+
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        assert_matches::assert_matches!(
+            recv_engine_event(&mut events).await,
+            EngineEvent::LogonReceived { session_id: sid, .. } if sid == session_id
+        );
+
+        initiator.send_admin(|s| admin::resend_request(s, 1, 0)).await;
+        initiator.recv().await;
+        let resend_completed = EngineEvent::ResendCompleted {
+            session_id: session_id.clone(),
+            begin: 1,
+            end: 0,
+        };
+        assert_eq!(recv_engine_event(&mut events).await, resend_completed);
+
+        // seqnums 3 and 4 never sent
+        initiator.session_mut().state_mut().incr_next_sender_msg_seq_num();
+        initiator.session_mut().state_mut().incr_next_sender_msg_seq_num();
+        initiator.send_admin(|s| admin::heartbeat(s, None)).await;
+        let gap = EngineEvent::SeqGapDetected {
+            session_id: session_id.clone(),
+            expected: 3,
+            received: 5,
+        };
+        assert_eq!(recv_engine_event(&mut events).await, gap);
+
+        drop(initiator);
+        assert_matches::assert_matches!(
+            recv_engine_event(&mut events).await,
+            EngineEvent::Disconnected { session_id: Some(sid), .. } if sid == session_id
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_schedule_window_closed() {
+        use crate::clock::ManualClock;
+        let config = format!("{}start_time = \"09:00:00\"\nend_time = \"17:00:00\"\n", CONFIG);
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T16:59:58").unwrap().and_utc();
+        let clock = ManualClock::new(start);
+        let (app, mut app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let mut events = acceptor.subscribe();
+        let (acceptor, addr) = run_acceptor(acceptor.with_clock(clock.clone()));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        clock.advance(chrono::Duration::seconds(5));
+        let closed = loop {
+            match recv_engine_event(&mut events).await {
+                EngineEvent::ScheduleWindowClosed(sid) => break sid,
+                _ => continue,
+            }
+        };
+        assert_eq!(closed, session_id);
+        // the end of the schedule is only published, the session is not logged out for it
+        assert!(acceptor.session_map().get_session(&session_id).unwrap().is_active());
+        assert!(app_events.try_recv().is_err());
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        assert_eq!(msg_type(&initiator.recv().await), "0");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reset_on_schedule() {
+        use crate::clock::ManualClock;
+        // daily windows back to back, 17:00:00 to 16:59:59 the next day
+        let config = format!(
+            "{}start_time = \"17:00:00\"\nend_time = \"16:59:59\"\nreset_on_schedule = true\n",
+            CONFIG
+        );
+        let at = |datetime: &str| chrono::NaiveDateTime::from_str(datetime).unwrap().and_utc();
+        let clock = ManualClock::new(at("2024-03-05T16:59:58"));
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let mut events = acceptor.subscribe();
+        let (acceptor, addr) = run_acceptor(acceptor.with_clock(clock.clone()));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        // the next window logs out the session still logged on from the last one and starts over
+        clock.set(at("2024-03-05T17:00:01"));
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(logout.get_field::<String>(58).unwrap(), SESSION_RESET_REASON);
+        let reset = loop {
+            match recv_engine_event(&mut events).await {
+                EngineEvent::SessionReset(sid) => break sid,
+                _ => continue,
+            }
+        };
+        assert_eq!(reset, session_id);
+        let status = &acceptor.status()[0];
+        assert_eq!(status.next_sender_msg_seq_num, 1);
+        assert_eq!(status.next_target_msg_seq_num, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_engines_in_one_process() {
+        // prod and uat with the same session, their own settings and dictionaries
+        let prod_config = format!(
+            r#"{}
+        [Session]
+        sender_comp_id = "ACCEPTOR"
+        target_comp_id = "DROPCOPY"
+        socket_accept_port = 0
+    "#,
+            CONFIG
+        );
+        let uat_config =
+            CONFIG.replace("heartbeat_interval = 30", "heartbeat_interval = 45").replace(
+                "[Session]",
+                "[Session]\n        data_dictionary = \"resources/FIX43-tiny.xml\"",
+            );
+        let (prod, _prod_events, prod_addr) = start_acceptor(&prod_config);
+        let (uat, _uat_events, uat_addr) = start_acceptor(&uat_config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let drop_copy = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "DROPCOPY").build().unwrap();
+
+        let prod_dd =
+            Arc::clone(prod.session_map().get_session(&session_id).unwrap().data_dictionary());
+        let uat_dd =
+            Arc::clone(uat.session_map().get_session(&session_id).unwrap().data_dictionary());
+        assert!(Arc::ptr_eq(
+            &prod_dd,
+            prod.session_map().get_session(&drop_copy).unwrap().data_dictionary()
+        ));
+        assert!(!Arc::ptr_eq(&prod_dd, &uat_dd));
+        assert!(prod_dd.is_msg_type("5") && !uat_dd.is_msg_type("5"));
+
+        let mut prod_initiator = TestInitiator::connect(prod_addr, CONFIG, &session_id).await;
+        prod_initiator.send_admin(admin::logon).await;
+        let prod_logon = prod_initiator.recv().await;
+        let mut uat_initiator = TestInitiator::connect(uat_addr, CONFIG, &session_id).await;
+        uat_initiator.send_admin(admin::logon).await;
+        let uat_logon = uat_initiator.recv().await;
+        assert_eq!(prod_logon.get_field::<u32>(108).unwrap(), 30);
+        assert_eq!(uat_logon.get_field::<u32>(108).unwrap(), 45);
+
+        // the same session in the other engine is not touched when one is dropped
+        uat.disconnect(&session_id, "maintenance").unwrap();
+        assert!(prod.session_map().get_session(&session_id).unwrap().is_active());
+        assert!(!uat.session_map().get_session(&session_id).unwrap().is_active());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_connections_on_one_port() {
+        let config = format!(
+            r#"{}
+        [Session]
+        sender_comp_id = "ACCEPTOR"
+        target_comp_id = "DROPCOPY"
+        socket_accept_port = 0
+    "#,
+            CONFIG
+        );
+        let (acceptor, _events, addr) = start_acceptor(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let drop_copy = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "DROPCOPY").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, &config, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        assert_eq!(msg_type(&initiator.recv().await), "A");
+        let mut drop_copy_initiator = TestInitiator::connect(addr, &config, &drop_copy).await;
+        drop_copy_initiator.send_admin(admin::logon).await;
+        assert_eq!(msg_type(&drop_copy_initiator.recv().await), "A");
+
+        // each connection only gets what is sent to its own session
+        let order = acceptor.session_map().get_session(&session_id).unwrap().new_message("D");
+        let mut order = order.unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        Session::sync_send_to_target(&session_id, acceptor.session_map(), order).unwrap();
+        drop_copy_initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        let heartbeat = drop_copy_initiator.recv().await;
+        assert_eq!(msg_type(&heartbeat), "0");
+        assert_eq!(seq_num(&heartbeat), 2);
+        let order = initiator.recv().await;
+        assert_eq!(order.get_field::<String>(11).unwrap(), "ORDER1");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logon_outside_schedule() {
+        use crate::clock::ManualClock;
+        let config = format!(
+            "{}start_time = \"09:00:00\"\nend_time = \"17:00:00\"\n\
+             default_timezone = \"Europe/London\"\n",
+            CONFIG
+        );
+        // 08:30 in London, summer time
+        let now = chrono::NaiveDateTime::from_str("2024-07-01T07:30:00").unwrap().and_utc();
+        let (app, mut app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let mut events = acceptor.subscribe();
+        let (acceptor, addr) = run_acceptor(acceptor.with_clock(ManualClock::new(now)));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.time_zone(), chrono_tz::Europe::London);
+        drop(session);
+
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        let reason = "logon rejected: outside session hours 09:00:00-17:00:00 Europe/London, \
+                      local time is 2024-07-01 08:30:00";
+        assert_eq!(logout.get_field::<String>(58).unwrap(), reason);
+        assert_matches::assert_matches!(
+            recv_event(&mut app_events).await,
+            AppEvent::Logout(_, Some(logged_out)) if logged_out == reason
+        );
+        let refused = loop {
+            match recv_engine_event(&mut events).await {
+                event @ EngineEvent::LogonOutsideSchedule { .. } => break event,
+                _ => continue,
+            }
+        };
+        let expected = EngineEvent::LogonOutsideSchedule {
+            session_id,
+            local_time: chrono::NaiveDateTime::from_str("2024-07-01T08:30:00").unwrap(),
+            schedule: "09:00:00-17:00:00 Europe/London".to_string(),
+        };
+        assert_eq!(refused, expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_manual_clock() {
+        use crate::clock::ManualClock;
+        let config = format!("{}start_time = \"09:00:00\"\nend_time = \"17:00:00\"\n", CONFIG);
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T16:59:58").unwrap().and_utc();
+        let clock = ManualClock::new(start);
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let mut events = acceptor.subscribe();
+        let (acceptor, addr) = run_acceptor(acceptor.with_clock(clock.clone()));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(logon.header().get_field::<String>(52).unwrap(), "20240305-16:59:58.000");
+        let status = &acceptor.status()[0];
+        assert_eq!(status.last_received_time, Some(start));
+        assert_eq!(status.last_sent_time, Some(start));
+
+        // the window ends once the clock passes 17:00:00
+        clock.advance(chrono::Duration::seconds(5));
+        loop {
+            if let EngineEvent::ScheduleWindowClosed(sid) = recv_engine_event(&mut events).await {
+                assert_eq!(sid, session_id);
+                break;
+            }
+        }
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        let heartbeat = initiator.recv().await;
+        assert_eq!(heartbeat.header().get_field::<String>(52).unwrap(), "20240305-17:00:03.000");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_scheduled_send() {
+        use crate::clock::ManualClock;
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T10:00:00").unwrap().and_utc();
+        let clock = ManualClock::new(start);
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app);
+        let (acceptor, addr) = run_acceptor(acceptor.with_clock(clock.clone()));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        {
+            let mut session = acceptor.session_map().get_session_mut(&session_id).unwrap();
+            let mut quote = session.new_message("D").unwrap();
+            quote.set_field(StringField::new(11, "REFRESH"));
+            session.send_after(quote, chrono::Duration::seconds(30));
+        }
+        // goes out with the test request answered before it, once the clock gets there
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        assert_eq!(msg_type(&initiator.recv().await), "0");
+        clock.advance(chrono::Duration::seconds(30));
+        let quote = initiator.recv().await;
+        assert_eq!(quote.get_field::<String>(11).unwrap(), "REFRESH");
+        assert_eq!(quote.header().get_field::<String>(52).unwrap(), "20240305-10:00:30.000");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_response() {
+        use crate::quickfix_errors::RequestError;
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app);
+        let (acceptor, addr) = run_acceptor(acceptor);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        let sessions = acceptor.session_map();
+        let request = |test_req_id: &str| {
+            let msg = admin::test_request(&sessions.get_session(&session_id).unwrap(), test_req_id);
+            let msg = msg.unwrap();
+            let matcher = Session::same_field(&msg, 112);
+            Session::request(&session_id, sessions, msg, matcher, Duration::from_secs(2))
+        };
+        let first = request("PING1");
+        let second = request("PING2");
+        assert_eq!(initiator.recv().await.get_field::<String>(112).unwrap(), "PING1");
+        assert_eq!(initiator.recv().await.get_field::<String>(112).unwrap(), "PING2");
+        // answered out of order, each request gets its own response
+        initiator.send_admin(|s| admin::heartbeat(s, Some("PING2"))).await;
+        initiator.send_admin(|s| admin::heartbeat(s, Some("PING1"))).await;
+        let response = second.await.unwrap();
+        assert_eq!(response.get_field::<String>(112).unwrap(), "PING2");
+        let response = first.await.unwrap();
+        assert_eq!(response.get_field::<String>(112).unwrap(), "PING1");
+
+        let msg = admin::test_request(&sessions.get_session(&session_id).unwrap(), "PING3");
+        let unanswered = Session::request(
+            &session_id,
+            sessions,
+            msg.unwrap(),
+            |_| false,
+            Duration::from_millis(100),
+        );
+        assert!(matches!(unanswered.await, Err(RequestError::Timeout(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_confirmed() {
+        let (acceptor, _app_events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        let sessions = acceptor.session_map();
+        let heartbeat = admin::heartbeat(&sessions.get_session(&session_id).unwrap(), None);
+        Session::send_confirmed(&session_id, sessions, heartbeat.unwrap()).await.unwrap();
+        assert_eq!(seq_num(&initiator.recv().await), 2);
+
+        acceptor.disconnect(&session_id, "done").unwrap();
+        let heartbeat = admin::heartbeat(&sessions.get_session(&session_id).unwrap(), None);
+        let sent = Session::send_confirmed(&session_id, sessions, heartbeat.unwrap()).await;
+        assert!(matches!(sent, Err(SendError::Disconnected)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_warm_standby() {
+        let (app, _app_events) = TestApplication::new();
+        // every seqnum change is checkpointed and replicated
+        let config = format!("{}\nseqnum_checkpoint_messages = 1", CONFIG);
+        let primary = SocketAcceptor::new(Properties::from_str(&config), app)
+            .with_replication("127.0.0.1:0".parse().unwrap());
+        let (primary, addr) = run_acceptor(primary);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        // logon is in the snapshot, the heartbeat comes after it
+        let (app, _app_events) = TestApplication::new();
+        let standby = SocketAcceptor::new(Properties::from_str(CONFIG), app);
+        let sessions = standby.session_map().clone();
+        let replication_addr = primary.replication_addr().unwrap();
+        tokio::spawn(async move { replication::follow_primary(replication_addr, &sessions).await });
+        let sessions = primary.session_map();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let heartbeat = admin::heartbeat(&sessions.get_session(&session_id).unwrap(), None);
+        sessions.get_session_mut(&session_id).unwrap().send_to_target(heartbeat.unwrap()).unwrap();
+        assert_eq!(seq_num(&initiator.recv().await), 2);
+
+        let replicated = || {
+            let session = standby.session_map().get_session(&session_id).unwrap();
+            let store = session.message_store().lock();
+            (recover_seq_nums(&*store), store.stored_count())
+        };
+        for _ in 0..100 {
+            if replicated() == ((3, 2), 2) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(replicated(), ((3, 2), 2));
+        standby.take_over();
+        let session = standby.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+        assert_eq!(session.state().next_target_msg_seq_num(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_session_windows() {
+        use crate::clock::ManualClock;
+        // neither side asks for a reset on logon, the seqnums only go by the schedule
+        let no_reset = format!("{}reset_on_logon = false\n", CONFIG);
+        let config = format!(
+            "{}session_windows = \"09:00:00-11:30:00,13:00:00-15:00:00\"\nreset_on_schedule = true\n",
+            no_reset
+        );
+        let at = |time: &str| {
+            chrono::NaiveDateTime::from_str(&format!("2024-03-05T{}", time)).unwrap().and_utc()
+        };
+        let clock = ManualClock::new(at("11:29:58"));
+        let (app, _app_events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let (_acceptor, addr) = run_acceptor(acceptor.with_clock(clock.clone()));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, &no_reset, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        assert_eq!(seq_num(&initiator.recv().await), 1);
+
+        // logged out for the lunch break
+        clock.set(at("11:30:01"));
+        let logout = initiator.recv().await;
+        assert_eq!(logout.get_field::<String>(58).unwrap(), SCHEDULE_ENDED_REASON);
+        assert_eq!(seq_num(&logout), 2);
+
+        // the afternoon window carries on with the seqnums of the morning
+        clock.set(at("13:00:01"));
+        tokio::time::sleep(SCHEDULE_CHECK_INTERVAL * 2).await;
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(msg_type(&logon), "A");
+        assert_eq!(seq_num(&logon), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_status() {
+        let (acceptor, _events, _) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let status = acceptor.status();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].session_id, session_id.to_string());
+        assert_eq!(status[0].state, ConnectionState::Disconnected);
+
+        let mut initiator = TestInitiator::connect_in_process(&acceptor, CONFIG, &session_id);
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        let status = acceptor.status().remove(0);
+        assert_eq!(status.state, ConnectionState::LoggedOn);
+        assert_eq!(status.peer_addr, Some(io::acceptor::IN_PROCESS_ADDR));
+        assert_eq!((status.next_sender_msg_seq_num, status.next_target_msg_seq_num), (2, 2));
+        assert!(status.last_sent_time.is_some() && status.last_received_time.is_some());
+        assert_eq!(status.connection.unwrap().messages_in, 1);
+
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        initiator.recv().await;
+        // the writer counts what it wrote once the write is done, the reply may be read before
+        let written = async {
+            loop {
+                let stats = acceptor.status().remove(0).connection.unwrap();
+                if stats.messages_out == 2 {
+                    break stats;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let stats = tokio::time::timeout(RECV_TIMEOUT, written).await.expect("not written");
+        assert_eq!(stats.messages_in, 2);
+        assert!(stats.bytes_in > 0 && stats.bytes_out > 0);
+        assert!(stats.last_sent_time.unwrap() >= stats.connected_at);
+
+        drop(initiator);
+        let disconnected = async {
+            while acceptor.status()[0].state != ConnectionState::Disconnected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(RECV_TIMEOUT, disconnected).await.expect("still connected");
+    }
+
+    #[test]
+    fn test_runtime_injection() {
+        // two engines on runtimes of their own, started from outside of any runtime
+        let runtimes: Vec<_> = (0..2)
+            .map(|_| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .enable_all()
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let acceptors: Vec<_> = runtimes
+            .iter()
+            .map(|runtime| {
+                let (app, _events) = TestApplication::new();
+                let acceptor = SocketAcceptor::new(Properties::from_str(CONFIG), app)
+                    .with_runtime(runtime.handle().clone());
+                run_acceptor(acceptor)
+            })
+            .collect();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let client = tokio::runtime::Runtime::new().unwrap();
+        for (_, addr) in acceptors.iter() {
+            client.block_on(async {
+                let mut initiator = TestInitiator::connect(*addr, CONFIG, &session_id).await;
+                initiator.send_admin(admin::logon).await;
+                assert_eq!(msg_type(&initiator.recv().await), "A");
+            });
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_poss_dup_deduplication() {
+        for (config, delivered) in [
+            (CONFIG.to_string(), vec!["ORDER1", "ORDER2"]),
+            (
+                format!("{}deduplicate_poss_dup = false\n", CONFIG),
+                vec!["ORDER1", "ORDER1", "ORDER2"],
+            ),
+        ] {
+            let (_acceptor, mut events, addr) = start_acceptor(&config);
+            let session_id =
+                SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+            let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+            initiator.send_admin(admin::logon).await;
+            initiator.recv().await;
+
+            let order = |initiator: &TestInitiator, cl_ord_id: &str| {
+                let mut order = initiator.session().new_message("D").unwrap();
+                order.set_field(StringField::new(11, cl_ord_id));
+                order
+            };
+            initiator.send(order(&initiator, "ORDER1")).await;
+            // ORDER1 again as recovery would resend it
+            initiator.session_mut().state_mut().set_next_sender_msg_seq_num(2);
+            let mut resent = order(&initiator, "ORDER1");
+            resent.header_mut().set_poss_dup(true);
+            initiator.send(resent).await;
+            initiator.send(order(&initiator, "ORDER2")).await;
+
+            for cl_ord_id in delivered {
+                match recv_event(&mut events).await {
+                    AppEvent::FromApp(_, msg) => {
+                        assert_eq!(msg.get_field::<String>(11).unwrap(), cl_ord_id)
+                    }
+                    event => panic!("unexpected event {:?}", event),
+                }
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_poss_resend() {
+        let (acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        let order = |initiator: &TestInitiator, cl_ord_id: &str, poss_resend: bool| {
+            let mut order = initiator.session().new_message("D").unwrap();
+            order.set_field(StringField::new(11, cl_ord_id));
+            if poss_resend {
+                order.header_mut().set_poss_resend(true);
+            }
+            order
+        };
+        // the application already has ORDER1, ORDER2 is new to it although resent
+        initiator.send(order(&initiator, "ORDER1", false)).await;
+        initiator.send(order(&initiator, "ORDER1", true)).await;
+        initiator.send(order(&initiator, "ORDER2", true)).await;
+        initiator.send(order(&initiator, "ORDER3", false)).await;
+        for cl_ord_id in ["ORDER1", "ORDER2", "ORDER3"] {
+            match recv_event(&mut events).await {
+                AppEvent::FromApp(_, msg) => {
+                    assert_eq!(msg.get_field::<String>(11).unwrap(), cl_ord_id)
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        // the dropped one still used up its seqnum
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.state().next_target_msg_seq_num(), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_begin_string() {
+        // sessions of different FIX versions share the acceptor port
+        let dir = tempfile::tempdir().unwrap();
+        let fix44_xml = dir.path().join("FIX44.xml");
+        let fix43_xml = std::fs::read_to_string("resources/FIX43.xml").unwrap();
+        std::fs::write(&fix44_xml, fix43_xml.replacen(r#"minor="3""#, r#"minor="4""#, 1)).unwrap();
+        let fix44_session = format!(
+            "sender_comp_id = \"ACCEPTOR\"\ntarget_comp_id = \"INITIATOR44\"\n\
+             socket_accept_port = 0\ndata_dictionary = \"{}\"\n",
+            fix44_xml.display()
+        );
+        let config =
+            format!("{}\n[Session]\nbegin_string = \"FIX.4.4\"\n{}", CONFIG, fix44_session);
+        let fix44_config = format!(
+            "[Default]\nconnection_type = \"acceptor\"\nbegin_string = \"FIX.4.4\"\n\
+             data_dictionary = \"{}\"\n[Session]\n{}",
+            fix44_xml.display(),
+            fix44_session
+        );
+        let (_acceptor, mut events, addr) = start_acceptor(&config);
+        let fix44 = SessionIdBuilder::new("FIX.4.4", "ACCEPTOR", "INITIATOR44").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, &fix44_config, &fix44).await;
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(msg_type(&logon), "A");
+        assert_eq!(logon.header().get_field::<String>(8).unwrap(), "FIX.4.4");
+
+        // Logon over a version the session does not use
+        let wrong = SessionIdBuilder::new("FIX.4.4", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator = TestInitiator::connect(addr, &fix44_config, &wrong).await;
+        initiator.send_admin(admin::logon).await;
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(logout.header().get_field::<String>(8).unwrap(), "FIX.4.3");
+        assert_eq!(
+            logout.get_field::<String>(58).unwrap(),
+            "BeginString FIX.4.4 does not match FIX.4.3"
+        );
+        // the session was never logged on, the application is not told of a logout
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_accept_any_begin_string() {
+        // the session takes on the version of the Logon, if it has a dictionary for it
+        let dir = tempfile::tempdir().unwrap();
+        let fix43_xml = std::fs::read_to_string("resources/FIX43.xml").unwrap();
+        let dictionary = |minor: &str| {
+            let path = dir.path().join(format!("FIX4{}.xml", minor));
+            let xml = fix43_xml.replacen(r#"minor="3""#, &format!(r#"minor="{}""#, minor), 1);
+            std::fs::write(&path, xml).unwrap();
+            path.display().to_string()
+        };
+        let (fix42_xml, fix44_xml) = (dictionary("2"), dictionary("4"));
+        let config = format!(
+            "{}accept_any_begin_string = true\nbegin_string_dictionaries = \"{}\"\n",
+            CONFIG, fix44_xml
+        );
+        let initiator_config = |begin_string: &str, xml: &str| {
+            CONFIG
+                .replace("FIX.4.3", begin_string)
+                .replace("[Session]", &format!("data_dictionary = \"{}\"\n[Session]", xml))
+        };
+        let (acceptor, _events, addr) = start_acceptor(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+
+        let fix42 = SessionIdBuilder::new("FIX.4.2", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let mut initiator =
+            TestInitiator::connect(addr, &initiator_config("FIX.4.2", &fix42_xml), &fix42).await;
+        initiator.send_admin(admin::logon).await;
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(
+            logout.get_field::<String>(58).unwrap(),
+            "BeginString FIX.4.2 does not match FIX.4.3"
+        );
+
+        // seqnums do not carry over to another version, the Logon has to reset them
+        let fix44 = SessionIdBuilder::new("FIX.4.4", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let no_reset_config = initiator_config("FIX.4.4", &fix44_xml)
+            .replace("[Session]", "reset_on_logon = false\n[Session]");
+        let mut initiator = TestInitiator::connect(addr, &no_reset_config, &fix44).await;
+        initiator.send_admin(admin::logon).await;
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(
+            logout.get_field::<String>(58).unwrap(),
+            "BeginString FIX.4.4 needs ResetSeqNumFlag to switch from FIX.4.3"
+        );
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.begin_string(), "FIX.4.3");
+        drop(session);
+
+        let mut initiator =
+            TestInitiator::connect(addr, &initiator_config("FIX.4.4", &fix44_xml), &fix44).await;
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(msg_type(&logon), "A");
+        assert_eq!(logon.header().get_field::<String>(8).unwrap(), "FIX.4.4");
+        let session = acceptor.session_map().get_session(&session_id).unwrap();
+        assert_eq!(session.begin_string(), "FIX.4.4");
+        assert_eq!(session.data_dictionary().begin_string(), "FIX.4.4");
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_manual_control() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let (acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        // reset logs out and starts the seqnums over
+        acceptor.reset(&session_id).unwrap();
+        let logout = initiator.recv().await;
+        assert_eq!(msg_type(&logout), "5");
+        assert_eq!(logout.get_field::<String>(58).unwrap(), SESSION_RESET_REASON);
+        match recv_event(&mut events).await {
+            AppEvent::Logout(_, reason) => assert_eq!(reason.unwrap(), SESSION_RESET_REASON),
+            event => panic!("unexpected event {:?}", event),
+        }
+        // the connection is closed once the Logout is written
+        assert!(initiator.is_disconnected().await);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        let logon = initiator.recv().await;
+        assert_eq!(msg_type(&logon), "A");
+        assert_eq!(logon.header().get_field::<u64>(34).unwrap(), 1);
+
+        // disconnect drops the connection without a Logout
+        acceptor.disconnect(&session_id, "operator request").unwrap();
+        assert!(initiator.is_disconnected().await);
+        match recv_event(&mut events).await {
+            AppEvent::Logout(_, reason) => assert_eq!(reason.unwrap(), "operator request"),
+            event => panic!("unexpected event {:?}", event),
+        }
+        let status = &acceptor.status()[0];
+        assert_eq!(status.state, ConnectionState::Disconnected);
+        assert_matches::assert_matches!(
+            acceptor.disconnect(&session_id, "again"),
+            Err(SendError::Disconnected)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_large_message() {
+        let text = "x".repeat(20_000);
+        let order = |initiator: &TestInitiator| {
+            let mut order = initiator.session().new_message("D").unwrap();
+            order.set_field(StringField::new(11, "ORDER1"));
+            order.set_field(StringField::new(58, &text));
+            order
+        };
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let (_acceptor, mut events, addr) = start_acceptor(CONFIG);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send(order(&initiator)).await;
+        match recv_event(&mut events).await {
+            AppEvent::FromApp(_, msg) => assert_eq!(msg.get_field::<String>(58).unwrap(), text),
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        let config = format!("{}max_message_size = 4096\n", CONFIG);
+        let (_acceptor, _events, addr) = start_acceptor(&config);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        initiator.send(order(&initiator)).await;
+        assert!(initiator.is_disconnected().await);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_idle_timeout() {
+        // heartbeats are 30 seconds apart, the connection goes long before the session would
+        // miss one
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let config = format!("{}socket_idle_timeout = 1\n", CONFIG);
+        let (acceptor, _events, addr) = start_acceptor(&config);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        // whatever is read starts the wait over
+        initiator.send_admin(|s| admin::heartbeat(s, None)).await;
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert!(acceptor.session_map().get_session(&session_id).unwrap().peer_addr().is_some());
+        assert!(initiator.is_disconnected().await);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_connection_manager() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let (acceptor, _events, addr) = start_acceptor(CONFIG);
+        let mut logged_on = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        logged_on.send_admin(admin::logon).await;
+        logged_on.recv().await;
+        let mut idle = TestInitiator::connect_in_process(&acceptor, CONFIG, &session_id);
+
+        let connections = acceptor.connections();
+        assert_eq!(connections.len(), 2);
+        let bound = connections.session_connections(&session_id);
+        assert_eq!(bound.len(), 1);
+        assert_eq!(bound[0].stats.messages_in, 1);
+        let other = connections.connections().into_iter().find(|c| c.id != bound[0].id).unwrap();
+        assert_eq!((other.peer, other.session_id), (io::acceptor::IN_PROCESS_ADDR, None));
+
+        assert!(connections.close(other.id));
+        assert!(idle.is_disconnected().await);
+        assert!(connections.close_all(RECV_TIMEOUT).await);
+        assert!(logged_on.is_disconnected().await);
+        assert!(connections.is_empty() && !connections.close(bound[0].id));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_disconnect_through_manager() {
+        // the session's connection is closed by the manager, which removes it once it is gone
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let (acceptor, _events, addr) = start_acceptor(CONFIG);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+        assert_eq!(acceptor.connections().session_connections(&session_id).len(), 1);
+        acceptor.disconnect(&session_id, "manual").unwrap();
+        assert!(initiator.is_disconnected().await);
+        assert!(acceptor.connections().close_all(RECV_TIMEOUT).await);
+        assert!(acceptor.connections().session_connections(&session_id).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_app_dispatch_worker() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let config = format!("{}app_dispatch = \"worker\"\n", CONFIG);
+        let (app, mut events) = TestApplication::new();
+        let app = app.with_from_app_delay(Duration::from_secs(1));
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let (_acceptor, addr) = run_acceptor(acceptor);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        initiator.recv().await;
+
+        for cl_ord_id in ["ORDER1", "ORDER2"] {
+            let mut order = initiator.session().new_message("D").unwrap();
+            order.set_field(StringField::new(11, cl_ord_id));
+            initiator.send(order).await;
+        }
+        // the session answers while the application is still busy with the first order
+        initiator.send_admin(|s| admin::test_request(s, "PING")).await;
+        let heartbeat = initiator.recv().await;
+        assert_eq!(heartbeat.get_field::<String>(112).unwrap(), "PING");
+        assert!(events.try_recv().is_err());
+        for cl_ord_id in ["ORDER1", "ORDER2"] {
+            match recv_event(&mut events).await {
+                AppEvent::FromApp(_, msg) => {
+                    assert_eq!(msg.get_field::<String>(11).unwrap(), cl_ord_id)
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_validation_pool() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "ACCEPTOR", "INITIATOR").build().unwrap();
+        let config = format!("{}validation_pool = true\n", CONFIG)
+            .replace("[Default]", "[Default]\nvalidation_pool_size = 2");
+        let (app, mut events) = TestApplication::new();
+        let acceptor = SocketAcceptor::new(Properties::from_str(&config), app);
+        let (_acceptor, addr) = run_acceptor(acceptor);
+        let mut initiator = TestInitiator::connect(addr, CONFIG, &session_id).await;
+        initiator.send_admin(admin::logon).await;
+        assert_eq!(initiator.recv().await.get_msg_type().unwrap(), "A");
+
+        // messages parsed off the session task still reach the application in order
+        let cl_ord_ids: Vec<String> = (0..20).map(|i| format!("ORDER{}", i)).collect();
+        for cl_ord_id in &cl_ord_ids {
+            let mut order = initiator.session().new_message("D").unwrap();
+            order.set_field(StringField::new(11, cl_ord_id));
+            initiator.send(order).await;
+        }
+        for cl_ord_id in &cl_ord_ids {
+            match recv_event(&mut events).await {
+                AppEvent::FromApp(_, msg) => {
+                    assert_eq!(&msg.get_field::<String>(11).unwrap(), cl_ord_id)
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+    }
+}
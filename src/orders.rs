@@ -0,0 +1,478 @@
+use crate::field_values::OrdStatus;
+use crate::fields::{
+    AvgPx, ClOrdID, CumQty, ExecType, LeavesQty, OrderID, OrderQty, OrigClOrdID, Price, Side,
+    Symbol,
+};
+use crate::message::Message;
+use crate::session::SessionId;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+// done orders kept for lookups unless set otherwise, the oldest go first
+pub const DEFAULT_MAX_DONE_ORDERS: usize = 10_000;
+
+type ChangeHandler = Box<dyn Fn(&SessionId, &OrderState) + Send + Sync>;
+
+// Cancel or replace sent for an order and not answered yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PendingRequest {
+    Cancel {
+        cl_ord_id: String,
+    },
+    Replace {
+        cl_ord_id: String,
+        order_qty: Option<String>,
+        price: Option<String>,
+    },
+}
+
+impl PendingRequest {
+    pub fn cl_ord_id(&self) -> &str {
+        match self {
+            PendingRequest::Cancel { cl_ord_id } | PendingRequest::Replace { cl_ord_id, .. } => {
+                cl_ord_id
+            }
+        }
+    }
+}
+
+// Where an order stands as far as the messages of its session tell. Quantities and prices are
+// kept as they were on the wire.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderState {
+    // ClOrdID(11) of the order now, after any accepted cancel or replace
+    pub cl_ord_id: String,
+    // ClOrdIDs it had before, oldest first
+    pub orig_cl_ord_ids: Vec<String>,
+    pub order_id: Option<String>,
+    pub symbol: Option<String>,
+    pub side: Option<String>,
+    pub order_qty: Option<String>,
+    pub price: Option<String>,
+    pub cum_qty: Option<String>,
+    pub leaves_qty: Option<String>,
+    pub avg_px: Option<String>,
+    // None until the first ExecutionReport
+    pub status: Option<OrdStatus>,
+    pub pending: Option<PendingRequest>,
+}
+
+impl OrderState {
+    pub fn is_open(&self) -> bool {
+        !matches!(
+            self.status,
+            Some(
+                OrdStatus::Filled
+                    | OrdStatus::Canceled
+                    | OrdStatus::Rejected
+                    | OrdStatus::DoneForDay
+                    | OrdStatus::Expired
+            )
+        )
+    }
+
+    fn update(&mut self, msg: &Message) {
+        let set = |field: &mut Option<String>, tag| {
+            if let Ok(value) = msg.get_field::<String>(tag) {
+                *field = Some(value);
+            }
+        };
+        set(&mut self.order_id, OrderID::field());
+        set(&mut self.symbol, Symbol::field());
+        set(&mut self.side, Side::field());
+        set(&mut self.order_qty, OrderQty::field());
+        set(&mut self.price, Price::field());
+        set(&mut self.cum_qty, CumQty::field());
+        set(&mut self.leaves_qty, LeavesQty::field());
+        set(&mut self.avg_px, AvgPx::field());
+        if let Ok(status) = msg.get_field::<OrdStatus>(OrdStatus::field()) {
+            self.status = Some(status);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Orders {
+    // by the order they were first seen in
+    states: BTreeMap<u64, (SessionId, OrderState)>,
+    next_idx: u64,
+    // every ClOrdID of an order's chain, and of its pending request, leads to it
+    by_cl_ord_id: HashMap<(SessionId, String), u64>,
+    // orders in the order they were done, evicted from the front
+    done: VecDeque<u64>,
+}
+
+impl Orders {
+    fn find(&self, session_id: &SessionId, cl_ord_ids: &[Option<String>]) -> Option<u64> {
+        cl_ord_ids.iter().flatten().find_map(|cl_ord_id| {
+            self.by_cl_ord_id.get(&(session_id.clone(), cl_ord_id.clone())).copied()
+        })
+    }
+
+    fn link(&mut self, session_id: &SessionId, cl_ord_id: &str, idx: u64) {
+        self.by_cl_ord_id.insert((session_id.clone(), cl_ord_id.to_string()), idx);
+    }
+
+    fn state(&mut self, idx: u64) -> &mut OrderState {
+        &mut self.states.get_mut(&idx).expect("linked order is kept").1
+    }
+
+    fn evict_done(&mut self, max_done: usize) {
+        // an order opened again since it was done is left, it goes when done again
+        while self.done.len() > max_done {
+            let Some(idx) = self.done.pop_front() else {
+                break;
+            };
+            if self.states.get(&idx).is_none_or(|(_, order)| order.is_open()) {
+                continue;
+            }
+            self.states.remove(&idx);
+            self.by_cl_ord_id.retain(|_, linked| *linked != idx);
+        }
+    }
+}
+
+// Follows orders through the NewOrderSingle(D), OrderCancelRequest(F),
+// OrderCancelReplaceRequest(G), ExecutionReport(8) and OrderCancelReject(9) messages of its
+// sessions, whichever side sends them. An order is found by any ClOrdID of its chain. The
+// engine feeds it every application message sent or received once it is set with
+// SocketAcceptor::with_order_tracker, and the change handler is called with the new state of
+// an order after each message that touched it. For sent messages that is while the session is
+// held, so the handler must not send on it. Done orders are kept up to max_done_orders, the
+// oldest are forgotten beyond that.
+//
+//     let mut tracker = OrderTracker::new();
+//     tracker.on_change(|sid, order| println!("{} {:?}", order.cl_ord_id, order.status));
+//     let tracker = Arc::new(tracker);
+//     let acceptor = SocketAcceptor::new(settings, app).with_order_tracker(Arc::clone(&tracker));
+//     // later, e.g. in Application::from_app
+//     let open = tracker.open_orders(ctx.session_id());
+pub struct OrderTracker {
+    orders: Mutex<Orders>,
+    on_change: Option<ChangeHandler>,
+    max_done_orders: usize,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self {
+            orders: Mutex::default(),
+            on_change: None,
+            max_done_orders: DEFAULT_MAX_DONE_ORDERS,
+        }
+    }
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_done_orders(&mut self, max_done_orders: usize) -> &mut Self {
+        self.max_done_orders = max_done_orders;
+        self
+    }
+
+    pub fn on_change(
+        &mut self, handler: impl Fn(&SessionId, &OrderState) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_change = Some(Box::new(handler));
+        self
+    }
+
+    pub fn observe(&self, session_id: &SessionId, msg: &Message) {
+        let changed = {
+            let mut orders = self.orders.lock().unwrap();
+            let changed = Self::apply(&mut orders, session_id, msg);
+            orders.evict_done(self.max_done_orders);
+            changed
+        };
+        // the lock is released, the handler may look up other orders
+        if let (Some(order), Some(handler)) = (changed, &self.on_change) {
+            handler(session_id, &order);
+        }
+    }
+
+    pub fn order(&self, session_id: &SessionId, cl_ord_id: &str) -> Option<OrderState> {
+        let orders = self.orders.lock().unwrap();
+        let idx = orders.find(session_id, &[Some(cl_ord_id.to_string())])?;
+        orders.states.get(&idx).map(|(_, order)| order.clone())
+    }
+
+    pub fn orders(&self, session_id: &SessionId) -> Vec<OrderState> {
+        let orders = self.orders.lock().unwrap();
+        orders
+            .states
+            .values()
+            .filter(|(sid, _)| sid == session_id)
+            .map(|(_, order)| order.clone())
+            .collect()
+    }
+
+    pub fn open_orders(&self, session_id: &SessionId) -> Vec<OrderState> {
+        let mut orders = self.orders(session_id);
+        orders.retain(OrderState::is_open);
+        orders
+    }
+
+    fn apply(orders: &mut Orders, session_id: &SessionId, msg: &Message) -> Option<OrderState> {
+        let cl_ord_id = msg.get_field::<String>(ClOrdID::field()).ok();
+        let orig_cl_ord_id = msg.get_field::<String>(OrigClOrdID::field()).ok();
+        let idx = match msg.get_msg_type().ok()?.as_str() {
+            "D" => {
+                let cl_ord_id = cl_ord_id?;
+                let mut order = OrderState {
+                    cl_ord_id: cl_ord_id.clone(),
+                    ..Default::default()
+                };
+                order.update(msg);
+                let idx = orders.next_idx;
+                orders.next_idx += 1;
+                orders.states.insert(idx, (session_id.clone(), order));
+                orders.link(session_id, &cl_ord_id, idx);
+                idx
+            }
+            msg_type @ ("F" | "G") => {
+                let (cl_ord_id, idx) = (cl_ord_id?, orders.find(session_id, &[orig_cl_ord_id])?);
+                orders.link(session_id, &cl_ord_id, idx);
+                orders.state(idx).pending = Some(match msg_type {
+                    "F" => PendingRequest::Cancel { cl_ord_id },
+                    _ => PendingRequest::Replace {
+                        cl_ord_id,
+                        order_qty: msg.get_field(OrderQty::field()).ok(),
+                        price: msg.get_field(Price::field()).ok(),
+                    },
+                });
+                idx
+            }
+            "8" => {
+                let idx = orders.find(session_id, &[cl_ord_id.clone(), orig_cl_ord_id])?;
+                let order = orders.state(idx);
+                let exec_type = msg.get_field::<String>(ExecType::field()).unwrap_or_default();
+                let answered = order
+                    .pending
+                    .as_ref()
+                    .is_some_and(|pending| cl_ord_id.as_deref() == Some(pending.cl_ord_id()));
+                match exec_type.as_str() {
+                    // the request was accepted, the order goes by its ClOrdID from now on
+                    "4" | "5" if answered => {
+                        if let Some(PendingRequest::Replace {
+                            order_qty, price, ..
+                        }) = order.pending.take()
+                        {
+                            order.order_qty = order_qty.or(order.order_qty.take());
+                            order.price = price.or(order.price.take());
+                        }
+                        let new_cl_ord_id = cl_ord_id.clone().unwrap_or_default();
+                        let old = std::mem::replace(&mut order.cl_ord_id, new_cl_ord_id);
+                        order.orig_cl_ord_ids.push(old);
+                    }
+                    "8" if answered => order.pending = None,
+                    _ => {}
+                }
+                order.update(msg);
+                if let Some(cl_ord_id) = cl_ord_id {
+                    orders.link(session_id, &cl_ord_id, idx);
+                }
+                idx
+            }
+            "9" => {
+                let idx = orders.find(session_id, &[orig_cl_ord_id, cl_ord_id])?;
+                let order = orders.state(idx);
+                order.pending = None;
+                if let Ok(status) = msg.get_field::<OrdStatus>(OrdStatus::field()) {
+                    order.status = Some(status);
+                }
+                idx
+            }
+            _ => return None,
+        };
+        let order = orders.state(idx).clone();
+        if !order.is_open() && !orders.done.contains(&idx) {
+            orders.done.push_back(idx);
+        }
+        Some(order)
+    }
+}
+
+impl std::fmt::Debug for OrderTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OrderTracker")
+    }
+}
+
+#[cfg(test)]
+mod orders_tests {
+    use super::*;
+    use crate::message::StringField;
+    use crate::network::SessionMap;
+    use crate::session::{Properties, Session, SessionIdBuilder};
+    use std::sync::Arc;
+    use tokio::sync::mpsc::channel;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10118
+    "#;
+
+    fn message(session: &Session, msg_type: &str, fields: &[(u32, &str)]) -> Message {
+        let mut msg = session.new_message(msg_type).unwrap();
+        for (tag, value) in fields {
+            msg.set_field(StringField::new(*tag, value));
+        }
+        msg
+    }
+
+    #[test]
+    fn test_cl_ord_id_chain() {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, _rx) = channel::<String>(16);
+        session.set_responder(Some(tx));
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&changes);
+        let mut tracker = OrderTracker::new();
+        tracker.on_change(move |_, order| {
+            seen.lock().unwrap().push((order.cl_ord_id.clone(), order.status))
+        });
+        let tracker = Arc::new(tracker);
+        session.set_order_tracker(Some(Arc::clone(&tracker)));
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        // orders sent by the session, execution reports received on it
+        let send = |msg_type, fields: &[(u32, &str)]| {
+            let msg = message(&sessions.get_session(&session_id).unwrap(), msg_type, fields);
+            Session::sync_send_to_target(&session_id, &sessions, msg).unwrap();
+        };
+        let receive = |fields: &[(u32, &str)]| {
+            let msg = message(&sessions.get_session(&session_id).unwrap(), "8", fields);
+            Session::next_app(&session_id, &sessions, &msg);
+        };
+
+        send(
+            "D",
+            &[
+                (11, "A1"),
+                (55, "IBM"),
+                (54, "1"),
+                (38, "100"),
+                (44, "10.5"),
+            ],
+        );
+        receive(&[
+            (11, "A1"),
+            (37, "O1"),
+            (150, "0"),
+            (39, "0"),
+            (14, "0"),
+            (151, "100"),
+        ]);
+        send(
+            "G",
+            &[
+                (11, "A2"),
+                (41, "A1"),
+                (55, "IBM"),
+                (54, "1"),
+                (38, "200"),
+                (44, "10.4"),
+            ],
+        );
+        let order = tracker.order(&session_id, "A2").unwrap();
+        assert_eq!(order.cl_ord_id, "A1");
+        assert!(matches!(order.pending, Some(PendingRequest::Replace { .. })));
+        receive(&[
+            (11, "A2"),
+            (41, "A1"),
+            (37, "O1"),
+            (150, "5"),
+            (39, "5"),
+            (151, "200"),
+        ]);
+        receive(&[
+            (11, "A2"),
+            (37, "O1"),
+            (150, "1"),
+            (39, "1"),
+            (14, "50"),
+            (6, "10.4"),
+        ]);
+
+        let order = tracker.order(&session_id, "A1").unwrap();
+        assert_eq!(order.cl_ord_id, "A2");
+        assert_eq!(order.orig_cl_ord_ids, vec!["A1".to_string()]);
+        assert_eq!(order.order_id.as_deref(), Some("O1"));
+        assert_eq!(order.order_qty.as_deref(), Some("200"));
+        assert_eq!(order.price.as_deref(), Some("10.4"));
+        assert_eq!(order.cum_qty.as_deref(), Some("50"));
+        assert_eq!(order.pending, None);
+        assert_eq!(tracker.open_orders(&session_id).len(), 1);
+
+        // a refused cancel leaves the order as it was
+        send("F", &[(11, "A3"), (41, "A2"), (55, "IBM"), (54, "1")]);
+        let reject = message(
+            &sessions.get_session(&session_id).unwrap(),
+            "9",
+            &[(11, "A3"), (41, "A2"), (37, "O1"), (39, "1"), (434, "1")],
+        );
+        Session::next_app(&session_id, &sessions, &reject);
+        let order = tracker.order(&session_id, "A3").unwrap();
+        assert_eq!((order.cl_ord_id.as_str(), order.pending), ("A2", None));
+
+        send("F", &[(11, "A4"), (41, "A2"), (55, "IBM"), (54, "1")]);
+        receive(&[
+            (11, "A4"),
+            (41, "A2"),
+            (37, "O1"),
+            (150, "4"),
+            (39, "4"),
+            (151, "0"),
+        ]);
+        let order = tracker.order(&session_id, "A2").unwrap();
+        assert_eq!(order.cl_ord_id, "A4");
+        assert_eq!(order.orig_cl_ord_ids, vec!["A1".to_string(), "A2".to_string()]);
+        assert_eq!(order.status, Some(OrdStatus::Canceled));
+        assert!(tracker.open_orders(&session_id).is_empty());
+        assert_eq!(tracker.orders(&session_id).len(), 1);
+
+        // orders of other sessions are kept apart
+        let other = SessionIdBuilder::new("FIX.4.3", "SENDER", "OTHER").build().unwrap();
+        assert!(tracker.order(&other, "A1").is_none());
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 9);
+        assert_eq!(changes[3], ("A2".to_string(), Some(OrdStatus::Replaced)));
+    }
+
+    #[test]
+    fn test_done_orders_evicted() {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let session = Session::with_settings(&session_id, &settings).unwrap();
+        let mut tracker = OrderTracker::new();
+        tracker.max_done_orders(2);
+        let order = |cl_ord_id| message(&session, "D", &[(11, cl_ord_id), (38, "100")]);
+        let filled = |cl_ord_id| message(&session, "8", &[(11, cl_ord_id), (39, "2")]);
+        for cl_ord_id in ["A1", "A2", "A3", "A4"] {
+            tracker.observe(&session_id, &order(cl_ord_id));
+        }
+        for cl_ord_id in ["A1", "A2", "A3"] {
+            tracker.observe(&session_id, &filled(cl_ord_id));
+        }
+        // the oldest done order goes, open ones stay whatever their number
+        assert!(tracker.order(&session_id, "A1").is_none());
+        let kept: Vec<_> =
+            tracker.orders(&session_id).into_iter().map(|order| order.cl_ord_id).collect();
+        assert_eq!(kept, ["A2", "A3", "A4"]);
+        assert_eq!(tracker.open_orders(&session_id).len(), 1);
+        tracker.observe(&session_id, &filled("A4"));
+        assert!(tracker.order(&session_id, "A2").is_none());
+        assert_eq!(tracker.orders(&session_id).len(), 2);
+    }
+}
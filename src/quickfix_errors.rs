@@ -1,10 +1,21 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::num::ParseIntError;
 
 #[derive(Debug, thiserror::Error)]
-#[error("Session Level Reject Reason - {:?}", .kind)]
+#[error(
+    "Session Level Reject Reason - {:?}{}{}{}",
+    .kind,
+    .tag.map(|tag| format!(" for tag {}", tag)).unwrap_or_default(),
+    .group.map(|(tag, idx)| format!(" in instance {} of group {}", idx + 1, tag)).unwrap_or_default(),
+    .position.as_ref().map(|(offset, field)| format!(" at byte {} ({:?})", offset, field)).unwrap_or_default()
+)]
 pub struct SessionRejectError {
     kind: SessionRejectReason,
-    // tag: Option<String>,
+    // field the reject is about, sent back as RefTagID(371)
+    tag: Option<u32>,
+    // NumInGroup tag and index of the group instance the tag is missing from or wrong in
+    group: Option<(u32, usize)>,
+    // byte offset of the offending field in the raw message, and the field as it was there
+    position: Option<(usize, String)>,
     // value: Option<String>,
     // pub source: Option<Box<dyn Error>>,
 }
@@ -14,12 +25,18 @@ impl SessionRejectError {
         // tag not parsed properly
         SessionRejectError {
             kind: SessionRejectReason::InvalidTag,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn required_tag_missing_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::RequiredTagMissing,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
@@ -27,110 +44,239 @@ impl SessionRejectError {
         // tag not defined in Xml
         SessionRejectError {
             kind: SessionRejectReason::UndefinedTag,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn tag_without_value_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::TagSpecifiedWithoutValue,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn value_out_of_range_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::ValueOutOfRange,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn incorrect_data_format_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::IncorrectDataFormatForValue,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn decryption_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::DecryptionProblem,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn signature_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::SignatureProblem,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn comp_id_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::CompIdProblem,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn sending_time_accuracy_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::SendingTimeAccuracyProblem,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn invalid_msg_type_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::InvalidMessageType,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn invalid_body_len_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::InvalidBodyLength,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn invalid_checksum() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::InvalidChecksum,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn tag_not_defined_for_msg() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::TagNotDefinedForMsgType,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn xml_validation_err() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::XmlValidationError,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn tag_appear_more_than_once() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::TagAppearsMoreThanOnce,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn tag_specified_out_of_order() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::TagSpecifiedOutOfOrder,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn repeating_grp_out_of_order() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::RepeatingGroupsOutOfOrder,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn incorrect_num_in_grp_count() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::IncorrectNumInGroupCountForRepeatingGroup,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
 
     pub fn non_data_field_contains_soh() -> Self {
         SessionRejectError {
             kind: SessionRejectReason::NonDataFieldIncludeSOHChar,
+            tag: None,
+            group: None,
+            position: None,
         }
     }
+
+    pub fn with_tag(mut self, tag: u32) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    pub fn with_group_instance(mut self, group_tag: u32, index: usize) -> Self {
+        self.group = Some((group_tag, index));
+        self
+    }
+
+    pub fn with_position(mut self, offset: usize, field: &str) -> Self {
+        self.position = Some((offset, field.to_string()));
+        self
+    }
+
+    pub(crate) fn locate_in(self, raw: &str) -> Self {
+        // position of the first field with the error's tag, when the parser did not know it. a
+        // missing tag has none, and within groups the first one may be in another instance
+        let locatable = !matches!(self.kind, SessionRejectReason::RequiredTagMissing);
+        match (self.tag, &self.position) {
+            (Some(tag), None) if locatable && self.group.is_none() => {
+                match crate::message::find_field(raw, tag) {
+                    Some((offset, field)) => self.with_position(offset, field),
+                    None => self,
+                }
+            }
+            _ => self,
+        }
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        self.position.as_ref().map(|(offset, _)| *offset)
+    }
+
+    pub fn raw_field(&self) -> Option<&str> {
+        self.position.as_ref().map(|(_, field)| field.as_str())
+    }
+
+    pub fn group_instance(&self) -> Option<(u32, usize)> {
+        self.group
+    }
+
+    pub fn ref_tag_id(&self) -> Option<u32> {
+        self.tag
+    }
+
+    pub fn reject_reason(&self) -> Option<u32> {
+        // value of SessionRejectReason(373) for this error. body length and checksum errors
+        // are garbled messages which are ignored rather than rejected, hence no reason code
+        let reason = match self.kind {
+            SessionRejectReason::InvalidTag => 0,
+            SessionRejectReason::RequiredTagMissing => 1,
+            SessionRejectReason::TagNotDefinedForMsgType => 2,
+            SessionRejectReason::UndefinedTag => 3,
+            SessionRejectReason::TagSpecifiedWithoutValue => 4,
+            SessionRejectReason::ValueOutOfRange => 5,
+            SessionRejectReason::IncorrectDataFormatForValue => 6,
+            SessionRejectReason::DecryptionProblem => 7,
+            SessionRejectReason::SignatureProblem => 8,
+            SessionRejectReason::CompIdProblem => 9,
+            SessionRejectReason::SendingTimeAccuracyProblem => 10,
+            SessionRejectReason::InvalidMessageType => 11,
+            SessionRejectReason::XmlValidationError => 12,
+            SessionRejectReason::TagAppearsMoreThanOnce => 13,
+            SessionRejectReason::TagSpecifiedOutOfOrder => 14,
+            SessionRejectReason::RepeatingGroupsOutOfOrder => 15,
+            SessionRejectReason::IncorrectNumInGroupCountForRepeatingGroup => 16,
+            SessionRejectReason::NonDataFieldIncludeSOHChar => 17,
+            SessionRejectReason::InvalidBodyLength | SessionRejectReason::InvalidChecksum => {
+                return None
+            }
+        };
+        Some(reason)
+    }
 }
 
 #[derive(Debug)]
@@ -173,6 +319,12 @@ enum SessionRejectReason {
 
 #[derive(Debug, thiserror::Error)]
 pub enum XmlError {
+    #[error("Could not read the document: {}", .0)]
+    DocumentNotRead(#[from] std::io::Error),
+    #[error("Document is not valid {}", .0)]
+    InvalidEncoding(&'static str),
+    #[error("Unsupported document encoding {}", .0)]
+    UnsupportedEncoding(String),
     #[error("Could not parse the document")]
     DocumentNotParsed(#[from] roxmltree::Error),
     #[error("Node {} not found", .0)]
@@ -192,9 +344,79 @@ pub enum XmlError {
     UnknownXmlTag(String),
 }
 
+// field value does not have the format of its FIX type
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid {fix_type} value {value:?}")]
+pub struct InvalidFieldValue {
+    fix_type: &'static str,
+    value: String,
+}
+
+impl InvalidFieldValue {
+    pub fn new(fix_type: &'static str, value: &str) -> Self {
+        Self {
+            fix_type,
+            value: value.to_string(),
+        }
+    }
+}
+
+// garbled messages are dropped without reject or seqnum increment
+#[derive(Debug, thiserror::Error)]
 pub enum InvalidMessage {
+    #[error("Field does not have '=' delimiter")]
     FieldDoesNotHaveDelimiter,
+    #[error("Field is not terminated by SOH")]
     MessageDoesNotHaveSOH,
+    #[error("Required header tag {} missing", .0)]
+    HeaderTagMissing(u32),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("Session {} not found", .0)]
+    SessionNotFound(String),
+    #[error("Session is not connected")]
+    Disconnected,
+    #[error("Send queue is full")]
+    QueueFull,
+    #[error("Message store error - {}", .0)]
+    Store(#[from] std::io::Error),
+    // an admin message the session could not build, e.g. for a dictionary without it
+    #[error("Message could not be built - {}", .0)]
+    Build(#[from] SessionRejectError),
+}
+
+// why a standby stopped following its primary, see replication::follow_primary
+#[derive(Debug, thiserror::Error)]
+pub enum FollowError {
+    // the stream ended before a whole snapshot came in, or the primary was never reached
+    #[error("No snapshot from the primary - {}", .0)]
+    Unsynced(std::io::Error),
+    // the primary dropped the standby for falling behind and it could not get a new snapshot
+    #[error("Fell behind the primary by {} changes", .0)]
+    Lagged(u64),
+    // the stream of a standby that was up to date ended
+    #[error("Primary is gone - {}", .0)]
+    PrimaryGone(std::io::Error),
+}
+
+impl FollowError {
+    // only a standby that was up to date has the primary's seqnums, the others would take over
+    // with stale ones
+    pub fn can_take_over(&self) -> bool {
+        matches!(self, FollowError::PrimaryGone(_))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    #[error("Request not sent - {}", .0)]
+    Send(#[from] SendError),
+    #[error("No response within {:?}", .0)]
+    Timeout(std::time::Duration),
+    #[error("Session was reset before the response arrived")]
+    Canceled,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -204,3 +426,31 @@ pub enum ConfigErr<'a> {
     #[error("Could not parse - {}", .0)]
     ParseError(String),
 }
+
+// where and why an acceptance test script failed, by line of the script
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Line {line}: {reason}")]
+    Syntax { line: usize, reason: String },
+    #[error("Line {line}: connection {connection} - {source}")]
+    Io {
+        line: usize,
+        connection: usize,
+        source: std::io::Error,
+    },
+    #[error("Line {line}: nothing received within {timeout:?}")]
+    Timeout {
+        line: usize,
+        timeout: std::time::Duration,
+    },
+    #[error("Line {line}: expected {expected} but received {received}")]
+    Mismatch {
+        line: usize,
+        expected: String,
+        received: String,
+    },
+    #[error("Line {line}: connection {connection} was closed")]
+    Disconnected { line: usize, connection: usize },
+    #[error("Line {line}: connection {connection} is still open")]
+    NotDisconnected { line: usize, connection: usize },
+}
@@ -0,0 +1,718 @@
+// Warm standby. A primary engine started with SocketAcceptor::with_replication streams every
+// change to its sessions' message stores to the standby engines connected to its replication
+// address. A standby applies them to the stores of its sessions with the same ids, so that
+// once the primary is gone it takes over with the primary's seqnums and can answer resend
+// requests for what the primary sent.
+//
+//     let mut standby = SocketAcceptor::new(settings, app);
+//     let stopped = standby.follow_primary(primary_replication_addr).await;
+//     println!("stopped following the primary: {}", stopped);
+//     if stopped.can_take_over() {
+//         standby.take_over();
+//         standby.start_accepting_connections()?;
+//     }
+//
+// A standby connecting gets a snapshot of every store first, then the changes made after it.
+// One falling behind by more than REPLICATION_BACKLOG changes is told so and disconnected, it
+// connects again for a new snapshot. Storing a sent message waits until a standby has applied
+// it, for up to REPLICATION_ACK_TIMEOUT, so that a standby taking over does not miss what the
+// primary sent last. On a current_thread runtime it does not wait, the standby's task could not
+// run meanwhile.
+use crate::message::store::StoredMessage;
+use crate::message::store::{recover_seq_nums, MessageCursor, MessageStore, SharedMessageStore};
+use crate::network::SessionMap;
+use crate::quickfix_errors::FollowError;
+use crate::session::SessionId;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Once};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::broadcast;
+
+// changes kept for standbys that are behind
+pub const REPLICATION_BACKLOG: usize = 4096;
+// longest a sent message waits for a standby to apply it
+pub const REPLICATION_ACK_TIMEOUT: Duration = Duration::from_secs(1);
+// seqnums of stored messages read at a time, the store is held while they are
+const READ_CHUNK: u64 = 256;
+
+// lines of the stream that are not records: a fence follows what a standby needs to be up to
+// date with the changes numbered below it, lagged tells it how many changes it missed. the
+// standby answers a fence with acked once it has applied what came before
+const FENCE: &str = "fence";
+const LAGGED: &str = "lagged";
+const ACKED: &str = "acked";
+
+fn control_line(kind: &str, number: u64) -> Vec<u8> {
+    format!("{}\t{}\n", kind, number).into_bytes()
+}
+
+fn control_number(line: &[u8], kind: &str) -> Option<u64> {
+    let line = std::str::from_utf8(line).ok()?;
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_prefix(kind)?.strip_prefix('\t')?.parse().ok()
+}
+
+// One change to a session's message store, sent to standbys as a line of
+// "session<TAB>kind<TAB>values". Messages never contain a newline, see FileStore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationRecord {
+    Stored(u64, StoredMessage),
+    SeqNums(u64, u64),
+    Received(u64),
+    Queued(String),
+    Dequeued(usize),
+    Purged(u64),
+    Reset,
+}
+
+impl ReplicationRecord {
+    pub fn apply(&self, store: &mut dyn MessageStore) -> io::Result<()> {
+        match self {
+            ReplicationRecord::Stored(seq_num, raw) => store.set(*seq_num, Arc::clone(raw)),
+            ReplicationRecord::SeqNums(sender, target) => store.set_seq_nums(*sender, *target),
+            ReplicationRecord::Received(seq_num) => store.set_received(*seq_num),
+            ReplicationRecord::Queued(raw) => store.enqueue(raw),
+            ReplicationRecord::Dequeued(count) => store.dequeue(*count),
+            ReplicationRecord::Purged(through) => store.purge(*through),
+            ReplicationRecord::Reset => store.reset(),
+        }
+    }
+
+    pub fn encode(&self, session_id: &SessionId) -> Vec<u8> {
+        let mut line = format!("{}\t", session_id.key()).into_bytes();
+        match self {
+            ReplicationRecord::Stored(seq_num, raw) => {
+                line.extend_from_slice(format!("stored\t{}\t", seq_num).as_bytes());
+                line.extend_from_slice(raw);
+            }
+            ReplicationRecord::SeqNums(sender, target) => {
+                line.extend_from_slice(format!("seqnums\t{}\t{}", sender, target).as_bytes())
+            }
+            ReplicationRecord::Received(seq_num) => {
+                line.extend_from_slice(format!("received\t{}", seq_num).as_bytes())
+            }
+            ReplicationRecord::Queued(raw) => {
+                line.extend_from_slice(format!("queued\t{}", raw).as_bytes())
+            }
+            ReplicationRecord::Dequeued(count) => {
+                line.extend_from_slice(format!("dequeued\t{}", count).as_bytes())
+            }
+            ReplicationRecord::Purged(through) => {
+                line.extend_from_slice(format!("purged\t{}", through).as_bytes())
+            }
+            ReplicationRecord::Reset => line.extend_from_slice(b"reset"),
+        }
+        line.push(b'\n');
+        line
+    }
+
+    // session id as it is displayed and the record, None for a line that is not one
+    pub fn decode(line: &[u8]) -> Option<(&str, ReplicationRecord)> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let mut parts = line.splitn(3, |b| *b == b'\t');
+        let session_id = std::str::from_utf8(parts.next()?).ok()?;
+        let kind = parts.next()?;
+        let values = parts.next().unwrap_or_default();
+        let text = || std::str::from_utf8(values).ok();
+        let number = || text()?.parse::<u64>().ok();
+        let record = match kind {
+            b"stored" => {
+                let tab = values.iter().position(|b| *b == b'\t')?;
+                let seq_num = std::str::from_utf8(&values[..tab]).ok()?.parse().ok()?;
+                ReplicationRecord::Stored(seq_num, values[tab + 1..].into())
+            }
+            b"seqnums" => {
+                let (sender, target) = text()?.split_once('\t')?;
+                ReplicationRecord::SeqNums(sender.parse().ok()?, target.parse().ok()?)
+            }
+            b"received" => ReplicationRecord::Received(number()?),
+            b"queued" => ReplicationRecord::Queued(text()?.to_string()),
+            b"dequeued" => ReplicationRecord::Dequeued(number()? as usize),
+            b"purged" => ReplicationRecord::Purged(number()?),
+            b"reset" => ReplicationRecord::Reset,
+            _ => return None,
+        };
+        Some((session_id, record))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Change {
+    // in the order the changes were made, to tell those in a snapshot from those after it
+    number: u64,
+    session_id: SessionId,
+    line: Arc<[u8]>,
+}
+
+// changes of the primary's stores, on their way to the standbys
+#[derive(Debug, Clone)]
+pub struct ReplicationFeed {
+    tx: broadcast::Sender<Change>,
+    changes: Arc<AtomicU64>,
+    // changes numbered below it are applied by a standby
+    acked: Arc<(Mutex<u64>, Condvar)>,
+    // standbys that have applied their snapshot and are still connected
+    standbys: Arc<AtomicUsize>,
+}
+
+impl Default for ReplicationFeed {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(REPLICATION_BACKLOG);
+        Self {
+            tx,
+            changes: Arc::default(),
+            acked: Arc::default(),
+            standbys: Arc::default(),
+        }
+    }
+}
+
+// counted in ReplicationFeed::standbys while it lives
+struct Following(Arc<AtomicUsize>);
+
+impl Following {
+    fn new(standbys: &Arc<AtomicUsize>) -> Self {
+        standbys.fetch_add(1, Ordering::SeqCst);
+        Self(Arc::clone(standbys))
+    }
+}
+
+impl Drop for Following {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ReplicationFeed {
+    fn publish(&self, session_id: &SessionId, record: ReplicationRecord) -> u64 {
+        // numbered while the store is held, like the snapshots are taken
+        let number = self.changes.fetch_add(1, Ordering::SeqCst);
+        let line = record.encode(session_id).into();
+        // no standby connected is not an error
+        let _ = self.tx.send(Change {
+            number,
+            session_id: session_id.clone(),
+            line,
+        });
+        number
+    }
+
+    fn ack(&self, applied: u64) {
+        let (acked, acks) = &*self.acked;
+        let mut acked = acked.lock().unwrap();
+        *acked = (*acked).max(applied);
+        acks.notify_all();
+    }
+
+    // blocks until a standby has applied the change numbered number, unless none is following
+    // or none does within REPLICATION_ACK_TIMEOUT. not on a current_thread runtime, the ack
+    // would only come once the wait is over
+    fn wait_acked(&self, number: u64) {
+        if self.standbys.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let flavor = Handle::try_current().map(|runtime| runtime.runtime_flavor());
+        if flavor.as_ref().is_ok_and(|flavor| *flavor != RuntimeFlavor::MultiThread) {
+            static UNACKED: Once = Once::new();
+            UNACKED.call_once(|| {
+                println!("not waiting for standby acks on a runtime other than multi-thread")
+            });
+            return;
+        }
+        let wait = || {
+            let (acked, acks) = &*self.acked;
+            let acked = acked.lock().unwrap();
+            let (_acked, waited) = acks
+                .wait_timeout_while(acked, REPLICATION_ACK_TIMEOUT, |acked| *acked <= number)
+                .unwrap();
+            if waited.timed_out() {
+                println!("no standby applied change {} in time", number);
+            }
+        };
+        match flavor {
+            // the other tasks of the worker, the standby's among them, go on meanwhile
+            Ok(_) => tokio::task::block_in_place(wait),
+            Err(_) => wait(),
+        }
+    }
+
+    // session's store is a ReplicatingStore of this feed
+    pub fn replicate(
+        &self, session_id: &SessionId, store: SharedMessageStore,
+    ) -> SharedMessageStore {
+        SharedMessageStore::new(ReplicatingStore {
+            inner: store,
+            session_id: session_id.clone(),
+            feed: self.clone(),
+        })
+    }
+
+    // accepts standbys on addr, returns the address actually listened on
+    pub fn start(
+        &self, runtime: &Handle, addr: SocketAddr, sessions: SessionMap,
+    ) -> io::Result<SocketAddr> {
+        let std_listener = std::net::TcpListener::bind(addr)?;
+        std_listener.set_nonblocking(true)?;
+        let _guard = runtime.enter();
+        let listener = TcpListener::from_std(std_listener)?;
+        let local_addr = listener.local_addr()?;
+        println!("replicating on {}", local_addr);
+        let feed = self.clone();
+        runtime.spawn(async move {
+            loop {
+                let (stream, standby) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        println!("Error in accepting standby: {:?}", e);
+                        continue;
+                    }
+                };
+                println!("standby {} connected", standby);
+                let feed = feed.clone();
+                let sessions = sessions.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = feed.serve_standby(stream, &sessions).await {
+                        println!("standby {} disconnected: {}", standby, e);
+                    }
+                });
+            }
+        });
+        Ok(local_addr)
+    }
+
+    async fn serve_standby(&self, stream: TcpStream, sessions: &SessionMap) -> io::Result<()> {
+        // subscribed before the snapshots are taken, so that nothing falls in between
+        let mut changes = self.tx.subscribe();
+        let (acks, out) = stream.into_split();
+        let mut acks = BufReader::new(acks).lines();
+        let mut out = BufWriter::new(out);
+        let mut snapshot_at = HashMap::new();
+        for session_id in sessions.session_ids() {
+            let Some(store) = sessions.get_session(&session_id).map(|s| s.message_store().clone())
+            else {
+                continue;
+            };
+            let mut snapshot = Vec::new();
+            {
+                let store = store.lock();
+                snapshot_at.insert(session_id.clone(), self.changes.load(Ordering::SeqCst));
+                write_snapshot(&session_id, &*store, &mut snapshot)?;
+            }
+            out.write_all(&snapshot).await?;
+        }
+        // changes below the first snapshot are in the snapshots, the later ones follow
+        let synced = snapshot_at.values().min().copied();
+        let synced = synced.unwrap_or_else(|| self.changes.load(Ordering::SeqCst));
+        out.write_all(&control_line(FENCE, synced)).await?;
+        out.flush().await?;
+        let mut following = None;
+        loop {
+            let change = tokio::select! {
+                change = changes.recv() => change,
+                ack = acks.next_line() => {
+                    let ack = ack?.ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "standby closed the stream")
+                    })?;
+                    if let Some(applied) = control_number(ack.as_bytes(), ACKED) {
+                        self.ack(applied);
+                        following.get_or_insert_with(|| Following::new(&self.standbys));
+                    }
+                    continue;
+                }
+            };
+            let change = match change {
+                Ok(change) => change,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    // so that it does not take the disconnect for the primary going away
+                    out.write_all(&control_line(LAGGED, missed)).await?;
+                    out.flush().await?;
+                    return Err(io::Error::other(format!("fell behind by {} changes", missed)));
+                }
+            };
+            // those before the snapshot are in it already
+            if snapshot_at.get(&change.session_id).is_none_or(|at| change.number >= *at) {
+                out.write_all(&change.line).await?;
+            }
+            if changes.is_empty() {
+                out.write_all(&control_line(FENCE, change.number + 1)).await?;
+                out.flush().await?;
+            }
+        }
+    }
+}
+
+fn write_snapshot(
+    session_id: &SessionId, store: &dyn MessageStore, out: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut write = |record: ReplicationRecord| out.extend(record.encode(session_id));
+    write(ReplicationRecord::Reset);
+    if let Some((sender, target)) = store.seq_nums() {
+        write(ReplicationRecord::SeqNums(sender, target));
+    }
+    for stored in store.iter_range(1, u64::MAX) {
+        let (seq_num, raw) = stored?;
+        write(ReplicationRecord::Stored(seq_num, raw));
+    }
+    for seq_num in store.received() {
+        write(ReplicationRecord::Received(seq_num));
+    }
+    for raw in store.queued()? {
+        write(ReplicationRecord::Queued(raw));
+    }
+    Ok(())
+}
+
+// Session's store on the primary: changes go to the store it wraps, and those that succeed to
+// the standbys
+#[derive(Debug)]
+pub struct ReplicatingStore {
+    inner: SharedMessageStore,
+    session_id: SessionId,
+    feed: ReplicationFeed,
+}
+
+impl ReplicatingStore {
+    fn replicate<T>(&self, result: io::Result<T>, record: ReplicationRecord) -> io::Result<T> {
+        if result.is_ok() {
+            self.feed.publish(&self.session_id, record);
+        }
+        result
+    }
+}
+
+impl MessageStore for ReplicatingStore {
+    fn set(&mut self, seq_num: u64, raw: StoredMessage) -> io::Result<()> {
+        self.inner.lock().set(seq_num, Arc::clone(&raw))?;
+        let number = self.feed.publish(&self.session_id, ReplicationRecord::Stored(seq_num, raw));
+        self.feed.wait_acked(number);
+        Ok(())
+    }
+
+    fn iter_range(&self, begin: u64, end: u64) -> MessageCursor<'_> {
+        // the wrapped store is held while a chunk of the range is read, not while it is used
+        let end = end.min(self.inner.lock().last_stored().unwrap_or_default());
+        let mut next = Some(begin).filter(|begin| *begin <= end);
+        let chunks = std::iter::from_fn(move || {
+            let begin = next?;
+            let chunk_end = begin.saturating_add(READ_CHUNK - 1).min(end);
+            next = chunk_end.checked_add(1).filter(|next| *next <= end);
+            Some(match self.inner.lock().get_range(begin, chunk_end) {
+                Ok(chunk) => chunk.into_iter().map(Ok).collect(),
+                Err(e) => {
+                    next = None;
+                    vec![Err(e)]
+                }
+            })
+        });
+        MessageCursor::new(chunks.flatten())
+    }
+
+    fn enqueue(&mut self, raw: &str) -> io::Result<()> {
+        let result = self.inner.lock().enqueue(raw);
+        self.replicate(result, ReplicationRecord::Queued(raw.to_string()))
+    }
+
+    fn queued(&self) -> io::Result<Vec<String>> {
+        self.inner.lock().queued()
+    }
+
+    fn dequeue(&mut self, count: usize) -> io::Result<()> {
+        let result = self.inner.lock().dequeue(count);
+        self.replicate(result, ReplicationRecord::Dequeued(count))
+    }
+
+    fn set_received(&mut self, seq_num: u64) -> io::Result<()> {
+        let result = self.inner.lock().set_received(seq_num);
+        self.replicate(result, ReplicationRecord::Received(seq_num))
+    }
+
+    fn is_received(&self, seq_num: u64) -> bool {
+        self.inner.lock().is_received(seq_num)
+    }
+
+    fn received(&self) -> Vec<u64> {
+        self.inner.lock().received()
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        let result = self.inner.lock().reset();
+        self.replicate(result, ReplicationRecord::Reset)
+    }
+
+    fn stored_count(&self) -> usize {
+        self.inner.lock().stored_count()
+    }
+
+    fn purge(&mut self, through: u64) -> io::Result<()> {
+        let result = self.inner.lock().purge(through);
+        self.replicate(result, ReplicationRecord::Purged(through))
+    }
+
+    fn seq_nums(&self) -> Option<(u64, u64)> {
+        self.inner.lock().seq_nums()
+    }
+
+    fn set_seq_nums(&mut self, next_sender: u64, next_target: u64) -> io::Result<()> {
+        let result = self.inner.lock().set_seq_nums(next_sender, next_target);
+        self.replicate(result, ReplicationRecord::SeqNums(next_sender, next_target))
+    }
+
+    fn last_stored(&self) -> Option<u64> {
+        self.inner.lock().last_stored()
+    }
+
+    fn last_received(&self) -> Option<u64> {
+        self.inner.lock().last_received()
+    }
+}
+
+// Applies the replication stream of the primary at addr to the stores of the sessions, until
+// the stream ends. Changes for sessions the standby does not have are skipped. A standby the
+// primary drops for falling behind connects again for a new snapshot, the error it stops
+// with tells whether it is up to date, see FollowError::can_take_over.
+pub async fn follow_primary(primary: SocketAddr, sessions: &SessionMap) -> FollowError {
+    let session_ids: HashMap<String, SessionId> =
+        sessions.session_ids().into_iter().map(|sid| (sid.key(), sid)).collect();
+    let mut lagged = None;
+    loop {
+        let ended = match TcpStream::connect(primary).await {
+            Ok(stream) => follow_stream(stream, sessions, &session_ids).await,
+            Err(e) => StreamEnd::Closed(e, false),
+        };
+        match ended {
+            StreamEnd::Lagged(missed) => {
+                println!("fell behind the primary by {} changes, connecting again", missed);
+                lagged = Some(missed);
+            }
+            StreamEnd::Closed(e, true) => return FollowError::PrimaryGone(e),
+            StreamEnd::Closed(e, false) => {
+                return lagged.map_or(FollowError::Unsynced(e), FollowError::Lagged)
+            }
+        }
+    }
+}
+
+enum StreamEnd {
+    // the primary dropped the standby, having missed this many changes
+    Lagged(u64),
+    // the stream ended, after a whole snapshot came in if set
+    Closed(io::Error, bool),
+}
+
+async fn follow_stream(
+    stream: TcpStream, sessions: &SessionMap, session_ids: &HashMap<String, SessionId>,
+) -> StreamEnd {
+    let (reader, mut acks) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    let mut synced = false;
+    loop {
+        line.clear();
+        // a line without newline was cut short by the primary going away
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(_) if line.ends_with(b"\n") => {}
+            Ok(_) => {
+                let closed = io::Error::new(io::ErrorKind::UnexpectedEof, "stream closed");
+                return StreamEnd::Closed(closed, synced);
+            }
+            Err(e) => return StreamEnd::Closed(e, synced),
+        }
+        if let Some(fence) = control_number(&line, FENCE) {
+            synced = true;
+            // a stream that is gone shows on the reading side
+            let _ = acks.write_all(&control_line(ACKED, fence)).await;
+            continue;
+        }
+        if let Some(missed) = control_number(&line, LAGGED) {
+            return StreamEnd::Lagged(missed);
+        }
+        let Some((session_id, record)) = ReplicationRecord::decode(&line) else {
+            println!("unknown replication record {}", String::from_utf8_lossy(&line));
+            continue;
+        };
+        let Some(session_id) = session_ids.get(session_id) else {
+            continue;
+        };
+        let Some(store) = sessions.get_session(session_id).map(|s| s.message_store().clone())
+        else {
+            continue;
+        };
+        let applied = record.apply(&mut *store.lock());
+        if let Err(e) = applied {
+            println!("could not replicate {:?} for {}: {}", record, session_id, e);
+        }
+    }
+}
+
+// Sessions of a standby go on from where the replicated stores say the primary was, like a
+// restarted primary would, see recover_seq_nums
+pub fn take_over(sessions: &SessionMap) {
+    for session_id in sessions.session_ids() {
+        if let Some(mut session) = sessions.get_session_mut(&session_id) {
+            let (next_sender, next_target) = recover_seq_nums(&*session.message_store().lock());
+            session
+                .state_mut()
+                .set_next_sender_msg_seq_num(next_sender)
+                .set_next_target_msg_seq_num(next_target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod replication_tests {
+    use super::*;
+    use crate::message::store::MemoryStore;
+    use crate::session::SessionIdBuilder;
+
+    #[test]
+    fn test_records() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let records = [
+            ReplicationRecord::Stored(2, b"8=FIX.4.3\x0135=D\x01"[..].into()),
+            ReplicationRecord::SeqNums(3, 5),
+            ReplicationRecord::Received(4),
+            ReplicationRecord::Queued("35=D\x0111=ORDER1\x01".into()),
+            ReplicationRecord::Dequeued(1),
+            ReplicationRecord::Purged(1),
+            ReplicationRecord::Reset,
+        ];
+        for record in records {
+            let line = record.encode(&session_id);
+            let sid = session_id.to_string();
+            assert_eq!(ReplicationRecord::decode(&line), Some((sid.as_str(), record)));
+        }
+        // a qualified session's records are not taken for the plain one's
+        let orders = "FIX.4.3:SENDER->TARGET:orders".parse::<SessionId>().unwrap();
+        let line = ReplicationRecord::Reset.encode(&orders);
+        let decoded = ReplicationRecord::decode(&line);
+        assert_eq!(decoded, Some(("FIX.4.3:SENDER->TARGET:orders", ReplicationRecord::Reset)));
+        assert_eq!(decoded.unwrap().0.parse(), Ok(orders));
+        assert_eq!(ReplicationRecord::decode(b"FIX.4.3:A->B\tunknown\t1\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_replicating_store() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let feed = ReplicationFeed::default();
+        let mut changes = feed.tx.subscribe();
+        let primary = SharedMessageStore::default();
+        let store = feed.replicate(&session_id, primary.clone());
+        store.lock().set(1, b"35=A\x01"[..].into()).unwrap();
+        store.lock().set_received(1).unwrap();
+        store.lock().set_seq_nums(2, 2).unwrap();
+        assert_eq!(primary.lock().get_range(1, 1).unwrap().len(), 1);
+
+        let mut standby = MemoryStore::new();
+        while let Ok(change) = changes.try_recv() {
+            let (_, record) = ReplicationRecord::decode(&change.line).unwrap();
+            record.apply(&mut standby).unwrap();
+        }
+        assert_eq!(recover_seq_nums(&standby), (2, 2));
+        assert_eq!(standby.get_range(1, 1).unwrap(), store.lock().get_range(1, 1).unwrap());
+        assert!(standby.is_received(1));
+    }
+
+    #[test]
+    fn test_read_in_chunks() {
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let store = ReplicatingStore {
+            inner: SharedMessageStore::default(),
+            session_id: session_id.clone(),
+            feed: ReplicationFeed::default(),
+        };
+        let mut inner = store.inner.lock();
+        for seq_num in (1..=600).chain(1000..=1010) {
+            inner.set(seq_num, format!("34={}\x01", seq_num).as_bytes().into()).unwrap();
+        }
+        inner.set_received(3).unwrap();
+        inner.set_received(7).unwrap();
+        drop(inner);
+        let seq_nums = |begin, end| {
+            let range = store.iter_range(begin, end).map(|stored| stored.unwrap().0);
+            range.collect::<Vec<_>>()
+        };
+        assert_eq!(seq_nums(1, u64::MAX).len(), 611);
+        assert_eq!(seq_nums(250, 520), (250..=520).collect::<Vec<_>>());
+        assert_eq!(seq_nums(590, 1001), [(590..=600).collect(), vec![1000, 1001]].concat());
+        assert!(seq_nums(1011, u64::MAX).is_empty());
+
+        let mut snapshot = Vec::new();
+        write_snapshot(&session_id, &store, &mut snapshot).unwrap();
+        let received: Vec<ReplicationRecord> = snapshot
+            .split_inclusive(|b| *b == b'\n')
+            .filter_map(|line| ReplicationRecord::decode(line).map(|(_, record)| record))
+            .filter(|record| matches!(record, ReplicationRecord::Received(_)))
+            .collect();
+        assert_eq!(
+            received,
+            [
+                ReplicationRecord::Received(3),
+                ReplicationRecord::Received(7)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wait_acked() {
+        let feed = ReplicationFeed::default();
+        // nothing to wait for while no standby follows
+        feed.wait_acked(5);
+        let _following = Following::new(&feed.standbys);
+        let standby = feed.clone();
+        let acking = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            standby.ack(5);
+            std::thread::sleep(Duration::from_millis(20));
+            standby.ack(6);
+        });
+        let started = std::time::Instant::now();
+        feed.wait_acked(5);
+        assert!(started.elapsed() < REPLICATION_ACK_TIMEOUT);
+        assert_eq!(*feed.acked.0.lock().unwrap(), 6);
+        acking.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_current_thread_not_waiting() {
+        // the standby's ack could not come in while the only thread waits for it
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let feed = ReplicationFeed::default();
+        let _following = Following::new(&feed.standbys);
+        let store = feed.replicate(&session_id, SharedMessageStore::default());
+        let started = std::time::Instant::now();
+        store.lock().set(1, b"35=A\x01"[..].into()).unwrap();
+        assert!(started.elapsed() < REPLICATION_ACK_TIMEOUT / 2);
+    }
+
+    #[tokio::test]
+    async fn test_follow_primary_stops() {
+        // what a primary sends on the connections, one after the other
+        async fn follow(streams: &'static [&'static str]) -> FollowError {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let primary = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                for stream in streams {
+                    let (mut standby, _) = listener.accept().await.unwrap();
+                    standby.write_all(stream.as_bytes()).await.unwrap();
+                }
+            });
+            follow_primary(primary, &SessionMap::from_iter([])).await
+        }
+
+        assert!(matches!(follow(&["fence\t0\n"]).await, FollowError::PrimaryGone(_)));
+        assert!(matches!(follow(&[""]).await, FollowError::Unsynced(_)));
+        // dropped for falling behind, a new snapshot brings it up to date again
+        let stopped = follow(&["fence\t0\nlagged\t5\n", "fence\t7\n"]).await;
+        assert!(stopped.can_take_over());
+        let stopped = follow(&["fence\t0\nlagged\t5\n"]).await;
+        assert!(matches!(stopped, FollowError::Lagged(5)));
+        assert!(!stopped.can_take_over());
+    }
+}
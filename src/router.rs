@@ -0,0 +1,209 @@
+use crate::message::*;
+use crate::network::SessionMap;
+use crate::session::{SessionId, SessionIdPattern};
+
+type Handler = Box<dyn Fn(&SessionId, &SessionMap, Message) + Send + Sync>;
+type Predicate = Box<dyn Fn(&Message) -> bool + Send + Sync>;
+
+struct Route {
+    msg_type: String,
+    predicate: Option<Predicate>,
+    sessions: Option<SessionIdPattern>,
+    handler: Handler,
+}
+
+// Dispatches application messages to handlers registered by MsgType(35), optionally narrowed
+// by a predicate on the message or to the sessions matching a pattern. Routes are tried in the
+// order they were registered and the first match handles the message, so specific routes go
+// before the catch-all for a msg type.
+//
+//     let mut router = MessageRouter::new();
+//     router
+//         .on::<NewOrderSingle>(|sid, sessions, order| { .. })
+//         .on_msg_type_where("8", |msg| msg.get_field::<String>(150).as_deref() == Ok("F"), fill)
+//         .on_msg_type_for("FIX.4.3:BROKER*->*".parse()?, "8", broker_execution)
+//         .on_msg_type("8", other_execution);
+//     // in Application::from_app
+//     router.dispatch(ctx.session_id(), ctx.sessions(), msg);
+#[derive(Default)]
+pub struct MessageRouter {
+    routes: Vec<Route>,
+}
+
+impl MessageRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on<M: TypedMessage>(
+        &mut self, handler: impl Fn(&SessionId, &SessionMap, M) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_route(M::MSG_TYPE, None, None, move |sid, sessions, msg| {
+            handler(sid, sessions, M::from_message(msg))
+        })
+    }
+
+    pub fn on_msg_type(
+        &mut self, msg_type: &str,
+        handler: impl Fn(&SessionId, &SessionMap, Message) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_route(msg_type, None, None, handler)
+    }
+
+    pub fn on_msg_type_where(
+        &mut self, msg_type: &str, predicate: impl Fn(&Message) -> bool + Send + Sync + 'static,
+        handler: impl Fn(&SessionId, &SessionMap, Message) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_route(msg_type, Some(Box::new(predicate)), None, handler)
+    }
+
+    pub fn on_msg_type_for(
+        &mut self, sessions: SessionIdPattern, msg_type: &str,
+        handler: impl Fn(&SessionId, &SessionMap, Message) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_route(msg_type, None, Some(sessions), handler)
+    }
+
+    fn add_route(
+        &mut self, msg_type: &str, predicate: Option<Predicate>,
+        sessions: Option<SessionIdPattern>,
+        handler: impl Fn(&SessionId, &SessionMap, Message) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.routes.push(Route {
+            msg_type: msg_type.to_string(),
+            predicate,
+            sessions,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    pub fn dispatch(&self, session_id: &SessionId, sessions: &SessionMap, msg: Message) -> bool {
+        // false if no route matched, the message is unsupported by the application
+        let msg_type = match msg.get_msg_type() {
+            Ok(msg_type) => msg_type,
+            Err(_) => return false,
+        };
+        let route = self.routes.iter().find(|route| {
+            route.msg_type == msg_type
+                && route.sessions.as_ref().is_none_or(|pattern| pattern.matches(session_id))
+                && route.predicate.as_ref().is_none_or(|p| p(&msg))
+        });
+        match route {
+            Some(route) => {
+                (route.handler)(session_id, sessions, msg);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for MessageRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let routes: Vec<&str> = self.routes.iter().map(|route| route.msg_type.as_str()).collect();
+        f.debug_struct("MessageRouter").field("routes", &routes).finish()
+    }
+}
+
+#[cfg(test)]
+mod router_tests {
+    use super::*;
+    use crate::messages::{MessageCracker, NewOrderSingle, OrderCancelRequest};
+    use crate::session::SessionIdBuilder;
+    use std::sync::{Arc, Mutex};
+
+    fn message(msg_type: &str, fields: &[(u32, &str)]) -> Message {
+        let mut msg = Message::new();
+        msg.header_mut().set_msg_type(msg_type);
+        for (tag, value) in fields {
+            msg.set_field(StringField::new(*tag, value));
+        }
+        msg
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let mut router = MessageRouter::new();
+        let (h1, h2, h3) = (Arc::clone(&handled), Arc::clone(&handled), Arc::clone(&handled));
+        router
+            .on::<NewOrderSingle>(move |_, _, order| {
+                let cl_ord_id = order.get_field::<String>(11).unwrap();
+                h1.lock().unwrap().push(format!("order {}", cl_ord_id));
+            })
+            .on_msg_type_where(
+                "8",
+                |msg| msg.get_field::<String>(150).as_deref() == Ok("F"),
+                move |_, _, _| h2.lock().unwrap().push("fill".to_string()),
+            )
+            .on_msg_type("8", move |_, _, _| h3.lock().unwrap().push("execution".to_string()));
+
+        let sid = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let sessions = SessionMap::default();
+        assert!(router.dispatch(&sid, &sessions, message("D", &[(11, "ORDER1")])));
+        assert!(router.dispatch(&sid, &sessions, message("8", &[(150, "F")])));
+        assert!(router.dispatch(&sid, &sessions, message("8", &[(150, "0")])));
+        assert!(!router.dispatch(&sid, &sessions, message("F", &[])));
+        assert!(!router.dispatch(&sid, &sessions, Message::new()));
+        assert_eq!(*handled.lock().unwrap(), vec!["order ORDER1", "fill", "execution"]);
+    }
+
+    #[test]
+    fn test_dispatch_by_session() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let mut router = MessageRouter::new();
+        let (h1, h2) = (Arc::clone(&handled), Arc::clone(&handled));
+        router
+            .on_msg_type_for("FIX.4.3:BROKER*->*".parse().unwrap(), "8", move |sid, _, _| {
+                h1.lock().unwrap().push(format!("broker {}", sid.sender_compid()))
+            })
+            .on_msg_type("8", move |sid, _, _| {
+                h2.lock().unwrap().push(format!("other {}", sid.sender_compid()))
+            });
+
+        let sessions = SessionMap::default();
+        for sender in ["BROKER_EU", "DESK", "BROKER"] {
+            let sid = SessionIdBuilder::new("FIX.4.3", sender, "TARGET").build().unwrap();
+            assert!(router.dispatch(&sid, &sessions, message("8", &[])));
+        }
+        assert_eq!(
+            *handled.lock().unwrap(),
+            vec!["broker BROKER_EU", "other DESK", "broker BROKER"]
+        );
+    }
+
+    #[derive(Default)]
+    struct OrderHandler {
+        handled: Mutex<Vec<String>>,
+    }
+
+    impl MessageCracker for OrderHandler {
+        fn on_new_order_single(
+            &self, _session_id: &SessionId, _sessions: &SessionMap, msg: NewOrderSingle,
+        ) -> bool {
+            self.handled.lock().unwrap().push(msg.get_field::<String>(11).unwrap());
+            true
+        }
+
+        fn on_order_cancel_request(
+            &self, _session_id: &SessionId, _sessions: &SessionMap, msg: OrderCancelRequest,
+        ) -> bool {
+            self.handled.lock().unwrap().push(format!("cancel {}", msg.get_msg_type().unwrap()));
+            true
+        }
+    }
+
+    #[test]
+    fn test_message_cracker() {
+        let handler = OrderHandler::default();
+        let sid = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let sessions = SessionMap::default();
+        assert!(handler.crack(&sid, &sessions, message("D", &[(11, "ORDER1")])));
+        assert!(handler.crack(&sid, &sessions, message("F", &[])));
+        // known to the dictionary but not implemented by the handler
+        assert!(!handler.crack(&sid, &sessions, message("G", &[])));
+        assert!(!handler.crack(&sid, &sessions, message("ZZ", &[])));
+        assert_eq!(*handler.handled.lock().unwrap(), vec!["ORDER1", "cancel F"]);
+    }
+}
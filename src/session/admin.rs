@@ -0,0 +1,235 @@
+use crate::fields::*;
+use crate::message::*;
+use crate::quickfix_errors::SessionRejectError;
+use crate::session::Session;
+
+type SessResult<T> = Result<T, SessionRejectError>;
+
+// Constructors for session level (admin) messages. Every message gets the session's comp ids,
+// the next outgoing MsgSeqNum and SendingTime in the header, required body fields for its
+// msg type, and body length and checksum computed so that it is ready to be sent.
+
+pub fn logon(session: &Session) -> SessResult<Message> {
+    let mut msg = new_admin_message(session, "A")?;
+    // no encryption
+    msg.set_field(StringField::new(EncryptMethod::field(), "0"));
+    msg.set_field(StringField::new(HeartBtInt::field(), &session.heartbeat_intrvl().to_string()));
+    if session.reset_on_logon() {
+        msg.set_field(StringField::new(ResetSeqNumFlag::field(), "Y"));
+    }
+    if let Some(username) = session.logon_username() {
+        msg.set_field(StringField::new(Username::field(), username));
+    }
+    if let Some(password) = session.logon_password() {
+        msg.set_field(StringField::new(Password::field(), password));
+    }
+    Ok(finalize(msg))
+}
+
+pub fn logout(session: &Session, text: Option<&str>) -> SessResult<Message> {
+    let mut msg = new_admin_message(session, "5")?;
+    if let Some(reason) = text {
+        msg.set_field(StringField::new(Text::field(), reason));
+    }
+    Ok(finalize(msg))
+}
+
+pub fn heartbeat(session: &Session, test_req_id: Option<&str>) -> SessResult<Message> {
+    // test_req_id is echoed back when heartbeat is sent in response to a TestRequest
+    let mut msg = new_admin_message(session, "0")?;
+    if let Some(req_id) = test_req_id {
+        msg.set_field(StringField::new(TestReqID::field(), req_id));
+    }
+    Ok(finalize(msg))
+}
+
+pub fn test_request(session: &Session, test_req_id: &str) -> SessResult<Message> {
+    let mut msg = new_admin_message(session, "1")?;
+    msg.set_field(StringField::new(TestReqID::field(), test_req_id));
+    Ok(finalize(msg))
+}
+
+pub fn resend_request(
+    session: &Session, begin_seq_no: u64, end_seq_no: u64,
+) -> SessResult<Message> {
+    // end_seq_no of 0 means all messages after begin_seq_no
+    let mut msg = new_admin_message(session, "2")?;
+    msg.set_field(StringField::new(BeginSeqNo::field(), &begin_seq_no.to_string()));
+    msg.set_field(StringField::new(EndSeqNo::field(), &end_seq_no.to_string()));
+    Ok(finalize(msg))
+}
+
+pub fn reject(
+    session: &Session, ref_seq_num: u64, ref_msg_type: Option<&str>, err: &SessionRejectError,
+) -> SessResult<Message> {
+    // answer to an inbound message that does not parse or validate, see
+    // Session::reject_inbound. RefTagID and SessionRejectReason come from the error
+    let mut msg = new_admin_message(session, "3")?;
+    msg.set_field(StringField::new(RefSeqNum::field(), &ref_seq_num.to_string()));
+    if let Some(msg_type) = ref_msg_type {
+        msg.set_field(StringField::new(RefMsgType::field(), msg_type));
+    }
+    if let Some(tag) = err.ref_tag_id() {
+        msg.set_field(StringField::new(RefTagID::field(), &tag.to_string()));
+    }
+    if let Some(reason) = err.reject_reason() {
+        msg.set_field(StringField::new(SessionRejectReason::field(), &reason.to_string()));
+    }
+    msg.set_field(StringField::new(Text::field(), &err.to_string()));
+    Ok(finalize(msg))
+}
+
+pub fn sequence_reset(session: &Session, new_seq_no: u64, gap_fill: bool) -> SessResult<Message> {
+    let mut msg = new_admin_message(session, "4")?;
+    if gap_fill {
+        msg.set_field(StringField::new(GapFillFlag::field(), "Y"));
+    }
+    msg.set_field(StringField::new(NewSeqNo::field(), &new_seq_no.to_string()));
+    Ok(finalize(msg))
+}
+
+fn new_admin_message(session: &Session, msg_type: &str) -> SessResult<Message> {
+    let mut msg = session.new_message(msg_type)?;
+    let seq_num = session.state().next_sender_msg_seq_num();
+    msg.header_mut().set_seq_num(seq_num);
+    session.stamp_last_processed(msg.header_mut());
+    msg.set_sending_time(session.clock());
+    Ok(msg)
+}
+
+fn finalize(mut msg: Message) -> Message {
+    msg.set_body_len();
+    msg.set_checksum();
+    msg
+}
+
+#[cfg(test)]
+mod admin_tests {
+    use super::*;
+    use crate::session::{Properties, SessionIdBuilder};
+    use lazy_static::lazy_static;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        heartbeat_interval = 45
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10117
+    "#;
+
+    lazy_static! {
+        static ref SESSION: Session = {
+            let settings = Properties::from_str(CONFIG);
+            let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+            Session::with_settings(&session_id, &settings).unwrap()
+        };
+    }
+
+    fn assert_admin_header(msg: &Message, msg_type: &str) {
+        // header is complete and message survives a round trip through the parser
+        let header = msg.header();
+        assert_eq!(header.get_field::<String>(8).unwrap(), "FIX.4.3");
+        assert_eq!(header.get_field::<String>(35).unwrap(), msg_type);
+        assert_eq!(header.get_field::<String>(49).unwrap(), "SENDER");
+        assert_eq!(header.get_field::<String>(56).unwrap(), "TARGET");
+        assert_eq!(header.get_field::<u64>(34).unwrap(), 1);
+        assert!(header.get_field::<String>(52).is_ok());
+        assert!(header.get_field::<u32>(9).is_ok());
+        assert!(msg.trailer().get_field::<String>(10).is_ok());
+        let parsed = Message::from_str(&msg.to_string(), SESSION.data_dictionary());
+        assert!(parsed.is_ok());
+    }
+
+    #[test]
+    fn test_logon() {
+        let msg = logon(&SESSION).unwrap();
+        assert_admin_header(&msg, "A");
+        assert_eq!(msg.get_field::<u32>(98).unwrap(), 0);
+        assert_eq!(msg.get_field::<u32>(108).unwrap(), 45);
+        // reset_on_logon defaults to true
+        assert_eq!(msg.get_field::<String>(141).unwrap(), "Y");
+        assert!(msg.get_field::<String>(553).is_err());
+        assert!(msg.get_field::<String>(554).is_err());
+    }
+
+    #[test]
+    fn test_logon_credentials() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "TARGET"
+            socket_accept_port = 10117
+            logon_username = "trader"
+            logon_password = "secret"
+        "#;
+        let settings = Properties::from_str(config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let session = Session::with_settings(&session_id, &settings).unwrap();
+        let msg = logon(&session).unwrap();
+        assert_eq!(msg.get_field::<String>(553).unwrap(), "trader");
+        assert_eq!(msg.get_field::<String>(554).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_logout() {
+        let msg = logout(&SESSION, Some("end of day")).unwrap();
+        assert_admin_header(&msg, "5");
+        assert_eq!(msg.get_field::<String>(58).unwrap(), "end of day");
+        let msg = logout(&SESSION, None).unwrap();
+        assert!(msg.get_field::<String>(58).is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_and_test_request() {
+        let msg = heartbeat(&SESSION, None).unwrap();
+        assert_admin_header(&msg, "0");
+        assert!(msg.get_field::<String>(112).is_err());
+        let msg = heartbeat(&SESSION, Some("TEST1")).unwrap();
+        assert_eq!(msg.get_field::<String>(112).unwrap(), "TEST1");
+        let msg = test_request(&SESSION, "TEST2").unwrap();
+        assert_admin_header(&msg, "1");
+        assert_eq!(msg.get_field::<String>(112).unwrap(), "TEST2");
+    }
+
+    #[test]
+    fn test_resend_request() {
+        let msg = resend_request(&SESSION, 5, 0).unwrap();
+        assert_admin_header(&msg, "2");
+        assert_eq!(msg.get_field::<u64>(7).unwrap(), 5);
+        assert_eq!(msg.get_field::<u64>(16).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reject() {
+        let err = SessionRejectError::required_tag_missing_err();
+        let msg = reject(&SESSION, 7, Some("D"), &err).unwrap();
+        assert_admin_header(&msg, "3");
+        assert_eq!(msg.get_field::<u64>(45).unwrap(), 7);
+        assert_eq!(msg.get_field::<String>(372).unwrap(), "D");
+        assert_eq!(msg.get_field::<u32>(373).unwrap(), 1);
+        assert!(msg.get_field::<String>(58).is_ok());
+        assert!(msg.get_field::<u32>(371).is_err());
+        let err = SessionRejectError::incorrect_data_format_err().with_tag(44);
+        let msg = reject(&SESSION, 7, Some("D"), &err).unwrap();
+        assert_eq!(msg.get_field::<u32>(371).unwrap(), 44);
+        assert_eq!(msg.get_field::<u32>(373).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_sequence_reset() {
+        let msg = sequence_reset(&SESSION, 10, true).unwrap();
+        assert_admin_header(&msg, "4");
+        assert_eq!(msg.get_field::<String>(123).unwrap(), "Y");
+        assert_eq!(msg.get_field::<u64>(36).unwrap(), 10);
+        let msg = sequence_reset(&SESSION, 10, false).unwrap();
+        assert!(msg.get_field::<String>(123).is_err());
+    }
+}
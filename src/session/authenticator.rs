@@ -0,0 +1,64 @@
+use crate::message::Message;
+use crate::session::SessionId;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// outcome of checking an inbound Logon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogonDecision {
+    Accept,
+    // answered with a Logout carrying the reason as Text(58), the connection stays open
+    Reject(String),
+    // connection is dropped without answering
+    Disconnect,
+}
+
+// Checks an inbound Logon before the session answers it. It is given the whole Logon so that
+// deployments can look at any field (tokens, RawData, ...) and the address of the peer.
+// Closures with the same signature implement it as well.
+//
+//     let acceptor = SocketAcceptor::new(settings, app).with_authenticator(
+//         |sid: &SessionId, logon: &Message, source: SocketAddr| {
+//             if source.ip().is_loopback() { LogonDecision::Accept } else { LogonDecision::Disconnect }
+//         },
+//     );
+pub trait Authenticator: Send + Sync {
+    fn authenticate(
+        &self, session_id: &SessionId, logon: &Message, source: SocketAddr,
+    ) -> LogonDecision;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(&SessionId, &Message, SocketAddr) -> LogonDecision + Send + Sync,
+{
+    fn authenticate(
+        &self, session_id: &SessionId, logon: &Message, source: SocketAddr,
+    ) -> LogonDecision {
+        self(session_id, logon, source)
+    }
+}
+
+// shared handle to an authenticator, so that holders can still derive Debug
+#[derive(Clone)]
+pub struct SharedAuthenticator(Arc<dyn Authenticator>);
+
+impl SharedAuthenticator {
+    pub fn new(authenticator: impl Authenticator + 'static) -> Self {
+        Self(Arc::new(authenticator))
+    }
+}
+
+impl std::ops::Deref for SharedAuthenticator {
+    type Target = dyn Authenticator;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for SharedAuthenticator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedAuthenticator")
+    }
+}
@@ -0,0 +1,200 @@
+use crate::session::SessionId;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+// why an outbound message was given up on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    // queued while the session was down for longer than queue_max_age
+    QueueExpired,
+    // queued or scheduled when the session was reset
+    SessionReset,
+    // a scheduled send that could not go out
+    SendFailed(String),
+    // a queued message that no longer parses, e.g. after a dictionary change
+    Unparsable(String),
+    // handed to a connection that closed before writing it. it is still in the message store
+    // and goes out again if the counterparty asks for a resend
+    Unwritten,
+}
+
+impl fmt::Display for DeadLetterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeadLetterReason::QueueExpired => f.write_str("queue expired"),
+            DeadLetterReason::SessionReset => f.write_str("session reset"),
+            DeadLetterReason::SendFailed(e) => write!(f, "send failed: {}", e),
+            DeadLetterReason::Unparsable(e) => write!(f, "could not be parsed: {}", e),
+            DeadLetterReason::Unwritten => f.write_str("connection closed before it was written"),
+        }
+    }
+}
+
+// Outbound message the engine could not deliver. raw is the message as it was queued or
+// scheduled, without the MsgSeqNum it would have gone out with, unless it is Unwritten.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub session_id: SessionId,
+    pub raw: String,
+    pub reason: DeadLetterReason,
+    pub time: DateTime<Utc>,
+}
+
+// Where undeliverable outbound messages go instead of being dropped. Closures with the same
+// signature implement it as well. Sessions without a sink print them, or append them to the
+// file dead_letter_path names. It is called while the session is held, so it must not look
+// the session up itself.
+//
+//     let acceptor = SocketAcceptor::new(settings, app).with_dead_letter_sink(
+//         |letter: &DeadLetter| alert(&letter.session_id, &letter.reason, &letter.raw),
+//     );
+pub trait DeadLetterSink: Send + Sync {
+    fn dead_letter(&self, letter: &DeadLetter);
+}
+
+impl<F> DeadLetterSink for F
+where
+    F: Fn(&DeadLetter) + Send + Sync,
+{
+    fn dead_letter(&self, letter: &DeadLetter) {
+        self(letter)
+    }
+}
+
+// shared handle to a sink, so that holders can still derive Debug
+#[derive(Clone)]
+pub struct SharedDeadLetterSink(Arc<dyn DeadLetterSink>);
+
+impl SharedDeadLetterSink {
+    pub fn new(sink: impl DeadLetterSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+}
+
+impl Default for SharedDeadLetterSink {
+    fn default() -> Self {
+        Self::new(|letter: &DeadLetter| {
+            println!(
+                "dead letter for {} ({}): {}",
+                letter.session_id,
+                letter.reason,
+                letter.raw.replace('\x01', "|")
+            );
+        })
+    }
+}
+
+impl std::ops::Deref for SharedDeadLetterSink {
+    type Target = dyn DeadLetterSink;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for SharedDeadLetterSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedDeadLetterSink")
+    }
+}
+
+// Appends a line per dead letter: time, session id, reason and the message, separated by tabs
+pub struct FileDeadLetterSink {
+    file: Mutex<File>,
+}
+
+impl FileDeadLetterSink {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    // the sink of the file at path, opened once however many sessions name it, so that their
+    // lines don't interleave
+    pub fn shared<P: AsRef<Path>>(path: P) -> io::Result<SharedDeadLetterSink> {
+        static OPEN: OnceLock<Mutex<HashMap<PathBuf, Weak<FileDeadLetterSink>>>> = OnceLock::new();
+        let mut open = OPEN.get_or_init(Mutex::default).lock().unwrap();
+        // the file is created first, the path only canonicalizes once it exists
+        let sink = FileDeadLetterSink::open(&path)?;
+        let path = path.as_ref().canonicalize()?;
+        open.retain(|_, sink| sink.strong_count() > 0);
+        let sink = match open.get(&path).and_then(Weak::upgrade) {
+            Some(sink) => sink,
+            None => {
+                let sink = Arc::new(sink);
+                open.insert(path, Arc::downgrade(&sink));
+                sink
+            }
+        };
+        Ok(SharedDeadLetterSink(sink))
+    }
+}
+
+impl DeadLetterSink for FileDeadLetterSink {
+    fn dead_letter(&self, letter: &DeadLetter) {
+        let line = format!(
+            "{}\t{}\t{}\t{}\n",
+            letter.time.to_rfc3339(),
+            letter.session_id,
+            letter.reason,
+            letter.raw
+        );
+        let written = self.file.lock().unwrap().write_all(line.as_bytes());
+        if let Err(e) = written {
+            println!("could not write dead letter for {}: {}", letter.session_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod dead_letter_tests {
+    use super::*;
+    use crate::session::SessionIdBuilder;
+
+    #[test]
+    fn test_file_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead_letters");
+        let sink = FileDeadLetterSink::open(&path).unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let time = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        for reason in [
+            DeadLetterReason::QueueExpired,
+            DeadLetterReason::SendFailed("gone".into()),
+        ] {
+            let raw = "35=D\x0111=ORDER1\x01".to_string();
+            sink.dead_letter(&DeadLetter {
+                session_id: session_id.clone(),
+                raw,
+                reason,
+                time,
+            });
+        }
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let fields: Vec<&str> = lines[1].split('\t').collect();
+        assert_eq!(fields[0], "2023-11-14T22:13:20+00:00");
+        assert_eq!(fields[1], session_id.to_string());
+        assert_eq!((fields[2], fields[3]), ("send failed: gone", "35=D\x0111=ORDER1\x01"));
+    }
+
+    #[test]
+    fn test_shared_file_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead_letters");
+        let first = FileDeadLetterSink::shared(&path).unwrap();
+        let second = FileDeadLetterSink::shared(dir.path().join(".").join("dead_letters")).unwrap();
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+        let other = FileDeadLetterSink::shared(dir.path().join("other")).unwrap();
+        assert!(!Arc::ptr_eq(&first.0, &other.0));
+        assert!(FileDeadLetterSink::shared(dir.path().join("missing/dead_letters")).is_err());
+    }
+}
@@ -4,6 +4,14 @@
 pub const FIX42_BEGIN_STR: &str = "FIX.4.2";
 pub const FIX43_BEGIN_STR: &str = "FIX.4.3";
 pub const FIX44_BEGIN_STR: &str = "FIX.4.4";
+pub const FIXT11_BEGIN_STR: &str = "FIXT.1.1";
+// begin strings a session can be configured with
+pub const BEGIN_STRINGS: [&str; 4] = [
+    FIX42_BEGIN_STR,
+    FIX43_BEGIN_STR,
+    FIX44_BEGIN_STR,
+    FIXT11_BEGIN_STR,
+];
 
 // Connection types
 pub const ACCEPTOR_CONN_TYPE: &str = "acceptor";
@@ -12,6 +20,10 @@ pub const INITIATOR_CONN_TYPE: &str = "initiator";
 // config toml's section name
 pub const DEFAULT_SECTION_NAME: &str = "Default";
 pub const SESSION_SECTION_NAME: &str = "Session";
+// [Group "name"] sections hold settings shared by the sessions that name them
+pub const GROUP_SECTION_NAME: &str = "Group";
+// [Sessions "pattern"] sections hold settings shared by the sessions matching a SessionIdPattern
+pub const PATTERN_SECTION_NAME: &str = "Sessions";
 
 // settings name
 pub const BEGIN_STRING_SETTING: &str = "begin_string";
@@ -24,21 +36,106 @@ pub const TARGET_LOCATIONID_SETTING: &str = "target_location_id";
 pub const SESSION_QUALIFIER_SETTING: &str = "session_qualifier";
 pub const CONNECTION_TYPE_SETTING: &str = "connection_type";
 pub const SOCKET_ACCEPT_PORT_SETTING: &str = "socket_accept_port";
+pub const SOCKET_ACCEPT_HOST_SETTING: &str = "socket_accept_host";
 pub const SOCKET_CONNECT_PORT_SETTING: &str = "socket_connect_port";
 pub const SOCKET_CONNECT_HOST_SETTING: &str = "socket_connect_host";
 pub const RESET_ON_LOGON_SETTING: &str = "reset_on_logon";
 pub const RESET_ON_LOGOUT_SETTING: &str = "reset_on_logout";
 pub const RESET_ON_DISCONNECT_SETTING: &str = "reset_on_disconnect";
+pub const RESET_ON_SCHEDULE_SETTING: &str = "reset_on_schedule";
 pub const HEARTBEAT_INTERVAL_SETTING: &str = "heartbeat_interval";
 pub const DATA_DICTIONARY_FILE_PATH: &str = "data_dictionary";
+// dictionary of sessions that do not set data_dictionary, relative to the working directory
+pub const DEFAULT_DATA_DICTIONARY: &str = "resources/FIX43.xml";
+// acceptor sessions refuse a Logon with a BeginString other than the one of their id, unless
+// this is set: a Logon over a version one of begin_string_dictionaries is for then switches the
+// session to it. set on [Default] or a group for every session of a port
+pub const ACCEPT_ANY_BEGIN_STRING_SETTING: &str = "accept_any_begin_string";
+// comma separated paths of the dictionaries of the other BeginStrings a session accepts
+pub const BEGIN_STRING_DICTIONARIES_SETTING: &str = "begin_string_dictionaries";
+pub const SEND_QUEUE_SIZE_SETTING: &str = "send_queue_size";
+pub const SEND_QUEUE_OVERFLOW_SETTING: &str = "send_queue_overflow";
+pub const APP_DISPATCH_SETTING: &str = "app_dispatch";
+// threads of the pool shared by sessions with app_dispatch = "pool", only read from [Default]
+pub const APP_POOL_SIZE_SETTING: &str = "app_pool_size";
+// inbound messages are parsed and validated on a pool of threads instead of the session's task
+pub const VALIDATION_POOL_SETTING: &str = "validation_pool";
+// threads of the validation pool, only read from [Default]
+pub const VALIDATION_POOL_SIZE_SETTING: &str = "validation_pool_size";
+// outbound headers carry LastMsgSeqNumProcessed(369), the last seqnum received from the peer
+pub const LAST_MSG_SEQ_NUM_PROCESSED_SETTING: &str = "last_msg_seq_num_processed";
+pub const FILE_STORE_PATH_SETTING: &str = "file_store_path";
+pub const STORE_MAX_MESSAGES_SETTING: &str = "store_max_messages";
+// seconds
+pub const STORE_MAX_AGE_SETTING: &str = "store_max_age";
+pub const STORE_ARCHIVE_PATH_SETTING: &str = "store_archive_path";
+// seconds a message queued while the session is down waits for the next logon before it is
+// handed to the dead letter sink
+pub const QUEUE_MAX_AGE_SETTING: &str = "queue_max_age";
+// file dead letters are appended to, when no sink is given in code
+pub const DEAD_LETTER_PATH_SETTING: &str = "dead_letter_path";
+// seqnums are written to the store every that many changes, or that many millis after the
+// first unwritten one
+pub const SEQNUM_CHECKPOINT_MESSAGES_SETTING: &str = "seqnum_checkpoint_messages";
+pub const SEQNUM_CHECKPOINT_INTERVAL_MS_SETTING: &str = "seqnum_checkpoint_interval_ms";
+pub const CORRECT_NUM_IN_GROUP_SETTING: &str = "correct_num_in_group";
+pub const RELAXED_FIELD_ORDER_SETTING: &str = "relaxed_field_order";
+pub const LENIENT_GROUP_FIELD_ORDER_SETTING: &str = "lenient_group_field_order";
+// inbound messages with more fields, or a NumInGroup above the limit, are rejected with
+// ValueOutOfRange
+pub const MAX_FIELDS_SETTING: &str = "max_fields";
+pub const MAX_GROUP_INSTANCES_SETTING: &str = "max_group_instances";
+pub const DEDUPLICATE_POSS_DUP_SETTING: &str = "deduplicate_poss_dup";
+pub const DROP_COPY_SESSIONS_SETTING: &str = "drop_copy_sessions";
+// routing ids stamped on outbound application messages that do not have them already, for
+// sessions to or through a hub
+pub const ON_BEHALF_OF_COMP_ID_SETTING: &str = "on_behalf_of_comp_id";
+pub const ON_BEHALF_OF_SUB_ID_SETTING: &str = "on_behalf_of_sub_id";
+pub const ON_BEHALF_OF_LOCATION_ID_SETTING: &str = "on_behalf_of_location_id";
+pub const DELIVER_TO_COMP_ID_SETTING: &str = "deliver_to_comp_id";
+pub const DELIVER_TO_SUB_ID_SETTING: &str = "deliver_to_sub_id";
+pub const DELIVER_TO_LOCATION_ID_SETTING: &str = "deliver_to_location_id";
+pub const LOGON_USERNAME_SETTING: &str = "logon_username";
+pub const LOGON_PASSWORD_SETTING: &str = "logon_password";
+pub const SOCKET_NODELAY_SETTING: &str = "socket_nodelay";
+pub const SOCKET_KEEPALIVE_SETTING: &str = "socket_keepalive";
+pub const SOCKET_SEND_BUFFER_SIZE_SETTING: &str = "socket_send_buffer_size";
+pub const SOCKET_RECEIVE_BUFFER_SIZE_SETTING: &str = "socket_receive_buffer_size";
+pub const SOCKET_FLUSH_INTERVAL_MS_SETTING: &str = "socket_flush_interval_ms";
+pub const MAX_MESSAGE_SIZE_SETTING: &str = "max_message_size";
+// seconds
+pub const SOCKET_IDLE_TIMEOUT_SETTING: &str = "socket_idle_timeout";
+pub const CAPTURE_PATH_SETTING: &str = "capture_path";
+pub const PROXY_TYPE_SETTING: &str = "proxy_type";
+pub const PROXY_HOST_SETTING: &str = "proxy_host";
+pub const PROXY_PORT_SETTING: &str = "proxy_port";
+pub const PROXY_USERNAME_SETTING: &str = "proxy_username";
+pub const PROXY_PASSWORD_SETTING: &str = "proxy_password";
+// group a session or a group takes the settings it leaves out from
+pub const GROUP_SETTING: &str = "group";
 
+// defaults
+pub const DEFAULT_SEND_QUEUE_SIZE: usize = 32;
 
+pub mod admin;
+pub mod authenticator;
+pub mod dead_letter;
+pub mod resend;
 pub mod session_and_state;
 pub mod session_id;
 pub mod session_schedule;
 pub mod session_settings;
+pub mod session_status;
+pub mod settings_schema;
 
+pub use authenticator::{Authenticator, LogonDecision, SharedAuthenticator};
+pub use dead_letter::{
+    DeadLetter, DeadLetterReason, DeadLetterSink, FileDeadLetterSink, SharedDeadLetterSink,
+};
+pub use resend::{ResendAction, ResendPolicies, ResendPolicy};
 pub use session_and_state::*;
 pub use session_id::*;
 pub use session_schedule::*;
 pub use session_settings::*;
+pub use session_status::{ConnectionState, SessionStatus};
+pub use settings_schema::{DiagnosticKind, SettingsDiagnostic};
@@ -0,0 +1,68 @@
+use crate::message::Message;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// what is sent in place of a stored message asked for by a ResendRequest
+// short lived, returned once per resent message so the Message is not boxed
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum ResendAction {
+    // sent again with PossDupFlag(43) and OrigSendingTime(122)
+    Replay(Message),
+    // covered by a SequenceReset-GapFill, consecutive gap fills are sent as one
+    GapFill,
+}
+
+#[derive(Clone)]
+pub enum ResendPolicy {
+    GapFill,
+    Replay,
+    // decides per message, e.g. skips stale orders or updates a field before the replay
+    Hook(Arc<dyn Fn(Message) -> ResendAction + Send + Sync>),
+}
+
+impl ResendPolicy {
+    pub fn hook(hook: impl Fn(Message) -> ResendAction + Send + Sync + 'static) -> Self {
+        ResendPolicy::Hook(Arc::new(hook))
+    }
+
+    pub fn apply(&self, msg: Message) -> ResendAction {
+        match self {
+            ResendPolicy::GapFill => ResendAction::GapFill,
+            ResendPolicy::Replay => ResendAction::Replay(msg),
+            ResendPolicy::Hook(hook) => hook(msg),
+        }
+    }
+}
+
+impl std::fmt::Debug for ResendPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResendPolicy::GapFill => f.write_str("GapFill"),
+            ResendPolicy::Replay => f.write_str("Replay"),
+            ResendPolicy::Hook(_) => f.write_str("Hook"),
+        }
+    }
+}
+
+// ResendPolicy by MsgType(35). Without one, admin messages are gap filled and application
+// messages are replayed as they were sent.
+#[derive(Debug, Default, Clone)]
+pub struct ResendPolicies {
+    by_msg_type: HashMap<String, ResendPolicy>,
+}
+
+impl ResendPolicies {
+    pub fn set(&mut self, msg_type: &str, policy: ResendPolicy) -> &mut Self {
+        self.by_msg_type.insert(msg_type.to_string(), policy);
+        self
+    }
+
+    pub fn get(&self, msg_type: &str, is_admin: bool) -> &ResendPolicy {
+        match self.by_msg_type.get(msg_type) {
+            Some(policy) => policy,
+            None if is_admin => &ResendPolicy::GapFill,
+            None => &ResendPolicy::Replay,
+        }
+    }
+}
@@ -1,43 +1,255 @@
+use crate::clock::SharedClock;
 use crate::data_dictionary::DataDictionary;
-use crate::fields::MaxMessageSize;
-use crate::io::TioBroadcastSender;
+use crate::fields::*;
+use crate::io::inbound::ConnectionHandle;
+use crate::io::stats::ConnectionCounters;
+use crate::io::{ConnectionStats, InboundMessage, SocketOptions};
+use crate::message::store::sending_time;
 use crate::message::*;
 use crate::network::SessionMap;
+use crate::orders::OrderTracker;
+use crate::quickfix_errors::{RequestError, SendError, SessionRejectError};
+use crate::session::session_schedule::SessionSchedule;
 use crate::session::*;
-use dashmap::DashMap;
-use getset::Getters;
-use getset::Setters;
-use std::collections::{HashMap, VecDeque};
+use crate::types::{TimePrecision, UtcTimestamp};
+use chrono::{DateTime, Utc};
+use getset::{CopyGetters, Getters, MutGetters, Setters};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use tokio::io::AsyncWriteExt;
-use tokio::net::tcp::OwnedWriteHalf;
-use tokio::net::TcpStream;
-use tokio::sync::mpsc::{Receiver as TioReceiver, Sender as TioSender};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender as TioSender;
+use tokio::sync::{oneshot, Notify};
 
-#[derive(Debug, Default, Clone)]
-struct SessionState;
+// Text(58) of the Logout sent to a logged on session that is reset
+pub const SESSION_RESET_REASON: &str = "session reset";
+
+#[derive(Debug, Clone, CopyGetters, Setters)]
+#[getset(get_copy = "pub", set = "pub")]
+pub struct SessionState {
+    next_sender_msg_seq_num: u64,
+    next_target_msg_seq_num: u64,
+    last_sent_time: Option<DateTime<Utc>>,
+    last_received_time: Option<DateTime<Utc>>,
+    // LastMsgSeqNumProcessed(369) of the last message received that had one
+    peer_last_msg_seq_num_processed: Option<u64>,
+    // highest MsgSeqNum seen when the outstanding ResendRequest(2) was sent, no other one goes
+    // out until the expected seqnum is past it
+    resend_requested_through: Option<u64>,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self {
+            next_sender_msg_seq_num: 1,
+            next_target_msg_seq_num: 1,
+            last_sent_time: None,
+            last_received_time: None,
+            peer_last_msg_seq_num_processed: None,
+            resend_requested_through: None,
+        }
+    }
+}
 
 impl SessionState {
     fn new() -> Self {
-        SessionState
+        Self::default()
+    }
+
+    pub fn incr_next_sender_msg_seq_num(&mut self) {
+        self.next_sender_msg_seq_num += 1;
+    }
+
+    pub fn incr_next_target_msg_seq_num(&mut self) {
+        self.next_target_msg_seq_num += 1;
+    }
+}
+
+// What a send does when send_queue_size messages are already waiting for the connection. The
+// message is refused with QueueFull either way and does not use up a seqnum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SendQueueOverflow {
+    // the connection stays up, the application can send again once the queue has drained
+    #[default]
+    Reject,
+    // a counterparty that does not keep up is dropped, it catches up by resend after logging on
+    Disconnect,
+}
+
+impl FromStr for SendQueueOverflow {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("reject") {
+            Ok(SendQueueOverflow::Reject)
+        } else if s.eq_ignore_ascii_case("disconnect") {
+            Ok(SendQueueOverflow::Disconnect)
+        } else {
+            Err("invalid send queue overflow")
+        }
+    }
+}
+
+// Where the session's application messages are handed to from_app. Admin messages and the other
+// callbacks are handled on the session task either way, a slow handler off that task does not
+// hold up heartbeats and test requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AppDispatch {
+    // on the session task, nothing else of the session is handled until from_app returns
+    #[default]
+    Inline,
+    // on a thread of the session's own
+    Worker,
+    // on one of app_pool_size threads shared by the sessions, always the same one for a session
+    Pool,
+}
+
+impl FromStr for AppDispatch {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("inline") {
+            Ok(AppDispatch::Inline)
+        } else if s.eq_ignore_ascii_case("worker") {
+            Ok(AppDispatch::Worker)
+        } else if s.eq_ignore_ascii_case("pool") {
+            Ok(AppDispatch::Pool)
+        } else {
+            Err("invalid app dispatch")
+        }
     }
 }
 
-#[derive(Debug, Default, Getters, Setters, Clone)]
+// what a stored message is resent as
+enum Resend {
+    // the stored frame with the resend fields spliced in
+    Frame(String),
+    // changed by a resend hook, serialized again
+    Message(Box<Message>),
+    GapFill,
+}
+
+#[derive(Debug, Default, Getters, CopyGetters, MutGetters, Setters, Clone)]
 pub struct Session {
     pub session_id: SessionId,
+    #[getset(get_copy = "pub")]
     heartbeat_intrvl: u32,
+    #[getset(get_copy = "pub")]
     is_active: bool,
+    // address of the connection the session logged on over, while it is connected
+    #[getset(get_copy = "pub", set = "pub")]
+    peer_addr: Option<SocketAddr>,
+    // the connection itself, so that it can be dropped
+    connection: Option<ConnectionHandle>,
+    // Logon sent by logon() and not answered yet
+    logon_sent: bool,
+    #[getset(get_copy = "pub")]
     reset_on_logon: bool,
+    // stamped as Username(553) and Password(554) on outbound Logon
+    #[getset(get = "pub")]
+    logon_username: Option<String>,
+    #[getset(get = "pub")]
+    logon_password: Option<String>,
     reset_on_logout: bool,
     reset_on_disconnect: bool,
-    msg_q: VecDeque<Message>,
+    // seqnums start over with every new window of the session's schedule
+    #[getset(get_copy = "pub")]
+    reset_on_schedule: bool,
+    // PossDup application messages already received are dropped instead of delivered again
+    #[getset(get_copy = "pub")]
+    deduplicate_poss_dup: bool,
+    // how inbound messages of the session are parsed
+    #[getset(get_copy = "pub")]
+    parse_options: ParseOptions,
+    #[getset(get = "pub", get_mut = "pub")]
     state: SessionState,
     // session_map: Option<Arc<Mutex<HashMap<se>>>>,
+    // queue of the connection the session writes to
     #[getset(set = "pub")]
-    responder: Option<TioBroadcastSender<String>>,
+    responder: Option<TioSender<String>>,
+    // counters of the connection responder writes to, a responder set on its own has none
+    responder_counters: Option<Arc<ConnectionCounters>>,
+    #[getset(get_copy = "pub")]
+    send_queue_size: usize,
+    #[getset(get_copy = "pub")]
+    send_queue_overflow: SendQueueOverflow,
+    #[getset(get_copy = "pub")]
+    app_dispatch: AppDispatch,
+    #[getset(get_copy = "pub")]
+    validation_pool: bool,
+    // outbound headers carry LastMsgSeqNumProcessed(369)
+    #[getset(get_copy = "pub")]
+    last_msg_seq_num_processed: bool,
+    // OnBehalfOf and DeliverTo ids from the settings
+    #[getset(get = "pub")]
+    routing_ids: Vec<(u32, String)>,
+    #[getset(get_copy = "pub")]
+    socket_options: SocketOptions,
     #[getset(get = "pub")]
     data_dictionary: Arc<DataDictionary>,
+    // BeginString of what is sent and received, the one of the session id unless a Logon
+    // switched the session to another, see switch_begin_string
+    #[getset(get = "pub")]
+    begin_string: String,
+    #[getset(get_copy = "pub")]
+    accept_any_begin_string: bool,
+    #[getset(get = "pub")]
+    settings: Arc<SessionSettings>,
+    #[getset(get = "pub")]
+    message_factory: MessageFactory,
+    // app messages received or sent on this session are mirrored onto these
+    #[getset(get = "pub")]
+    drop_copy_sessions: Vec<SessionId>,
+    // sent messages, replayed or gap filled on ResendRequest according to resend_policies,
+    // and messages queued while the session is down. kept on disk when file_store_path is set
+    #[getset(get = "pub", set = "pub")]
+    message_store: SharedMessageStore,
+    // how much of message_store is kept, see purge_store
+    #[getset(get = "pub")]
+    retention: RetentionPolicy,
+    // how often the seqnums in state are written to message_store, see checkpoint_seq_nums
+    #[getset(get_copy = "pub")]
+    seq_num_checkpoint: SeqNumCheckpoint,
+    // seqnum changes not written yet, and the time of the first of them
+    pending_seq_nums: u64,
+    pending_since: Option<DateTime<Utc>>,
+    #[getset(get = "pub", get_mut = "pub")]
+    resend_policies: ResendPolicies,
+    // SendingTime and the last sent and received times are taken from it
+    #[getset(get = "pub")]
+    clock: SharedClock,
+    // hours the session is up, logons outside of them are refused
+    #[getset(get = "pub")]
+    schedule: Arc<SessionSchedule>,
+    // messages to send once the clock reaches their time, earliest first
+    scheduled_sends: VecDeque<(DateTime<Utc>, Message)>,
+    // woken when a send is scheduled, so that the session task waits for the new earliest one
+    send_scheduled: Arc<Notify>,
+    // how long a message queued by send_or_queue waits for a logon, forever when None
+    #[getset(get_copy = "pub")]
+    queue_max_age: Option<chrono::Duration>,
+    // gets the outbound messages that could not be delivered
+    #[getset(get = "pub", set = "pub")]
+    dead_letter_sink: SharedDeadLetterSink,
+    // sees the order messages sent and received on the session
+    #[getset(get = "pub")]
+    order_tracker: Option<Arc<OrderTracker>>,
+    // requests waiting for their response, see request. clones of the session share them like
+    // they share the message store
+    pending_responses: Arc<Mutex<Vec<PendingResponse>>>,
+}
+
+type ResponseMatcher = Box<dyn Fn(&Message) -> bool + Send + Sync>;
+
+struct PendingResponse {
+    matcher: ResponseMatcher,
+    reply: oneshot::Sender<Message>,
+}
+
+impl std::fmt::Debug for PendingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PendingResponse")
+    }
 }
 
 impl Session {
@@ -45,7 +257,35 @@ impl Session {
         self.session_id = sid;
     }
 
-    pub fn with_settings(session_id: &SessionId, session_setting: &Properties) -> Self {
+    // fails when the message store or dead letter file the settings name cannot be opened or the
+    // schedule is not valid
+    pub fn with_settings(
+        session_id: &SessionId, session_setting: &Properties,
+    ) -> std::io::Result<Self> {
+        let data_dictionary = Session::load_dictionary(session_id, session_setting);
+        Session::with_dictionary(session_id, session_setting, data_dictionary)
+    }
+
+    // dictionary the settings name for the session, loaded anew on every call
+    pub fn load_dictionary(
+        session_id: &SessionId, session_setting: &Properties,
+    ) -> Arc<DataDictionary> {
+        let data_dict_path: Option<String> =
+            session_setting.get_optional_config(session_id, DATA_DICTIONARY_FILE_PATH);
+        match data_dict_path {
+            Some(path) => Arc::new(DataDictionary::from_xml(path)),
+            #[cfg(feature = "embedded-dictionary")]
+            None => DataDictionary::embedded(),
+            #[cfg(not(feature = "embedded-dictionary"))]
+            None => Arc::new(DataDictionary::from_xml(DEFAULT_DATA_DICTIONARY)),
+        }
+    }
+
+    // session of the given settings over an already loaded dictionary, so that sessions of one
+    // engine can share it
+    pub fn with_dictionary(
+        session_id: &SessionId, session_setting: &Properties, data_dictionary: Arc<DataDictionary>,
+    ) -> std::io::Result<Self> {
         // setting should have begin_string, sender_compid and target_compid
         // it should also have either accept port or (connect_host, connect_port)
         let heartbeat_interval: u32 = session_setting
@@ -59,22 +299,271 @@ impl Session {
         let reset_on_disconnect: bool = session_setting
             .get_optional_config(session_id, RESET_ON_DISCONNECT_SETTING)
             .unwrap_or(true);
-        let data_dict_path: String = session_setting
-            .get_optional_config(session_id, DATA_DICTIONARY_FILE_PATH)
-            .unwrap_or_else(|| "resources/FIX43.xml".to_string());
-        // .unwrap_or("resources/FIX43.xml");
-        let data_dictionary = DataDictionary::from_xml(data_dict_path);
-        Self {
+        let reset_on_schedule: bool = session_setting
+            .get_optional_config(session_id, RESET_ON_SCHEDULE_SETTING)
+            .unwrap_or(false);
+        let deduplicate_poss_dup: bool = session_setting
+            .get_optional_config(session_id, DEDUPLICATE_POSS_DUP_SETTING)
+            .unwrap_or(true);
+        let parse_options = ParseOptions {
+            correct_num_in_group: session_setting
+                .get_optional_config(session_id, CORRECT_NUM_IN_GROUP_SETTING)
+                .unwrap_or(false),
+            relaxed_field_order: session_setting
+                .get_optional_config(session_id, RELAXED_FIELD_ORDER_SETTING)
+                .unwrap_or(false),
+            lenient_group_field_order: session_setting
+                .get_optional_config(session_id, LENIENT_GROUP_FIELD_ORDER_SETTING)
+                .unwrap_or(false),
+            max_fields: session_setting.get_optional_config(session_id, MAX_FIELDS_SETTING),
+            max_group_instances: session_setting
+                .get_optional_config(session_id, MAX_GROUP_INSTANCES_SETTING),
+        };
+        let logon_username: Option<String> =
+            session_setting.get_optional_config(session_id, LOGON_USERNAME_SETTING);
+        let logon_password: Option<String> =
+            session_setting.get_optional_config(session_id, LOGON_PASSWORD_SETTING);
+        let send_queue_size: usize = session_setting
+            .get_optional_config(session_id, SEND_QUEUE_SIZE_SETTING)
+            .unwrap_or(DEFAULT_SEND_QUEUE_SIZE);
+        let send_queue_overflow: SendQueueOverflow = session_setting
+            .get_optional_config(session_id, SEND_QUEUE_OVERFLOW_SETTING)
+            .unwrap_or_default();
+        let app_dispatch: AppDispatch = session_setting
+            .get_optional_config(session_id, APP_DISPATCH_SETTING)
+            .unwrap_or_default();
+        let validation_pool: bool = session_setting
+            .get_optional_config(session_id, VALIDATION_POOL_SETTING)
+            .unwrap_or(false);
+        let last_msg_seq_num_processed: bool = session_setting
+            .get_optional_config(session_id, LAST_MSG_SEQ_NUM_PROCESSED_SETTING)
+            .unwrap_or(false);
+        let routing_ids = [
+            (OnBehalfOfCompID::field(), ON_BEHALF_OF_COMP_ID_SETTING),
+            (OnBehalfOfSubID::field(), ON_BEHALF_OF_SUB_ID_SETTING),
+            (OnBehalfOfLocationID::field(), ON_BEHALF_OF_LOCATION_ID_SETTING),
+            (DeliverToCompID::field(), DELIVER_TO_COMP_ID_SETTING),
+            (DeliverToSubID::field(), DELIVER_TO_SUB_ID_SETTING),
+            (DeliverToLocationID::field(), DELIVER_TO_LOCATION_ID_SETTING),
+        ]
+        .into_iter()
+        .filter_map(|(tag, setting)| {
+            Some((tag, session_setting.get_optional_config(session_id, setting)?))
+        })
+        .collect();
+        let accept_any_begin_string: bool = session_setting
+            .get_optional_config(session_id, ACCEPT_ANY_BEGIN_STRING_SETTING)
+            .unwrap_or(false);
+        let mut message_factory = MessageFactory::new();
+        // dictionaries of the other BeginStrings the session accepts, its own one wins
+        if accept_any_begin_string {
+            session_setting
+                .get_optional_config::<String>(session_id, BEGIN_STRING_DICTIONARIES_SETTING)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .for_each(|path| {
+                    message_factory.add_dictionary(Arc::new(DataDictionary::from_xml(path)))
+                });
+        }
+        message_factory.add_dictionary(Arc::clone(&data_dictionary));
+        // comma separated target comp ids of sessions with the same begin string and sender, or
+        // full session ids like FIX.4.3:SENDER/DESK->RISK
+        let drop_copy_sessions = session_setting
+            .get_optional_config::<String>(session_id, DROP_COPY_SESSIONS_SETTING)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .filter_map(|target| {
+                if target.contains("->") {
+                    return target.parse::<SessionId>().ok();
+                }
+                SessionIdBuilder::new(
+                    session_id.begin_string().as_str(),
+                    session_id.sender_compid(),
+                    target,
+                )
+                .build()
+                .ok()
+            })
+            .filter(|dc_id| dc_id != session_id)
+            .collect();
+        let queue_max_age = session_setting
+            .get_optional_config::<i64>(session_id, QUEUE_MAX_AGE_SETTING)
+            .map(chrono::Duration::seconds);
+        let dead_letter_sink = match session_setting
+            .get_optional_config::<String>(session_id, DEAD_LETTER_PATH_SETTING)
+        {
+            Some(path) => FileDeadLetterSink::shared(&path).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("could not open dead letter file {}: {}", path, e),
+                )
+            })?,
+            None => SharedDeadLetterSink::default(),
+        };
+        let message_store = match session_setting
+            .get_optional_config::<String>(session_id, FILE_STORE_PATH_SETTING)
+        {
+            Some(path) => {
+                let store = FileStore::open(&path, session_id).map_err(|e| {
+                    std::io::Error::new(
+                        e.kind(),
+                        format!("could not open message store {}: {}", path, e),
+                    )
+                })?;
+                SharedMessageStore::new(store)
+            }
+            None => SharedMessageStore::default(),
+        };
+        let schedule =
+            SessionSchedule::create_schedule(session_id, session_setting).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("schedule of {}: {}", session_id, e),
+                )
+            })?;
+        // a restarted session goes on from where its store says it was
+        let (next_sender, next_target) = recover_seq_nums(&*message_store.lock());
+        let mut state = SessionState::new();
+        state.set_next_sender_msg_seq_num(next_sender).set_next_target_msg_seq_num(next_target);
+        Ok(Self {
             session_id: session_id.clone(),
             heartbeat_intrvl: heartbeat_interval,
             reset_on_disconnect,
             reset_on_logon,
+            logon_username,
+            logon_password,
             reset_on_logout,
-            msg_q: VecDeque::new(),
+            reset_on_schedule,
+            deduplicate_poss_dup,
+            parse_options,
             is_active: false,
-            state: SessionState::default(),
+            peer_addr: None,
+            connection: None,
+            logon_sent: false,
+            state,
             responder: None,
-            data_dictionary: Arc::new(data_dictionary),
+            responder_counters: None,
+            send_queue_size,
+            send_queue_overflow,
+            app_dispatch,
+            validation_pool,
+            last_msg_seq_num_processed,
+            routing_ids,
+            settings: Arc::new(session_setting.for_session(session_id)),
+            socket_options: SocketOptions::from_settings(session_setting, session_id),
+            data_dictionary,
+            begin_string: session_id.begin_string().clone(),
+            accept_any_begin_string,
+            message_factory,
+            drop_copy_sessions,
+            message_store,
+            retention: RetentionPolicy::from_settings(session_setting, session_id),
+            seq_num_checkpoint: SeqNumCheckpoint::from_settings(session_setting, session_id),
+            pending_seq_nums: 0,
+            pending_since: None,
+            resend_policies: ResendPolicies::default(),
+            clock: SharedClock::default(),
+            schedule: Arc::new(schedule),
+            scheduled_sends: VecDeque::new(),
+            send_scheduled: Arc::new(Notify::new()),
+            queue_max_age,
+            dead_letter_sink,
+            order_tracker: None,
+            pending_responses: Arc::default(),
+        })
+    }
+
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        // the schedule goes by the same clock
+        self.schedule = Arc::new(self.schedule.as_ref().clone().with_clock(clock.clone()));
+        self.clock = clock;
+    }
+
+    pub fn set_order_tracker(&mut self, tracker: Option<Arc<OrderTracker>>) {
+        self.order_tracker = tracker;
+    }
+
+    // zone of the session's schedule, the one its hours are given in
+    pub fn time_zone(&self) -> chrono_tz::Tz {
+        self.schedule.time_zone()
+    }
+
+    pub(crate) fn set_connection(&mut self, inbound: Option<&InboundMessage>) {
+        // connection the session logged on over, None once it is gone
+        self.peer_addr = inbound.map(|inbound| inbound.source);
+        self.connection = inbound.map(|inbound| inbound.connection().clone());
+        self.responder = inbound.map(|inbound| inbound.connection().outbound().clone());
+        self.responder_counters =
+            inbound.map(|inbound| Arc::clone(inbound.connection().counters()));
+    }
+
+    pub fn dictionary_for(&self, begin_string: &str) -> Option<&Arc<DataDictionary>> {
+        // dictionary a message in begin_string is parsed with, the session's own or, for a
+        // session accepting any BeginString that is not logged on, one it could switch to
+        if begin_string == self.begin_string {
+            return Some(&self.data_dictionary);
+        }
+        if !self.accept_any_begin_string || self.is_active {
+            return None;
+        }
+        self.message_factory.get_dictionary(begin_string)
+    }
+
+    pub fn switch_begin_string(&mut self, begin_string: &str) -> bool {
+        // false unless the session speaks begin_string from now on. only a session accepting
+        // any BeginString switches, to one it has a dictionary for, while it is not logged on
+        let Some(dd) = self.dictionary_for(begin_string).map(Arc::clone) else {
+            return false;
+        };
+        self.data_dictionary = dd;
+        self.begin_string = begin_string.to_string();
+        true
+    }
+
+    pub(crate) fn reply_over(&mut self, inbound: &InboundMessage) {
+        // until a connection is logged on, whatever is sent goes back over the one the last
+        // message came from, e.g. the answer to its Logon or the reason it is refused
+        if self.connection.is_none() {
+            self.responder = Some(inbound.connection().outbound().clone());
+            self.responder_counters = Some(Arc::clone(inbound.connection().counters()));
+        }
+    }
+
+    pub fn new_message(&self, msg_type: &str) -> Result<Message, SessionRejectError> {
+        // message for this session with comp ids stamped in the header
+        let mut msg = self.message_factory.create(&self.begin_string, msg_type)?;
+        self.stamp_comp_ids(msg.header_mut());
+        Ok(msg)
+    }
+
+    pub fn new_response(
+        &self, msg_type: &str, request: &Message,
+    ) -> Result<Message, SessionRejectError> {
+        // like new_message, routed back the way request came in. the reversed ids take the
+        // place of the ones from the settings
+        let mut msg = self.new_message(msg_type)?;
+        msg.header_mut().reverse_routing(request.header());
+        Ok(msg)
+    }
+
+    fn stamp_comp_ids(&self, header: &mut Header) {
+        let sid = &self.session_id;
+        header.set_sender_comp_id(sid.sender_compid());
+        header.set_target_comp_id(sid.target_compid());
+        let optional_ids: [(SetId, _); 4] = [
+            (Header::set_sender_sub_id, sid.sender_subid()),
+            (Header::set_sender_location_id, sid.sender_locationid()),
+            (Header::set_target_sub_id, sid.target_subid()),
+            (Header::set_target_location_id, sid.target_locationid()),
+        ];
+        for (set_id, value) in optional_ids {
+            if let Some(val) = value {
+                set_id(header, val);
+            }
         }
     }
 
@@ -82,32 +571,1876 @@ impl Session {
         Ok(())
     }
 
-    // pub fn sync_send(
-    //     msg: Message, session_id: &SessionId, sessions: &Arc<DashMap<SessionId, Session>>,
-    // ) {
-    //     let session = sessions.get(session_id).unwrap();
-    //     session.send_to_target(msg);
-    // }
-
-    // pub fn send_to_target(&self, msg: Message) {
-    //     let responder = self.responder.as_ref().unwrap();
-    //     responder.blocking_send(msg.to_string()).unwrap();
-    // }
-
-    // pub async fn async_send(session_id: &SessionId, msg: Message) {
-    //     let session =
-    // }
-    pub fn sync_send_to_target(session_id: &SessionId, sessions: &SessionMap, msg: Message) {
-        // let synchronous_send = true;
-        let sess_ref = sessions.get_session(session_id).unwrap();
-        let responder = sess_ref.responder.as_ref().unwrap().clone();
-        responder.send(msg.to_string()).unwrap();
-        // if !synchronous_send {
-        //     tokio::spawn(async move {
-        //         responder.send(msg.to_string()).await.unwrap();
-        //     });
-        // } else {
-        //     responder.send(msg.to_string());
-        // }
+    pub fn send_to_target(&mut self, mut msg: Message) -> Result<(), SendError> {
+        // never blocks. messages are refused once send_queue_size messages are waiting to be
+        // written to the socket, or if there is no connection to write them to. a full queue is
+        // handled as send_queue_overflow says
+        self.send_counted(&mut msg).map(|_| ())
+    }
+
+    fn send_counted(&mut self, msg: &mut Message) -> Result<Option<u64>, SendError> {
+        // send_to_target, with the send number the writer confirms it by. msg is left stamped
+        let seq_num = self.state.next_sender_msg_seq_num();
+        let raw = self.stamp(msg, seq_num);
+        let send = self.write(raw.clone())?;
+        self.record_sent(seq_num, &raw);
+        self.track(msg);
+        Ok(send)
+    }
+
+    pub fn send_wire(&mut self, msg: &WireBuilder) -> Result<(), SendError> {
+        // like send_to_target for a message built with WireBuilder, queued like send_or_queue
+        // does while there is no connection. drop copy sessions only see it when it is sent
+        // with sync_send_wire
+        self.write_wire(msg, false).map(|_| ())
+    }
+
+    fn write_wire(
+        &mut self, msg: &WireBuilder, mirror: bool,
+    ) -> Result<Option<Message>, SendError> {
+        // the message as sent, parsed back for drop copy when mirror is set. it is only parsed
+        // for the order tracker if the session has one
+        let sid = &self.session_id;
+        let seq_num = self.state.next_sender_msg_seq_num();
+        let mut seq_num_buf = itoa::Buffer::new();
+        let sending_time = UtcTimestamp::new(self.clock.now(), TimePrecision::Millis).to_string();
+        let mut header = vec![
+            (SenderCompID::field(), sid.sender_compid().as_str()),
+            (TargetCompID::field(), sid.target_compid().as_str()),
+        ];
+        let optional_ids = [
+            (SenderSubID::field(), sid.sender_subid()),
+            (SenderLocationID::field(), sid.sender_locationid()),
+            (TargetSubID::field(), sid.target_subid()),
+            (TargetLocationID::field(), sid.target_locationid()),
+        ];
+        header.extend(optional_ids.into_iter().filter_map(|(tag, id)| Some((tag, id.as_deref()?))));
+        header.push((MsgSeqNum::field(), seq_num_buf.format(seq_num)));
+        header.push((SendingTime::field(), &sending_time));
+        // routing ids the message already has are left as they are
+        let routing_ids = self.routing_ids.iter().filter(|(tag, _)| msg.get(*tag).is_none());
+        header.extend(routing_ids.map(|(tag, id)| (*tag, id.as_str())));
+        let mut last_processed_buf = itoa::Buffer::new();
+        if let Some(last_processed) = self.last_processed_to_stamp() {
+            header
+                .push((LastMsgSeqNumProcessed::field(), last_processed_buf.format(last_processed)));
+        }
+        let mut raw = String::with_capacity(256);
+        msg.encode(&self.begin_string, &header, &self.data_dictionary, &mut raw);
+        // the store's copy is the only one, raw itself goes to the connection
+        let stored: StoredMessage = raw.as_bytes().into();
+        let written = self.write(raw);
+        let raw = std::str::from_utf8(&stored).expect("encoded from a String");
+        match written {
+            // no connection, it goes out after the next logon
+            Err(SendError::Disconnected) => {}
+            Err(e) => return Err(e),
+            Ok(_) => {
+                let sent = (mirror || self.order_tracker.is_some())
+                    .then(|| Message::from_str(raw, &self.data_dictionary).ok())
+                    .flatten();
+                self.record_stored(seq_num, Arc::clone(&stored));
+                if let Some(sent) = &sent {
+                    self.track(sent);
+                }
+                return Ok(sent.filter(|_| mirror));
+            }
+        }
+        self.message_store.lock().enqueue(raw)?;
+        Ok(None)
+    }
+
+    pub fn sync_send_wire(
+        session_id: &SessionId, sessions: &SessionMap, msg: &WireBuilder,
+    ) -> Result<(), SendError> {
+        // like sync_send_to_target for a message built with WireBuilder, queued while there is
+        // no connection
+        let mirror = {
+            let mut sess_ref = sessions
+                .get_session_mut(session_id)
+                .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+            let mirror = !sess_ref.drop_copy_sessions.is_empty();
+            sess_ref.write_wire(msg, mirror)?
+        };
+        // session ref is released by now, drop copy sessions may live in the same shard
+        if let Some(msg) = mirror {
+            Session::drop_copy(session_id, sessions, &msg);
+        }
+        Ok(())
+    }
+
+    pub fn send_batch(&mut self, mut msgs: Vec<Message>) -> Result<(), SendError> {
+        // messages with seqnums following each other that go out in a single write, e.g. a
+        // snapshot or a mass cancel. the batch takes one place in the send queue and is refused
+        // as a whole, without using up any seqnum
+        let first = self.state.next_sender_msg_seq_num();
+        let raws: Vec<String> =
+            (first..).zip(msgs.iter_mut()).map(|(seq_num, msg)| self.stamp(msg, seq_num)).collect();
+        if raws.is_empty() {
+            return Ok(());
+        }
+        self.write(raws.concat())?;
+        for (seq_num, raw) in (first..).zip(raws.iter()) {
+            self.record_sent(seq_num, raw);
+        }
+        msgs.iter().for_each(|msg| self.track(msg));
+        Ok(())
+    }
+
+    fn stamp(&self, msg: &mut Message, seq_num: u64) -> String {
+        msg.header_mut().set_seq_num(seq_num);
+        self.stamp_last_processed(msg.header_mut());
+        self.stamp_routing_ids(msg);
+        msg.set_sending_time(&self.clock);
+        msg.set_body_len();
+        msg.set_checksum();
+        msg.to_string()
+    }
+
+    fn stamp_routing_ids(&self, msg: &mut Message) {
+        // session level messages are between the session's own comp ids. ids the message
+        // already has are left as they are
+        let msg_type = msg.get_msg_type().unwrap_or_default();
+        if self.routing_ids.is_empty() || self.data_dictionary.is_admin_msg_type(&msg_type) {
+            return;
+        }
+        let header = msg.header_mut();
+        for (tag, id) in &self.routing_ids {
+            if header.get_field::<String>(*tag).is_ok() {
+                continue;
+            }
+            if let Err(e) = header.set_field(StringField::new(*tag, id), &self.data_dictionary) {
+                println!("could not stamp routing id for {}: {}", self.session_id, e);
+            }
+        }
+    }
+
+    pub(crate) fn stamp_last_processed(&self, header: &mut Header) {
+        if let Some(last_processed) = self.last_processed_to_stamp() {
+            header.set_last_msg_seq_num_processed(last_processed);
+        }
+    }
+
+    fn last_processed_to_stamp(&self) -> Option<u64> {
+        // the last seqnum received in order, 0 before anything was
+        self.last_msg_seq_num_processed
+            .then(|| self.state.next_target_msg_seq_num().saturating_sub(1))
+    }
+
+    pub fn peer_processing_lag(&self) -> Option<u64> {
+        // messages sent that the peer has not reported as processed yet, known once a message
+        // with LastMsgSeqNumProcessed(369) came in
+        let sent = self.state.next_sender_msg_seq_num().saturating_sub(1);
+        Some(sent.saturating_sub(self.state.peer_last_msg_seq_num_processed()?))
+    }
+
+    fn track(&self, msg: &Message) {
+        if let Some(tracker) = &self.order_tracker {
+            tracker.observe(&self.session_id, msg);
+        }
+    }
+
+    fn record_sent(&mut self, seq_num: u64, raw: &str) {
+        self.record_stored(seq_num, raw.as_bytes().into());
+    }
+
+    fn record_stored(&mut self, seq_num: u64, raw: StoredMessage) {
+        self.state.incr_next_sender_msg_seq_num();
+        if let Err(e) = self.message_store.lock().set(seq_num, raw) {
+            // already on its way, only a later resend of it is affected
+            println!("could not store message {} for {}: {}", seq_num, self.session_id, e);
+        }
+        self.seq_nums_changed();
+    }
+
+    fn seq_nums_changed(&mut self) {
+        self.pending_seq_nums += 1;
+        self.pending_since.get_or_insert_with(|| self.clock.now());
+        self.checkpoint_seq_nums();
+    }
+
+    pub fn checkpoint_seq_nums(&mut self) {
+        // writes the seqnums to the store once seq_num_checkpoint says it is time. a failed
+        // write is tried again with the next change
+        let Some(since) = self.pending_since else {
+            return;
+        };
+        let since_last = (self.clock.now() - since).to_std().unwrap_or_default();
+        if !self.seq_num_checkpoint.is_due(self.pending_seq_nums, since_last) {
+            return;
+        }
+        let (sender, target) =
+            (self.state.next_sender_msg_seq_num(), self.state.next_target_msg_seq_num());
+        match self.message_store.lock().set_seq_nums(sender, target) {
+            Ok(()) => {
+                self.pending_seq_nums = 0;
+                self.pending_since = None;
+            }
+            Err(e) => println!("could not checkpoint seqnums of {}: {}", self.session_id, e),
+        }
+    }
+
+    pub fn send_or_queue(&mut self, mut msg: Message) -> Result<(), SendError> {
+        // like send_to_target, but a message that cannot go out because the session is not
+        // logged on or connected is queued in the message store and sent after the next logon
+        if self.is_active {
+            match self.send_to_target(msg.clone()) {
+                Err(SendError::Disconnected) => {}
+                result => return result,
+            }
+        }
+        // SendingTime is when it was queued until it goes out, queue_max_age goes by it
+        msg.set_sending_time(&self.clock);
+        msg.set_body_len();
+        msg.set_checksum();
+        self.message_store.lock().enqueue(&msg.to_string())?;
+        Ok(())
+    }
+
+    pub fn expire_queued(&mut self) -> std::io::Result<()> {
+        // hands the queued messages older than queue_max_age to the dead letter sink
+        let Some(max_age) = self.queue_max_age else {
+            return Ok(());
+        };
+        let oldest_kept = self.clock.now() - max_age;
+        let mut store = self.message_store.lock();
+        let expired: Vec<String> = store
+            .queued()?
+            .into_iter()
+            .take_while(|raw| sending_time(raw.as_bytes()).is_some_and(|time| time < oldest_kept))
+            .collect();
+        store.dequeue(expired.len())?;
+        drop(store);
+        for raw in expired {
+            self.dead_letter(raw, DeadLetterReason::QueueExpired);
+        }
+        Ok(())
+    }
+
+    fn dead_letter(&self, raw: String, reason: DeadLetterReason) {
+        self.dead_letter_sink.dead_letter(&DeadLetter {
+            session_id: self.session_id.clone(),
+            raw,
+            reason,
+            time: self.clock.now(),
+        });
+    }
+
+    pub fn send_at(&mut self, msg: Message, at: DateTime<Utc>) {
+        // goes out with send_due once the session's clock reaches at. messages due at the same
+        // time go in the order they were scheduled
+        let pos = self.scheduled_sends.partition_point(|(due, _)| *due <= at);
+        self.scheduled_sends.insert(pos, (at, msg));
+        self.send_scheduled.notify_one();
+    }
+
+    pub fn send_after(&mut self, msg: Message, after: chrono::Duration) {
+        let at = self.clock.now() + after;
+        self.send_at(msg, at);
+    }
+
+    pub fn next_send_time(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_sends.front().map(|(due, _)| *due)
+    }
+
+    pub(crate) fn send_scheduled(&self) -> Arc<Notify> {
+        Arc::clone(&self.send_scheduled)
+    }
+
+    pub fn send_due(session_id: &SessionId, sessions: &SessionMap) -> Result<(), SendError> {
+        // sends the scheduled messages that are due by now, mirrored to drop copy sessions like
+        // any other. one that cannot go out is dropped with the error, the rest wait for the
+        // next call
+        loop {
+            let due = {
+                let mut sess_ref = sessions
+                    .get_session_mut(session_id)
+                    .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+                let now = sess_ref.clock.now();
+                match sess_ref.next_send_time() {
+                    Some(due) if due <= now => sess_ref.scheduled_sends.pop_front(),
+                    _ => None,
+                }
+            };
+            let Some((_, mut msg)) = due else {
+                return Ok(());
+            };
+            if let Err(e) = Session::sync_send_counted(session_id, sessions, &mut msg) {
+                // stamped by the attempt, the MsgSeqNum it did not go out with is taken off
+                msg.header_mut().remove_field(MsgSeqNum::field());
+                if let Some(sess) = sessions.get_session(session_id) {
+                    sess.dead_letter(
+                        dead_letter_raw(msg),
+                        DeadLetterReason::SendFailed(e.to_string()),
+                    );
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    pub fn send_queued(&mut self) -> Result<(), SendError> {
+        // delivers queued messages in the order they were queued. they leave the queue only
+        // once sent, so a crash in between sends them again rather than losing them
+        self.expire_queued()?;
+        let queued = self.message_store.lock().queued()?;
+        let mut delivered = 0;
+        let mut result = Ok(());
+        for raw in queued.iter() {
+            // one that no longer parses is given up on rather than blocking the rest
+            match Message::from_str(raw, &self.data_dictionary) {
+                Ok(mut msg) => {
+                    // queued before a Logon switched the session's BeginString
+                    msg.header_mut().set_begin_string(&self.begin_string);
+                    result = self.send_to_target(msg);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    self.dead_letter(raw.clone(), DeadLetterReason::Unparsable(e.to_string()))
+                }
+            }
+            delivered += 1;
+        }
+        self.message_store.lock().dequeue(delivered)?;
+        result
+    }
+
+    // traffic of the connection the session is logged on over
+    pub fn connection_stats(&self) -> Option<ConnectionStats> {
+        self.connection.as_ref().map(|connection| connection.counters().stats())
+    }
+
+    pub fn send_queue_depth(&self) -> usize {
+        // messages handed to the connection that are not written yet
+        self.responder
+            .as_ref()
+            .map_or(0, |responder| responder.max_capacity() - responder.capacity())
+    }
+
+    fn write(&mut self, raw: String) -> Result<Option<u64>, SendError> {
+        // the send number is there when a connection counts the writes
+        let responder = self.responder.as_ref().ok_or(SendError::Disconnected)?;
+        let sent = match self.send_queue_depth() >= self.send_queue_size {
+            true => Err(TrySendError::Full(raw)),
+            false => responder.try_send(raw),
+        };
+        match sent {
+            Ok(()) => {
+                self.state.last_sent_time = Some(self.clock.now());
+                Ok(self.responder_counters.as_ref().map(|counters| counters.queued()))
+            }
+            Err(TrySendError::Closed(_)) => Err(SendError::Disconnected),
+            Err(TrySendError::Full(_)) => {
+                if self.send_queue_overflow == SendQueueOverflow::Disconnect {
+                    let _ = self.drop_connection("send queue full");
+                }
+                Err(SendError::QueueFull)
+            }
+        }
+    }
+
+    fn drop_connection(&mut self, reason: &str) -> Result<(), SendError> {
+        let connection = self.connection.take().ok_or(SendError::Disconnected)?;
+        println!("disconnecting {}: {}", self.session_id, reason);
+        self.is_active = false;
+        self.logon_sent = false;
+        self.peer_addr = None;
+        self.responder = None;
+        self.responder_counters = None;
+        connection.close();
+        Ok(())
+    }
+
+    pub fn resend(&mut self, begin_seq_no: u64, end_seq_no: u64) -> Result<(), SendError> {
+        // answers a ResendRequest from the message store. EndSeqNo of 0 means everything sent
+        // so far, seqnums that are not stored or not replayed are gap filled. the replay goes
+        // out in a single write, so it is either queued as a whole or refused as a whole
+        let last_sent = self.state.next_sender_msg_seq_num() - 1;
+        let end = match end_seq_no {
+            0 => last_sent,
+            end => end.min(last_sent),
+        };
+        let store = self.message_store.clone();
+        let store = store.lock();
+        let mut frames = String::new();
+        let mut gap_start = None;
+        let mut next_seq_num = begin_seq_no;
+        for stored in store.iter_range(begin_seq_no, end) {
+            let (seq_num, raw) = stored?;
+            if seq_num > next_seq_num {
+                gap_start.get_or_insert(next_seq_num);
+            }
+            match self.resend_action(&raw) {
+                Resend::GapFill => {
+                    gap_start.get_or_insert(seq_num);
+                }
+                replay => {
+                    if let Some(start) = gap_start.take() {
+                        frames.push_str(&self.gap_fill_frame(start, seq_num)?);
+                    }
+                    match replay {
+                        Resend::Frame(frame) => frames.push_str(&frame),
+                        Resend::Message(msg) => frames.push_str(&self.resend_frame(*msg)),
+                        Resend::GapFill => {}
+                    }
+                }
+            }
+            next_seq_num = seq_num + 1;
+        }
+        if next_seq_num <= end {
+            gap_start.get_or_insert(next_seq_num);
+        }
+        if let Some(start) = gap_start {
+            frames.push_str(&self.gap_fill_frame(start, end + 1)?);
+        }
+        match frames.is_empty() {
+            true => Ok(()),
+            false => self.write(frames).map(|_| ()),
+        }
+    }
+
+    fn check_resend_range(begin_seq_no: u64, end_seq_no: u64) -> Result<(), SessionRejectError> {
+        // BeginSeqNo(7) starts at 1 and EndSeqNo(16), unless 0 for everything, is not before it
+        if begin_seq_no == 0 {
+            return Err(SessionRejectError::value_out_of_range_err().with_tag(BeginSeqNo::field()));
+        }
+        if end_seq_no != 0 && end_seq_no < begin_seq_no {
+            return Err(SessionRejectError::value_out_of_range_err().with_tag(EndSeqNo::field()));
+        }
+        Ok(())
+    }
+
+    pub async fn purge_store(
+        session_id: &SessionId, sessions: &SessionMap,
+    ) -> std::io::Result<usize> {
+        // drops, and archives, the sent messages the retention policy no longer keeps. ages
+        // go by the session's clock. the files are rewritten on a blocking thread with only the
+        // store locked, the session goes on meanwhile
+        let Some((store, retention, now)) = sessions
+            .get_session(session_id)
+            .map(|sess| (sess.message_store.clone(), sess.retention.clone(), sess.clock.now()))
+        else {
+            return Ok(0);
+        };
+        let (session_id, sessions) = (session_id.clone(), sessions.clone());
+        let purge = move || {
+            let purged = retention.purge(&mut *store.lock(), &session_id, now)?;
+            if purged > 0 {
+                // the seqnums can no longer be recovered from the purged messages. the session
+                // is taken before the store, like sends do
+                if let Some(sess) = sessions.get_session(&session_id) {
+                    let (sender, target) = (
+                        sess.state.next_sender_msg_seq_num(),
+                        sess.state.next_target_msg_seq_num(),
+                    );
+                    sess.message_store.lock().set_seq_nums(sender, target)?;
+                }
+            }
+            Ok(purged)
+        };
+        tokio::task::spawn_blocking(purge).await.map_err(std::io::Error::other)?
+    }
+
+    fn resend_action(&self, raw: &[u8]) -> Resend {
+        // the stored bytes are replayed as they are but for the resend fields, only a hook
+        // gets them parsed
+        let Ok(raw) = std::str::from_utf8(raw) else {
+            return Resend::GapFill;
+        };
+        let msg_type = find_field(raw, MsgType::field()).map_or("", |(_, field)| &field[3..]);
+        let is_admin = self.data_dictionary.is_admin_msg_type(msg_type);
+        match self.resend_policies.get(msg_type, is_admin) {
+            ResendPolicy::GapFill => Resend::GapFill,
+            ResendPolicy::Replay => {
+                let now = UtcTimestamp::new(self.clock.now(), TimePrecision::Millis);
+                Resend::Frame(resend_frame(raw, &now.to_string()))
+            }
+            hook => match Message::from_str(raw, &self.data_dictionary) {
+                Ok(msg) => match hook.apply(msg) {
+                    ResendAction::Replay(msg) => Resend::Message(Box::new(msg)),
+                    ResendAction::GapFill => Resend::GapFill,
+                },
+                Err(_) => Resend::GapFill,
+            },
+        }
+    }
+
+    fn gap_fill_frame(&self, seq_num: u64, new_seq_no: u64) -> Result<String, SendError> {
+        let mut gap_fill = admin::sequence_reset(self, new_seq_no, true)?;
+        gap_fill.header_mut().set_seq_num(seq_num);
+        Ok(self.resend_frame(gap_fill))
+    }
+
+    fn resend_frame(&self, mut msg: Message) -> String {
+        // keeps its MsgSeqNum, the original SendingTime goes into OrigSendingTime
+        let header = msg.header_mut();
+        header.set_poss_dup(true);
+        if let Ok(sending_time) = header.get_field::<String>(SendingTime::field()) {
+            header.set_orig_sending_time(&sending_time);
+        }
+        msg.set_sending_time(&self.clock);
+        msg.set_body_len();
+        msg.set_checksum();
+        msg.to_string()
+    }
+
+    pub fn sync_send_to_target(
+        session_id: &SessionId, sessions: &SessionMap, mut msg: Message,
+    ) -> Result<(), SendError> {
+        Session::sync_send_counted(session_id, sessions, &mut msg).map(|_| ())
+    }
+
+    fn sync_send_counted(
+        session_id: &SessionId, sessions: &SessionMap, msg: &mut Message,
+    ) -> Result<Option<(Arc<ConnectionCounters>, u64)>, SendError> {
+        // the counters and send number are taken under the session lock, a concurrent send
+        // may queue right after it is released
+        let (mirror, counted) = {
+            let mut sess_ref = sessions
+                .get_session_mut(session_id)
+                .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+            let mirror = (!sess_ref.drop_copy_sessions.is_empty()).then(|| msg.clone());
+            let send = sess_ref.send_counted(msg)?;
+            let counters = sess_ref.responder_counters.clone();
+            (mirror, counters.zip(send))
+        };
+        // session ref is released by now, drop copy sessions may live in the same shard
+        if let Some(msg) = mirror {
+            Session::drop_copy(session_id, sessions, &msg);
+        }
+        Ok(counted)
+    }
+
+    pub fn send_confirmed(
+        session_id: &SessionId, sessions: &SessionMap, mut msg: Message,
+    ) -> impl Future<Output = Result<(), SendError>> {
+        // like sync_send_to_target, and resolves once the message is written to the socket, for
+        // applications that acknowledge upstream only what went out. Disconnected if the
+        // connection is gone before it is, or if no connection writes for the responder
+        let queued = Session::sync_send_counted(session_id, sessions, &mut msg);
+        async move {
+            let (counters, send) = queued?.ok_or(SendError::Disconnected)?;
+            match counters.wait_written(send).await {
+                true => Ok(()),
+                false => Err(SendError::Disconnected),
+            }
+        }
+    }
+
+    pub fn request(
+        session_id: &SessionId, sessions: &SessionMap, msg: Message,
+        matcher: impl Fn(&Message) -> bool + Send + Sync + 'static, timeout: std::time::Duration,
+    ) -> impl Future<Output = Result<Message, RequestError>> {
+        // sends msg and resolves to the first message received on the session afterwards that
+        // matcher accepts, e.g. the Heartbeat answering a TestRequest. the response still goes
+        // to the application as well
+        let (reply, response) = oneshot::channel();
+        let pending = sessions.get_session(session_id).map(|sess| sess.pending_responses.clone());
+        let sent = match pending {
+            Some(pending) => {
+                let mut pending = pending.lock().unwrap();
+                // waiters that gave up are dropped on the way
+                pending.retain(|pending| !pending.reply.is_closed());
+                pending.push(PendingResponse {
+                    matcher: Box::new(matcher),
+                    reply,
+                });
+                drop(pending);
+                Session::sync_send_to_target(session_id, sessions, msg)
+            }
+            None => Err(SendError::SessionNotFound(session_id.to_string())),
+        };
+        async move {
+            sent?;
+            match tokio::time::timeout(timeout, response).await {
+                Ok(Ok(msg)) => Ok(msg),
+                Ok(Err(_)) => Err(RequestError::Canceled),
+                Err(_) => Err(RequestError::Timeout(timeout)),
+            }
+        }
+    }
+
+    pub fn same_field(request: &Message, tag: u32) -> impl Fn(&Message) -> bool + Send + Sync {
+        // matcher for responses that carry the request's value of tag, e.g. TestReqID(112),
+        // MDReqID(262) or ClOrdID(11)
+        let value = request.get_field::<String>(tag).ok();
+        move |msg| value.is_some() && msg.get_field::<String>(tag).ok() == value
+    }
+
+    pub fn correlate(session_id: &SessionId, sessions: &SessionMap, msg: &Message) {
+        // hands a received message to the first pending request it answers
+        if let Some(sess) = sessions.get_session(session_id) {
+            let mut pending = sess.pending_responses.lock().unwrap();
+            pending.retain(|pending| !pending.reply.is_closed());
+            if let Some(idx) = pending.iter().position(|pending| (pending.matcher)(msg)) {
+                // a waiter that gave up since is the same as no waiter
+                let _ = pending.remove(idx).reply.send(msg.clone());
+            }
+        }
+    }
+
+    pub fn drop_copy(session_id: &SessionId, sessions: &SessionMap, msg: &Message) {
+        // every copy gets the drop copy session's comp ids and seqnum. OnBehalfOfCompID names
+        // the counterparty of the primary session
+        let (targets, counterparty) = match sessions.get_session(session_id) {
+            Some(sess) if !sess.drop_copy_sessions.is_empty() => {
+                (sess.drop_copy_sessions.clone(), sess.session_id.target_compid().clone())
+            }
+            _ => return,
+        };
+        for dc_id in targets.iter() {
+            let mut dc = match sessions.get_session_mut(dc_id) {
+                Some(dc) => dc,
+                None => {
+                    println!("drop copy session {} not found", dc_id);
+                    continue;
+                }
+            };
+            let mut copy = msg.clone();
+            let header = copy.header_mut();
+            let primary_only = [
+                SenderSubID::field(),
+                SenderLocationID::field(),
+                TargetSubID::field(),
+                TargetLocationID::field(),
+                PossDupFlag::field(),
+                PossResend::field(),
+                OrigSendingTime::field(),
+            ];
+            for tag in primary_only {
+                header.remove_field(tag);
+            }
+            header.set_on_behalf_of_comp_id(&counterparty);
+            dc.stamp_comp_ids(header);
+            if let Err(e) = dc.send_to_target(copy) {
+                println!("could not drop copy to {}: {}", dc_id, e);
+            }
+        }
+    }
+
+    pub fn forward(
+        session_id: &SessionId, sessions: &SessionMap, to: &SessionId, msg: &Message,
+    ) -> Result<(), SendError> {
+        // sends a message received on session_id with DeliverToCompID(128) on to the session it
+        // is meant for, as a hub does. OnBehalfOf ids name where it came from and DeliverTo ids
+        // are dropped, the session forwarded on reaches the addressee directly
+        let mut fwd = msg.clone();
+        let header = fwd.header_mut();
+        let on_behalf_of: [(SetId, _); 2] = [
+            (Header::set_on_behalf_of_sub_id, header.remove_field(SenderSubID::field())),
+            (Header::set_on_behalf_of_location_id, header.remove_field(SenderLocationID::field())),
+        ];
+        let inbound_only = [
+            TargetSubID::field(),
+            TargetLocationID::field(),
+            DeliverToCompID::field(),
+            DeliverToSubID::field(),
+            DeliverToLocationID::field(),
+            PossDupFlag::field(),
+            PossResend::field(),
+            OrigSendingTime::field(),
+            LastMsgSeqNumProcessed::field(),
+        ];
+        for tag in inbound_only {
+            header.remove_field(tag);
+        }
+        header.set_on_behalf_of_comp_id(session_id.target_compid());
+        for (set_id, field) in on_behalf_of {
+            if let Some(field) = field {
+                set_id(header, field.value());
+            }
+        }
+        let mut sess_ref = sessions
+            .get_session_mut(to)
+            .ok_or_else(|| SendError::SessionNotFound(to.to_string()))?;
+        sess_ref.stamp_comp_ids(fwd.header_mut());
+        sess_ref.send_to_target(fwd)
+    }
+
+    pub fn next_admin(
+        session_id: &SessionId, sessions: &SessionMap, msg: &Message,
+    ) -> Result<(), SendError> {
+        // responds to the session level messages received from counterparty
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        let session = sess_ref.value_mut();
+        // a message past a gap does not use up a seqnum, the resend covers the ones before it
+        let seq_num = msg.header().seq_num().unwrap_or_default();
+        let expected = session.state.next_target_msg_seq_num();
+        if seq_num == expected {
+            session.state.incr_next_target_msg_seq_num();
+            session.seq_nums_changed();
+        }
+        let msg_type = msg.header().get_field::<String>(MsgType::field()).unwrap_or_default();
+        match msg_type.as_str() {
+            "A" if !session.is_active => {
+                session.is_active = true;
+                let logon_sent = std::mem::take(&mut session.logon_sent);
+                // ResetSeqNumFlag(141)=Y from the counterparty, or in the Logon we answer with,
+                // starts both sides over. our own Logon reset the sender side when it was sent
+                let reset_requested = is_reset_logon(msg);
+                if reset_requested || (!logon_sent && session.reset_on_logon) {
+                    if !logon_sent {
+                        session.reset_seq_nums()?;
+                    }
+                    let seq_num = msg.header().seq_num().unwrap_or(1);
+                    session.state.set_next_target_msg_seq_num(seq_num + 1);
+                    session.seq_nums_changed();
+                }
+                // a Logon answering our own is not answered again
+                if !logon_sent {
+                    let mut logon = admin::logon(session)?;
+                    if reset_requested {
+                        logon.set_field(StringField::new(ResetSeqNumFlag::field(), "Y"));
+                    }
+                    session.send_to_target(logon)?;
+                }
+                session.send_queued()
+            }
+            "2" => {
+                let begin_seq_no = msg.get_field::<u64>(BeginSeqNo::field()).unwrap_or(1);
+                let end_seq_no = msg.get_field::<u64>(EndSeqNo::field()).unwrap_or(0);
+                if let Err(e) = Session::check_resend_range(begin_seq_no, end_seq_no) {
+                    let ref_seq_num = msg.header().seq_num().unwrap_or_default();
+                    let reject = admin::reject(session, ref_seq_num, Some("2"), &e)?;
+                    return session.send_to_target(reject);
+                }
+                session.resend(begin_seq_no, end_seq_no)
+            }
+            "1" => {
+                let test_req_id = msg.get_field::<String>(TestReqID::field()).ok();
+                let heartbeat = admin::heartbeat(session, test_req_id.as_deref())?;
+                session.send_to_target(heartbeat)
+            }
+            "4" => {
+                // a gap fill past the expected seqnum is itself a gap and a resent one from
+                // before is ignored. in reset mode MsgSeqNum does not matter
+                let gap_fill =
+                    msg.get_field::<String>(GapFillFlag::field()).is_ok_and(|f| f == "Y");
+                if gap_fill && seq_num != expected {
+                    return Ok(());
+                }
+                match msg.get_field::<u64>(NewSeqNo::field()) {
+                    Ok(new_seq_no) if new_seq_no >= session.state.next_target_msg_seq_num() => {
+                        session.state.set_next_target_msg_seq_num(new_seq_no);
+                        session.seq_nums_changed();
+                        Ok(())
+                    }
+                    _ => {
+                        // NewSeqNo(36) would move the expected seqnum back
+                        let err = SessionRejectError::value_out_of_range_err()
+                            .with_tag(NewSeqNo::field());
+                        let reject = admin::reject(session, seq_num, Some("4"), &err)?;
+                        session.send_to_target(reject)
+                    }
+                }
+            }
+            "5" if session.is_active => {
+                session.is_active = false;
+                let logout = admin::logout(session, None)?;
+                session.send_to_target(logout)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn request_resend(
+        session_id: &SessionId, sessions: &SessionMap, received: u64,
+    ) -> Result<(), SendError> {
+        // MsgSeqNum higher than expected, the counterparty is asked for everything from the
+        // expected seqnum on. while one ResendRequest is outstanding no other goes out
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        let session = sess_ref.value_mut();
+        let expected = session.state.next_target_msg_seq_num();
+        if received <= expected {
+            return Ok(());
+        }
+        match session.state.resend_requested_through() {
+            Some(through) if expected <= through => {
+                session.state.set_resend_requested_through(Some(through.max(received)));
+                Ok(())
+            }
+            _ => {
+                let resend_request = admin::resend_request(session, expected, 0)?;
+                session.send_to_target(resend_request)?;
+                session.state.set_resend_requested_through(Some(received));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn reject_logon(
+        session_id: &SessionId, sessions: &SessionMap, reason: &str,
+    ) -> Result<(), SendError> {
+        // logon is consumed but the session stays inactive, counterparty is told why
+        if let Some(mut sess_ref) = sessions.get_session_mut(session_id) {
+            sess_ref.state.incr_next_target_msg_seq_num();
+            sess_ref.seq_nums_changed();
+        }
+        Session::terminate(session_id, sessions, reason)
+    }
+
+    pub fn reject_inbound(
+        session_id: &SessionId, sessions: &SessionMap, raw: &str, err: &SessionRejectError,
+    ) -> Result<(), SendError> {
+        // inbound message that does not parse or validate. the counterparty gets a Reject(3)
+        // telling why, and its seqnum is used up like that of a message that is delivered
+        let ref_seq_num = Message::raw_field(raw, MsgSeqNum::field())
+            .and_then(|seq_num| seq_num.parse::<u64>().ok())
+            .unwrap_or_default();
+        let ref_msg_type = Message::raw_field(raw, MsgType::field());
+        let reject = {
+            let mut sess_ref = sessions
+                .get_session_mut(session_id)
+                .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+            if ref_seq_num >= sess_ref.state.next_target_msg_seq_num() {
+                sess_ref.state.incr_next_target_msg_seq_num();
+                if let Err(e) = sess_ref.message_store.lock().set_received(ref_seq_num) {
+                    println!("could not store received {} for {}: {}", ref_seq_num, session_id, e);
+                }
+                sess_ref.seq_nums_changed();
+            }
+            admin::reject(&sess_ref, ref_seq_num, ref_msg_type, err)
+        };
+        match reject {
+            Ok(reject) => Session::sync_send_to_target(session_id, sessions, reject),
+            Err(e) => {
+                println!("could not build reject for {}: {}", session_id, e);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn terminate(
+        session_id: &SessionId, sessions: &SessionMap, reason: &str,
+    ) -> Result<(), SendError> {
+        // engine initiated logout, reason goes out as Text(58)
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        let session = sess_ref.value_mut();
+        session.is_active = false;
+        let logout = admin::logout(session, Some(reason))?;
+        session.send_to_target(logout)
+    }
+
+    pub fn logon(session_id: &SessionId, sessions: &SessionMap) -> Result<(), SendError> {
+        // initiator side, sends a Logon over the session's connection. the session becomes
+        // active with the counterparty's answer, nothing is sent while it is logged on
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        let session = sess_ref.value_mut();
+        if session.is_active || session.logon_sent {
+            return Ok(());
+        }
+        // the Logon carries ResetSeqNumFlag(141)=Y, it goes out as seqnum 1
+        if session.reset_on_logon {
+            session.reset_seq_nums()?;
+        }
+        let logon = admin::logon(session)?;
+        session.send_to_target(logon)?;
+        session.logon_sent = true;
+        Ok(())
+    }
+
+    pub fn disconnect(
+        session_id: &SessionId, sessions: &SessionMap, reason: &str,
+    ) -> Result<(), SendError> {
+        // drops the connection without a Logout, e.g. for a counterparty that stopped
+        // responding. the session can log on again over a new connection
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        sess_ref.value_mut().drop_connection(reason)
+    }
+
+    pub fn reset(session_id: &SessionId, sessions: &SessionMap) -> Result<(), SendError> {
+        // starts the session over. a logged on session is logged out first and its connection
+        // closed once the Logout is written, then seqnums go back to 1 and stored, queued and
+        // scheduled messages are dropped
+        let mut sess_ref = sessions
+            .get_session_mut(session_id)
+            .ok_or_else(|| SendError::SessionNotFound(session_id.to_string()))?;
+        let session = sess_ref.value_mut();
+        let mut result = Ok(());
+        if session.is_active {
+            session.is_active = false;
+            result = admin::logout(session, Some(SESSION_RESET_REASON))
+                .map_err(SendError::from)
+                .and_then(|logout| session.send_to_target(logout));
+            let _ = session.drop_connection(SESSION_RESET_REASON);
+        }
+        session.logon_sent = false;
+        for (_, msg) in std::mem::take(&mut session.scheduled_sends) {
+            session.dead_letter(dead_letter_raw(msg), DeadLetterReason::SessionReset);
+        }
+        let queued = session.message_store.lock().queued().unwrap_or_default();
+        for raw in queued {
+            session.dead_letter(raw, DeadLetterReason::SessionReset);
+        }
+        // whoever waits on a response gets Canceled
+        session.pending_responses.lock().unwrap().clear();
+        session.state = SessionState::new();
+        session.pending_seq_nums = 0;
+        session.pending_since = None;
+        session.message_store.lock().reset()?;
+        result
+    }
+
+    fn reset_seq_nums(&mut self) -> Result<(), SendError> {
+        // both seqnums start over at 1 and the messages sent so far can no longer be resent.
+        // messages queued for the logon are kept
+        let mut store = self.message_store.lock();
+        let queued = store.queued()?;
+        store.reset()?;
+        for raw in &queued {
+            store.enqueue(raw)?;
+        }
+        drop(store);
+        self.state.set_next_sender_msg_seq_num(1);
+        self.state.set_next_target_msg_seq_num(1);
+        self.seq_nums_changed();
+        Ok(())
+    }
+
+    pub fn check_target_seq_num(&self, msg: &Message) -> Result<(), String> {
+        // a MsgSeqNum lower than expected is fatal unless the message is a possible duplicate
+        // or a Logon resetting the sequence
+        let header = msg.header();
+        let seq_num = header.get_field::<u64>(MsgSeqNum::field()).unwrap_or_default();
+        let expected = self.state.next_target_msg_seq_num();
+        let poss_dup = header.get_field::<String>(PossDupFlag::field()).is_ok_and(|f| f == "Y");
+        let reset = header.get_field::<String>(MsgType::field()).is_ok_and(|t| t == "A")
+            && msg.get_field::<String>(ResetSeqNumFlag::field()).is_ok_and(|f| f == "Y");
+        if seq_num >= expected || poss_dup || reset {
+            return Ok(());
+        }
+        Err(format!("MsgSeqNum too low, expecting {} but received {}", expected, seq_num))
+    }
+
+    pub fn is_poss_resend(msg: &Message) -> bool {
+        // sent before under another seqnum, unlike PossDup the seqnum itself is new
+        msg.header().get_field::<String>(PossResend::field()).is_ok_and(|f| f == "Y")
+    }
+
+    pub fn is_duplicate(&self, msg: &Message) -> bool {
+        // resent application message that was delivered before
+        let header = msg.header();
+        let poss_dup = header.get_field::<String>(PossDupFlag::field()).is_ok_and(|f| f == "Y");
+        let seq_num = header.get_field::<u64>(MsgSeqNum::field()).unwrap_or_default();
+        self.deduplicate_poss_dup && poss_dup && self.message_store.lock().is_received(seq_num)
+    }
+
+    pub fn next_app(session_id: &SessionId, sessions: &SessionMap, msg: &Message) {
+        // application messages are handed to the Application, session only tracks the seqnum,
+        // remembers it as received, mirrors them to drop copy sessions and shows them to the
+        // order tracker
+        Session::skip_app(session_id, sessions, msg);
+        Session::drop_copy(session_id, sessions, msg);
+        let tracker = sessions.get_session(session_id).and_then(|sess| sess.order_tracker.clone());
+        if let Some(tracker) = tracker {
+            tracker.observe(session_id, msg);
+        }
+    }
+
+    pub fn skip_app(session_id: &SessionId, sessions: &SessionMap, msg: &Message) {
+        // application message that is not delivered, e.g. a PossResend the application already
+        // has. its seqnum is used up all the same, drop copy sessions do not see it
+        if let Some(mut sess_ref) = sessions.get_session_mut(session_id) {
+            let seq_num = msg.header().get_field::<u64>(MsgSeqNum::field()).unwrap_or_default();
+            // a resent message from before does not move the expected seqnum
+            if seq_num >= sess_ref.state.next_target_msg_seq_num() {
+                sess_ref.state.incr_next_target_msg_seq_num();
+            }
+            if let Err(e) = sess_ref.message_store.lock().set_received(seq_num) {
+                println!("could not store received {} for {}: {}", seq_num, session_id, e);
+            }
+            sess_ref.seq_nums_changed();
+        }
+    }
+}
+
+pub(crate) fn is_reset_logon(msg: &Message) -> bool {
+    msg.header().msg_type() == Some("A")
+        && msg.get_field::<String>(ResetSeqNumFlag::field()).is_ok_and(|f| f == "Y")
+}
+
+fn dead_letter_raw(mut msg: Message) -> String {
+    // complete like a queued message, just without the seqnum
+    msg.set_body_len();
+    msg.set_checksum();
+    msg.to_string()
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+    use crate::messages::NewOrderSingle;
+    use assert_matches::assert_matches;
+    use tokio::sync::mpsc::channel;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+        send_queue_size = 2
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10117
+    "#;
+
+    fn test_session() -> Session {
+        // the clock stands still, so that a message stamps the same SendingTime when it is
+        // built and when it is sent
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T10:00:00").unwrap().and_utc();
+        session.set_clock(SharedClock::new(crate::clock::ManualClock::new(start)));
+        session
+    }
+
+    #[tokio::test]
+    async fn test_purge_store() {
+        let settings = Properties::from_str(&format!("{}store_max_messages = 1\n", CONFIG));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        session.send_queue_size = 8;
+        let (tx, _rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        for _ in 0..3 {
+            let msg = admin::heartbeat(&session, None).unwrap();
+            session.send_to_target(msg).unwrap();
+        }
+        let store = session.message_store.clone();
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        assert_eq!(Session::purge_store(&session_id, &sessions).await.unwrap(), 2);
+        assert_eq!(store.lock().stored_count(), 1);
+        assert_eq!(store.lock().seq_nums(), Some((4, 1)));
+        assert_eq!(Session::purge_store(&session_id, &sessions).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_send_without_connection() {
+        let mut session = test_session();
+        let msg = admin::heartbeat(&session, None).unwrap();
+        assert_matches!(session.send_to_target(msg.clone()), Err(SendError::Disconnected));
+
+        // responder of a connection that is gone
+        let (tx, rx) = channel::<String>(8);
+        drop(rx);
+        session.set_responder(Some(tx));
+        assert_matches!(session.send_to_target(msg), Err(SendError::Disconnected));
+    }
+
+    #[test]
+    fn test_send_queue_full() {
+        let mut session = test_session();
+        assert_eq!(session.send_queue_size(), 2);
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let msg = admin::heartbeat(&session, None).unwrap();
+        assert!(session.send_to_target(msg.clone()).is_ok());
+        assert!(session.send_to_target(msg.clone()).is_ok());
+        assert_matches!(session.send_to_target(msg.clone()), Err(SendError::QueueFull));
+        // draining the queue makes room again
+        assert_eq!(rx.try_recv().unwrap(), msg.to_string());
+        assert!(session.send_to_target(msg).is_ok());
+    }
+
+    #[test]
+    fn test_send_queue_overflow() {
+        let settings =
+            Properties::from_str(&format!("{}send_queue_overflow = \"disconnect\"", CONFIG));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        assert_eq!(session.send_queue_overflow(), SendQueueOverflow::Disconnect);
+        assert_eq!(test_session().send_queue_overflow(), SendQueueOverflow::Reject);
+        let (tx, _rx) = channel::<String>(8);
+        let source = "127.0.0.1:9880".parse().unwrap();
+        let inbound = InboundMessage::new(String::new(), source, ConnectionHandle::new(tx));
+        session.set_connection(Some(&inbound));
+        let msg = admin::heartbeat(&session, None).unwrap();
+        assert!(session.send_to_target(msg.clone()).is_ok());
+        assert!(session.send_to_target(msg.clone()).is_ok());
+        assert_eq!(session.send_queue_depth(), 2);
+
+        // the message is refused and the slow connection dropped with it
+        assert_matches!(session.send_to_target(msg.clone()), Err(SendError::QueueFull));
+        assert!(session.peer_addr().is_none());
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+        assert_matches!(session.send_to_target(msg), Err(SendError::Disconnected));
+    }
+
+    #[test]
+    fn test_scheduled_sends() {
+        use crate::clock::ManualClock;
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T10:00:00").unwrap().and_utc();
+        let clock = ManualClock::new(start);
+        let mut session = test_session();
+        session.send_queue_size = 8;
+        session.set_clock(SharedClock::new(clock.clone()));
+        let session_id = session.session_id.clone();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let dd = Arc::clone(session.data_dictionary());
+        let order = |session: &Session, cl_ord_id: &str| {
+            let mut msg = session.new_message("D").unwrap();
+            msg.set_field(StringField::new(11, cl_ord_id));
+            msg
+        };
+        let heartbeat = admin::heartbeat(&session, None).unwrap();
+        session.send_after(heartbeat, chrono::Duration::seconds(5));
+        let at = start + chrono::Duration::seconds(2);
+        session.send_at(order(&session, "ORDER1"), at);
+        session.send_at(order(&session, "ORDER2"), at);
+        assert_eq!(session.next_send_time(), Some(at));
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        let mut sent = || {
+            Session::send_due(&session_id, &sessions).unwrap();
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|raw| Message::from_str(&raw, &dd).unwrap())
+                .map(|msg| msg.get_field::<String>(11).unwrap_or(msg.get_msg_type().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(sent().is_empty());
+        clock.advance(chrono::Duration::seconds(2));
+        assert_eq!(sent(), ["ORDER1", "ORDER2"]);
+        clock.advance(chrono::Duration::seconds(2));
+        assert!(sent().is_empty());
+        clock.advance(chrono::Duration::seconds(1));
+        assert_eq!(sent(), ["0"]);
+        assert_eq!(sessions.get_session(&session_id).unwrap().next_send_time(), None);
+    }
+
+    #[test]
+    fn test_dead_letters() {
+        use crate::clock::ManualClock;
+        let start = chrono::NaiveDateTime::from_str("2024-03-05T10:00:00").unwrap().and_utc();
+        let clock = ManualClock::new(start);
+        let settings = Properties::from_str(&format!("{}queue_max_age = 60\n", CONFIG));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        session.set_clock(SharedClock::new(clock.clone()));
+        let letters = Arc::new(Mutex::new(Vec::new()));
+        let (sink_letters, dd) = (Arc::clone(&letters), Arc::clone(session.data_dictionary()));
+        session.set_dead_letter_sink(SharedDeadLetterSink::new(move |letter: &DeadLetter| {
+            let msg = Message::from_str(&letter.raw, &dd).unwrap();
+            let cl_ord_id = msg.get_field::<String>(11).unwrap();
+            // without the MsgSeqNum a failed send was stamped with
+            assert!(msg.header().get_field::<u64>(34).is_err());
+            sink_letters.lock().unwrap().push((cl_ord_id, letter.reason.clone()));
+        }));
+        let order = |session: &Session, cl_ord_id: &str| {
+            let mut msg = session.new_message("D").unwrap();
+            msg.set_field(StringField::new(11, cl_ord_id));
+            msg
+        };
+        let dead = || std::mem::take(&mut *letters.lock().unwrap());
+
+        // not logged on, queued until they are too old
+        session.send_or_queue(order(&session, "ORDER1")).unwrap();
+        clock.advance(chrono::Duration::seconds(30));
+        session.send_or_queue(order(&session, "ORDER2")).unwrap();
+        clock.advance(chrono::Duration::seconds(31));
+        session.expire_queued().unwrap();
+        assert_eq!(dead(), [("ORDER1".to_string(), DeadLetterReason::QueueExpired)]);
+        assert_eq!(session.message_store().lock().queued().unwrap().len(), 1);
+
+        // scheduled sends that cannot go out
+        session.send_at(order(&session, "ORDER3"), start);
+        session.send_at(order(&session, "ORDER4"), start + chrono::Duration::days(1));
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        assert!(Session::send_due(&session_id, &sessions).is_err());
+        let reason = DeadLetterReason::SendFailed(SendError::Disconnected.to_string());
+        assert_eq!(dead(), [("ORDER3".to_string(), reason)]);
+
+        // a reset drops the rest
+        Session::reset(&session_id, &sessions).unwrap();
+        let reset = DeadLetterReason::SessionReset;
+        let dropped = [
+            ("ORDER4".to_string(), reset.clone()),
+            ("ORDER2".to_string(), reset),
+        ];
+        assert_eq!(dead(), dropped);
+    }
+
+    #[test]
+    fn test_dead_letter_path_not_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("dead_letters");
+        let config = format!("{}dead_letter_path = \"{}\"\n", CONFIG, path.display());
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let opened = Session::with_settings(&session_id, &Properties::from_str(&config));
+        assert!(opened.is_err());
+    }
+
+    #[test]
+    fn test_send_wire_queued_and_routed() {
+        let config = format!("{}on_behalf_of_comp_id = HUB\ndeliver_to_comp_id = BROKER\n", CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session =
+            Session::with_settings(&session_id, &Properties::from_str(&config)).unwrap();
+        let tracker = Arc::new(OrderTracker::new());
+        session.set_order_tracker(Some(Arc::clone(&tracker)));
+        let order = |cl_ord_id: &str| {
+            let mut order = WireBuilder::new("D", NewOrderSingle::LAYOUT);
+            order.set(11, cl_ord_id).set(21, '1').set(55, "IBM").set(54, '1').set(40, '1');
+            order
+        };
+
+        // no connection, queued without using up a seqnum
+        session.send_wire(&order("ORDER1")).unwrap();
+        assert_eq!(session.message_store().lock().queued().unwrap().len(), 1);
+        assert_eq!(session.state().next_sender_msg_seq_num(), 1);
+
+        // routing ids it has are not stamped again, header tags go in the header
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let mut routed = order("ORDER2");
+        routed.set(115, "CLIENT");
+        session.send_wire(&routed).unwrap();
+        let raw = rx.try_recv().unwrap();
+        assert_eq!(raw.matches("\u{1}115=").count(), 1);
+        assert!(raw.contains("\u{1}115=CLIENT\u{1}128=BROKER\u{1}34=1\u{1}"));
+        let msg = Message::from_str(&raw, session.data_dictionary()).unwrap();
+        assert_eq!(msg.get_field::<String>(11).unwrap(), "ORDER2");
+        assert_eq!(session.state().next_sender_msg_seq_num(), 2);
+        assert_eq!(session.message_store().lock().get_range(1, 1).unwrap().len(), 1);
+        assert!(tracker.order(&session_id, "ORDER2").is_some());
+        assert!(tracker.order(&session_id, "ORDER1").is_none());
+    }
+
+    #[test]
+    fn test_send_queued_unparsable() {
+        let mut session = test_session();
+        let letters = Arc::new(Mutex::new(Vec::new()));
+        let sink_letters = Arc::clone(&letters);
+        session.set_dead_letter_sink(SharedDeadLetterSink::new(move |letter: &DeadLetter| {
+            sink_letters.lock().unwrap().push(letter.clone());
+        }));
+        let mut order = session.new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        session.message_store().lock().enqueue("8=FIX.4.3\u{1}garbled\u{1}").unwrap();
+        session.send_or_queue(order).unwrap();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+
+        // the garbled one is dead lettered, the order behind it still goes out
+        session.send_queued().unwrap();
+        assert!(rx.try_recv().unwrap().contains("\u{1}11=ORDER1\u{1}"));
+        assert!(rx.try_recv().is_err());
+        assert!(session.message_store().lock().queued().unwrap().is_empty());
+        let letters = letters.lock().unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].raw, "8=FIX.4.3\u{1}garbled\u{1}");
+        assert_matches!(letters[0].reason, DeadLetterReason::Unparsable(_));
+    }
+
+    #[test]
+    fn test_store_open_error() {
+        // the store path is a file, not a directory the store can be created in
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let config = format!("{}file_store_path = \"{}\"\n", CONFIG, file.path().display());
+        let settings = Properties::from_str(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        assert!(Session::with_settings(&session_id, &settings).is_err());
+    }
+
+    #[test]
+    fn test_send_batch() {
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let cancels: Vec<Message> = ["ORDER1", "ORDER2", "ORDER3"]
+            .iter()
+            .map(|cl_ord_id| {
+                let mut msg = session.new_message("F").unwrap();
+                msg.set_field(StringField::new(41, cl_ord_id));
+                msg
+            })
+            .collect();
+        session.send_batch(cancels.clone()).unwrap();
+
+        // one write for all of them
+        let written = rx.try_recv().unwrap();
+        assert!(rx.try_recv().is_err());
+        let frames = split_frames(&written);
+        assert_eq!(frames.len(), 3);
+        for (seq_num, frame) in (1..).zip(frames) {
+            let msg = Message::from_str(frame, session.data_dictionary()).unwrap();
+            assert_eq!(msg.header().get_field::<u64>(34).unwrap(), seq_num);
+            assert_eq!(
+                session.message_store().lock().get_range(seq_num, seq_num).unwrap().len(),
+                1
+            );
+        }
+        assert_eq!(session.state().next_sender_msg_seq_num(), 4);
+
+        // a refused batch uses up no seqnum
+        for _ in 0..2 {
+            session.send_to_target(admin::heartbeat(&session, None).unwrap()).unwrap();
+        }
+        assert_matches!(session.send_batch(cancels), Err(SendError::QueueFull));
+        assert_eq!(session.state().next_sender_msg_seq_num(), 6);
+    }
+
+    #[test]
+    fn test_send_stamps_seq_num() {
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        for expected in 1..=2 {
+            let msg = session.new_message("0").unwrap();
+            assert!(session.send_to_target(msg).is_ok());
+            let sent = Message::from_str(&rx.try_recv().unwrap(), session.data_dictionary());
+            assert_eq!(sent.unwrap().header().get_field::<u64>(34).unwrap(), expected);
+        }
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+
+        // failed sends do not consume a seqnum
+        drop(rx);
+        let msg = session.new_message("0").unwrap();
+        assert!(session.send_to_target(msg).is_err());
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+    }
+
+    #[test]
+    fn test_send_wire() {
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        session.send_to_target(session.new_message("0").unwrap()).unwrap();
+        rx.try_recv().unwrap();
+
+        let mut order = crate::messages::NewOrderSingle::builder();
+        order.set(11, "ORDER1").set(21, '1').set(55, "IBM").set(54, '1').set(40, '1');
+        order.set(60, "20221006-08:43:37.100").set(38, 100);
+        session.send_wire(&order).unwrap();
+        let raw = rx.try_recv().unwrap();
+        let sent = Message::from_str(&raw, session.data_dictionary()).unwrap();
+        assert_eq!(sent.header().get_field::<u64>(34).unwrap(), 2);
+        assert_eq!(sent.header().get_field::<String>(49).unwrap(), "SENDER");
+        assert_eq!(sent.header().get_field::<String>(56).unwrap(), "TARGET");
+        assert_eq!(sent.get_field::<String>(11).unwrap(), "ORDER1");
+        // stored for resend like any other
+        let stored = session.message_store().lock().get_range(2, 2).unwrap();
+        assert_eq!(stored, vec![(2, raw.as_bytes().into())]);
+        assert_eq!(session.state().next_sender_msg_seq_num(), 3);
+    }
+
+    #[test]
+    fn test_drop_copy() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "TARGET"
+            sender_sub_id = "DESK"
+            socket_accept_port = 10117
+            drop_copy_sessions = "RISK, FIX.4.3:SENDER->COMPLIANCE"
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "RISK"
+            socket_accept_port = 10117
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "COMPLIANCE"
+            socket_accept_port = 10117
+        "#;
+        let settings = Properties::from_str(config);
+        let mut receivers = Vec::new();
+        let sessions = SessionMap::from_iter(settings.session_ids().into_iter().map(|sid| {
+            let mut session = Session::with_settings(sid, &settings).unwrap();
+            let (tx, rx) = channel::<String>(8);
+            session.set_responder(Some(tx));
+            receivers.push((sid.target_compid().clone(), rx));
+            (sid.clone(), session)
+        }));
+        let sid = |target| SessionIdBuilder::new("FIX.4.3", "SENDER", target).build().unwrap();
+        let primary_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET")
+            .sender_subid("DESK")
+            .build()
+            .unwrap();
+        let primary = sessions.get_session(&primary_id).map(|s| s.clone()).unwrap();
+        assert_eq!(primary.drop_copy_sessions(), &vec![sid("RISK"), sid("COMPLIANCE")]);
+        // bump the drop copy seqnum so that it differs from the primary's
+        sessions.get_session_mut(&sid("RISK")).unwrap().state_mut().incr_next_sender_msg_seq_num();
+
+        let mut order = primary.new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        assert!(Session::sync_send_to_target(&primary_id, &sessions, order).is_ok());
+
+        let dd = primary.data_dictionary();
+        for (target, rx) in receivers.iter_mut() {
+            let sent = Message::from_str(&rx.try_recv().unwrap(), dd).unwrap();
+            let header = sent.header();
+            assert_eq!(&header.get_field::<String>(56).unwrap(), target);
+            assert_eq!(sent.get_field::<String>(11).unwrap(), "ORDER1");
+            match target.as_str() {
+                "TARGET" => {
+                    assert_eq!(header.get_field::<String>(50).unwrap(), "DESK");
+                    assert!(header.get_field::<String>(115).is_err());
+                }
+                drop_copy => {
+                    assert!(header.get_field::<String>(50).is_err());
+                    assert_eq!(header.get_field::<String>(115).unwrap(), "TARGET");
+                    let expected_seq_num = if drop_copy == "RISK" { 2 } else { 1 };
+                    assert_eq!(header.get_field::<u64>(34).unwrap(), expected_seq_num);
+                }
+            }
+        }
+
+        // inbound app messages are mirrored too, drop copy sessions themselves are not
+        let execution = primary.new_message("8").unwrap();
+        Session::next_app(&primary_id, &sessions, &execution);
+        Session::next_app(&sid("RISK"), &sessions, &execution);
+        for (target, rx) in receivers.iter_mut() {
+            assert_eq!(rx.try_recv().is_ok(), target != "TARGET");
+            assert!(rx.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn test_sync_send_unknown_session() {
+        let session = test_session();
+        let msg = admin::heartbeat(&session, None).unwrap();
+        let unknown = SessionIdBuilder::new("FIX.4.3", "A", "B").build().unwrap();
+        let sessions = SessionMap::from_iter([(session.session_id.clone(), session)]);
+        assert_matches!(
+            Session::sync_send_to_target(&unknown, &sessions, msg),
+            Err(SendError::SessionNotFound(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_confirmed_counts_under_lock() {
+        let mut session = test_session();
+        session.send_queue_size = 8;
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let session_id = session.session_id.clone();
+        let msg = admin::heartbeat(&session, None).unwrap();
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+
+        // nothing writes for this responder, so the write can't be confirmed
+        let sent = Session::send_confirmed(&session_id, &sessions, msg.clone()).await;
+        assert_matches!(sent, Err(SendError::Disconnected));
+        assert!(rx.try_recv().is_ok());
+
+        let counters = Arc::new(ConnectionCounters::default());
+        sessions.get_session_mut(&session_id).unwrap().responder_counters =
+            Some(Arc::clone(&counters));
+        Session::sync_send_to_target(&session_id, &sessions, msg.clone()).unwrap();
+        let mut msg = msg;
+        let (_, send) =
+            Session::sync_send_counted(&session_id, &sessions, &mut msg).unwrap().unwrap();
+        assert_eq!(send, 2);
+    }
+
+    #[test]
+    fn test_resend() {
+        let mut session = test_session();
+        session.send_queue_size = 16;
+        let (tx, mut rx) = channel::<String>(16);
+        session.set_responder(Some(tx));
+        session.resend_policies_mut().set(
+            "8",
+            ResendPolicy::hook(|msg| match msg.get_field::<String>(150).as_deref() {
+                Ok("F") => ResendAction::GapFill,
+                _ => ResendAction::Replay(msg),
+            }),
+        );
+        let app_message = |session: &Session, msg_type: &str, fields: &[(u32, &str)]| {
+            let mut msg = session.new_message(msg_type).unwrap();
+            for (tag, value) in fields {
+                msg.set_field(StringField::new(*tag, value));
+            }
+            msg
+        };
+        // 1 heartbeat, 2 order, 3 and 4 executions, 5 fill, 6 order
+        let heartbeat = admin::heartbeat(&session, None).unwrap();
+        let order = app_message(&session, "D", &[(11, "ORDER1")]);
+        let new = app_message(&session, "8", &[(150, "0")]);
+        let fill = app_message(&session, "8", &[(150, "F")]);
+        for msg in [heartbeat, order.clone(), new.clone(), new, fill, order] {
+            session.send_to_target(msg).unwrap();
+        }
+        let mut sent = Vec::new();
+        while let Ok(raw) = rx.try_recv() {
+            sent.push(raw);
+        }
+
+        // the whole replay is a single write
+        session.resend(1, 0).unwrap();
+        let dd = Arc::clone(session.data_dictionary());
+        let written = rx.try_recv().unwrap();
+        assert!(rx.try_recv().is_err());
+        let resent: Vec<Message> = split_frames(&written)
+            .into_iter()
+            .map(|raw| Message::from_str(raw, &dd).unwrap())
+            .collect();
+        let summary: Vec<(u64, String)> = resent
+            .iter()
+            .map(|msg| (msg.header().get_field(34).unwrap(), msg.get_msg_type().unwrap()))
+            .collect();
+        let expected = [(1, "4"), (2, "D"), (3, "8"), (4, "8"), (5, "4"), (6, "D")];
+        let expected: Vec<(u64, String)> =
+            expected.iter().map(|(seq_num, msg_type)| (*seq_num, msg_type.to_string())).collect();
+        assert_eq!(summary, expected);
+        assert_eq!(resent[0].get_field::<u64>(36).unwrap(), 2);
+        assert_eq!(resent[4].get_field::<u64>(36).unwrap(), 6);
+        let original = Message::from_str(&sent[1], &dd).unwrap();
+        for msg in &resent {
+            assert_eq!(msg.header().get_field::<String>(43).unwrap(), "Y");
+        }
+        assert_eq!(
+            resent[1].header().get_field::<String>(122).unwrap(),
+            original.header().get_field::<String>(52).unwrap()
+        );
+        assert_eq!(resent[1].get_field::<String>(11).unwrap(), "ORDER1");
+        // nothing resent moves the outgoing seqnum
+        assert_eq!(session.state().next_sender_msg_seq_num(), 7);
+
+        // range past what was sent is gap filled up to the next seqnum
+        session.resend(5, 10).unwrap();
+        let written = rx.try_recv().unwrap();
+        let frames = split_frames(&written);
+        assert_eq!(frames.len(), 2);
+        let gap_fill = Message::from_str(frames[0], &dd).unwrap();
+        assert_eq!(gap_fill.get_field::<u64>(36).unwrap(), 6);
+        let order = Message::from_str(frames[1], &dd).unwrap();
+        assert_eq!(order.header().get_field::<u64>(34).unwrap(), 6);
+        assert!(rx.try_recv().is_err());
+
+        // seqnums missing from the store are gap filled around the stored ones
+        session.message_store().lock().reset().unwrap();
+        session.message_store().lock().set(2, sent[1].as_bytes().into()).unwrap();
+        session.resend(1, 3).unwrap();
+        let written = rx.try_recv().unwrap();
+        let resent: Vec<Message> = split_frames(&written)
+            .into_iter()
+            .map(|raw| Message::from_str(raw, &dd).unwrap())
+            .collect();
+        let new_seq_nos: Vec<Option<u64>> =
+            resent.iter().map(|msg| msg.get_field::<u64>(36).ok()).collect();
+        assert_eq!(new_seq_nos, vec![Some(2), None, Some(4)]);
+    }
+
+    #[test]
+    fn test_resend_queue_full() {
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let msg = admin::heartbeat(&session, None).unwrap();
+        session.send_to_target(msg.clone()).unwrap();
+        session.send_to_target(msg).unwrap();
+
+        // nothing of the replay is written when the queue is full
+        assert_matches!(session.resend(1, 0), Err(SendError::QueueFull));
+        assert_eq!(session.send_queue_depth(), 2);
+        rx.try_recv().unwrap();
+        rx.try_recv().unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // and all of it once there is room
+        session.resend(1, 0).unwrap();
+        assert_eq!(split_frames(&rx.try_recv().unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn test_resend_invalid_range() {
+        let session_id = test_session().session_id.clone();
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let dd = Arc::clone(session.data_dictionary());
+        let request = |session: &Session, begin_seq_no, end_seq_no| {
+            let mut msg = admin::resend_request(session, begin_seq_no, end_seq_no).unwrap();
+            msg.header_mut().set_sender_comp_id("TARGET");
+            msg.header_mut().set_target_comp_id("SENDER");
+            msg.header_mut().set_seq_num(1);
+            msg
+        };
+        let zero_begin = request(&session, 0, 5);
+        let reversed = request(&session, 5, 3);
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        for (msg, tag) in [(zero_begin, "7"), (reversed, "16")] {
+            Session::next_admin(&session_id, &sessions, &msg).unwrap();
+            let reject = Message::from_str(&rx.try_recv().unwrap(), &dd).unwrap();
+            assert_eq!(reject.get_msg_type().unwrap(), "3");
+            assert_eq!(reject.get_field::<String>(371).unwrap(), tag);
+            assert_eq!(reject.get_field::<String>(372).unwrap(), "2");
+            assert_eq!(reject.get_field::<String>(373).unwrap(), "5");
+        }
+    }
+
+    #[test]
+    fn test_queue_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = format!(
+            "{}\nfile_store_path = \"{}\"",
+            CONFIG.replace("send_queue_size = 2", "send_queue_size = 8"),
+            dir.path().display()
+        );
+        let settings = Properties::from_str(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let order = |session: &Session, cl_ord_id: &str| {
+            let mut msg = session.new_message("D").unwrap();
+            msg.set_field(StringField::new(11, cl_ord_id));
+            msg
+        };
+
+        // not logged on, both orders are queued on disk
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        session.send_or_queue(order(&session, "ORDER1")).unwrap();
+        session.send_or_queue(order(&session, "ORDER2")).unwrap();
+        assert_eq!(session.state().next_sender_msg_seq_num(), 1);
+        drop(session);
+
+        // restarted engine delivers them right after answering the logon
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let dd = Arc::clone(session.data_dictionary());
+        let mut logon = admin::logon(&session).unwrap();
+        logon.header_mut().set_sender_comp_id("TARGET");
+        logon.header_mut().set_target_comp_id("SENDER");
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+        Session::next_admin(&session_id, &sessions, &logon).unwrap();
+        let sent: Vec<Message> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|raw| Message::from_str(&raw, &dd).unwrap())
+            .collect();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].get_msg_type().unwrap(), "A");
+        for (msg, (seq_num, cl_ord_id)) in sent[1..].iter().zip([(2, "ORDER1"), (3, "ORDER2")]) {
+            assert_eq!(msg.header().get_field::<u64>(34).unwrap(), seq_num);
+            assert_eq!(msg.get_field::<String>(11).unwrap(), cl_ord_id);
+        }
+        let session = sessions.get_session(&session_id).unwrap();
+        assert!(session.message_store().lock().queued().unwrap().is_empty());
+        assert_eq!(session.message_store().lock().get_range(1, 3).unwrap().len(), 3);
+
+        // once logged on nothing is queued
+        drop(session);
+        let mut session = sessions.get_session_mut(&session_id).unwrap();
+        let msg = order(&session, "ORDER3");
+        session.send_or_queue(msg).unwrap();
+        assert!(rx.try_recv().is_ok());
+        assert!(session.message_store().lock().queued().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_seq_nums_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = format!(
+            "{}\nfile_store_path = \"{}\"\nseqnum_checkpoint_messages = 3",
+            CONFIG.replace("send_queue_size = 2", "send_queue_size = 8"),
+            dir.path().display()
+        );
+        let settings = Properties::from_str(&config);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        assert_eq!(session.seq_num_checkpoint().messages, 3);
+        let (tx, _rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        for _ in 0..4 {
+            let heartbeat = admin::heartbeat(&session, None).unwrap();
+            session.send_to_target(heartbeat).unwrap();
+        }
+        // the fourth is not checkpointed yet
+        assert_eq!(session.message_store().lock().seq_nums(), Some((4, 1)));
+        drop(session);
+
+        // but it is stored, the restarted session does not send 4 again
+        let session = Session::with_settings(&session_id, &settings).unwrap();
+        assert_eq!(session.state().next_sender_msg_seq_num(), 5);
+        assert_eq!(session.state().next_target_msg_seq_num(), 1);
+    }
+
+    #[test]
+    fn test_logon_disconnect_reset() {
+        let mut session = test_session();
+        let session_id = session.session_id.clone();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let dd = Arc::clone(session.data_dictionary());
+        let mut answer = admin::logon(&session).unwrap();
+        answer.header_mut().set_sender_comp_id("TARGET");
+        answer.header_mut().set_target_comp_id("SENDER");
+        let sessions = SessionMap::from_iter([(session_id.clone(), session)]);
+
+        // sent once until answered, the answer is not answered again
+        Session::logon(&session_id, &sessions).unwrap();
+        Session::logon(&session_id, &sessions).unwrap();
+        let sent = Message::from_str(&rx.try_recv().unwrap(), &dd).unwrap();
+        assert_eq!(sent.get_msg_type().unwrap(), "A");
+        assert!(rx.try_recv().is_err());
+        Session::next_admin(&session_id, &sessions, &answer).unwrap();
+        assert!(sessions.get_session(&session_id).unwrap().is_active());
+        assert!(rx.try_recv().is_err());
+
+        // not connected to the counterparty
+        let unknown = SessionIdBuilder::new("FIX.4.3", "SENDER", "OTHER").build().unwrap();
+        assert_matches!(Session::logon(&unknown, &sessions), Err(SendError::SessionNotFound(_)));
+        assert_matches!(
+            Session::disconnect(&session_id, &sessions, "no connection"),
+            Err(SendError::Disconnected)
+        );
+
+        // reset logs out and forgets what was sent
+        Session::reset(&session_id, &sessions).unwrap();
+        let sent = Message::from_str(&rx.try_recv().unwrap(), &dd).unwrap();
+        assert_eq!(sent.get_msg_type().unwrap(), "5");
+        let session = sessions.get_session(&session_id).unwrap();
+        assert!(!session.is_active());
+        assert_eq!(session.state().next_sender_msg_seq_num(), 1);
+        assert!(session.message_store().lock().get_range(1, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_target_seq_num() {
+        let mut session = test_session();
+        session.state_mut().set_next_target_msg_seq_num(5);
+        let message = |msg_type: &str, seq_num: u64, fields: &[(u32, &str)]| {
+            let mut msg = session.new_message(msg_type).unwrap();
+            msg.header_mut().set_seq_num(seq_num);
+            for (tag, value) in fields {
+                msg.set_field(StringField::new(*tag, value));
+            }
+            msg
+        };
+        assert!(session.check_target_seq_num(&message("0", 5, &[])).is_ok());
+        assert!(session.check_target_seq_num(&message("0", 7, &[])).is_ok());
+        let reason = session.check_target_seq_num(&message("0", 3, &[])).unwrap_err();
+        assert_eq!(reason, "MsgSeqNum too low, expecting 5 but received 3");
+        let mut dup = message("0", 3, &[]);
+        dup.header_mut().set_poss_dup(true);
+        assert!(session.check_target_seq_num(&dup).is_ok());
+        assert!(session.check_target_seq_num(&message("A", 1, &[(141, "Y")])).is_ok());
+        assert!(session.check_target_seq_num(&message("A", 1, &[])).is_err());
+    }
+
+    #[test]
+    fn test_last_msg_seq_num_processed() {
+        let mut session = test_session();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        session.send_to_target(session.new_message("0").unwrap()).unwrap();
+        let sent = Message::from_str(&rx.try_recv().unwrap(), session.data_dictionary()).unwrap();
+        assert_eq!(sent.header().last_msg_seq_num_processed(), None);
+
+        let settings =
+            Properties::from_str(&format!("{}last_msg_seq_num_processed = true\n", CONFIG));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        for _ in 0..3 {
+            session.state_mut().incr_next_target_msg_seq_num();
+        }
+        session.send_to_target(admin::heartbeat(&session, None).unwrap()).unwrap();
+        let sent = Message::from_str(&rx.try_recv().unwrap(), session.data_dictionary()).unwrap();
+        assert_eq!(sent.header().last_msg_seq_num_processed(), Some(3));
+        let mut order = crate::messages::NewOrderSingle::builder();
+        order.set(11, "ORDER1").set(21, '1').set(55, "IBM").set(54, '1').set(40, '1');
+        order.set(60, "20221006-08:43:37.100").set(38, 100);
+        session.send_wire(&order).unwrap();
+        let sent = Message::from_str(&rx.try_recv().unwrap(), session.data_dictionary()).unwrap();
+        assert_eq!(sent.header().last_msg_seq_num_processed(), Some(3));
+
+        // two sent, the peer has processed the first
+        assert_eq!(session.peer_processing_lag(), None);
+        session.state_mut().set_peer_last_msg_seq_num_processed(Some(1));
+        assert_eq!(session.peer_processing_lag(), Some(1));
+    }
+
+    #[test]
+    fn test_routing_ids() {
+        let settings = Properties::from_str(&format!(
+            "{}on_behalf_of_comp_id = \"DESK\"\ndeliver_to_comp_id = \"BROKER\"\n",
+            CONFIG
+        ));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let (tx, mut rx) = channel::<String>(8);
+        session.set_responder(Some(tx));
+        let mut sent = |msg: Message, session: &mut Session| {
+            session.send_to_target(msg).unwrap();
+            Message::from_str(&rx.try_recv().unwrap(), session.data_dictionary()).unwrap()
+        };
+
+        let order = sent(session.new_message("D").unwrap(), &mut session);
+        assert_eq!(order.header().on_behalf_of_comp_id(), Some("DESK"));
+        assert_eq!(order.header().deliver_to_comp_id(), Some("BROKER"));
+        let heartbeat = sent(admin::heartbeat(&session, None).unwrap(), &mut session);
+        assert_eq!(heartbeat.header().on_behalf_of_comp_id(), None);
+        // ids set on the message win over the settings
+        let mut order = session.new_message("D").unwrap();
+        order.header_mut().set_deliver_to_comp_id("OTHER");
+        let order = sent(order, &mut session);
+        assert_eq!(order.header().deliver_to_comp_id(), Some("OTHER"));
+        assert_eq!(order.header().on_behalf_of_comp_id(), Some("DESK"));
+
+        // the response goes back to where the request came from
+        let mut request = Message::new();
+        request.header_mut().set_on_behalf_of_comp_id("CLIENT");
+        let report = sent(session.new_response("8", &request).unwrap(), &mut session);
+        assert_eq!(report.header().deliver_to_comp_id(), Some("CLIENT"));
+        assert_eq!(report.header().on_behalf_of_comp_id(), Some("DESK"));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(test_session().parse_options(), ParseOptions::default());
+        let settings = Properties::from_str(&format!(
+            "{}correct_num_in_group = true\nrelaxed_field_order = true\nmax_fields = 500\n\
+             lenient_group_field_order = true\n",
+            CONFIG
+        ));
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let session = Session::with_settings(&session_id, &settings).unwrap();
+        assert!(session.parse_options().correct_num_in_group);
+        assert!(session.parse_options().relaxed_field_order);
+        assert!(session.parse_options().lenient_group_field_order);
+        assert_eq!(session.parse_options().max_fields, Some(500));
+        assert_eq!(session.parse_options().max_group_instances, None);
+    }
+
+    #[cfg(feature = "embedded-dictionary")]
+    #[test]
+    fn test_embedded_dictionary() {
+        // no data_dictionary path in the settings
+        let session = test_session();
+        assert!(Arc::ptr_eq(session.data_dictionary(), &DataDictionary::embedded()));
+    }
+
+    #[test]
+    fn test_is_duplicate() {
+        let session = test_session();
+        let sessions = SessionMap::from_iter([(session.session_id.clone(), session.clone())]);
+        let mut order = session.new_message("D").unwrap();
+        order.header_mut().set_seq_num(1);
+        let mut resent = order.clone();
+        resent.header_mut().set_poss_dup(true);
+        assert!(!session.is_duplicate(&resent));
+        Session::next_app(&session.session_id, &sessions, &order);
+        // the store is shared with the session in the map
+        assert!(session.is_duplicate(&resent));
+        assert!(!session.is_duplicate(&order));
     }
 }
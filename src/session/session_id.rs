@@ -4,6 +4,7 @@ use getset::Getters;
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{self, Hash};
+use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Eq, Getters, Clone, Builder)]
 #[builder(setter(into, strip_option), default, build_fn(skip))]
@@ -34,6 +35,46 @@ impl Hash for SessionId {
 }
 
 impl SessionId {
+    pub fn eq_ignoring_begin_string(&self, other: &SessionId) -> bool {
+        // same counterparty over another FIX version
+        self.sender_compid == other.sender_compid
+            && self.sender_subid == other.sender_subid
+            && self.sender_locationid == other.sender_locationid
+            && self.target_compid == other.target_compid
+            && self.target_subid == other.target_subid
+            && self.target_locationid == other.target_locationid
+            && self.session_qualifier == other.session_qualifier
+    }
+
+    // lossless form of the id, the extended one FromStr parses. it tells apart what the display
+    // does not, sessions differing only by qualifier or by a location id without sub id, and
+    // is the display for sessions without either
+    pub fn key(&self) -> String {
+        let party = |comp_id: &str, sub_id: &Option<String>, location_id: &Option<String>| {
+            let mut party = comp_id.to_string();
+            if sub_id.is_some() || location_id.is_some() {
+                party.push('/');
+                party.push_str(sub_id.as_deref().unwrap_or_default());
+            }
+            if let Some(location_id) = location_id {
+                party.push('/');
+                party.push_str(location_id);
+            }
+            party
+        };
+        let mut key = format!(
+            "{}:{}->{}",
+            self.begin_string,
+            party(&self.sender_compid, &self.sender_subid, &self.sender_locationid),
+            party(&self.target_compid, &self.target_subid, &self.target_locationid)
+        );
+        if let Some(qualifier) = &self.session_qualifier {
+            key.push(':');
+            key.push_str(qualifier);
+        }
+        key
+    }
+
     fn set_session_id(&mut self) {
         self.id.push_str(&self.begin_string);
         self.id.push(':');
@@ -109,24 +150,163 @@ impl SessionId {
         {
             builder.target_locationid(target_loc);
         }
+        if let Some(qualifier) = prop_map
+            .get(SESSION_QUALIFIER_SETTING)
+            .or_else(|| defaults.get(SESSION_QUALIFIER_SETTING))
+        {
+            builder.session_qualifier(qualifier);
+        }
 
         builder.build().unwrap()
     }
 }
 
+// comp id, sub id and location id of COMP/SUB/LOC, None for those left out
+type PartyIds<'a> = [Option<&'a str>; 3];
+
+// BEGIN:SENDER->TARGET:QUALIFIER split into the begin string, the ids of both parties and the
+// qualifier, for SessionId and SessionIdPattern alike
+fn split_id(s: &str) -> Result<(&str, PartyIds<'_>, PartyIds<'_>, Option<&str>), &'static str> {
+    let (begin_string, parties) = s.split_once(':').ok_or("missing begin string")?;
+    if begin_string.is_empty() {
+        return Err("missing begin string");
+    }
+    let (sender, target) = parties.split_once("->").ok_or("missing ->")?;
+    let (target, qualifier) = match target.split_once(':') {
+        Some((target, qualifier)) => (target, Some(qualifier)),
+        None => (target, None),
+    };
+    Ok((begin_string, split_party(sender)?, split_party(target)?, qualifier))
+}
+
+fn split_party(party: &str) -> Result<PartyIds<'_>, &'static str> {
+    let mut ids = party.split('/');
+    let comp_id = ids.next().filter(|comp_id| !comp_id.is_empty()).ok_or("missing comp id")?;
+    let party = [Some(comp_id), ids.next(), ids.next()];
+    match ids.next() {
+        Some(_) => Err("too many ids for one party"),
+        None => Ok(party),
+    }
+}
+
+impl FromStr for SessionId {
+    type Err = &'static str;
+    // the form SessionId is displayed in, e.g. FIX.4.3:SENDER->TARGET or
+    // FIX.4.3:SENDER/DESK->TARGET. the display leaves out the qualifier and a location id
+    // without sub id reads as a sub id, those are given as FIX.4.3:SENDER->TARGET//LONDON:orders
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (begin_string, sender, target, qualifier) = split_id(s)?;
+        let [sender_compid, sender_subid, sender_locationid] =
+            sender.map(Option::unwrap_or_default);
+        let [target_compid, target_subid, target_locationid] =
+            target.map(Option::unwrap_or_default);
+        let mut builder = SessionIdBuilder::new(begin_string, sender_compid, target_compid);
+        builder
+            .sender_subid(sender_subid)
+            .sender_locationid(sender_locationid)
+            .target_subid(target_subid)
+            .target_locationid(target_locationid);
+        if let Some(qualifier) = qualifier.filter(|qualifier| !qualifier.is_empty()) {
+            builder.session_qualifier(qualifier);
+        }
+        builder.build().map_err(|_| "invalid session id")
+    }
+}
+
 impl fmt::Display for SessionId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.id)
     }
 }
 
+// Matches a family of sessions, for settings shared by them and for routing by session. It is
+// written like a SessionId with * in any id for any number of characters. Sub and location ids
+// and the qualifier left out match any, so FIX.4.3:BROKER*->* matches every FIX.4.3 session
+// with a sender comp id starting with BROKER, whatever its sub ids.
+//
+//     let pattern: SessionIdPattern = "FIX.4.*:BROKER*/DESK->*".parse()?;
+//     if pattern.matches(ctx.session_id()) { .. }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionIdPattern {
+    pattern: String,
+    // begin string, sender comp/sub/location ids, target comp/sub/location ids and qualifier.
+    // None matches any
+    ids: [Option<String>; 8],
+}
+
+impl SessionIdPattern {
+    pub fn matches(&self, session_id: &SessionId) -> bool {
+        let values = [
+            Some(&session_id.begin_string),
+            Some(&session_id.sender_compid),
+            session_id.sender_subid.as_ref(),
+            session_id.sender_locationid.as_ref(),
+            Some(&session_id.target_compid),
+            session_id.target_subid.as_ref(),
+            session_id.target_locationid.as_ref(),
+            session_id.session_qualifier.as_ref(),
+        ];
+        self.ids.iter().zip(values).all(|(pattern, value)| match pattern {
+            Some(pattern) => glob_match(pattern, value.map_or("", String::as_str)),
+            None => true,
+        })
+    }
+}
+
+impl FromStr for SessionIdPattern {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (begin_string, sender, target, qualifier) = split_id(s)?;
+        let [sender_compid, sender_subid, sender_locationid] = sender;
+        let [target_compid, target_subid, target_locationid] = target;
+        let ids = [
+            Some(begin_string),
+            sender_compid,
+            sender_subid,
+            sender_locationid,
+            target_compid,
+            target_subid,
+            target_locationid,
+            qualifier,
+        ];
+        Ok(Self {
+            pattern: s.to_string(),
+            ids: ids.map(|id| id.map(str::to_string)),
+        })
+    }
+}
+
+impl fmt::Display for SessionIdPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    // * matches any run of characters, everything else itself
+    match pattern.split_once('*') {
+        None => pattern == value,
+        Some((prefix, rest)) => {
+            let Some(value) = value.strip_prefix(prefix) else {
+                return false;
+            };
+            value
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([value.len()])
+                .any(|i| glob_match(rest, &value[i..]))
+        }
+    }
+}
+
 impl SessionIdBuilder {
     pub fn new<S: Into<String>>(begin_string: S, sender_comp: S, target_comp: S) -> Self {
-        let mut sessionid_builder = SessionIdBuilder::default();
-        sessionid_builder.begin_string = Some(begin_string.into());
-        sessionid_builder.sender_compid = Some(sender_comp.into());
-        sessionid_builder.target_compid = Some(target_comp.into());
-        sessionid_builder
+        SessionIdBuilder {
+            begin_string: Some(begin_string.into()),
+            sender_compid: Some(sender_comp.into()),
+            target_compid: Some(target_comp.into()),
+            ..Default::default()
+        }
     }
 
     pub fn build(&self) -> Result<SessionId, SessionIdBuilderError> {
@@ -134,14 +314,14 @@ impl SessionIdBuilder {
             begin_string: self.begin_string.as_ref().unwrap().to_string(),
             sender_compid: self.sender_compid.as_ref().unwrap().to_string(),
             sender_subid: self.sender_subid.clone().flatten().and_then(|s| {
-                if !s.is_empty() && s != "" {
+                if !s.is_empty() {
                     Some(s.to_owned())
                 } else {
                     None
                 }
             }),
             sender_locationid: self.sender_locationid.clone().flatten().and_then(|s| {
-                if s.is_empty() || s == "" {
+                if s.is_empty() {
                     None
                 } else {
                     Some(s.to_owned())
@@ -149,14 +329,14 @@ impl SessionIdBuilder {
             }),
             target_compid: self.target_compid.as_ref().unwrap().to_string(),
             target_subid: self.target_subid.clone().flatten().and_then(|s| {
-                if s.is_empty() || s == "" {
+                if s.is_empty() {
                     None
                 } else {
                     Some(s.to_owned())
                 }
             }),
             target_locationid: self.target_locationid.clone().flatten().and_then(|s| {
-                if s.is_empty() || s == "" {
+                if s.is_empty() {
                     None
                 } else {
                     Some(s.to_owned())
@@ -170,3 +350,94 @@ impl SessionIdBuilder {
         Ok(session_id)
     }
 }
+
+#[cfg(test)]
+mod session_id_tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let plain = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let full = SessionIdBuilder::new("FIXT.1.1", "SENDER", "TARGET")
+            .sender_subid("DESK")
+            .sender_locationid("NY")
+            .target_locationid("LONDON")
+            .session_qualifier("orders")
+            .build()
+            .unwrap();
+        assert_eq!(full.to_string(), "FIXT.1.1:SENDER/DESK/NY->TARGET/LONDON");
+        let extended = "FIXT.1.1:SENDER/DESK/NY->TARGET//LONDON:orders".parse::<SessionId>();
+        assert_eq!(extended, Ok(full));
+        let sub_and_location = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET")
+            .target_subid("DESK")
+            .target_locationid("LONDON")
+            .build()
+            .unwrap();
+        for session_id in [plain, sub_and_location] {
+            assert_eq!(session_id.to_string().parse::<SessionId>(), Ok(session_id.clone()));
+        }
+        let sub_only = "FIX.4.3:SENDER/DESK->TARGET".parse::<SessionId>().unwrap();
+        assert_eq!(sub_only.sender_subid().as_deref(), Some("DESK"));
+        assert_eq!(sub_only.sender_locationid(), &None);
+
+        for invalid in [
+            "SENDER->TARGET",
+            ":SENDER->TARGET",
+            "FIX.4.3:SENDER",
+            "FIX.4.3:->TARGET",
+        ] {
+            assert!(invalid.parse::<SessionId>().is_err(), "{}", invalid);
+        }
+        assert!("FIX.4.3:SENDER/A/B/C->TARGET".parse::<SessionId>().is_err());
+    }
+
+    #[test]
+    fn test_key() {
+        let plain = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        assert_eq!(plain.key(), plain.to_string());
+        let qualified = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET")
+            .target_locationid("LONDON")
+            .session_qualifier("orders")
+            .build()
+            .unwrap();
+        assert_eq!(qualified.key(), "FIX.4.3:SENDER->TARGET//LONDON:orders");
+        assert_eq!(qualified.key().parse(), Ok(qualified));
+    }
+
+    #[test]
+    fn test_from_map_qualifier() {
+        let defaults = HashMap::from([
+            (BEGIN_STRING_SETTING.to_string(), "FIX.4.3".to_string()),
+            (SENDER_COMPID_SETTING.to_string(), "SENDER".to_string()),
+            (TARGET_COMPID_SETTING.to_string(), "TARGET".to_string()),
+        ]);
+        let orders = HashMap::from([(SESSION_QUALIFIER_SETTING.to_string(), "orders".to_string())]);
+        let plain = SessionId::from_map(&HashMap::new(), &defaults);
+        let qualified = SessionId::from_map(&orders, &defaults);
+        assert_eq!(qualified, "FIX.4.3:SENDER->TARGET:orders".parse().unwrap());
+        assert_ne!(plain, qualified);
+    }
+
+    #[test]
+    fn test_pattern() {
+        let sid = |s: &str| s.parse::<SessionId>().unwrap();
+        let broker: SessionIdPattern = "FIX.4.3:BROKER*->*".parse().unwrap();
+        assert!(broker.matches(&sid("FIX.4.3:BROKER->CLIENT")));
+        assert!(broker.matches(&sid("FIX.4.3:BROKER_EU/DESK->CLIENT:orders")));
+        assert!(!broker.matches(&sid("FIX.4.4:BROKER->CLIENT")));
+        assert!(!broker.matches(&sid("FIX.4.3:CLIENT->BROKER")));
+
+        let desk: SessionIdPattern = "FIX.4.*:*->CLIENT/*_DESK/*:".parse().unwrap();
+        assert!(desk.matches(&sid("FIX.4.4:BROKER->CLIENT/FX_DESK/LONDON")));
+        assert!(desk.matches(&sid("FIX.4.2:BROKER->CLIENT/_DESK")));
+        // the empty qualifier matches only sessions without one
+        assert!(!desk.matches(&sid("FIX.4.4:BROKER->CLIENT/FX_DESK:orders")));
+        assert!(!desk.matches(&sid("FIX.4.4:BROKER->CLIENT/FX")));
+        assert!(!desk.matches(&sid("FIX.4.4:BROKER->CLIENT")));
+        assert_eq!(desk.to_string(), "FIX.4.*:*->CLIENT/*_DESK/*:");
+
+        for invalid in ["*", "FIX.4.3:*", "FIX.4.3:->*", "FIX.4.3:*/a/b/c->*"] {
+            assert!(invalid.parse::<SessionIdPattern>().is_err(), "{}", invalid);
+        }
+    }
+}
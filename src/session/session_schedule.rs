@@ -1,16 +1,26 @@
 use super::{Properties, SessionId};
-use chrono::{DateTime, Datelike, NaiveTime, Offset, TimeZone, Utc, Weekday};
-use chrono_tz::{Tz, Tz::GMT};
+use crate::clock::SharedClock;
+use crate::quickfix_errors::ConfigErr;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
 use derive_builder::Builder;
+use std::fmt;
 
 // schedule related settings
-const START_DAY_SETTING: &str = "start_day";
-const END_DAY_SETTING: &str = "end_day";
-const START_TIME_SETTING: &str = "start_time";
-const END_TIME_SETTING: &str = "end_time";
-const TIMEZONE_SETTING: &str = "default_timezone";
+pub const START_DAY_SETTING: &str = "start_day";
+pub const END_DAY_SETTING: &str = "end_day";
+pub const START_TIME_SETTING: &str = "start_time";
+pub const END_TIME_SETTING: &str = "end_time";
+pub const TIMEZONE_SETTING: &str = "default_timezone";
+// comma separated weekdays and dates (2024-12-25) on which no window starts
+pub const NON_TRADING_DAYS_SETTING: &str = "non_trading_days";
+pub const HOLIDAYS_SETTING: &str = "holidays";
+// instead of start_time and end_time, for a session split into several windows a day, e.g.
+// 09:00:00-11:30:00,13:00:00-15:00:00. it runs from the first start to the last end and is
+// logged out in between
+pub const SESSION_WINDOWS_SETTING: &str = "session_windows";
 
-#[derive(Debug, Builder)]
+#[derive(Debug, Clone, Builder)]
 pub struct SessionSchedule {
     start_time: NaiveTime,
     end_time: NaiveTime,
@@ -22,6 +32,49 @@ pub struct SessionSchedule {
     time_zone: chrono_tz::Tz,
     #[builder(default)]
     is_non_stop: bool,
+    #[builder(default)]
+    non_trading_days: Vec<Weekday>,
+    #[builder(default)]
+    holidays: Vec<NaiveDate>,
+    // parts of the day the session is up in, all of it between start and end when empty
+    #[builder(default)]
+    windows: Vec<(NaiveTime, NaiveTime)>,
+    #[builder(default)]
+    clock: SharedClock,
+}
+
+impl Default for SessionSchedule {
+    fn default() -> Self {
+        Self::new(NaiveTime::default(), None, NaiveTime::default(), None, chrono_tz::UTC, true)
+    }
+}
+
+impl fmt::Display for SessionSchedule {
+    // hours in the schedule's own time zone, e.g. Sun 17:00:00-Fri 17:00:00 America/New_York
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_non_stop {
+            return f.write_str("non stop");
+        }
+        let day = |day: Option<Weekday>| day.map(|day| format!("{} ", day)).unwrap_or_default();
+        let windows = match self.windows.is_empty() {
+            true => vec![(self.start_time, self.end_time)],
+            false => self.windows.clone(),
+        };
+        let windows: Vec<String> =
+            windows.iter().map(|(start, end)| format!("{}-{}", start, end)).collect();
+        match (self.start_day, self.end_day) {
+            (None, None) => write!(f, "{} {}", windows.join(","), self.time_zone),
+            (start_day, end_day) => write!(
+                f,
+                "{}{}-{}{} {}",
+                day(start_day),
+                self.start_time,
+                day(end_day),
+                self.end_time,
+                self.time_zone
+            ),
+        }
+    }
 }
 
 impl SessionSchedule {
@@ -36,51 +89,185 @@ impl SessionSchedule {
             end_day,
             time_zone: timezone,
             is_non_stop: non_stop,
+            non_trading_days: Vec::new(),
+            holidays: Vec::new(),
+            windows: Vec::new(),
+            clock: SharedClock::default(),
+        }
+    }
+
+    pub fn with_windows(mut self, windows: Vec<(NaiveTime, NaiveTime)>) -> Self {
+        // the session starts with the first window and ends with the last
+        if let (Some(first), Some(last)) = (windows.first(), windows.last()) {
+            self.start_time = first.0;
+            self.end_time = last.1;
+            self.is_non_stop = false;
         }
+        self.windows = windows;
+        self
     }
 
-    pub fn create_schedule(session_id: &SessionId, settings: &Properties) -> Self {
-        let start_time = settings.get_optional_config::<NaiveTime>(session_id, START_TIME_SETTING);
-        let end_time = settings.get_optional_config::<NaiveTime>(session_id, END_TIME_SETTING);
+    pub fn with_calendar(
+        mut self, non_trading_days: Vec<Weekday>, holidays: Vec<NaiveDate>,
+    ) -> Self {
+        self.non_trading_days = non_trading_days;
+        self.holidays = holidays;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
 
-        let mut is_non_stop = false;
-        if start_time.is_none() && end_time.is_none() {
-            is_non_stop = true;
-        } else if start_time.is_none() || end_time.is_none() {
-            panic!("start_time and end_time both are mandatory");
+    // times, days and windows that do not parse or do not go together are config errors
+    pub fn create_schedule(
+        session_id: &SessionId, settings: &Properties,
+    ) -> Result<Self, ConfigErr<'static>> {
+        let optional = |name: &'static str| -> Result<Option<String>, ConfigErr<'static>> {
+            match settings.get_or_default_config::<String>(session_id, name) {
+                Ok(value) => Ok(Some(value)),
+                Err(ConfigErr::NotFound(_)) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        fn parse<T: std::str::FromStr>(
+            name: &str, value: Option<String>,
+        ) -> Result<Option<T>, ConfigErr<'static>> {
+            value
+                .map(|value| {
+                    value.parse::<T>().map_err(|_| {
+                        ConfigErr::ParseError(format!("{} of {:?} is not valid", name, value))
+                    })
+                })
+                .transpose()
+        }
+        let invalid = |msg: &str| ConfigErr::ParseError(msg.to_string());
+        let mut start_time = parse::<NaiveTime>(START_TIME_SETTING, optional(START_TIME_SETTING)?)?;
+        let mut end_time = parse::<NaiveTime>(END_TIME_SETTING, optional(END_TIME_SETTING)?)?;
+        let windows = match optional(SESSION_WINDOWS_SETTING)? {
+            Some(_) if start_time.is_some() || end_time.is_some() => {
+                return Err(invalid("session_windows replaces start_time and end_time"))
+            }
+            Some(windows) => parse_windows(&windows).ok_or_else(|| {
+                invalid("session_windows should be like 09:00:00-11:30:00,13:00:00-15:00:00")
+            })?,
+            None => Vec::new(),
+        };
+        if let (Some(first), Some(last)) = (windows.first(), windows.last()) {
+            start_time = Some(first.0);
+            end_time = Some(last.1);
         }
 
-        let start_day = settings.get_optional_config::<Weekday>(session_id, START_DAY_SETTING);
-        let end_day = settings.get_optional_config::<Weekday>(session_id, END_DAY_SETTING);
+        let (start_time, end_time, is_non_stop) = match (start_time, end_time) {
+            (Some(start_time), Some(end_time)) => (start_time, end_time, false),
+            // times of a non stop schedule are never looked at
+            (None, None) => (NaiveTime::default(), NaiveTime::default(), true),
+            _ => return Err(invalid("start_time and end_time both are mandatory")),
+        };
+
+        let start_day = parse::<Weekday>(START_DAY_SETTING, optional(START_DAY_SETTING)?)?;
+        let end_day = parse::<Weekday>(END_DAY_SETTING, optional(END_DAY_SETTING)?)?;
         if is_non_stop && (start_day.is_some() || end_day.is_some()) {
-            panic!("start or end day specified without start time or end time");
+            return Err(invalid("start or end day specified without start time or end time"));
         }
+        let non_trading_days =
+            parse_list::<Weekday>(&optional(NON_TRADING_DAYS_SETTING)?.unwrap_or_default())
+                .map_err(|_| {
+                    invalid(&format!(
+                        "{} should be weekdays like Sat,Sun",
+                        NON_TRADING_DAYS_SETTING
+                    ))
+                })?;
+        let holidays = parse_list::<NaiveDate>(&optional(HOLIDAYS_SETTING)?.unwrap_or_default())
+            .map_err(|_| {
+                invalid(&format!("{} should be dates like 2024-12-25", HOLIDAYS_SETTING))
+            })?;
 
-        let time_zone: chrono_tz::Tz =
-            settings.get_optional_config(session_id, TIMEZONE_SETTING).unwrap_or(chrono_tz::UTC);
-        SessionSchedule::new(
-            start_time.unwrap(),
-            start_day,
-            end_time.unwrap(),
-            end_day,
-            time_zone,
-            is_non_stop,
-        )
+        let time_zone =
+            parse::<Tz>(TIMEZONE_SETTING, optional(TIMEZONE_SETTING)?)?.unwrap_or(chrono_tz::UTC);
+        Ok(SessionSchedule::new(start_time, start_day, end_time, end_day, time_zone, is_non_stop)
+            .with_calendar(non_trading_days, holidays)
+            .with_windows(windows))
+    }
+
+    pub fn is_non_stop(&self) -> bool {
+        self.is_non_stop
+    }
+
+    // zone the schedule's times are in
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
+    // now in the schedule's time zone
+    pub fn local_now(&self) -> NaiveDateTime {
+        self.time_zone.from_utc_datetime(&self.clock.now().naive_utc()).naive_local()
     }
 
     pub fn is_session_time(&self) -> bool {
+        self.is_non_stop || self.window(self.clock.now()).is_some()
+    }
+
+    // local start of the window now is in, None outside of it and for a non stop schedule.
+    // a new value is a new session, e.g. the weekly one starting on Sunday evening
+    pub fn window_start(&self) -> Option<NaiveDateTime> {
         if self.is_non_stop {
-            return true;
+            return None;
         }
+        self.window(self.clock.now()).map(|(start, _)| start)
+    }
+
+    // true in the breaks between the windows of a session with several a day, the session is
+    // logged out for them
+    pub fn is_between_windows(&self) -> bool {
+        let now = self.clock.now();
+        !self.windows.is_empty() && !self.in_window(now) && self.span(now).is_some()
+    }
 
-        let now_datetime = self.time_zone.from_utc_datetime(&Utc::now().naive_utc());
+    fn window(&self, now: DateTime<Utc>) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        // with several windows a day now has to be in one of them as well, the session stays
+        // the same across them
+        if !self.windows.is_empty() && !self.in_window(now) {
+            return None;
+        }
+        self.span(now)
+    }
+
+    fn in_window(&self, now: DateTime<Utc>) -> bool {
+        let time = self.time_zone.from_utc_datetime(&now.naive_utc()).naive_local().time();
+        let in_window = |(start, end): &(NaiveTime, NaiveTime)| match start <= end {
+            true => *start <= time && time <= *end,
+            // runs overnight
+            false => *start <= time || time <= *end,
+        };
+        self.windows.iter().any(in_window)
+    }
+
+    fn span(&self, now: DateTime<Utc>) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        // local start and end of the session now is in, if any
+        let now_datetime = self.time_zone.from_utc_datetime(&now.naive_utc()).naive_local();
         // get today's session start and end datetime
-        let today_start_datetime = now_datetime.date().and_time(self.start_time).unwrap();
-        let today_end_datetime = now_datetime.date().and_time(self.end_time).unwrap();
+        let today_start_datetime = now_datetime.date().and_time(self.start_time);
+        let today_end_datetime = now_datetime.date().and_time(self.end_time);
         if self.start_day.is_none() && self.end_day.is_none() {
-            // daily session start and end
-            // now should be between today's session start and end datetimes
-            return today_start_datetime <= now_datetime && now_datetime <= today_end_datetime;
+            // daily session start and end. a window ending before it starts runs overnight, into
+            // the next day, and before today's start now is in the one started yesterday
+            let overnight = self.end_time < self.start_time;
+            let start_date = match overnight && now_datetime < today_start_datetime {
+                true => now_datetime.date().pred_opt().unwrap(),
+                false => now_datetime.date(),
+            };
+            let end_date = match overnight {
+                true => start_date.succ_opt().unwrap(),
+                false => start_date,
+            };
+            let (start, end) =
+                (start_date.and_time(self.start_time), end_date.and_time(self.end_time));
+            return (start <= now_datetime
+                && now_datetime <= end
+                && self.is_trading_day(start_date))
+            .then_some((start, end));
         }
 
         // if weekdays are given, calculate the weekly start and end datetime
@@ -92,36 +279,43 @@ impl SessionSchedule {
         // same weekday as self.start_day
         while weekly_start_date.weekday() != session_start_weekday {
             // go back one date prior
-            weekly_start_date = weekly_start_date.pred();
+            weekly_start_date = weekly_start_date.pred_opt().unwrap();
             if weekly_start_date.weekday() == session_end_weekday {
                 // means that today's date if already out of sesssion window
                 // because going back end day is encountered
-                return false;
+                return None;
             }
         }
         // weekly start_date is on correct weekday for sesssion
         // update the date with time of self.start_time
-        let weekly_start_datetime =
-            weekly_start_date.and_time(today_start_datetime.time()).unwrap();
+        let weekly_start_datetime = weekly_start_date.and_time(today_start_datetime.time());
 
         while weekly_end_date.weekday() != session_end_weekday {
             // go forward one day
-            weekly_end_date = weekly_end_date.succ();
+            weekly_end_date = weekly_end_date.succ_opt().unwrap();
             if weekly_end_date.weekday() == session_start_weekday {
                 // means that today's date if already out of sesssion window
                 // because going forward start day is encountered
-                return false;
+                return None;
             }
         }
-        let weekly_end_datetime = weekly_end_date.and_time(today_end_datetime.time()).unwrap();
-        weekly_start_datetime <= now_datetime && now_datetime <= weekly_end_datetime
+        let weekly_end_datetime = weekly_end_date.and_time(today_end_datetime.time());
+        // a week is only interrupted by its days off, it does not start over after them
+        (weekly_start_datetime <= now_datetime
+            && now_datetime <= weekly_end_datetime
+            && self.is_trading_day(now_datetime.date()))
+        .then_some((weekly_start_datetime, weekly_end_datetime))
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !self.non_trading_days.contains(&date.weekday()) && !self.holidays.contains(&date)
     }
 
     // this is for testing purposes
     pub fn find_nearest_interval(&self) {
         let local_date_time = self.time_zone.from_utc_datetime(&Utc::now().naive_utc());
-        let start_date_time = local_date_time.date().and_time(self.start_time).unwrap();
-        let end_date_time = local_date_time.date().and_time(self.end_time).unwrap();
+        let start_date_time = local_date_time.date_naive().and_time(self.start_time);
+        let end_date_time = local_date_time.date_naive().and_time(self.end_time);
         println!("local_date_time {}", local_date_time);
         if self.start_day.is_none() && self.end_day.is_none() {
             // daily start and end time
@@ -131,7 +325,7 @@ impl SessionSchedule {
         let start_weekday = self.start_day.unwrap();
         let end_weekday = self.end_day.unwrap();
         while weekly_start.weekday() != start_weekday {
-            weekly_start = weekly_start.pred();
+            weekly_start = weekly_start.pred_opt().unwrap();
             if weekly_start.weekday() == end_weekday {
                 // going back if it encounters end weekday first then
                 // it means if was already outside of the
@@ -139,22 +333,40 @@ impl SessionSchedule {
             }
         }
         // start date is weekly start
-        let weekly_start = weekly_start.and_time(start_date_time.time()).unwrap();
+        let weekly_start = weekly_start.and_time(start_date_time.time());
 
         let mut weekly_end = end_date_time.date();
         while weekly_end.weekday() != end_weekday {
-            weekly_end = weekly_end.succ();
+            weekly_end = weekly_end.succ_opt().unwrap();
             if weekly_end.weekday() == start_weekday {
                 // start weekdat encountered going forward in time
                 // means current datetime is already out of session time
                 panic!("Out of session: start day goind forwward");
             }
         }
-        let weekly_end = weekly_end.and_time(end_date_time.time()).unwrap();
+        let weekly_end = weekly_end.and_time(end_date_time.time());
         println!("\n\n session interval start {}, end {}\n\n", weekly_start, weekly_end);
     }
 }
 
+pub(crate) fn parse_windows(value: &str) -> Option<Vec<(NaiveTime, NaiveTime)>> {
+    // start-end pairs, comma separated
+    let windows = value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (start, end) = item.split_once('-')?;
+            Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!windows.is_empty()).then_some(windows)
+}
+
+pub(crate) fn parse_list<T: std::str::FromStr>(value: &str) -> Result<Vec<T>, T::Err> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::parse).collect()
+}
+
 pub fn session_time(time_zone: chrono_tz::Tz) -> bool {
     // create a current datetime = Utc::now()
     // extract the time from above
@@ -182,7 +394,11 @@ pub fn session_time(time_zone: chrono_tz::Tz) -> bool {
 }
 
 pub fn is_current_time_between(curr_timezone: Tz, start: &str, end: &str) -> bool {
-    let utc_naive_date_time = Utc::now().naive_utc();
+    is_time_between(Utc::now(), curr_timezone, start, end)
+}
+
+pub fn is_time_between(now: DateTime<Utc>, curr_timezone: Tz, start: &str, end: &str) -> bool {
+    let utc_naive_date_time = now.naive_utc();
     let curr_naive_tz_time = curr_timezone.from_utc_datetime(&utc_naive_date_time).time();
     let start = start.parse::<NaiveTime>().unwrap();
     let end = end.parse::<NaiveTime>().unwrap();
@@ -211,7 +427,144 @@ mod schedule_tests {
             local_time.naive_local(),
             local_time.naive_utc()
         );
-        assert_eq!(is_current_time_between(Tz::Asia__Kolkata, "05:00:00", "13:00:00"), false);
+        // 14:30 in Kolkata
+        let now = NaiveDateTime::from_str("2024-03-05T09:00:00").unwrap().and_utc();
+        assert!(!is_time_between(now, Tz::Asia__Kolkata, "05:00:00", "13:00:00"));
+        assert!(is_time_between(now, Tz::Asia__Kolkata, "05:00:00", "15:00:00"));
+    }
+
+    #[test]
+    fn test_window_start() {
+        let time = |s: &str| NaiveTime::from_str(s).unwrap();
+        let utc = |s: &str| NaiveDateTime::from_str(s).unwrap().and_utc();
+        // weekly session from Sunday 17:00 to Friday 17:00 New York time
+        let weekly = SessionScheduleBuilder::default()
+            .start_time(time("17:00:00"))
+            .end_time(time("17:00:00"))
+            .start_day(Weekday::Sun)
+            .end_day(Weekday::Fri)
+            .time_zone(Tz::America__New_York)
+            .build()
+            .unwrap();
+        assert_eq!(weekly.to_string(), "Sun 17:00:00-Fri 17:00:00 America/New_York");
+        let sunday = NaiveDateTime::from_str("2024-03-03T17:00:00").unwrap();
+        // Tuesday and Friday noon are in the week started on Sunday, Saturday is not
+        let start = |now| weekly.window(utc(now)).map(|(start, _)| start);
+        assert_eq!(start("2024-03-05T17:00:00"), Some(sunday));
+        assert_eq!(start("2024-03-08T17:00:00"), Some(sunday));
+        assert_eq!(start("2024-03-09T17:00:00"), None);
+        assert_eq!(start("2024-03-10T22:00:00"), Some(sunday + chrono::Duration::weeks(1)));
+
+        let daily = SessionScheduleBuilder::default()
+            .start_time(time("09:00:00"))
+            .end_time(time("17:00:00"))
+            .build()
+            .unwrap();
+        let start = |now| daily.window(utc(now)).map(|(start, _)| start);
+        assert_eq!(start("2024-03-05T12:00:00").unwrap().to_string(), "2024-03-05 09:00:00");
+        assert_eq!(start("2024-03-05T18:00:00"), None);
+        let non_stop = SessionScheduleBuilder::default()
+            .start_time(time("00:00:00"))
+            .end_time(time("00:00:00"))
+            .is_non_stop(true)
+            .build()
+            .unwrap();
+        assert_eq!(non_stop.window_start(), None);
+    }
+
+    #[test]
+    fn test_overnight_and_holidays() {
+        let time = |s: &str| NaiveTime::from_str(s).unwrap();
+        let local = |s: &str| NaiveDateTime::from_str(s).unwrap();
+        // 17:00 to 03:00 every day but the ones starting on a weekend or on Christmas
+        let overnight = SessionScheduleBuilder::default()
+            .start_time(time("17:00:00"))
+            .end_time(time("03:00:00"))
+            .non_trading_days(vec![Weekday::Sat, Weekday::Sun])
+            .holidays(vec![NaiveDate::from_str("2024-12-25").unwrap()])
+            .build()
+            .unwrap();
+        let start = |now| overnight.window(local(now).and_utc()).map(|(start, _)| start);
+        assert_eq!(start("2024-12-23T18:00:00"), Some(local("2024-12-23T17:00:00")));
+        assert_eq!(start("2024-12-24T02:59:59"), Some(local("2024-12-23T17:00:00")));
+        assert_eq!(start("2024-12-24T12:00:00"), None);
+        assert_eq!(start("2024-12-25T01:00:00"), Some(local("2024-12-24T17:00:00")));
+        assert_eq!(start("2024-12-25T18:00:00"), None);
+        assert_eq!(start("2024-12-26T02:00:00"), None);
+        // Friday's window runs into Saturday, Saturday's does not start
+        assert_eq!(start("2024-12-28T01:00:00"), Some(local("2024-12-27T17:00:00")));
+        assert_eq!(start("2024-12-28T18:00:00"), None);
+
+        // a holiday inside a weekly window pauses it
+        let weekly = SessionScheduleBuilder::default()
+            .start_time(time("17:00:00"))
+            .end_time(time("17:00:00"))
+            .start_day(Weekday::Sun)
+            .end_day(Weekday::Fri)
+            .holidays(vec![NaiveDate::from_str("2024-12-25").unwrap()])
+            .build()
+            .unwrap();
+        let start = |now| weekly.window(local(now).and_utc()).map(|(start, _)| start);
+        assert_eq!(start("2024-12-24T12:00:00"), Some(local("2024-12-22T17:00:00")));
+        assert_eq!(start("2024-12-25T12:00:00"), None);
+        assert_eq!(start("2024-12-26T12:00:00"), Some(local("2024-12-22T17:00:00")));
+        assert_eq!(parse_list::<Weekday>("Sat, Sun,").unwrap(), [Weekday::Sat, Weekday::Sun]);
+        assert!(parse_list::<NaiveDate>("2024-13-01").is_err());
+    }
+
+    #[test]
+    fn test_session_windows() {
+        let local = |s: &str| NaiveDateTime::from_str(s).unwrap();
+        // morning and afternoon session with a lunch break
+        let windows = parse_windows("09:00:00-11:30:00, 13:00:00-15:00:00").unwrap();
+        let split = SessionSchedule::new(
+            NaiveTime::default(),
+            None,
+            NaiveTime::default(),
+            None,
+            Tz::UTC,
+            true,
+        )
+        .with_windows(windows);
+        assert!(!split.is_non_stop());
+        let start = |now| split.window(local(now).and_utc()).map(|(start, _)| start);
+        let morning = Some(local("2024-03-05T09:00:00"));
+        assert_eq!(start("2024-03-05T08:59:59"), None);
+        assert_eq!(start("2024-03-05T10:00:00"), morning);
+        assert_eq!(start("2024-03-05T12:00:00"), None);
+        // the afternoon belongs to the same session as the morning
+        assert_eq!(start("2024-03-05T13:00:00"), morning);
+        assert_eq!(start("2024-03-05T15:00:01"), None);
+        assert_eq!(split.to_string(), "09:00:00-11:30:00,13:00:00-15:00:00 UTC");
+        assert!(parse_windows("09:00:00-11:30:00,13:00:00").is_none());
+        assert!(parse_windows("").is_none());
+    }
+
+    #[test]
+    fn test_between_windows() {
+        use crate::clock::ManualClock;
+        let at = |s: &str| NaiveDateTime::from_str(s).unwrap().and_utc();
+        let clock = ManualClock::new(at("2024-03-05T10:00:00"));
+        let windows = parse_windows("09:00:00-11:30:00,13:00:00-15:00:00").unwrap();
+        let split = SessionSchedule::default()
+            .with_windows(windows)
+            .with_clock(SharedClock::new(clock.clone()));
+        assert!(!split.is_between_windows());
+        clock.set(at("2024-03-05T12:00:00"));
+        assert!(split.is_between_windows());
+        // after the last window the session has ended, it is not in a break
+        clock.set(at("2024-03-05T15:30:00"));
+        assert!(!split.is_between_windows());
+        let single = SessionSchedule::new(
+            NaiveTime::from_str("09:00:00").unwrap(),
+            None,
+            NaiveTime::from_str("15:00:00").unwrap(),
+            None,
+            Tz::UTC,
+            false,
+        )
+        .with_clock(SharedClock::new(clock));
+        assert!(!single.is_between_windows());
     }
 
     #[test]
@@ -1,13 +1,18 @@
 use crate::quickfix_errors::ConfigErr;
+use crate::session::session_schedule::{
+    END_DAY_SETTING, END_TIME_SETTING, START_DAY_SETTING, START_TIME_SETTING, TIMEZONE_SETTING,
+};
 use crate::session::*;
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
 use std::collections::HashMap;
-use std::fmt::{Debug, Display};
+use std::fmt::Debug;
 use std::fs;
 use std::iter::{Iterator, Peekable};
 use std::path::Path;
 use std::str::{FromStr, Lines};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Properties {
     default_session_id: SessionId,
     session_settings: HashMap<SessionId, HashMap<String, String>>,
@@ -92,42 +97,110 @@ impl Properties {
         parsed_val
     }
 
+    pub fn validate(config: &str) -> Vec<SettingsDiagnostic> {
+        // unknown settings, values that do not parse and settings that contradict each other,
+        // with their line in config
+        settings_schema::validate(config)
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
-        let mut default_found = false;
+        Self::try_from_str(s).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_str(s: &str) -> Result<Self, ConfigErr<'static>> {
+        // like from_str, the first problem of the config is returned instead of panicking
+        let errors = Self::validation_errors(s);
+        if !errors.is_empty() {
+            return Err(invalid(format!("invalid settings:\n{}", errors.join("\n"))));
+        }
         let mut lines = s.lines().peekable();
-        let mut default_session_id: Option<SessionId> = None;
-        let mut setting_map = HashMap::new();
+        let mut defaults: Option<HashMap<String, String>> = None;
+        let mut groups = HashMap::new();
+        let mut patterns = Vec::new();
+        let mut sections = Vec::new();
         while let Some(line) = lines.next() {
             let line = line.trim();
-            if line.starts_with('[') && line.ends_with(']') && line.contains(DEFAULT_SECTION_NAME) {
-                if default_found {
+            if let Some(name) = group_section(line) {
+                if groups.insert(name.to_string(), parse_table(&mut lines)).is_some() {
+                    return Err(invalid(format!("duplicate group section {} found", name)));
+                }
+            } else if let Some(pattern) = pattern_section(line) {
+                let pattern = pattern
+                    .parse::<SessionIdPattern>()
+                    .map_err(|e| invalid(format!("invalid session pattern {}: {}", pattern, e)))?;
+                patterns.push((pattern, parse_table(&mut lines)));
+            } else if line.starts_with('[')
+                && line.ends_with(']')
+                && line.contains(DEFAULT_SECTION_NAME)
+            {
+                if defaults.is_some() {
                     // duplicate default section
-                    panic!("duplicate default section found");
+                    return Err(invalid("duplicate default section found"));
                 }
-                let default_section = parse_table(&mut lines);
-                default_found = true;
-                default_session_id = Some(SessionId::default());
-                setting_map.insert(default_session_id.clone().unwrap(), default_section);
-            } else if line.starts_with('[') && line.ends_with(']') && default_found {
+                defaults = Some(parse_table(&mut lines));
+            } else if line.starts_with('[') && line.ends_with(']') && defaults.is_some() {
                 // some other section in config file
-                if !default_found {
-                    panic!("default section should be first section. not found");
-                }
-                let section = parse_table(&mut lines);
-                let defaults = setting_map.get(default_session_id.as_ref().unwrap()).unwrap();
-                let session_id = SessionId::from_map(&section, defaults);
-                setting_map.insert(session_id, section);
+                sections.push(parse_table(&mut lines));
             }
         }
-        if !default_found {
-            panic!("default section not found");
+        match defaults {
+            Some(defaults) => Self::from_sections(defaults, groups, patterns, sections),
+            None => Err(invalid("default section not found")),
+        }
+    }
+
+    fn validation_errors(config: &str) -> Vec<String> {
+        Self::validate(config)
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn from_sections(
+        defaults: HashMap<String, String>, groups: HashMap<String, HashMap<String, String>>,
+        patterns: Vec<(SessionIdPattern, HashMap<String, String>)>,
+        sections: Vec<HashMap<String, String>>,
+    ) -> Result<Self, ConfigErr<'static>> {
+        let mut setting_map = HashMap::new();
+        for mut section in sections {
+            inherit(&mut section, &groups)?;
+            let missing = |name| !section.contains_key(name) && !defaults.contains_key(name);
+            if missing(BEGIN_STRING_SETTING) {
+                return Err(invalid("begin string is missing"));
+            }
+            if missing(SENDER_COMPID_SETTING) || missing(TARGET_COMPID_SETTING) {
+                return Err(invalid("sender and/or target compid missing"));
+            }
+            let session_id = SessionId::from_map(&section, &defaults);
+            // after the groups, the first matching pattern setting a name wins
+            for (_, settings) in patterns.iter().filter(|(pattern, _)| pattern.matches(&session_id))
+            {
+                for (setting, value) in settings {
+                    section.entry(setting.clone()).or_insert_with(|| value.clone());
+                }
+            }
+            setting_map.insert(session_id, section);
         }
+        let default_session_id = SessionId::default();
+        setting_map.insert(default_session_id.clone(), defaults);
         let properties = Self {
-            default_session_id: default_session_id.unwrap(),
+            default_session_id,
             session_settings: setting_map,
         };
-        properties.check();
-        properties
+        properties.check()?;
+        Ok(properties)
+    }
+
+    pub fn for_session(&self, session_id: &SessionId) -> SessionSettings {
+        // the [Default] settings overridden by those of the session's section
+        let mut settings = self.session_settings.get(&self.default_session_id).cloned();
+        let settings = settings.get_or_insert_with(HashMap::new);
+        if let Some(section) = self.session_settings.get(session_id) {
+            settings.extend(section.iter().map(|(name, value)| (name.clone(), value.clone())));
+        }
+        SessionSettings(std::mem::take(settings))
     }
 
     pub fn session_ids(&self) -> Vec<&SessionId> {
@@ -137,20 +210,20 @@ impl Properties {
             .collect::<Vec<&SessionId>>()
     }
 
-    fn check(&self) {
+    fn check(&self) -> Result<(), ConfigErr<'static>> {
         let connection_type: String = match self.get_default_property(CONNECTION_TYPE_SETTING) {
             Some(s) => s.to_string(),
-            None => panic!("connection_type not found"),
+            None => return Err(invalid("connection_type not found")),
         };
         if connection_type != ACCEPTOR_CONN_TYPE && connection_type != INITIATOR_CONN_TYPE {
-            panic!("invalid connection type");
+            return Err(invalid("invalid connection type"));
         }
         for session_id in self.session_ids() {
             // verify ports
             if connection_type == ACCEPTOR_CONN_TYPE {
                 if self.get_optional_config::<u16>(session_id, SOCKET_ACCEPT_PORT_SETTING).is_none()
                 {
-                    panic!("acceptor port not found");
+                    return Err(invalid("acceptor port not found"));
                 }
             } else {
                 if self
@@ -160,32 +233,244 @@ impl Properties {
                         .get_optional_config::<u16>(session_id, SOCKET_CONNECT_PORT_SETTING)
                         .is_none()
                 {
-                    panic!("socket connect host or port is missing");
+                    return Err(invalid("socket connect host or port is missing"));
                 }
             }
 
             // verify begin string
             let begin_string = self
                 .get_optional_config::<String>(session_id, BEGIN_STRING_SETTING)
-                .expect("begin string is missing");
-            if begin_string != FIX42_BEGIN_STR
-                && begin_string != FIX43_BEGIN_STR
-                && begin_string != FIX44_BEGIN_STR
-            {
-                panic!("invalid begin string");
+                .ok_or_else(|| invalid("begin string is missing"))?;
+            if !BEGIN_STRINGS.contains(&begin_string.as_str()) {
+                return Err(invalid("invalid begin string"));
             }
 
             // verify comp_ids
             if self.get_optional_config::<String>(session_id, SENDER_COMPID_SETTING).is_none()
                 || self.get_optional_config::<String>(session_id, TARGET_COMPID_SETTING).is_none()
             {
-                panic!("sender and/or target compid missing");
+                return Err(invalid("sender and/or target compid missing"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Settings a session was created with, for applications to read their own settings from the
+// session's section. Values are the strings of the config, get parses them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSettings(HashMap<String, String>);
+
+impl SessionSettings {
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub fn get<F: FromStr>(&self, name: &str) -> Option<F> {
+        self.get_property(name)?.parse().ok()
+    }
+}
+
+// Properties built in code rather than read from a config file, for embedders that manage their
+// own configuration. Sections take the same settings as the [Default] and [Session] sections.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsBuilder {
+    defaults: SectionBuilder,
+    groups: HashMap<String, SectionBuilder>,
+    patterns: Vec<(SessionIdPattern, SectionBuilder)>,
+    sessions: Vec<SectionBuilder>,
+}
+
+impl SettingsBuilder {
+    pub fn acceptor() -> Self {
+        Self::default().connection_type(ACCEPTOR_CONN_TYPE)
+    }
+
+    pub fn initiator() -> Self {
+        Self::default().connection_type(INITIATOR_CONN_TYPE)
+    }
+
+    fn connection_type(mut self, connection_type: &str) -> Self {
+        self.defaults = self.defaults.set(CONNECTION_TYPE_SETTING, connection_type);
+        self
+    }
+
+    pub fn defaults(mut self, defaults: SectionBuilder) -> Self {
+        // merged into the default section, later values win
+        self.defaults.settings.extend(defaults.settings);
+        self
+    }
+
+    pub fn group(mut self, name: &str, group: SectionBuilder) -> Self {
+        // like a [Group "name"] section
+        self.groups.insert(name.to_string(), group);
+        self
+    }
+
+    pub fn matching(mut self, pattern: SessionIdPattern, settings: SectionBuilder) -> Self {
+        // like a [Sessions "pattern"] section
+        self.patterns.push((pattern, settings));
+        self
+    }
+
+    pub fn session(mut self, session: SectionBuilder) -> Self {
+        self.sessions.push(session);
+        self
+    }
+
+    pub fn build(self) -> Properties {
+        // checked like a config file, a setting missing from the session, its groups and the
+        // defaults panics. the lines of validation errors are those of the built config
+        let config = self.config();
+        let errors = Properties::validation_errors(&config);
+        if !errors.is_empty() {
+            panic!("invalid settings:\n{}\nin built config:\n{}", errors.join("\n"), config);
+        }
+        let groups = self.groups.into_iter().map(|(name, group)| (name, group.settings)).collect();
+        let patterns =
+            self.patterns.into_iter().map(|(pattern, section)| (pattern, section.settings));
+        let sessions = self.sessions.into_iter().map(|section| section.settings).collect();
+        Properties::from_sections(self.defaults.settings, groups, patterns.collect(), sessions)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn config(&self) -> String {
+        // the sections written out as a config file, for validation
+        let mut config = String::new();
+        let mut section = |header: String, settings: &SectionBuilder| {
+            config.push_str(&format!("[{}]\n", header));
+            for (name, value) in settings.settings.iter() {
+                config.push_str(&format!("{} = \"{}\"\n", name, value));
             }
+        };
+        section(DEFAULT_SECTION_NAME.to_string(), &self.defaults);
+        for (name, group) in self.groups.iter() {
+            section(format!("{} \"{}\"", GROUP_SECTION_NAME, name), group);
         }
+        for (pattern, settings) in self.patterns.iter() {
+            section(format!("{} \"{}\"", PATTERN_SECTION_NAME, pattern), settings);
+        }
+        for session in self.sessions.iter() {
+            section(SESSION_SECTION_NAME.to_string(), session);
+        }
+        config
     }
 }
 
-fn parse_table(lines: &mut Peekable<Lines>) -> HashMap<String, String> {
+#[derive(Debug, Clone, Default)]
+pub struct SectionBuilder {
+    settings: HashMap<String, String>,
+}
+
+impl SectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn session(sender_comp_id: &str, target_comp_id: &str) -> Self {
+        Self::default()
+            .set(SENDER_COMPID_SETTING, sender_comp_id)
+            .set(TARGET_COMPID_SETTING, target_comp_id)
+    }
+
+    pub fn set<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.settings.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn group(self, name: &str) -> Self {
+        self.set(GROUP_SETTING, name)
+    }
+
+    pub fn begin_string(self, begin_string: &str) -> Self {
+        self.set(BEGIN_STRING_SETTING, begin_string)
+    }
+
+    pub fn session_qualifier(self, qualifier: &str) -> Self {
+        self.set(SESSION_QUALIFIER_SETTING, qualifier)
+    }
+
+    pub fn accept_port(self, port: u16) -> Self {
+        self.set(SOCKET_ACCEPT_PORT_SETTING, port)
+    }
+
+    pub fn connect(self, host: &str, port: u16) -> Self {
+        self.set(SOCKET_CONNECT_HOST_SETTING, host).set(SOCKET_CONNECT_PORT_SETTING, port)
+    }
+
+    pub fn heartbeat_interval(self, seconds: u32) -> Self {
+        self.set(HEARTBEAT_INTERVAL_SETTING, seconds)
+    }
+
+    pub fn daily_schedule(self, start_time: NaiveTime, end_time: NaiveTime) -> Self {
+        self.set(START_TIME_SETTING, start_time).set(END_TIME_SETTING, end_time)
+    }
+
+    pub fn weekly_schedule(
+        self, start_day: Weekday, start_time: NaiveTime, end_day: Weekday, end_time: NaiveTime,
+    ) -> Self {
+        self.daily_schedule(start_time, end_time)
+            .set(START_DAY_SETTING, start_day)
+            .set(END_DAY_SETTING, end_day)
+    }
+
+    pub fn time_zone(self, time_zone: Tz) -> Self {
+        self.set(TIMEZONE_SETTING, time_zone)
+    }
+
+    pub fn file_store<P: AsRef<Path>>(self, path: P) -> Self {
+        self.set(FILE_STORE_PATH_SETTING, path.as_ref().display())
+    }
+
+    pub fn data_dictionary<P: AsRef<Path>>(self, path: P) -> Self {
+        self.set(DATA_DICTIONARY_FILE_PATH, path.as_ref().display())
+    }
+}
+
+pub(crate) fn group_section(line: &str) -> Option<&str> {
+    // name of a [Group "name"] section
+    named_section(line, GROUP_SECTION_NAME)
+}
+
+pub(crate) fn pattern_section(line: &str) -> Option<&str> {
+    // pattern of a [Sessions "pattern"] section
+    named_section(line, PATTERN_SECTION_NAME)
+}
+
+fn named_section<'a>(line: &'a str, kind: &str) -> Option<&'a str> {
+    let section = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let name = section.strip_prefix(kind)?.trim();
+    let name = name.strip_prefix('"')?.strip_suffix('"')?;
+    Some(name)
+}
+
+fn inherit(
+    section: &mut HashMap<String, String>, groups: &HashMap<String, HashMap<String, String>>,
+) -> Result<(), ConfigErr<'static>> {
+    // settings the session leaves out are taken from its group, then from the group's group and
+    // so on. the default section comes last, when the setting is looked up
+    let mut seen = Vec::new();
+    let mut next = section.get(GROUP_SETTING).cloned();
+    while let Some(name) = next {
+        if seen.contains(&name) {
+            return Err(invalid(format!("group {} inherits from itself", name)));
+        }
+        let group =
+            groups.get(&name).ok_or_else(|| invalid(format!("group {} not found", name)))?;
+        for (setting, value) in group {
+            section.entry(setting.clone()).or_insert_with(|| value.clone());
+        }
+        next = group.get(GROUP_SETTING).cloned();
+        seen.push(name);
+    }
+    Ok(())
+}
+
+fn invalid(reason: impl Into<String>) -> ConfigErr<'static> {
+    ConfigErr::ParseError(reason.into())
+}
+
+fn parse_table(lines: &mut Peekable<Lines<'_>>) -> HashMap<String, String> {
     // takes only the lines between 2 sections and creates a map out of it
     // let peekable_lines = lines.peekable();
     let mut properties = HashMap::new();
@@ -194,11 +479,11 @@ fn parse_table(lines: &mut Peekable<Lines>) -> HashMap<String, String> {
         if !line.is_empty() {
             let (prop_key, prop_val) = line
                 .split_once('=')
-                .and_then(|(key, val)| {
-                    Some((
+                .map(|(key, val)| {
+                    (
                         key.trim().trim_start_matches('"').trim_end_matches('"'),
                         val.trim().trim_start_matches('"').trim_end_matches('"'),
-                    ))
+                    )
                 })
                 .unwrap();
             properties.insert(prop_key.to_string(), prop_val.to_string());
@@ -250,6 +535,32 @@ mod session_setting_tests {
         let settings = Properties::from_str(cfg_toml);
     }
 
+    #[test]
+    fn test_try_from_str() {
+        let no_default = "[Session]\nsender_comp_id = \"sender\"\ntarget_comp_id = \"target\"\n";
+        assert_matches::assert_matches!(
+            Properties::try_from_str(no_default),
+            Err(ConfigErr::ParseError(reason)) if reason == "default section not found"
+        );
+        let invalid = "[Default]\nconnection_type = \"acceptor\"\nbegin_string = \"FIX.5\"\n";
+        assert_matches::assert_matches!(
+            Properties::try_from_str(invalid),
+            Err(ConfigErr::ParseError(reason)) if reason.contains("line 3: begin_string")
+        );
+        let fixt = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIXT.1.1"
+            socket_accept_port = 10117
+
+            [Session]
+            sender_comp_id = "sender"
+            target_comp_id = "target"
+        "#;
+        let settings = Properties::try_from_str(fixt).unwrap();
+        assert_eq!(settings.session_ids()[0].begin_string(), FIXT11_BEGIN_STR);
+    }
+
     #[test]
     #[should_panic(expected = "connection_type not found")]
     fn test_default_no_connection_type() {
@@ -266,6 +577,273 @@ mod session_setting_tests {
         let settings = Properties::from_str(cfg_toml);
     }
 
+    #[test]
+    fn test_settings_builder() {
+        let cfg_toml = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = 30
+            start_time = "08:00:00"
+            end_time = "17:30:00"
+            default_timezone = "Europe/London"
+
+            [Session]
+            sender_comp_id = "sender"
+            target_comp_id = "target"
+            socket_accept_port = 10117
+            file_store_path = "store"
+
+            [Session]
+            sender_comp_id = "sender"
+            target_comp_id = "target"
+            session_qualifier = "order"
+            socket_accept_port = 10118
+            start_day = "Mon"
+            start_time = "08:00:00"
+            end_day = "Fri"
+            end_time = "17:30:00"
+        "#;
+        let open = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+        let close = NaiveTime::from_hms_opt(17, 30, 0).unwrap();
+        let settings = SettingsBuilder::acceptor()
+            .defaults(
+                SectionBuilder::new()
+                    .begin_string(FIX43_BEGIN_STR)
+                    .heartbeat_interval(30)
+                    .daily_schedule(open, close)
+                    .time_zone(chrono_tz::Europe::London),
+            )
+            .session(
+                SectionBuilder::session("sender", "target").accept_port(10117).file_store("store"),
+            )
+            .session(
+                SectionBuilder::session("sender", "target")
+                    .session_qualifier("order")
+                    .accept_port(10118)
+                    .weekly_schedule(Weekday::Mon, open, Weekday::Fri, close),
+            )
+            .build();
+        assert_eq!(settings, Properties::from_str(cfg_toml));
+        let order_session = SessionIdBuilder::new(FIX43_BEGIN_STR, "sender", "target")
+            .session_qualifier("order")
+            .build()
+            .unwrap();
+        let schedule =
+            session_schedule::SessionSchedule::create_schedule(&order_session, &settings).unwrap();
+        assert!(!schedule.is_non_stop());
+    }
+
+    #[test]
+    #[should_panic(expected = "acceptor port not found")]
+    fn test_settings_builder_checked() {
+        SettingsBuilder::acceptor()
+            .defaults(SectionBuilder::new().begin_string(FIX43_BEGIN_STR))
+            .session(SectionBuilder::session("sender", "target"))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "end_time is set without start_time")]
+    fn test_settings_builder_validated() {
+        SettingsBuilder::acceptor()
+            .defaults(SectionBuilder::new().begin_string(FIX43_BEGIN_STR))
+            .session(
+                SectionBuilder::session("sender", "target")
+                    .accept_port(10117)
+                    .set(END_TIME_SETTING, "17:00:00"),
+            )
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "line 5: heartbeat_interval should be an unsigned integer")]
+    fn test_invalid_value() {
+        let cfg_toml = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = "abc"
+
+            [Session]
+            sender_comp_id = "sender"
+            target_comp_id = "target"
+            socket_accept_port = 10117
+        "#;
+        Properties::from_str(cfg_toml);
+    }
+
+    #[test]
+    fn test_for_session() {
+        let cfg_toml = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = 30
+            desk = "equities"
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "TARGET"
+            socket_accept_port = 10117
+            heartbeat_interval = 60
+        "#;
+        let properties = Properties::from_str(cfg_toml);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let settings = properties.for_session(&session_id);
+        assert_eq!(settings.get::<u32>(HEARTBEAT_INTERVAL_SETTING), Some(60));
+        assert_eq!(settings.get_property("desk"), Some("equities"));
+        assert_eq!(settings.get_property(TARGET_COMPID_SETTING), Some("TARGET"));
+        assert_eq!(settings.get::<u32>("desk"), None);
+        assert_eq!(settings.get_property("missing"), None);
+    }
+
+    #[test]
+    fn test_groups() {
+        let cfg_toml = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = 30
+
+            [Group "EU"]
+            default_timezone = "Europe/London"
+            start_time = "08:00:00"
+            end_time = "16:30:00"
+
+            [Group "LSE"]
+            group = "EU"
+            sender_comp_id = "BROKER"
+            heartbeat_interval = 20
+
+            [Session]
+            group = "LSE"
+            target_comp_id = "LSE1"
+            socket_accept_port = 10117
+
+            [Session]
+            group = "LSE"
+            target_comp_id = "LSE2"
+            socket_accept_port = 10118
+            end_time = "17:30:00"
+        "#;
+        let settings = Properties::from_str(cfg_toml);
+        let lse =
+            |target| SessionIdBuilder::new(FIX43_BEGIN_STR, "BROKER", target).build().unwrap();
+        let get = |target, name| settings.get_optional_config::<String>(&lse(target), name);
+        assert_eq!(settings.session_ids().len(), 2);
+        // session, then its group, then the group's group, then the defaults
+        assert_eq!(get("LSE1", END_TIME_SETTING).as_deref(), Some("16:30:00"));
+        assert_eq!(get("LSE2", END_TIME_SETTING).as_deref(), Some("17:30:00"));
+        assert_eq!(get("LSE2", TIMEZONE_SETTING).as_deref(), Some("Europe/London"));
+        assert_eq!(get("LSE1", HEARTBEAT_INTERVAL_SETTING).as_deref(), Some("20"));
+
+        let open = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+        let built = SettingsBuilder::acceptor()
+            .defaults(SectionBuilder::new().begin_string(FIX43_BEGIN_STR).heartbeat_interval(30))
+            .group(
+                "EU",
+                SectionBuilder::new()
+                    .time_zone(chrono_tz::Europe::London)
+                    .daily_schedule(open, NaiveTime::from_hms_opt(16, 30, 0).unwrap()),
+            )
+            .group(
+                "LSE",
+                SectionBuilder::new()
+                    .group("EU")
+                    .set(SENDER_COMPID_SETTING, "BROKER")
+                    .heartbeat_interval(20),
+            )
+            .session(
+                SectionBuilder::new()
+                    .group("LSE")
+                    .set(TARGET_COMPID_SETTING, "LSE1")
+                    .accept_port(10117),
+            )
+            .session(
+                SectionBuilder::new()
+                    .group("LSE")
+                    .set(TARGET_COMPID_SETTING, "LSE2")
+                    .accept_port(10118)
+                    .set(END_TIME_SETTING, "17:30:00"),
+            )
+            .build();
+        assert_eq!(built, settings);
+    }
+
+    #[test]
+    #[should_panic(expected = "group names no [Group \"NYSE\"]")]
+    fn test_unknown_group() {
+        SettingsBuilder::acceptor()
+            .defaults(SectionBuilder::new().begin_string(FIX43_BEGIN_STR))
+            .session(SectionBuilder::session("sender", "target").group("NYSE").accept_port(10117))
+            .build();
+    }
+
+    #[test]
+    fn test_patterns() {
+        let cfg_toml = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = 30
+
+            [Sessions "FIX.4.3:BROKER*->*"]
+            heartbeat_interval = 20
+            start_time = "08:00:00"
+            end_time = "16:30:00"
+
+            [Sessions "FIX.4.3:*->LSE*"]
+            heartbeat_interval = 10
+
+            [Group "EU"]
+            end_time = "17:00:00"
+
+            [Session]
+            sender_comp_id = "BROKER1"
+            target_comp_id = "LSE1"
+            socket_accept_port = 10117
+
+            [Session]
+            group = "EU"
+            sender_comp_id = "BROKER2"
+            target_comp_id = "XETRA"
+            socket_accept_port = 10118
+            heartbeat_interval = 40
+
+            [Session]
+            sender_comp_id = "DESK"
+            target_comp_id = "LSE2"
+            socket_accept_port = 10119
+        "#;
+        let settings = Properties::from_str(cfg_toml);
+        let id = |sender, target| {
+            SessionIdBuilder::new(FIX43_BEGIN_STR, sender, target).build().unwrap()
+        };
+        let get = |sender, target, name| {
+            settings.get_optional_config::<String>(&id(sender, target), name)
+        };
+        // session, then its groups, then the first pattern it matches, then the defaults
+        assert_eq!(get("BROKER1", "LSE1", HEARTBEAT_INTERVAL_SETTING).as_deref(), Some("20"));
+        assert_eq!(get("BROKER2", "XETRA", HEARTBEAT_INTERVAL_SETTING).as_deref(), Some("40"));
+        assert_eq!(get("BROKER2", "XETRA", END_TIME_SETTING).as_deref(), Some("17:00:00"));
+        assert_eq!(get("BROKER2", "XETRA", START_TIME_SETTING).as_deref(), Some("08:00:00"));
+        assert_eq!(get("DESK", "LSE2", HEARTBEAT_INTERVAL_SETTING).as_deref(), Some("10"));
+        assert_eq!(get("DESK", "LSE2", START_TIME_SETTING), None);
+
+        let broker_pattern = "FIX.4.3:BROKER*->*".parse().unwrap();
+        let built = SettingsBuilder::acceptor()
+            .defaults(SectionBuilder::new().begin_string(FIX43_BEGIN_STR).heartbeat_interval(30))
+            .matching(broker_pattern, SectionBuilder::new().heartbeat_interval(20))
+            .session(SectionBuilder::session("BROKER1", "LSE1").accept_port(10117))
+            .session(SectionBuilder::session("DESK", "LSE2").accept_port(10119))
+            .build();
+        let get = |sender, target| {
+            built.get_optional_config::<u32>(&id(sender, target), HEARTBEAT_INTERVAL_SETTING)
+        };
+        assert_eq!((get("BROKER1", "LSE1"), get("DESK", "LSE2")), (Some(20), Some(30)));
+    }
+
     #[test]
     fn test_no_mandatory_fields() {
         // no begin_string, no sender_compid, no target_compid
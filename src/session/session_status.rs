@@ -0,0 +1,99 @@
+use crate::io::ConnectionStats;
+use crate::session::Session;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::SocketAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected,
+    // connected over a connection that logged on before, but not logged on now
+    Connected,
+    LoggedOn,
+}
+
+// Point in time view of a session, for health endpoints and operator tooling
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionStatus {
+    pub session_id: String,
+    pub state: ConnectionState,
+    pub next_sender_msg_seq_num: u64,
+    pub next_target_msg_seq_num: u64,
+    pub last_sent_time: Option<DateTime<Utc>>,
+    pub last_received_time: Option<DateTime<Utc>>,
+    pub peer_addr: Option<SocketAddr>,
+    // messages waiting to be written to the connection
+    pub send_queue_depth: usize,
+    // messages kept in the store until the next logon
+    pub queued_messages: usize,
+    // messages sent that the peer has not reported in LastMsgSeqNumProcessed(369) yet, unknown
+    // until it sends one
+    pub peer_processing_lag: Option<u64>,
+    // traffic of the connection the session is logged on over, while it is
+    pub connection: Option<ConnectionStats>,
+}
+
+impl Session {
+    pub fn status(&self) -> SessionStatus {
+        let state = match (self.peer_addr(), self.is_active()) {
+            (None, _) => ConnectionState::Disconnected,
+            (Some(_), true) => ConnectionState::LoggedOn,
+            (Some(_), false) => ConnectionState::Connected,
+        };
+        let queued_messages = self.message_store().lock().queued().map_or(0, |queued| queued.len());
+        SessionStatus {
+            session_id: self.session_id.to_string(),
+            state,
+            next_sender_msg_seq_num: self.state().next_sender_msg_seq_num(),
+            next_target_msg_seq_num: self.state().next_target_msg_seq_num(),
+            last_sent_time: self.state().last_sent_time(),
+            last_received_time: self.state().last_received_time(),
+            peer_addr: self.peer_addr(),
+            send_queue_depth: self.send_queue_depth(),
+            queued_messages,
+            peer_processing_lag: self.peer_processing_lag(),
+            connection: self.connection_stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod status_tests {
+    use super::*;
+    use crate::session::{Properties, SessionIdBuilder};
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+
+        [Session]
+        sender_comp_id = "SENDER"
+        target_comp_id = "TARGET"
+        socket_accept_port = 10117
+    "#;
+
+    #[test]
+    fn test_status() {
+        let settings = Properties::from_str(CONFIG);
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SENDER", "TARGET").build().unwrap();
+        let mut session = Session::with_settings(&session_id, &settings).unwrap();
+        let status = session.status();
+        assert_eq!(status.session_id, session_id.to_string());
+        assert_eq!(status.state, ConnectionState::Disconnected);
+        assert_eq!((status.next_sender_msg_seq_num, status.next_target_msg_seq_num), (1, 1));
+        assert_eq!(status.last_sent_time, None);
+
+        // message sent while down is queued for the next logon
+        let order = session.new_message("D").unwrap();
+        session.send_or_queue(order).unwrap();
+        session.set_peer_addr(Some("127.0.0.1:9880".parse().unwrap()));
+        let status = session.status();
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.queued_messages, 1);
+        assert_eq!(status.send_queue_depth, 0);
+        assert_eq!(status.connection, None);
+        assert_eq!(status.peer_processing_lag, None);
+    }
+}
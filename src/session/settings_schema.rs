@@ -0,0 +1,590 @@
+use crate::io::connector::ProxyKind;
+use crate::session::session_schedule::{
+    parse_list, parse_windows, END_DAY_SETTING, END_TIME_SETTING, HOLIDAYS_SETTING,
+    NON_TRADING_DAYS_SETTING, SESSION_WINDOWS_SETTING, START_DAY_SETTING, START_TIME_SETTING,
+    TIMEZONE_SETTING,
+};
+use crate::session::session_settings::{group_section, pattern_section};
+use crate::session::*;
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Str,
+    Bool,
+    Port,
+    Unsigned,
+    Time,
+    Day,
+    Days,
+    Dates,
+    Windows,
+    TimeZone,
+    ConnectionType,
+    BeginString,
+    Proxy,
+    SendQueueOverflow,
+    AppDispatch,
+}
+
+impl ValueKind {
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            ValueKind::Str => true,
+            ValueKind::Bool => value.parse::<bool>().is_ok(),
+            ValueKind::Port => value.parse::<u16>().is_ok(),
+            ValueKind::Unsigned => value.parse::<u64>().is_ok(),
+            ValueKind::Time => value.parse::<NaiveTime>().is_ok(),
+            ValueKind::Day => value.parse::<Weekday>().is_ok(),
+            ValueKind::Days => parse_list::<Weekday>(value).is_ok(),
+            ValueKind::Dates => parse_list::<NaiveDate>(value).is_ok(),
+            ValueKind::Windows => parse_windows(value).is_some(),
+            ValueKind::TimeZone => value.parse::<chrono_tz::Tz>().is_ok(),
+            ValueKind::ConnectionType => [ACCEPTOR_CONN_TYPE, INITIATOR_CONN_TYPE].contains(&value),
+            ValueKind::BeginString => BEGIN_STRINGS.contains(&value),
+            ValueKind::Proxy => value.parse::<ProxyKind>().is_ok(),
+            ValueKind::SendQueueOverflow => value.parse::<SendQueueOverflow>().is_ok(),
+            ValueKind::AppDispatch => value.parse::<AppDispatch>().is_ok(),
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            ValueKind::Str => "a string",
+            ValueKind::Bool => "true or false",
+            ValueKind::Port => "a port number",
+            ValueKind::Unsigned => "an unsigned integer",
+            ValueKind::Time => "a time like 08:30:00",
+            ValueKind::Day => "a weekday like Mon",
+            ValueKind::Days => "weekdays like Sat,Sun",
+            ValueKind::Dates => "dates like 2024-12-25,2025-01-01",
+            ValueKind::Windows => "windows like 09:00:00-11:30:00,13:00:00-15:00:00",
+            ValueKind::TimeZone => "a time zone like Europe/London",
+            ValueKind::ConnectionType => "acceptor or initiator",
+            ValueKind::BeginString => "FIX.4.2, FIX.4.3, FIX.4.4 or FIXT.1.1",
+            ValueKind::Proxy => "http or socks5",
+            ValueKind::SendQueueOverflow => "reject or disconnect",
+            ValueKind::AppDispatch => "inline, worker or pool",
+        }
+    }
+}
+
+const SCHEMA: &[(&str, ValueKind)] = &[
+    (BEGIN_STRING_SETTING, ValueKind::BeginString),
+    (SENDER_COMPID_SETTING, ValueKind::Str),
+    (SENDER_SUBID_SETTING, ValueKind::Str),
+    (SENDER_LOCATIONID_SETTING, ValueKind::Str),
+    (TARGET_COMPID_SETTING, ValueKind::Str),
+    (TARGET_SUBID_SETTING, ValueKind::Str),
+    (TARGET_LOCATIONID_SETTING, ValueKind::Str),
+    (SESSION_QUALIFIER_SETTING, ValueKind::Str),
+    (CONNECTION_TYPE_SETTING, ValueKind::ConnectionType),
+    (SOCKET_ACCEPT_PORT_SETTING, ValueKind::Port),
+    (SOCKET_ACCEPT_HOST_SETTING, ValueKind::Str),
+    (SOCKET_CONNECT_PORT_SETTING, ValueKind::Port),
+    (SOCKET_CONNECT_HOST_SETTING, ValueKind::Str),
+    (RESET_ON_LOGON_SETTING, ValueKind::Bool),
+    (RESET_ON_LOGOUT_SETTING, ValueKind::Bool),
+    (RESET_ON_DISCONNECT_SETTING, ValueKind::Bool),
+    (RESET_ON_SCHEDULE_SETTING, ValueKind::Bool),
+    (HEARTBEAT_INTERVAL_SETTING, ValueKind::Unsigned),
+    (DATA_DICTIONARY_FILE_PATH, ValueKind::Str),
+    (ACCEPT_ANY_BEGIN_STRING_SETTING, ValueKind::Bool),
+    (BEGIN_STRING_DICTIONARIES_SETTING, ValueKind::Str),
+    (SEND_QUEUE_SIZE_SETTING, ValueKind::Unsigned),
+    (SEND_QUEUE_OVERFLOW_SETTING, ValueKind::SendQueueOverflow),
+    (APP_DISPATCH_SETTING, ValueKind::AppDispatch),
+    (APP_POOL_SIZE_SETTING, ValueKind::Unsigned),
+    (VALIDATION_POOL_SETTING, ValueKind::Bool),
+    (VALIDATION_POOL_SIZE_SETTING, ValueKind::Unsigned),
+    (LAST_MSG_SEQ_NUM_PROCESSED_SETTING, ValueKind::Bool),
+    (FILE_STORE_PATH_SETTING, ValueKind::Str),
+    (STORE_MAX_MESSAGES_SETTING, ValueKind::Unsigned),
+    (STORE_MAX_AGE_SETTING, ValueKind::Unsigned),
+    (STORE_ARCHIVE_PATH_SETTING, ValueKind::Str),
+    (QUEUE_MAX_AGE_SETTING, ValueKind::Unsigned),
+    (DEAD_LETTER_PATH_SETTING, ValueKind::Str),
+    (SEQNUM_CHECKPOINT_MESSAGES_SETTING, ValueKind::Unsigned),
+    (SEQNUM_CHECKPOINT_INTERVAL_MS_SETTING, ValueKind::Unsigned),
+    (CORRECT_NUM_IN_GROUP_SETTING, ValueKind::Bool),
+    (RELAXED_FIELD_ORDER_SETTING, ValueKind::Bool),
+    (LENIENT_GROUP_FIELD_ORDER_SETTING, ValueKind::Bool),
+    (MAX_FIELDS_SETTING, ValueKind::Unsigned),
+    (MAX_GROUP_INSTANCES_SETTING, ValueKind::Unsigned),
+    (DEDUPLICATE_POSS_DUP_SETTING, ValueKind::Bool),
+    (DROP_COPY_SESSIONS_SETTING, ValueKind::Str),
+    (ON_BEHALF_OF_COMP_ID_SETTING, ValueKind::Str),
+    (ON_BEHALF_OF_SUB_ID_SETTING, ValueKind::Str),
+    (ON_BEHALF_OF_LOCATION_ID_SETTING, ValueKind::Str),
+    (DELIVER_TO_COMP_ID_SETTING, ValueKind::Str),
+    (DELIVER_TO_SUB_ID_SETTING, ValueKind::Str),
+    (DELIVER_TO_LOCATION_ID_SETTING, ValueKind::Str),
+    (LOGON_USERNAME_SETTING, ValueKind::Str),
+    (LOGON_PASSWORD_SETTING, ValueKind::Str),
+    (SOCKET_NODELAY_SETTING, ValueKind::Bool),
+    (SOCKET_KEEPALIVE_SETTING, ValueKind::Bool),
+    (SOCKET_SEND_BUFFER_SIZE_SETTING, ValueKind::Unsigned),
+    (SOCKET_RECEIVE_BUFFER_SIZE_SETTING, ValueKind::Unsigned),
+    (SOCKET_FLUSH_INTERVAL_MS_SETTING, ValueKind::Unsigned),
+    (MAX_MESSAGE_SIZE_SETTING, ValueKind::Unsigned),
+    (SOCKET_IDLE_TIMEOUT_SETTING, ValueKind::Unsigned),
+    (CAPTURE_PATH_SETTING, ValueKind::Str),
+    (PROXY_TYPE_SETTING, ValueKind::Proxy),
+    (PROXY_HOST_SETTING, ValueKind::Str),
+    (PROXY_PORT_SETTING, ValueKind::Port),
+    (PROXY_USERNAME_SETTING, ValueKind::Str),
+    (PROXY_PASSWORD_SETTING, ValueKind::Str),
+    (START_DAY_SETTING, ValueKind::Day),
+    (END_DAY_SETTING, ValueKind::Day),
+    (START_TIME_SETTING, ValueKind::Time),
+    (END_TIME_SETTING, ValueKind::Time),
+    (TIMEZONE_SETTING, ValueKind::TimeZone),
+    (NON_TRADING_DAYS_SETTING, ValueKind::Days),
+    (HOLIDAYS_SETTING, ValueKind::Dates),
+    (SESSION_WINDOWS_SETTING, ValueKind::Windows),
+    (GROUP_SETTING, ValueKind::Str),
+];
+
+// settings that only mean something for the other connection type
+const INITIATOR_ONLY: [&str; 4] = [
+    SOCKET_CONNECT_HOST_SETTING,
+    SOCKET_CONNECT_PORT_SETTING,
+    PROXY_TYPE_SETTING,
+    PROXY_HOST_SETTING,
+];
+const ACCEPTOR_ONLY: [&str; 4] = [
+    SOCKET_ACCEPT_PORT_SETTING,
+    SOCKET_ACCEPT_HOST_SETTING,
+    ACCEPT_ANY_BEGIN_STRING_SETTING,
+    BEGIN_STRING_DICTIONARIES_SETTING,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticKind {
+    // line that is neither a section nor name = value
+    Malformed,
+    UnknownSetting,
+    InvalidValue { expected: &'static str },
+    Conflict(String),
+}
+
+// Problem of a config file, line is 1 based. ordered by line
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SettingsDiagnostic {
+    pub line: usize,
+    pub setting: String,
+    pub kind: DiagnosticKind,
+}
+
+impl SettingsDiagnostic {
+    pub fn is_error(&self) -> bool {
+        // an unknown setting is ignored by the engine, likely a typo but not fatal
+        self.kind != DiagnosticKind::UnknownSetting
+    }
+}
+
+impl fmt::Display for SettingsDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DiagnosticKind::Malformed => write!(f, "expected name = value"),
+            DiagnosticKind::UnknownSetting => write!(f, "unknown setting {}", self.setting),
+            DiagnosticKind::InvalidValue { expected } => {
+                write!(f, "{} should be {}", self.setting, expected)
+            }
+            DiagnosticKind::Conflict(reason) => write!(f, "{} {}", self.setting, reason),
+        }
+    }
+}
+
+struct Entry<'a> {
+    line: usize,
+    value: &'a str,
+}
+
+type Section<'a> = HashMap<&'a str, Entry<'a>>;
+
+// section the lines being read go to
+#[derive(Clone, Copy)]
+enum Current<'a> {
+    Default,
+    Group(&'a str),
+    Pattern,
+    Session,
+}
+
+pub(crate) fn validate(config: &str) -> Vec<SettingsDiagnostic> {
+    // every problem of the config, in line order. the checks of Properties stop at the first one
+    let mut diagnostics = BTreeSet::new();
+    let mut defaults: Section = HashMap::new();
+    let mut groups: HashMap<&str, Section> = HashMap::new();
+    // None for a pattern that does not parse
+    let mut patterns: Vec<(Option<SessionIdPattern>, Section)> = Vec::new();
+    let mut sessions: Vec<Section> = Vec::new();
+    let mut current = Current::Default;
+    for (idx, line) in config.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            current = match group_section(line) {
+                Some(name) => {
+                    groups.entry(name).or_default();
+                    Current::Group(name)
+                }
+                None if pattern_section(line).is_some() => {
+                    let pattern = pattern_section(line).unwrap_or_default().parse().ok();
+                    if pattern.is_none() {
+                        diagnostics.insert(diagnostic(line_no, line, DiagnosticKind::Malformed));
+                    }
+                    patterns.push((pattern, HashMap::new()));
+                    Current::Pattern
+                }
+                None if line.contains(DEFAULT_SECTION_NAME) => Current::Default,
+                None => {
+                    sessions.push(HashMap::new());
+                    Current::Session
+                }
+            };
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (unquote(name), unquote(value)),
+            None => {
+                diagnostics.insert(diagnostic(line_no, line, DiagnosticKind::Malformed));
+                continue;
+            }
+        };
+        match SCHEMA.iter().find(|(setting, _)| *setting == name) {
+            None => {
+                diagnostics.insert(diagnostic(line_no, name, DiagnosticKind::UnknownSetting));
+            }
+            Some((_, kind)) if !kind.accepts(value) => {
+                let kind = DiagnosticKind::InvalidValue {
+                    expected: kind.expected(),
+                };
+                diagnostics.insert(diagnostic(line_no, name, kind));
+            }
+            Some(_) => {}
+        }
+        let section = match current {
+            Current::Group(group) => groups.get_mut(group).expect("group section"),
+            Current::Pattern => &mut patterns.last_mut().expect("pattern section").1,
+            Current::Session => sessions.last_mut().expect("session section"),
+            Current::Default => &mut defaults,
+        };
+        section.insert(
+            name,
+            Entry {
+                line: line_no,
+                value,
+            },
+        );
+    }
+    if sessions.is_empty() {
+        sessions.push(HashMap::new());
+    }
+    for session in &sessions {
+        let mut layers = vec![session];
+        inherited(session, &groups, &mut layers, &mut diagnostics);
+        if let Some(session_id) = session_id(&layers, &defaults) {
+            let matching = patterns.iter().filter(|(pattern, _)| {
+                pattern.as_ref().is_some_and(|pattern| pattern.matches(&session_id))
+            });
+            layers.extend(matching.map(|(_, section)| section));
+        }
+        layers.push(&defaults);
+        check_conflicts(&layers, &mut diagnostics);
+    }
+    diagnostics.into_iter().collect()
+}
+
+fn inherited<'s, 'a>(
+    session: &'s Section<'a>, groups: &'s HashMap<&'a str, Section<'a>>,
+    layers: &mut Vec<&'s Section<'a>>, diagnostics: &mut BTreeSet<SettingsDiagnostic>,
+) {
+    // groups the session takes settings from, nearest first
+    let mut next = session.get(GROUP_SETTING);
+    while let Some(entry) = next {
+        let group = match groups.get(entry.value) {
+            Some(group) if layers.iter().any(|layer| std::ptr::eq(*layer, group)) => {
+                let kind = DiagnosticKind::Conflict("makes the group inherit from itself".into());
+                diagnostics.insert(diagnostic(entry.line, GROUP_SETTING, kind));
+                return;
+            }
+            Some(group) => group,
+            None => {
+                let kind =
+                    DiagnosticKind::Conflict(format!("names no [Group \"{}\"]", entry.value));
+                diagnostics.insert(diagnostic(entry.line, GROUP_SETTING, kind));
+                return;
+            }
+        };
+        layers.push(group);
+        next = group.get(GROUP_SETTING);
+    }
+}
+
+fn session_id(layers: &[&Section], defaults: &Section) -> Option<SessionId> {
+    // id of the session the layers are of, none while its comp ids are missing
+    let ids = [
+        BEGIN_STRING_SETTING,
+        SENDER_COMPID_SETTING,
+        SENDER_SUBID_SETTING,
+        SENDER_LOCATIONID_SETTING,
+        TARGET_COMPID_SETTING,
+        TARGET_SUBID_SETTING,
+        TARGET_LOCATIONID_SETTING,
+        SESSION_QUALIFIER_SETTING,
+    ];
+    let get = |name: &str| layers.iter().chain([&defaults]).find_map(|layer| layer.get(name));
+    let section: HashMap<String, String> = ids
+        .iter()
+        .filter_map(|name| Some((name.to_string(), get(name)?.value.to_string())))
+        .collect();
+    [
+        BEGIN_STRING_SETTING,
+        SENDER_COMPID_SETTING,
+        TARGET_COMPID_SETTING,
+    ]
+    .iter()
+    .all(|name| section.contains_key(*name))
+    .then(|| SessionId::from_map(&section, &HashMap::new()))
+}
+
+fn check_conflicts(layers: &[&Section], diagnostics: &mut BTreeSet<SettingsDiagnostic>) {
+    // layers are the session, its groups, the patterns it matches and the defaults, the first
+    // one setting a name wins
+    let get = |name: &str| layers.iter().find_map(|layer| layer.get(name));
+    let mut conflict = |name: &str, entry: &Entry, reason: &str| {
+        let kind = DiagnosticKind::Conflict(reason.to_string());
+        diagnostics.insert(diagnostic(entry.line, name, kind));
+    };
+    let (other_only, reason) = match get(CONNECTION_TYPE_SETTING).map(|entry| entry.value) {
+        Some(ACCEPTOR_CONN_TYPE) => {
+            (&INITIATOR_ONLY[..], "is an initiator setting, not for an acceptor")
+        }
+        Some(INITIATOR_CONN_TYPE) => {
+            (&ACCEPTOR_ONLY[..], "is an acceptor setting, not for an initiator")
+        }
+        _ => (&[][..], ""),
+    };
+    for name in other_only {
+        if let Some(entry) = get(name) {
+            conflict(name, entry, reason);
+        }
+    }
+    match (get(START_TIME_SETTING), get(END_TIME_SETTING)) {
+        (Some(entry), None) => conflict(START_TIME_SETTING, entry, "is set without end_time"),
+        (None, Some(entry)) => conflict(END_TIME_SETTING, entry, "is set without start_time"),
+        _ => {}
+    }
+    if let Some(entry) = get(SESSION_WINDOWS_SETTING) {
+        if get(START_TIME_SETTING).is_some() || get(END_TIME_SETTING).is_some() {
+            conflict(SESSION_WINDOWS_SETTING, entry, "replaces start_time and end_time");
+        }
+    }
+    if get(START_TIME_SETTING).is_none() && get(SESSION_WINDOWS_SETTING).is_none() {
+        for name in [
+            START_DAY_SETTING,
+            END_DAY_SETTING,
+            NON_TRADING_DAYS_SETTING,
+            HOLIDAYS_SETTING,
+        ] {
+            if let Some(entry) = get(name) {
+                conflict(name, entry, "is set without start_time and end_time");
+            }
+        }
+        if let Some(entry) = get(RESET_ON_SCHEDULE_SETTING).filter(|entry| entry.value == "true") {
+            conflict(RESET_ON_SCHEDULE_SETTING, entry, "is set without start_time and end_time");
+        }
+    }
+    if let Some(entry) = get(STORE_ARCHIVE_PATH_SETTING) {
+        if get(STORE_MAX_MESSAGES_SETTING).is_none() && get(STORE_MAX_AGE_SETTING).is_none() {
+            let reason = "is set without store_max_messages or store_max_age";
+            conflict(STORE_ARCHIVE_PATH_SETTING, entry, reason);
+        }
+    }
+    if let (Some(entry), None) = (get(LOGON_PASSWORD_SETTING), get(LOGON_USERNAME_SETTING)) {
+        conflict(LOGON_PASSWORD_SETTING, entry, "is set without logon_username");
+    }
+    if let Some(entry) = get(BEGIN_STRING_DICTIONARIES_SETTING) {
+        if get(ACCEPT_ANY_BEGIN_STRING_SETTING).is_none_or(|entry| entry.value != "true") {
+            let reason = "is set without accept_any_begin_string";
+            conflict(BEGIN_STRING_DICTIONARIES_SETTING, entry, reason);
+        }
+    }
+}
+
+fn diagnostic(line: usize, setting: &str, kind: DiagnosticKind) -> SettingsDiagnostic {
+    SettingsDiagnostic {
+        line,
+        setting: setting.to_string(),
+        kind,
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim().trim_start_matches('"').trim_end_matches('"')
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            heartbeat_interval = "abc"
+            hearbeat_interval = 30
+
+            [Session]
+            sender_comp_id = "SENDER"
+            target_comp_id = "TARGET"
+            socket_accept_port = 10117
+            socket_connect_host = "localhost"
+            start_time = "25:00:00"
+            start_day = "Mon"
+            holidays = "2024-12-25,Dec 26"
+            session_windows = "09:00:00-11:30:00"
+            reset_on_logon
+        "#;
+        let diagnostics: Vec<String> = validate(config).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "line 5: heartbeat_interval should be an unsigned integer",
+                "line 6: unknown setting hearbeat_interval",
+                "line 12: socket_connect_host is an initiator setting, not for an acceptor",
+                "line 13: start_time should be a time like 08:30:00",
+                "line 13: start_time is set without end_time",
+                "line 15: holidays should be dates like 2024-12-25,2025-01-01",
+                "line 16: session_windows replaces start_time and end_time",
+                "line 17: expected name = value",
+            ]
+        );
+        assert!(!validate(config)[1].is_error());
+    }
+
+    #[test]
+    fn test_validate_defaults_conflict_once() {
+        // a setting of the default section is reported once, however many sessions inherit it
+        let config = r#"
+            [Default]
+            connection_type = "initiator"
+            begin_string = "FIX.4.3"
+            socket_accept_port = 10117
+            logon_password = "secret"
+            reset_on_schedule = true
+
+            [Session]
+            sender_comp_id = "A"
+            target_comp_id = "B"
+
+            [Session]
+            sender_comp_id = "A"
+            target_comp_id = "C"
+            logon_username = "user"
+        "#;
+        let diagnostics = validate(config);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line, 5);
+        assert_eq!(diagnostics[1].setting, LOGON_PASSWORD_SETTING);
+        assert_eq!(diagnostics[2].setting, RESET_ON_SCHEDULE_SETTING);
+        assert!(validate(include_str!("../FixConfig.toml")).is_empty());
+    }
+
+    #[test]
+    fn test_validate_groups() {
+        // conflicts are found across the groups a session inherits from
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+
+            [Group "EU"]
+            start_time = "08:00:00"
+
+            [Group "LSE"]
+            group = "EU"
+            socket_accept_port = 10117
+
+            [Session]
+            group = "LSE"
+            sender_comp_id = "A"
+            target_comp_id = "B"
+
+            [Session]
+            group = "NYSE"
+            sender_comp_id = "A"
+            target_comp_id = "C"
+        "#;
+        let diagnostics: Vec<String> = validate(config).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "line 7: start_time is set without end_time",
+                "line 19: group names no [Group \"NYSE\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_patterns() {
+        // a pattern's settings conflict with those of the sessions it matches only
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+
+            [Sessions "FIX.4.3:BROKER*->*"]
+            start_time = "08:00:00"
+
+            [Sessions "FIX.4.3:->"]
+            heartbeat_interval = 20
+
+            [Session]
+            sender_comp_id = "BROKER1"
+            target_comp_id = "B"
+
+            [Session]
+            sender_comp_id = "DESK"
+            target_comp_id = "C"
+        "#;
+        let diagnostics: Vec<String> = validate(config).iter().map(ToString::to_string).collect();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0], "line 7: start_time is set without end_time");
+        assert!(diagnostics[1].starts_with("line 9:"));
+    }
+
+    #[test]
+    fn test_validate_begin_string_dictionaries() {
+        let config = r#"
+            [Default]
+            connection_type = "acceptor"
+            begin_string = "FIX.4.3"
+            begin_string_dictionaries = "resources/FIX44.xml"
+
+            [Session]
+            sender_comp_id = "A"
+            target_comp_id = "B"
+            accept_any_begin_string = true
+
+            [Session]
+            sender_comp_id = "A"
+            target_comp_id = "C"
+        "#;
+        let diagnostics: Vec<String> = validate(config).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            diagnostics,
+            ["line 5: begin_string_dictionaries is set without accept_any_begin_string"]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+// A scripted FIX counterparty for examples and tests. It accepts any Logon (replying with the comp
+// ids reversed), answers TestRequest and ResendRequest, acks every NewOrderSingle with a New and a
+// Filled ExecutionReport, and can be told to misbehave on chosen outgoing seqnums so that the
+// engine's gap detection and garbled message handling get exercised.
+use crate::clock::{Clock, SharedClock};
+use crate::data_dictionary::DataDictionary;
+use crate::fields::*;
+use crate::io::frame::{read_frame, FrameDecoder};
+use crate::message::*;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    // skip this many seqnums before sending, so the counterparty sees a gap
+    SeqGap(u64),
+    // send the message with a wrong checksum
+    Garble,
+}
+
+#[derive(Debug)]
+pub struct Simulator {
+    data_dictionary: Arc<DataDictionary>,
+    factory: MessageFactory,
+    // keyed by the outgoing seqnum the fault applies to
+    faults: HashMap<u64, Fault>,
+    next_sender_msg_seq_num: u64,
+    next_target_msg_seq_num: u64,
+    next_order_id: u64,
+    logged_out: bool,
+    // SendingTime of what the simulator sends
+    clock: SharedClock,
+}
+
+impl Simulator {
+    pub fn new(dd: Arc<DataDictionary>) -> Self {
+        Self {
+            factory: MessageFactory::with_dictionary(Arc::clone(&dd)),
+            data_dictionary: dd,
+            faults: HashMap::new(),
+            next_sender_msg_seq_num: 1,
+            next_target_msg_seq_num: 1,
+            next_order_id: 1,
+            logged_out: false,
+            clock: SharedClock::default(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    pub fn with_fault(mut self, seq_num: u64, fault: Fault) -> Self {
+        self.faults.insert(seq_num, fault);
+        self
+    }
+
+    pub fn next_sender_msg_seq_num(&self) -> u64 {
+        self.next_sender_msg_seq_num
+    }
+
+    pub fn next_target_msg_seq_num(&self) -> u64 {
+        self.next_target_msg_seq_num
+    }
+
+    pub fn on_message(&mut self, msg: &Message) -> Vec<String> {
+        // raw messages to write back in response to msg
+        self.next_target_msg_seq_num += 1;
+        let msg_type = msg.get_msg_type().unwrap_or_default();
+        match msg_type.as_str() {
+            "A" => {
+                if msg.get_field::<String>(ResetSeqNumFlag::field()).as_deref() == Ok("Y") {
+                    self.next_sender_msg_seq_num = 1;
+                    self.next_target_msg_seq_num = 2;
+                }
+                self.logged_out = false;
+                let mut logon = self.reply_to(msg, "A");
+                logon.set_field(StringField::new(EncryptMethod::field(), "0"));
+                let heartbeat_intrvl = msg.get_field::<String>(HeartBtInt::field());
+                logon.set_field(StringField::new(
+                    HeartBtInt::field(),
+                    heartbeat_intrvl.as_deref().unwrap_or("30"),
+                ));
+                vec![self.send(logon)]
+            }
+            "1" => {
+                let mut heartbeat = self.reply_to(msg, "0");
+                if let Ok(test_req_id) = msg.get_field::<String>(TestReqID::field()) {
+                    heartbeat.set_field(StringField::new(TestReqID::field(), &test_req_id));
+                }
+                vec![self.send(heartbeat)]
+            }
+            "2" => vec![self.gap_fill(msg)],
+            "5" => {
+                self.logged_out = true;
+                vec![self.send(self.reply_to(msg, "5"))]
+            }
+            "D" => self.execute(msg),
+            _ => Vec::new(),
+        }
+    }
+
+    fn reply_to(&self, msg: &Message, msg_type: &str) -> Message {
+        let header = msg.header();
+        let begin_string = header.get_field::<String>(BeginString::field()).unwrap_or_default();
+        let mut reply = self.factory.create(&begin_string, msg_type).unwrap_or_else(|_| {
+            let mut reply = Message::new();
+            reply.header_mut().set_begin_string(&begin_string);
+            reply.header_mut().set_msg_type(msg_type);
+            reply
+        });
+        let reverse_ids: [(_, SetId); 4] = [
+            (SenderCompID::field(), Header::set_target_comp_id),
+            (TargetCompID::field(), Header::set_sender_comp_id),
+            (SenderSubID::field(), Header::set_target_sub_id),
+            (TargetSubID::field(), Header::set_sender_sub_id),
+        ];
+        for (from_tag, set_id) in reverse_ids {
+            if let Ok(value) = header.get_field::<String>(from_tag) {
+                set_id(reply.header_mut(), &value);
+            }
+        }
+        reply.header_mut().reverse_routing(header);
+        reply
+    }
+
+    fn execute(&mut self, order: &Message) -> Vec<String> {
+        // everything is filled in full at the order price
+        let order_id = self.next_order_id.to_string();
+        self.next_order_id += 1;
+        let qty = order.get_field::<String>(OrderQty::field()).unwrap_or_else(|_| "0".into());
+        let price = order.get_field::<String>(Price::field()).unwrap_or_else(|_| "0".into());
+        let copied_tags = [
+            ClOrdID::field(),
+            Symbol::field(),
+            Side::field(),
+            OrderQty::field(),
+        ];
+        let mut reports = Vec::with_capacity(2);
+        // (ExecType, OrdStatus)
+        for (exec_type, ord_status) in [("0", "0"), ("F", "2")] {
+            let mut report = self.reply_to(order, "8");
+            for tag in copied_tags {
+                if let Ok(value) = order.get_field::<String>(tag) {
+                    report.set_field(StringField::new(tag, &value));
+                }
+            }
+            let filled = exec_type == "F";
+            let exec_id = format!("{}-{}", order_id, exec_type);
+            report.set_field(StringField::new(OrderID::field(), &order_id));
+            report.set_field(StringField::new(ExecID::field(), &exec_id));
+            report.set_field(StringField::new(ExecType::field(), exec_type));
+            report.set_field(StringField::new(OrdStatus::field(), ord_status));
+            report.set_field(StringField::new(LeavesQty::field(), if filled { "0" } else { &qty }));
+            report.set_field(StringField::new(CumQty::field(), if filled { &qty } else { "0" }));
+            report.set_field(StringField::new(AvgPx::field(), if filled { &price } else { "0" }));
+            if filled {
+                report.set_field(StringField::new(LastQty::field(), &qty));
+                report.set_field(StringField::new(LastPx::field(), &price));
+            }
+            reports.push(self.send(report));
+        }
+        reports
+    }
+
+    fn gap_fill(&self, resend_request: &Message) -> String {
+        // nothing is stored, so every requested message is replaced by a single gap fill
+        let begin_seq_no = resend_request.get_field::<u64>(BeginSeqNo::field()).unwrap_or(1);
+        let mut reset = self.reply_to(resend_request, "4");
+        reset.header_mut().set_seq_num(begin_seq_no);
+        reset.header_mut().set_poss_dup(true);
+        reset.set_field(StringField::new(GapFillFlag::field(), "Y"));
+        reset.set_field(StringField::new(
+            NewSeqNo::field(),
+            &self.next_sender_msg_seq_num.to_string(),
+        ));
+        finalize(&mut reset, &self.clock);
+        reset.to_string()
+    }
+
+    fn send(&mut self, mut msg: Message) -> String {
+        let fault = self.faults.get(&self.next_sender_msg_seq_num).copied();
+        if let Some(Fault::SeqGap(skipped)) = fault {
+            self.next_sender_msg_seq_num += skipped;
+        }
+        msg.header_mut().set_seq_num(self.next_sender_msg_seq_num);
+        self.next_sender_msg_seq_num += 1;
+        finalize(&mut msg, &self.clock);
+        if let Some(Fault::Garble) = fault {
+            let checksum = msg.trailer().checksum().unwrap_or(0);
+            msg.trailer_mut().set_checksum((checksum + 1) % 256);
+        }
+        msg.to_string()
+    }
+
+    pub fn listen(mut self, addr: SocketAddr) -> std::io::Result<(SocketAddr, JoinHandle<()>)> {
+        // serves one connection at a time until the task is aborted
+        let std_listener = std::net::TcpListener::bind(addr)?;
+        std_listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(std_listener)?;
+        let local_addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                if let Err(e) = self.serve(stream).await {
+                    println!("simulator connection closed: {:?}", e);
+                }
+            }
+        });
+        Ok((local_addr, handle))
+    }
+
+    pub async fn serve(&mut self, stream: TcpStream) -> std::io::Result<()> {
+        let (mut read_half, mut write_half) = stream.into_split();
+        let mut decoder = FrameDecoder::default();
+        let dd = Arc::clone(&self.data_dictionary);
+        while !self.logged_out {
+            let buf = read_frame(&mut read_half, &mut decoder).await?;
+            let raw = decode_frame(&buf);
+            let msg = match Message::from_str(&raw, &dd) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    println!("simulator ignoring garbled message: {:?}", e);
+                    continue;
+                }
+            };
+            for reply in self.on_message(&msg) {
+                write_half.write_all(&encode_frame(&reply)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn finalize(msg: &mut Message, clock: &dyn Clock) {
+    msg.set_sending_time(clock);
+    msg.set_body_len();
+    msg.set_checksum();
+}
+
+#[cfg(test)]
+mod simulator_tests {
+    use super::*;
+    use crate::session::{admin, SessionIdBuilder};
+    use crate::test_utils::TestInitiator;
+    use lazy_static::lazy_static;
+
+    const CONFIG: &str = r#"
+        [Default]
+        connection_type = "acceptor"
+        begin_string = "FIX.4.3"
+
+        [Session]
+        sender_comp_id = "SIMULATOR"
+        target_comp_id = "CLIENT"
+        socket_accept_port = 0
+    "#;
+
+    lazy_static! {
+        static ref DD: Arc<DataDictionary> =
+            Arc::new(DataDictionary::from_xml("resources/FIX43.xml"));
+    }
+
+    fn parse(raw: &str) -> Message {
+        Message::from_str(raw, &DD).unwrap()
+    }
+
+    fn client_message(msg_type: &str, seq_num: u64) -> Message {
+        let mut msg =
+            MessageFactory::with_dictionary(Arc::clone(&DD)).create("FIX.4.3", msg_type).unwrap();
+        msg.header_mut().set_sender_comp_id("CLIENT");
+        msg.header_mut().set_target_comp_id("SIMULATOR");
+        msg.header_mut().set_seq_num(seq_num);
+        msg
+    }
+
+    fn new_order(seq_num: u64) -> Message {
+        let mut order = client_message("D", seq_num);
+        order.set_field(StringField::new(11, "ORDER1"));
+        order.set_field(StringField::new(55, "IBM"));
+        order.set_field(StringField::new(54, "1"));
+        order.set_field(StringField::new(38, "100"));
+        order.set_field(StringField::new(44, "15.5"));
+        order
+    }
+
+    #[test]
+    fn test_logon_reply_reverses_comp_ids() {
+        let mut sim = Simulator::new(Arc::clone(&DD));
+        let replies = sim.on_message(&client_message("A", 1));
+        assert_eq!(replies.len(), 1);
+        let logon = parse(&replies[0]);
+        assert_eq!(logon.get_msg_type().unwrap(), "A");
+        assert_eq!(logon.header().get_field::<String>(49).unwrap(), "SIMULATOR");
+        assert_eq!(logon.header().get_field::<String>(56).unwrap(), "CLIENT");
+        assert_eq!(logon.header().get_field::<u64>(34).unwrap(), 1);
+        assert_eq!(sim.next_target_msg_seq_num(), 2);
+    }
+
+    #[test]
+    fn test_new_order_is_filled() {
+        let mut sim = Simulator::new(Arc::clone(&DD));
+        sim.on_message(&client_message("A", 1));
+        let reports: Vec<Message> =
+            sim.on_message(&new_order(2)).iter().map(|r| parse(r)).collect();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].get_field::<String>(150).unwrap(), "0");
+        assert_eq!(reports[0].get_field::<String>(151).unwrap(), "100");
+        assert_eq!(reports[1].get_field::<String>(150).unwrap(), "F");
+        assert_eq!(reports[1].get_field::<String>(39).unwrap(), "2");
+        assert_eq!(reports[1].get_field::<String>(14).unwrap(), "100");
+        assert_eq!(reports[1].get_field::<String>(31).unwrap(), "15.5");
+        for (report, seq_num) in reports.iter().zip([2, 3]) {
+            assert_eq!(report.get_msg_type().unwrap(), "8");
+            assert_eq!(report.get_field::<String>(11).unwrap(), "ORDER1");
+            assert_eq!(report.header().get_field::<u64>(34).unwrap(), seq_num);
+        }
+    }
+
+    #[test]
+    fn test_garble_fault_corrupts_checksum() {
+        let mut sim = Simulator::new(Arc::clone(&DD)).with_fault(1, Fault::Garble);
+        let mut logon = parse(&sim.on_message(&client_message("A", 1))[0]);
+        let sent_checksum = logon.trailer().get_field::<String>(10).unwrap();
+        logon.set_checksum();
+        assert_ne!(logon.trailer().get_field::<String>(10).unwrap(), sent_checksum);
+    }
+
+    #[test]
+    fn test_resend_request_is_gap_filled() {
+        let mut sim = Simulator::new(Arc::clone(&DD)).with_fault(2, Fault::SeqGap(3));
+        sim.on_message(&client_message("A", 1));
+        let mut heartbeat_req = client_message("1", 2);
+        heartbeat_req.set_field(StringField::new(112, "T"));
+        let heartbeat = parse(&sim.on_message(&heartbeat_req)[0]);
+        assert_eq!(heartbeat.header().get_field::<u64>(34).unwrap(), 5);
+
+        let mut resend = client_message("2", 3);
+        resend.set_field(StringField::new(7, "2"));
+        resend.set_field(StringField::new(16, "0"));
+        let reset = parse(&sim.on_message(&resend)[0]);
+        assert_eq!(reset.get_msg_type().unwrap(), "4");
+        assert_eq!(reset.header().get_field::<u64>(34).unwrap(), 2);
+        assert_eq!(reset.header().get_field::<String>(43).unwrap(), "Y");
+        assert_eq!(reset.get_field::<String>(123).unwrap(), "Y");
+        assert_eq!(reset.get_field::<u64>(36).unwrap(), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_simulator_over_socket() {
+        let sim = Simulator::new(Arc::clone(&DD)).with_fault(2, Fault::SeqGap(2));
+        let (addr, handle) = sim.listen("127.0.0.1:0".parse().unwrap()).unwrap();
+        let session_id = SessionIdBuilder::new("FIX.4.3", "SIMULATOR", "CLIENT").build().unwrap();
+        let mut client = TestInitiator::connect(addr, CONFIG, &session_id).await;
+
+        client.send_admin(admin::logon).await;
+        assert_eq!(client.recv().await.header().get_field::<u64>(34).unwrap(), 1);
+
+        let mut order = client.session().new_message("D").unwrap();
+        order.set_field(StringField::new(11, "ORDER1"));
+        order.set_field(StringField::new(38, "10"));
+        client.send(order).await;
+        let ack = client.recv().await;
+        let fill = client.recv().await;
+        assert_eq!(ack.header().get_field::<u64>(34).unwrap(), 4);
+        assert_eq!(fill.header().get_field::<u64>(34).unwrap(), 5);
+        assert_eq!(fill.get_field::<String>(14).unwrap(), "10");
+        handle.abort();
+    }
+}
@@ -0,0 +1,248 @@
+// Helpers for tests that need a running acceptor and a counterparty talking to it over a real
+// socket or an in-process connection. Everything runs inside the test's tokio runtime, the
+// acceptor binds an ephemeral port.
+use crate::application::{Application, SessionContext, ThirdPartyRoute};
+use crate::events::EngineEvent;
+use crate::io::frame::{read_frame, FrameDecoder};
+use crate::io::TioBroadcastReceiver;
+use crate::message::*;
+use crate::network::SocketAcceptor;
+use crate::quickfix_errors::SessionRejectError;
+use crate::session::*;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{channel, unbounded_channel, Receiver, UnboundedReceiver, UnboundedSender};
+
+pub(crate) const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub(crate) enum AppEvent {
+    FromApp(SessionId, Box<Message>),
+    Logout(SessionId, Option<String>),
+}
+
+// records every callback so that tests can assert on what the application saw
+pub(crate) struct TestApplication {
+    events: UnboundedSender<AppEvent>,
+    // (username, password) a logon has to carry, any logon is accepted when None
+    credentials: Option<(String, String)>,
+    // ClOrdIDs delivered so far, a PossResend of one of them is a duplicate
+    cl_ord_ids: Mutex<HashSet<String>>,
+    // how long from_app takes, for a handler slower than the session
+    from_app_delay: Duration,
+    // route of every message with DeliverToCompID, Local when None
+    third_party_route: Option<ThirdPartyRoute>,
+}
+
+impl TestApplication {
+    pub(crate) fn new() -> (Self, UnboundedReceiver<AppEvent>) {
+        let (events, rx) = unbounded_channel();
+        let app = Self {
+            events,
+            credentials: None,
+            cl_ord_ids: Mutex::default(),
+            from_app_delay: Duration::ZERO,
+            third_party_route: None,
+        };
+        (app, rx)
+    }
+
+    pub(crate) fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    pub(crate) fn with_from_app_delay(mut self, delay: Duration) -> Self {
+        self.from_app_delay = delay;
+        self
+    }
+
+    pub(crate) fn with_third_party_route(mut self, route: ThirdPartyRoute) -> Self {
+        self.third_party_route = Some(route);
+        self
+    }
+}
+
+impl Application for TestApplication {
+    fn to_app(msg: String) {}
+
+    fn from_app(&self, ctx: &SessionContext, msg: Message) {
+        std::thread::sleep(self.from_app_delay);
+        if let Ok(cl_ord_id) = msg.get_field::<String>(11) {
+            self.cl_ord_ids.lock().unwrap().insert(cl_ord_id);
+        }
+        let _ = self.events.send(AppEvent::FromApp(ctx.session_id().clone(), Box::new(msg)));
+    }
+
+    fn on_logout(&self, session_id: &SessionId, reason: Option<&str>) {
+        let reason = reason.map(str::to_string);
+        let _ = self.events.send(AppEvent::Logout(session_id.clone(), reason));
+    }
+
+    fn authenticate(
+        &self, session_id: &SessionId, username: Option<&str>, password: Option<&str>,
+    ) -> bool {
+        match &self.credentials {
+            Some((user, pass)) => username == Some(user) && password == Some(pass),
+            None => true,
+        }
+    }
+
+    fn is_poss_resend_duplicate(&self, session_id: &SessionId, msg: &Message) -> bool {
+        let cl_ord_id = msg.get_field::<String>(11).unwrap_or_default();
+        self.cl_ord_ids.lock().unwrap().contains(&cl_ord_id)
+    }
+
+    fn route_third_party(&self, session_id: &SessionId, msg: &Message) -> ThirdPartyRoute {
+        self.third_party_route.clone().unwrap_or(ThirdPartyRoute::Local)
+    }
+}
+
+// panics with every field that is not as expected
+pub(crate) fn assert_fields(msg: &Message, expected: &[(u32, &str)]) {
+    let diff = diff_fields(expected, msg);
+    assert!(diff.is_empty(), "{}\nin {}", diff, msg);
+}
+
+pub(crate) async fn recv_event(events: &mut UnboundedReceiver<AppEvent>) -> AppEvent {
+    tokio::time::timeout(RECV_TIMEOUT, events.recv())
+        .await
+        .expect("timed out waiting for application event")
+        .expect("application dropped")
+}
+
+pub(crate) async fn recv_engine_event(
+    events: &mut TioBroadcastReceiver<EngineEvent>,
+) -> EngineEvent {
+    tokio::time::timeout(RECV_TIMEOUT, events.recv())
+        .await
+        .expect("timed out waiting for engine event")
+        .expect("event bus closed")
+}
+
+// config should use socket_accept_port = 0 so that tests do not fight over ports
+pub(crate) fn start_acceptor(
+    config: &str,
+) -> (SocketAcceptor<TestApplication>, UnboundedReceiver<AppEvent>, SocketAddr) {
+    let (app, events) = TestApplication::new();
+    let (acceptor, addr) = start_acceptor_with(config, app);
+    (acceptor, events, addr)
+}
+
+pub(crate) fn start_acceptor_with(
+    config: &str, app: TestApplication,
+) -> (SocketAcceptor<TestApplication>, SocketAddr) {
+    run_acceptor(SocketAcceptor::new(Properties::from_str(config), app))
+}
+
+// for acceptors that need more than settings and application before they are started
+pub(crate) fn run_acceptor(
+    mut acceptor: SocketAcceptor<TestApplication>,
+) -> (SocketAcceptor<TestApplication>, SocketAddr) {
+    acceptor.start_accepting_connections().expect("acceptor did not start");
+    let addr = acceptor.local_addrs()[0];
+    (acceptor, addr)
+}
+
+// Counterparty of an acceptor session. It keeps its own Session (with sender and target
+// swapped) so that outgoing messages get real seqnums and incoming seqnums can be checked.
+pub(crate) struct TestInitiator {
+    session: Session,
+    outgoing: Receiver<String>,
+    reader: Box<dyn AsyncRead + Send + Unpin>,
+    decoder: FrameDecoder,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+}
+
+impl TestInitiator {
+    pub(crate) async fn connect(addr: SocketAddr, config: &str, session_id: &SessionId) -> Self {
+        let stream = TcpStream::connect(addr).await.expect("could not connect to acceptor");
+        let (read_half, write_half) = stream.into_split();
+        Self::with_connection(config, session_id, Box::new(read_half), Box::new(write_half))
+    }
+
+    pub(crate) fn connect_in_process(
+        acceptor: &SocketAcceptor<TestApplication>, config: &str, session_id: &SessionId,
+    ) -> Self {
+        let stream = acceptor.connect_in_process(session_id).expect("could not connect");
+        let (read_half, write_half) = tokio::io::split(stream);
+        Self::with_connection(config, session_id, Box::new(read_half), Box::new(write_half))
+    }
+
+    fn with_connection(
+        config: &str, session_id: &SessionId, reader: Box<dyn AsyncRead + Send + Unpin>,
+        writer: Box<dyn AsyncWrite + Send + Unpin>,
+    ) -> Self {
+        let settings = Properties::from_str(config);
+        let mut session = Session::with_settings(session_id, &settings).unwrap();
+        session.session_id = SessionIdBuilder::new(
+            session_id.begin_string(),
+            session_id.target_compid(),
+            session_id.sender_compid(),
+        )
+        .build()
+        .unwrap();
+        let (tx, outgoing) = channel::<String>(session.send_queue_size());
+        session.set_responder(Some(tx));
+        Self {
+            session,
+            outgoing,
+            reader,
+            decoder: FrameDecoder::default(),
+            writer,
+        }
+    }
+
+    pub(crate) fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub(crate) fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub(crate) async fn send(&mut self, msg: Message) {
+        // goes through Session::send_to_target so that seqnum, sending time, body length and
+        // checksum are stamped the same way the engine does it
+        self.session.send_to_target(msg).expect("could not queue message");
+        let raw = self.outgoing.try_recv().unwrap();
+        self.writer.write_all(&encode_frame(&raw)).await.expect("could not write to acceptor");
+    }
+
+    // all messages go out in a single write, so that the acceptor reads them back to back
+    pub(crate) async fn send_all(&mut self, msgs: Vec<Message>) {
+        self.session.send_batch(msgs).expect("could not queue messages");
+        let raw = self.outgoing.try_recv().unwrap();
+        self.writer.write_all(raw.as_bytes()).await.expect("could not write to acceptor");
+    }
+
+    pub(crate) async fn send_admin(
+        &mut self, build: impl FnOnce(&Session) -> Result<Message, SessionRejectError>,
+    ) {
+        let msg = build(&self.session).unwrap();
+        self.send(msg).await;
+    }
+
+    // true once the acceptor has closed the connection
+    pub(crate) async fn is_disconnected(&mut self) -> bool {
+        let read =
+            tokio::time::timeout(RECV_TIMEOUT, read_frame(&mut self.reader, &mut self.decoder));
+        matches!(read.await, Ok(Err(_)))
+    }
+
+    pub(crate) async fn recv(&mut self) -> Message {
+        let buf =
+            tokio::time::timeout(RECV_TIMEOUT, read_frame(&mut self.reader, &mut self.decoder))
+                .await
+                .expect("timed out waiting for message")
+                .expect("connection closed");
+        let raw = decode_frame(&buf);
+        let msg = Message::from_str(&raw, self.session.data_dictionary()).unwrap();
+        self.session.state_mut().incr_next_target_msg_seq_num();
+        msg
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 use std::convert::TryFrom;
 use std::fmt::{self, Formatter};
 use std::str::FromStr;
@@ -84,7 +85,7 @@ impl FromStr for Int {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.parse::<i64>() {
             Ok(i) => Ok(Int::new(i)),
-            Err(e) => Err(SessionRejectError::parse_err(Some(Box::new(e)))),
+            Err(_) => Err(SessionRejectError::incorrect_data_format_err()),
         }
     }
 }
@@ -125,7 +126,7 @@ impl FromStr for Float {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.parse::<f64>() {
             Ok(f) => Ok(Float::new(f)),
-            Err(e) => Err(SessionRejectError::parse_err(Some(Box::new(e)))),
+            Err(_) => Err(SessionRejectError::incorrect_data_format_err()),
         }
     }
 }
@@ -202,6 +203,10 @@ impl Char {
     pub fn new<T: Into<char>>(value: T) -> Char {
         Char(value.into())
     }
+
+    pub fn value(&self) -> char {
+        self.0
+    }
 }
 
 impl fmt::Display for Char {
@@ -230,9 +235,8 @@ impl FromStr for Char {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.parse::<char>() {
-            Ok(c) if c.is_ascii() => Ok(Char::new(c)),
-            Ok(_) => Err(SessionRejectError::parse_err(None)),
-            Err(e) => Err(SessionRejectError::parse_err(Some(Box::new(e)))),
+            Ok(c) if c.is_ascii() && !c.is_ascii_control() => Ok(Char::new(c)),
+            _ => Err(SessionRejectError::incorrect_data_format_err()),
         }
     }
 }
@@ -268,6 +272,10 @@ impl Bool {
             Bool('N')
         }
     }
+
+    pub fn value(&self) -> bool {
+        self.0 == 'Y'
+    }
 }
 
 impl fmt::Display for Bool {
@@ -295,17 +303,11 @@ impl FromStr for Bool {
     type Err = SessionRejectError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.parse::<char>() {
-            Ok(ch) => {
-                if ch.eq_ignore_ascii_case(&'y') {
-                    Ok(Bool::new(true))
-                } else if ch.eq_ignore_ascii_case(&'n') {
-                    Ok(Bool::new(false))
-                } else {
-                    Err(SessionRejectError::parse_err(None))
-                }
-            }
-            Err(e) => Err(SessionRejectError::parse_err(Some(Box::new(e)))),
+        // the spec only allows upper case Y and N
+        match s {
+            "Y" => Ok(Bool::new(true)),
+            "N" => Ok(Bool::new(false)),
+            _ => Err(SessionRejectError::incorrect_data_format_err()),
         }
     }
 }
@@ -330,5 +332,581 @@ impl FromStr for Bool {
 //     }
 // }
 
+// Exact decimal for PRICE, QTY and AMT values, with the decimal feature. The scale is kept, so
+// a value is written back with the digits it was read with ("100.50" stays "100.50").
+#[cfg(feature = "decimal")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(rust_decimal::Decimal);
+
+#[cfg(feature = "decimal")]
+impl Decimal {
+    pub fn new(value: rust_decimal::Decimal) -> Self {
+        Decimal(value)
+    }
+
+    pub fn value(&self) -> rust_decimal::Decimal {
+        self.0
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Decimal(value)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal(value.into())
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl FromStr for Decimal {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // from_str_exact refuses values it would have to round
+        let err = || InvalidFieldValue::new("DECIMAL", s);
+        if !is_fix_decimal(s) {
+            return Err(err());
+        }
+        rust_decimal::Decimal::from_str_exact(s).map(Decimal).map_err(|_| err())
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Space separated values of a MULTIPLEVALUESTRING field, e.g. ExecInst(18) "1 G"
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct MultipleValueString(Vec<String>);
+
+impl MultipleValueString {
+    pub fn new<S: Into<String>>(values: impl IntoIterator<Item = S>) -> Self {
+        Self(values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn values(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn into_values(self) -> Vec<String> {
+        self.0
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.0.iter().any(|v| v == value)
+    }
+}
+
+impl From<Vec<String>> for MultipleValueString {
+    fn from(values: Vec<String>) -> Self {
+        Self(values)
+    }
+}
+
+impl FromStr for MultipleValueString {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // exactly one space between values, none around them
+        let values: Vec<String> = s.split(' ').map(str::to_string).collect();
+        if values.iter().any(String::is_empty) {
+            return Err(InvalidFieldValue::new("MULTIPLEVALUESTRING", s));
+        }
+        Ok(Self(values))
+    }
+}
+
+impl fmt::Display for MultipleValueString {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join(" "))
+    }
+}
+
+// FIX float syntax: optional leading '-', digits, optional '.' with more digits. no exponent,
+// no '+', at least one digit
+pub(crate) fn is_fix_decimal(s: &str) -> bool {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    !(int.is_empty() && frac.is_empty()) && all_digits(int) && all_digits(frac)
+}
+
+// Typed values for the FIX date and time types, usable with get_field:
+//
+//     let sending_time = msg.header().get_field::<UtcTimestamp>(52)?;
+
+// digits after the seconds. FIX allows none, milli, micro or nano seconds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TimePrecision {
+    Seconds,
+    #[default]
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimePrecision {
+    fn from_digits(digits: usize) -> Option<Self> {
+        match digits {
+            0 => Some(TimePrecision::Seconds),
+            3 => Some(TimePrecision::Millis),
+            6 => Some(TimePrecision::Micros),
+            9 => Some(TimePrecision::Nanos),
+            _ => None,
+        }
+    }
+
+    fn format(&self) -> &'static str {
+        match self {
+            TimePrecision::Seconds => "%H:%M:%S",
+            TimePrecision::Millis => "%H:%M:%S%.3f",
+            TimePrecision::Micros => "%H:%M:%S%.6f",
+            TimePrecision::Nanos => "%H:%M:%S%.9f",
+        }
+    }
+
+    fn truncate(&self, time: NaiveTime) -> NaiveTime {
+        // keeps a leap second (nanos >= 1s) a leap second
+        let nanos = time.nanosecond();
+        let (leap, frac) = (nanos / 1_000_000_000 * 1_000_000_000, nanos % 1_000_000_000);
+        let frac = match self {
+            TimePrecision::Seconds => 0,
+            TimePrecision::Millis => frac / 1_000_000 * 1_000_000,
+            TimePrecision::Micros => frac / 1_000 * 1_000,
+            TimePrecision::Nanos => frac,
+        };
+        time.with_nanosecond(leap + frac).unwrap_or(time)
+    }
+}
+
+// YYYYMMDD-HH:MM:SS[.sss[sss[sss]]], e.g. SendingTime(52)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcTimestamp {
+    value: DateTime<Utc>,
+    precision: TimePrecision,
+}
+
+impl UtcTimestamp {
+    pub fn new(value: DateTime<Utc>, precision: TimePrecision) -> Self {
+        let time = precision.truncate(value.time());
+        let value = NaiveDateTime::new(value.date_naive(), time).and_utc();
+        Self { value, precision }
+    }
+
+    pub fn now() -> Self {
+        Self::new(Utc::now(), TimePrecision::Millis)
+    }
+
+    pub fn value(&self) -> DateTime<Utc> {
+        self.value
+    }
+
+    pub fn precision(&self) -> TimePrecision {
+        self.precision
+    }
+
+    pub fn with_precision(&self, precision: TimePrecision) -> Self {
+        Self::new(self.value, precision)
+    }
+}
+
+impl FromStr for UtcTimestamp {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidFieldValue::new("UTCTIMESTAMP", s);
+        let (date, time) = s.split_once('-').ok_or_else(err)?;
+        let date = parse_date(date).ok_or_else(err)?;
+        let (time, precision) = parse_time(time).ok_or_else(err)?;
+        Ok(Self {
+            value: NaiveDateTime::new(date, time).and_utc(),
+            precision,
+        })
+    }
+}
+
+impl fmt::Display for UtcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = format!("%Y%m%d-{}", self.precision.format());
+        write!(f, "{}", self.value.format(&format))
+    }
+}
+
+// YYYYMMDD, e.g. MDEntryDate(272)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcDateOnly(NaiveDate);
+
+impl UtcDateOnly {
+    pub fn new(value: NaiveDate) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> NaiveDate {
+        self.0
+    }
+}
+
+impl FromStr for UtcDateOnly {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_date(s).map(Self).ok_or_else(|| InvalidFieldValue::new("UTCDATEONLY", s))
+    }
+}
+
+impl fmt::Display for UtcDateOnly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%Y%m%d"))
+    }
+}
+
+// HH:MM:SS[.sss[sss[sss]]], e.g. MDEntryTime(273)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcTimeOnly {
+    value: NaiveTime,
+    precision: TimePrecision,
+}
+
+impl UtcTimeOnly {
+    pub fn new(value: NaiveTime, precision: TimePrecision) -> Self {
+        Self {
+            value: precision.truncate(value),
+            precision,
+        }
+    }
+
+    pub fn value(&self) -> NaiveTime {
+        self.value
+    }
+
+    pub fn precision(&self) -> TimePrecision {
+        self.precision
+    }
+}
+
+impl FromStr for UtcTimeOnly {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, precision) =
+            parse_time(s).ok_or_else(|| InvalidFieldValue::new("UTCTIMEONLY", s))?;
+        Ok(Self { value, precision })
+    }
+}
+
+impl fmt::Display for UtcTimeOnly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value.format(self.precision.format()))
+    }
+}
+
+// YYYYMMDD in the market's local time zone, e.g. TradeDate(75)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LocalMktDate(NaiveDate);
+
+impl LocalMktDate {
+    pub fn new(value: NaiveDate) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> NaiveDate {
+        self.0
+    }
+}
+
+impl FromStr for LocalMktDate {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_date(s).map(Self).ok_or_else(|| InvalidFieldValue::new("LOCALMKTDATE", s))
+    }
+}
+
+impl fmt::Display for LocalMktDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%Y%m%d"))
+    }
+}
+
+// YYYYMM, YYYYMMDD or YYYYMMwN (week 1 to 5 of the month), e.g. MaturityMonthYear(200)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MonthYear {
+    year: i32,
+    month: u32,
+    period: Option<MonthPeriod>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MonthPeriod {
+    Day(u32),
+    Week(u32),
+}
+
+impl MonthYear {
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn period(&self) -> Option<MonthPeriod> {
+        self.period
+    }
+}
+
+impl FromStr for MonthYear {
+    type Err = InvalidFieldValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidFieldValue::new("MONTHYEAR", s);
+        if s.len() < 6 || !s.is_char_boundary(6) || !s[..6].bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+        let year: i32 = s[..4].parse().map_err(|_| err())?;
+        let month: u32 = s[4..6].parse().map_err(|_| err())?;
+        if !(1..=12).contains(&month) {
+            return Err(err());
+        }
+        let period = match &s[6..] {
+            "" => None,
+            week if week.len() == 2 && week.starts_with('w') => match week[1..].parse::<u32>() {
+                Ok(week @ 1..=5) => Some(MonthPeriod::Week(week)),
+                _ => return Err(err()),
+            },
+            _ => {
+                let date = parse_date(s).ok_or_else(err)?;
+                Some(MonthPeriod::Day(date.day()))
+            }
+        };
+        Ok(Self {
+            year,
+            month,
+            period,
+        })
+    }
+}
+
+impl fmt::Display for MonthYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}{:02}", self.year, self.month)?;
+        match self.period {
+            Some(MonthPeriod::Day(day)) => write!(f, "{:02}", day),
+            Some(MonthPeriod::Week(week)) => write!(f, "w{}", week),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    // chrono alone would also take signs and years of other widths
+    if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    NaiveDate::parse_from_str(s, "%Y%m%d").ok()
+}
+
+fn parse_time(s: &str) -> Option<(NaiveTime, TimePrecision)> {
+    let (hms, frac) = match s.split_once('.') {
+        Some((hms, frac)) => (hms, frac),
+        None => (s, ""),
+    };
+    let fields: Vec<&str> = hms.split(':').collect();
+    let two_digits = |f: &&str| f.len() == 2 && f.bytes().all(|b| b.is_ascii_digit());
+    if fields.len() != 3 || !fields.iter().all(two_digits) {
+        return None;
+    }
+    if !frac.bytes().all(|b| b.is_ascii_digit()) || (s.contains('.') && frac.is_empty()) {
+        return None;
+    }
+    let precision = TimePrecision::from_digits(frac.len())?;
+    let nanos = match frac {
+        "" => 0,
+        frac => frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32),
+    };
+    let (hour, min, sec): (u32, u32, u32) =
+        (fields[0].parse().ok()?, fields[1].parse().ok()?, fields[2].parse().ok()?);
+    // second 60 is a leap second
+    let time = match sec {
+        60 => NaiveTime::from_hms_nano_opt(hour, min, 59, 1_000_000_000 + nanos)?,
+        sec => NaiveTime::from_hms_nano_opt(hour, min, sec, nanos)?,
+    };
+    Some((time, precision))
+}
+
 #[cfg(test)]
-mod types_tests {}
+mod types_tests {
+    use super::*;
+    use crate::message::{Message, StringField};
+
+    #[test]
+    fn test_multiple_value_string() {
+        let exec_inst = "1 G".parse::<MultipleValueString>().unwrap();
+        assert_eq!(exec_inst.values(), ["1", "G"]);
+        assert!(exec_inst.contains("G"));
+        assert_eq!(exec_inst.to_string(), "1 G");
+        assert_eq!(MultipleValueString::new(["E"]).to_string(), "E");
+        for invalid in ["", " 1", "1 ", "1  G"] {
+            assert!(invalid.parse::<MultipleValueString>().is_err(), "{:?} parsed", invalid);
+        }
+
+        let mut msg = Message::new();
+        msg.set_multiple_values(18, ["1", "G"]);
+        assert_eq!(msg.get_field::<String>(18).unwrap(), "1 G");
+        assert_eq!(msg.get_multiple_values(18).unwrap(), vec!["1", "G"]);
+        assert!(msg.get_multiple_values(58).is_err());
+    }
+
+    #[test]
+    fn test_is_fix_decimal() {
+        for valid in ["0", "100", "-1.5", "100.50", ".5", "5."] {
+            assert!(is_fix_decimal(valid), "{:?} rejected", valid);
+        }
+        for invalid in [
+            "", "-", ".", "+1", "1e5", "1.2.3", "1,5", " 1", "NaN", "inf",
+        ] {
+            assert!(!is_fix_decimal(invalid), "{:?} accepted", invalid);
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal() {
+        for raw in ["100.50", "-0.0001", "12345678901234567890.12345678", "7"] {
+            assert_eq!(raw.parse::<Decimal>().unwrap().to_string(), raw);
+        }
+        let qty = "0.1".parse::<Decimal>().unwrap().value();
+        assert_eq!((qty + qty + qty).to_string(), "0.3");
+        assert!("1e5".parse::<Decimal>().is_err());
+        // more digits than 96 bits can hold exactly
+        assert!("123456789012345678901234567890.5".parse::<Decimal>().is_err());
+        // generated accessors take decimals
+        let _price = crate::fields::Price::new(Decimal::from(101));
+        let mut msg = Message::new();
+        msg.set_field(StringField::new(crate::fields::Price::field(), "101.250"));
+        assert_eq!(msg.get_field::<Decimal>(44).unwrap().to_string(), "101.250");
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        for raw in [
+            "20221015-09:30:00",
+            "20221015-09:30:00.123",
+            "20221015-09:30:00.123456",
+            "20221015-09:30:00.123456789",
+            "20161231-23:59:60.500",
+        ] {
+            let ts = raw.parse::<UtcTimestamp>().unwrap();
+            assert_eq!(ts.to_string(), raw);
+        }
+        let ts = "20221015-09:30:00.123456".parse::<UtcTimestamp>().unwrap();
+        assert_eq!(ts.precision(), TimePrecision::Micros);
+        assert_eq!(ts.value().timestamp_subsec_micros(), 123456);
+        assert_eq!(ts.with_precision(TimePrecision::Millis).to_string(), "20221015-09:30:00.123");
+        for raw in [
+            "",
+            "20221015",
+            "20221015 09:30:00",
+            "2022101-09:30:00",
+            "20221315-09:30:00",
+            "20221015-9:30:00",
+            "20221015-09:30:00.",
+            "20221015-09:30:00.12",
+            "20221015-09:30:00.1234",
+            "20221015-24:00:00",
+            "+2022101-09:30:00",
+        ] {
+            assert!(raw.parse::<UtcTimestamp>().is_err(), "{:?} parsed", raw);
+        }
+    }
+
+    #[test]
+    fn test_utc_date_and_time_only() {
+        let date = "20221015".parse::<UtcDateOnly>().unwrap();
+        assert_eq!(date.value(), NaiveDate::from_ymd_opt(2022, 10, 15).unwrap());
+        assert_eq!(date.to_string(), "20221015");
+        assert!("20220230".parse::<UtcDateOnly>().is_err());
+        assert!("2022-10-15".parse::<UtcDateOnly>().is_err());
+
+        let time = "09:30:00.250".parse::<UtcTimeOnly>().unwrap();
+        assert_eq!(time.value(), NaiveTime::from_hms_milli_opt(9, 30, 0, 250).unwrap());
+        assert_eq!(time.to_string(), "09:30:00.250");
+        assert_eq!("09:30:00".parse::<UtcTimeOnly>().unwrap().to_string(), "09:30:00");
+        assert!("09:30".parse::<UtcTimeOnly>().is_err());
+        assert!("09:60:00".parse::<UtcTimeOnly>().is_err());
+    }
+
+    #[test]
+    fn test_month_year() {
+        for (raw, period) in [
+            ("202212", None),
+            ("20221216", Some(MonthPeriod::Day(16))),
+            ("202212w3", Some(MonthPeriod::Week(3))),
+        ] {
+            let month_year = raw.parse::<MonthYear>().unwrap();
+            assert_eq!((month_year.year(), month_year.month()), (2022, 12));
+            assert_eq!(month_year.period(), period);
+            assert_eq!(month_year.to_string(), raw);
+        }
+        for invalid in [
+            "2022", "202213", "202200", "20221232", "202212w6", "202212w", "2022-12",
+        ] {
+            assert!(invalid.parse::<MonthYear>().is_err(), "{:?} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn test_local_mkt_date() {
+        let date = "20221015".parse::<LocalMktDate>().unwrap();
+        assert_eq!(date.to_string(), "20221015");
+        assert!("20221015-09:30:00".parse::<LocalMktDate>().is_err());
+        assert!("2022101".parse::<LocalMktDate>().is_err());
+    }
+
+    #[test]
+    fn test_get_field() {
+        let mut msg = Message::new();
+        msg.header_mut().set_orig_sending_time("2022-10-15 09:30:00");
+        let dd = crate::data_dictionary::DataDictionary::from_xml("resources/FIX43.xml");
+        msg.header_mut().set_field(StringField::new(52, "20221015-09:30:00.123"), &dd).unwrap();
+        let sending_time = msg.header().get_field::<UtcTimestamp>(52).unwrap();
+        assert_eq!(sending_time.precision(), TimePrecision::Millis);
+        assert!(msg.header().get_field::<UtcTimestamp>(122).is_err());
+
+        let now = chrono::DateTime::parse_from_rfc3339("2022-10-15T09:31:00.456Z").unwrap();
+        msg.set_sending_time(&crate::clock::ManualClock::new(now.to_utc()));
+        let sending_time = msg.header().get_field::<UtcTimestamp>(52).unwrap();
+        assert_eq!(sending_time.precision(), TimePrecision::Millis);
+        assert_eq!(sending_time.to_string(), "20221015-09:31:00.456");
+    }
+
+    #[test]
+    fn test_field_value_enums() {
+        use crate::field_values::Side;
+
+        assert_eq!(Side::Buy.to_string(), "1");
+        assert_eq!(Side::Buy.description(), "BUY");
+        assert_eq!(Side::field(), 54);
+        assert_eq!("5".parse::<Side>().unwrap(), Side::SellShort);
+        assert!("Z".parse::<Side>().is_err());
+        // serialized as the wire value
+        assert_eq!(serde_json::to_string(&Side::Cross).unwrap(), r#""8""#);
+        assert_eq!(serde_json::from_str::<Side>(r#""2""#).unwrap(), Side::Sell);
+
+        // descriptions starting with a digit
+        let benchmark = "2".parse::<crate::field_values::Benchmark>().unwrap();
+        assert_eq!(benchmark, crate::field_values::Benchmark::Val5Yr);
+        let msg_type = "A".parse::<crate::field_values::MsgType>().unwrap();
+        assert_eq!(msg_type.description(), "LOGON");
+    }
+}